@@ -0,0 +1,116 @@
+//! Python bindings (`website_checker` module) for the website status checker.
+//!
+//! Build with `maturin develop` (or `maturin build --release`) from this directory.
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use website_status_checker_rust::{
+    parse_byte_size, parse_header_assertion, parse_upload_method, read_url_file, CheckRequest, CheckResult, Checker,
+    HttpMethod,
+};
+
+// upload_size accepts either a byte count or the CLI's "5M"-style string
+#[derive(FromPyObject)]
+enum UploadSize {
+    Bytes(u64),
+    Text(String),
+}
+
+/// check(urls=None, file=None, workers=None, timeout=5, retries=0, assert_header=None,
+///       upload_size=None, upload_method=None)
+/// --
+///
+/// Checks the URLs concurrently with the same options as the CLI and returns a list of
+/// dicts with the `status.json` fields plus `ok`. The GIL is released while checking.
+#[pyfunction]
+#[pyo3(signature = (
+    urls=None, file=None, workers=None, timeout=5.0, retries=0, assert_header=None,
+    upload_size=None, upload_method=None
+))]
+#[allow(clippy::too_many_arguments)]
+fn check<'py>(
+    py: Python<'py>,
+    urls: Option<Vec<String>>,
+    file: Option<String>,
+    workers: Option<usize>,
+    timeout: f64,
+    retries: u32,
+    assert_header: Option<String>,
+    upload_size: Option<UploadSize>,
+    upload_method: Option<String>,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let mut all_urls: Vec<CheckRequest> = urls.unwrap_or_default().into_iter().map(CheckRequest::from).collect();
+    if let Some(path) = file {
+        all_urls.extend(read_url_file(&path).map_err(PyValueError::new_err)?);
+    }
+    let mut seen = std::collections::HashSet::new();
+    all_urls.retain(|request| seen.insert(request.url.clone()));
+
+    // try_from_secs_f64 rejects NaN, infinities, negatives and values too large for a Duration
+    let timeout = Duration::try_from_secs_f64(timeout)
+        .ok()
+        .filter(|timeout| !timeout.is_zero())
+        .ok_or_else(|| PyValueError::new_err(format!("timeout must be a positive number of seconds, not {}", timeout)))?;
+    let mut builder = Checker::builder()
+        .timeout(timeout)
+        .retries(retries);
+    if let Some(workers) = workers {
+        builder = builder.workers(workers);
+    }
+    if let Some(s) = assert_header {
+        let (name, value) = parse_header_assertion(&s)
+            .map_err(|e| PyValueError::new_err(format!("Invalid assert_header: {}", e)))?;
+        builder = builder.assert_header(name, value);
+    }
+    match (upload_size, upload_method) {
+        (Some(size), method) => {
+            let size = match size {
+                UploadSize::Bytes(n) => n,
+                UploadSize::Text(s) => parse_byte_size(&s)
+                    .ok_or_else(|| PyValueError::new_err(format!("Invalid upload_size: {}", s)))?,
+            };
+            let method = match method {
+                Some(m) => parse_upload_method(&m)
+                    .map_err(|e| PyValueError::new_err(format!("Invalid upload_method: {}", e)))?,
+                None => HttpMethod::Post,
+            };
+            builder = builder.upload(method, size);
+        }
+        (None, Some(_)) => return Err(PyValueError::new_err("upload_method requires upload_size")),
+        (None, None) => {}
+    }
+    let checker = builder.build().map_err(PyValueError::new_err)?;
+
+    let results = py.allow_threads(|| checker.check_all(all_urls));
+    results.iter().map(|result| result_to_dict(py, result)).collect()
+}
+
+fn result_to_dict<'py>(py: Python<'py>, result: &CheckResult) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("url", &result.url)?;
+    match &result.action_status {
+        Ok(code) => dict.set_item("status", code)?,
+        Err(e) => dict.set_item("status", e)?,
+    }
+    dict.set_item("ok", result.action_status.is_ok())?;
+    dict.set_item("responseTimeMs", result.response_time.as_millis() as u64)?;
+    let timestamp_epoch_s = result.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    dict.set_item("timestampEpochS", timestamp_epoch_s)?;
+    if let Some(upload) = &result.upload {
+        dict.set_item("uploadBytes", upload.bytes)?;
+        dict.set_item("uploadTimeMs", upload.upload_time.as_millis() as u64)?;
+        dict.set_item("uploadThroughputBytesPerS", upload.throughput_bytes_per_sec().round() as u64)?;
+        dict.set_item("serverProcessingMs", upload.processing_time.as_millis() as u64)?;
+    }
+    Ok(dict)
+}
+
+#[pymodule]
+fn website_checker(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(check, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,106 @@
+"""Tests for the website_checker module, against a local HTTP server.
+
+Run with `python -m pytest tests` (or `python -m unittest discover tests`) after
+`maturin develop`.
+"""
+
+import http.server
+import math
+import os
+import tempfile
+import threading
+import unittest
+
+import website_checker
+
+
+class Handler(http.server.BaseHTTPRequestHandler):
+    def do_GET(self):
+        code = 404 if self.path == "/missing" else 200
+        self.send_response(code)
+        self.send_header("Content-Type", "text/plain")
+        self.send_header("Content-Length", "2")
+        self.end_headers()
+        self.wfile.write(b"ok")
+
+    def do_POST(self):
+        self.rfile.read(int(self.headers["Content-Length"]))
+        self.send_response(201)
+        self.send_header("Content-Length", "0")
+        self.end_headers()
+
+    def log_message(self, *args):
+        pass
+
+
+class CheckTest(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.server = http.server.ThreadingHTTPServer(("127.0.0.1", 0), Handler)
+        threading.Thread(target=cls.server.serve_forever, daemon=True).start()
+        cls.base = "http://127.0.0.1:%d" % cls.server.server_address[1]
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.server.shutdown()
+
+    def test_up_url(self):
+        [result] = website_checker.check([self.base + "/"])
+        self.assertEqual(result["url"], self.base + "/")
+        self.assertEqual(result["status"], 200)
+        self.assertTrue(result["ok"])
+        self.assertIsInstance(result["responseTimeMs"], int)
+        self.assertGreater(result["timestampEpochS"], 0)
+
+    def test_any_http_answer_is_ok(self):
+        # Like the CLI: the check got an answer, the status code says what it was
+        [result] = website_checker.check([self.base + "/missing"])
+        self.assertEqual(result["status"], 404)
+        self.assertTrue(result["ok"])
+
+    def test_unreachable_url(self):
+        [result] = website_checker.check(["http://127.0.0.1:1/"], timeout=2)
+        self.assertFalse(result["ok"])
+        self.assertIsInstance(result["status"], str)
+
+    def test_duplicates_are_checked_once(self):
+        results = website_checker.check([self.base + "/", self.base + "/"])
+        self.assertEqual(len(results), 1)
+
+    def test_file(self):
+        with tempfile.NamedTemporaryFile("w", suffix=".txt", delete=False) as f:
+            f.write("# comment\n%s/a priority=critical tags=web\n%s/b\n" % (self.base, self.base))
+        self.addCleanup(os.unlink, f.name)
+        results = website_checker.check(file=f.name, workers=2)
+        self.assertEqual(sorted(r["url"] for r in results), [self.base + "/a", self.base + "/b"])
+
+    def test_assert_header(self):
+        [passing] = website_checker.check([self.base + "/"], assert_header="Content-Type: text/plain")
+        self.assertTrue(passing["ok"])
+        [failing] = website_checker.check([self.base + "/"], assert_header="Content-Type: text/html")
+        self.assertFalse(failing["ok"])
+
+    def test_upload(self):
+        [result] = website_checker.check([self.base + "/"], upload_size="1K")
+        self.assertEqual(result["status"], 201)
+        self.assertEqual(result["uploadBytes"], 1024)
+
+    def test_invalid_timeouts(self):
+        for timeout in [0, -1, math.nan, math.inf, 1e30]:
+            with self.subTest(timeout=timeout):
+                with self.assertRaises(ValueError):
+                    website_checker.check([self.base + "/"], timeout=timeout)
+
+    def test_invalid_options(self):
+        with self.assertRaises(ValueError):
+            website_checker.check([self.base + "/"], workers=0)
+        with self.assertRaises(ValueError):
+            website_checker.check([self.base + "/"], upload_method="PUT")
+        with self.assertRaises(ValueError):
+            website_checker.check([self.base + "/"], upload_size="lots")
+        with self.assertRaises(ValueError):
+            website_checker.check([self.base + "/"], assert_header="no colon")
+
+
+if __name__ == "__main__":
+    unittest.main()
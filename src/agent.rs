@@ -0,0 +1,319 @@
+//! Distributed checks: an `agent` runs the checks a central `serve` instance assigns
+//! to it from its own location, and sends the results back after every round.
+//!
+//! The protocol is two authenticated HTTP calls on the central API, made every round:
+//!
+//! * `GET /agent/checks?agent=<name>`: the agent's [`Assignment`].
+//! * `POST /agent/results`: a [`ResultBatch`] of the results since the last one.
+//!
+//! While the central instance can't be reached, an agent without an assignment retries
+//! with exponential backoff; one with an assignment keeps checking it and holds on to
+//! the results (up to [`MAX_PENDING_RESULTS`]) until they can be delivered.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::daemon;
+use crate::history::CheckRecord;
+use crate::{CheckRequest, Checker, Priority};
+
+/// Results an agent keeps while the central instance is unreachable; older ones are
+/// dropped first.
+pub const MAX_PENDING_RESULTS: usize = 10_000;
+
+// Results per POST /agent/results, to keep request bodies small
+const RESULTS_PER_BATCH: usize = 1_000;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The checks a central instance assigns to an agent, and how often to run them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Assignment {
+    pub period_seconds: u64,
+    pub checks: Vec<AssignedCheck>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignedCheck {
+    pub url: String,
+    pub priority: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl From<&CheckRequest> for AssignedCheck {
+    fn from(request: &CheckRequest) -> Self {
+        AssignedCheck { url: request.url.clone(), priority: request.priority.to_string(), tags: request.tags.clone() }
+    }
+}
+
+impl AssignedCheck {
+    fn to_request(&self) -> CheckRequest {
+        CheckRequest {
+            url: self.url.clone(),
+            priority: Priority::parse(&self.priority).unwrap_or_default(),
+            tags: self.tags.clone(),
+        }
+    }
+}
+
+/// Results an agent sends to the central instance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultBatch {
+    pub agent: String,
+    pub results: Vec<CheckRecord>,
+}
+
+/// Checks that an agent name is usable in a URL and a log line: 1 to 64 ASCII
+/// letters, digits, `-`, `_` or `.`, e.g. `eu-west-1`.
+pub fn validate_agent_name(name: &str) -> Result<(), String> {
+    let valid_chars = name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if name.is_empty() || name.len() > 64 || !valid_chars {
+        return Err(format!("invalid agent name '{}' (use 1-64 letters, digits, '-', '_' or '.')", name));
+    }
+    Ok(())
+}
+
+// Whether a failed call to the central instance is worth retrying
+enum CallError {
+    Retry(String),
+    Fatal(String),
+}
+
+pub struct Agent {
+    client: reqwest::blocking::Client,
+    server: String,
+    name: String,
+    token: Option<String>,
+    checker: Checker,
+}
+
+impl Agent {
+    /// An agent called `name` reporting to the central instance at `server` (its API
+    /// base URL, e.g. `http://central:8080`), authenticating with `token` if the
+    /// central instance has an `--api-token`.
+    pub fn new(server: &str, name: &str, token: Option<String>, checker: Checker) -> Result<Agent, String> {
+        validate_agent_name(name)?;
+        let client = reqwest::blocking::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+        Ok(Agent { client, server: server.trim_end_matches('/').to_string(), name: name.to_string(), token, checker })
+    }
+
+    /// Runs rounds until SIGTERM. Fails only if the central instance rejects the
+    /// agent's token.
+    pub fn run(&self) -> Result<(), String> {
+        let mut assignment: Option<Assignment> = None;
+        let mut pending: VecDeque<CheckRecord> = VecDeque::new();
+        let mut backoff = INITIAL_BACKOFF;
+        while !daemon::terminated() {
+            match self.fetch_assignment() {
+                Ok(new) => {
+                    if assignment.is_none() {
+                        info!(server = %self.server, checks = new.checks.len(), "connected to the central instance");
+                    }
+                    assignment = Some(new);
+                    backoff = INITIAL_BACKOFF;
+                }
+                Err(CallError::Fatal(e)) => return Err(e),
+                Err(CallError::Retry(e)) if assignment.is_none() => {
+                    warn!(server = %self.server, error = %e, retry_in_s = backoff.as_secs(), "failed to reach the central instance");
+                    daemon::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    continue;
+                }
+                Err(CallError::Retry(e)) => {
+                    warn!(server = %self.server, error = %e, "failed to refresh the assignment, keeping the last one");
+                }
+            }
+            let Some(assignment) = &assignment else { continue };
+
+            let started = Instant::now();
+            let requests: Vec<CheckRequest> = assignment.checks.iter().map(AssignedCheck::to_request).collect();
+            for result in self.checker.check_all(requests) {
+                if pending.len() == MAX_PENDING_RESULTS {
+                    pending.pop_front();
+                }
+                pending.push_back(CheckRecord::from(&result));
+            }
+            while !pending.is_empty() {
+                let batch: Vec<CheckRecord> = pending.iter().take(RESULTS_PER_BATCH).cloned().collect();
+                match self.send_results(batch) {
+                    Ok(sent) => {
+                        pending.drain(..sent);
+                    }
+                    Err(CallError::Fatal(e)) => return Err(e),
+                    Err(CallError::Retry(e)) => {
+                        warn!(server = %self.server, error = %e, pending = pending.len(), "failed to send results, keeping them for the next round");
+                        break;
+                    }
+                }
+            }
+            let period = Duration::from_secs(assignment.period_seconds.max(1));
+            daemon::sleep(period.saturating_sub(started.elapsed()));
+        }
+        Ok(())
+    }
+
+    fn fetch_assignment(&self) -> Result<Assignment, CallError> {
+        let url = format!("{}/agent/checks?agent={}", self.server, self.name);
+        let response = self.authorized(self.client.get(&url)).send().map_err(|e| CallError::Retry(e.to_string()))?;
+        let body = check_status(response)?.text().map_err(|e| CallError::Retry(e.to_string()))?;
+        serde_json::from_str(&body).map_err(|e| CallError::Retry(format!("invalid assignment: {}", e)))
+    }
+
+    // The number of results delivered
+    fn send_results(&self, results: Vec<CheckRecord>) -> Result<usize, CallError> {
+        let count = results.len();
+        let batch = ResultBatch { agent: self.name.clone(), results };
+        let body = serde_json::to_string(&batch).map_err(|e| CallError::Fatal(e.to_string()))?;
+        let url = format!("{}/agent/results", self.server);
+        let request = self.client.post(&url).header(reqwest::header::CONTENT_TYPE, "application/json").body(body);
+        let response = self.authorized(request).send().map_err(|e| CallError::Retry(e.to_string()))?;
+        check_status(response)?;
+        Ok(count)
+    }
+
+    fn authorized(&self, request: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
+
+fn check_status(response: reqwest::blocking::Response) -> Result<reqwest::blocking::Response, CallError> {
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(CallError::Fatal("the central instance rejected the agent's token (--token)".to_string()));
+    }
+    if status == reqwest::StatusCode::FORBIDDEN {
+        let body = response.text().unwrap_or_default();
+        return Err(CallError::Fatal(format!("the central instance refused the agent: {}", body.trim())));
+    }
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        return Err(CallError::Retry(format!("HTTP {}: {}", status.as_u16(), body.trim())));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::testing::{response, result, FakeBackend, TestServer};
+    use crate::{Config, HttpRequest};
+
+    fn agent(server: &str, token: Option<&str>) -> Agent {
+        let backend = FakeBackend(|_: HttpRequest<'_>| Ok(response(200, &[])));
+        let checker = Checker::with_backend(Config::default(), 1, Arc::new(backend)).unwrap();
+        Agent::new(server, "eu-west-1", token.map(str::to_string), checker).unwrap()
+    }
+
+    fn error(result: Result<impl std::fmt::Debug, CallError>) -> (bool, String) {
+        match result.unwrap_err() {
+            CallError::Retry(e) => (true, e),
+            CallError::Fatal(e) => (false, e),
+        }
+    }
+
+    #[test]
+    fn agent_names_are_short_and_plain() {
+        for name in ["eu-west-1", "probe_2", "home.lan", "a", &"x".repeat(64)] {
+            assert!(validate_agent_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", &"x".repeat(65), "eu west", "a/b", "a&agent=b", "zürich"] {
+            assert!(validate_agent_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn assigned_checks_keep_priority_and_tags() {
+        let mut request = CheckRequest::new("https://example.com/");
+        request.priority = Priority::Critical;
+        request.tags = vec!["web".to_string()];
+        let assigned = AssignedCheck::from(&request);
+        assert_eq!(assigned.priority, "critical");
+        let back = assigned.to_request();
+        assert_eq!((back.url, back.priority, back.tags), (request.url, request.priority, request.tags));
+
+        let newer = AssignedCheck { url: "https://example.com/".to_string(), priority: "urgent".to_string(), tags: Vec::new() };
+        assert_eq!(newer.to_request().priority, Priority::default());
+    }
+
+    #[test]
+    fn assignments_are_fetched_with_the_token() {
+        let server = TestServer::start(|_| {
+            (200, r#"{"periodSeconds":30,"checks":[{"url":"https://a.example/","priority":"high","tags":["web"]}]}"#.to_string())
+        });
+        let assignment = agent(&format!("{}/", server.url), Some("s3cret")).fetch_assignment().ok().unwrap();
+        assert_eq!(assignment.period_seconds, 30);
+        assert_eq!(assignment.checks[0].url, "https://a.example/");
+        assert_eq!(assignment.checks[0].tags, ["web"]);
+
+        let [request] = server.requests().try_into().unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.url, "/agent/checks?agent=eu-west-1");
+        assert_eq!(request.header("authorization"), Some("Bearer s3cret"));
+    }
+
+    #[test]
+    fn results_are_sent_as_a_batch() {
+        let server = TestServer::start(|_| (200, "{\"accepted\":2}".to_string()));
+        let records = vec![
+            CheckRecord::from(&result("https://a.example/", Ok(200))),
+            CheckRecord::from(&result("https://b.example/", Err("timed out".to_string()))),
+        ];
+        assert_eq!(agent(&server.url, None).send_results(records).ok(), Some(2));
+
+        let [request] = server.requests().try_into().unwrap();
+        assert_eq!((request.method.as_str(), request.url.as_str()), ("POST", "/agent/results"));
+        assert_eq!(request.header("authorization"), None);
+        let batch: ResultBatch = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(batch.agent, "eu-west-1");
+        assert_eq!(batch.results.len(), 2);
+        assert!(batch.results[0].ok && !batch.results[1].ok);
+        assert_eq!(batch.results[1].error.as_deref(), Some("timed out"));
+    }
+
+    #[test]
+    fn only_rejected_tokens_and_refusals_are_fatal() {
+        let unauthorized = TestServer::start(|_| (401, String::new()));
+        let (retry, e) = error(agent(&unauthorized.url, Some("wrong")).fetch_assignment());
+        assert!(!retry && e.contains("rejected the agent's token"), "{}", e);
+        // run() gives up rather than retrying forever
+        assert!(agent(&unauthorized.url, Some("wrong")).run().is_err());
+
+        let refused = TestServer::start(|_| (403, "{\"error\":\"agent endpoints are disabled\"}\n".to_string()));
+        let (retry, e) = error(agent(&refused.url, None).send_results(Vec::new()));
+        assert!(!retry);
+        assert_eq!(e, "the central instance refused the agent: {\"error\":\"agent endpoints are disabled\"}");
+
+        let failing = TestServer::start(|_| (503, "restarting".to_string()));
+        assert_eq!(error(agent(&failing.url, None).fetch_assignment()), (true, "HTTP 503: restarting".to_string()));
+        let garbled = TestServer::start(|_| (200, "<html>".to_string()));
+        let (retry, e) = error(agent(&garbled.url, None).fetch_assignment());
+        assert!(retry && e.starts_with("invalid assignment"), "{}", e);
+        let (retry, _) = error(agent("http://127.0.0.1:1", None).fetch_assignment());
+        assert!(retry);
+    }
+
+    #[test]
+    fn agents_need_a_valid_name() {
+        let backend = FakeBackend(|_: HttpRequest<'_>| Ok(response(200, &[])));
+        let checker = Checker::with_backend(Config::default(), 1, Arc::new(backend)).unwrap();
+        assert!(Agent::new("http://central:8080", "eu west", None, checker).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! `aggregate`: one result per URL from the latest results of several agents, so a
+//! monitor probing from many locations alerts and keeps history once per URL. A URL
+//! is up if enough of the agents that recently checked it (the quorum) got an answer.
+
+use std::time::{Duration, SystemTime};
+
+use crate::{format_duration, CheckRequest, CheckResult};
+
+/// How many of `reporting` agents must see a URL up: `configured` (`--quorum`, capped
+/// at the number of agents), or by default half of them, rounded up.
+pub fn quorum(configured: Option<usize>, reporting: usize) -> usize {
+    configured.map_or(reporting.div_ceil(2), |n| n.min(reporting)).max(1)
+}
+
+/// Merges the latest result of each agent (by name) for `request`; `None` without
+/// any. The merged result of an up URL has the median response time of the agents
+/// that saw it up and the status of the agent closest to it; a down URL's error
+/// lists what each failing agent got. Its timestamp is that of the newest result.
+pub fn merge(request: &CheckRequest, results: &[(&str, &CheckResult)], configured_quorum: Option<usize>) -> Option<CheckResult> {
+    let timestamp = results.iter().map(|(_, result)| result.timestamp).max()?;
+    let mut up: Vec<&CheckResult> = results.iter().map(|(_, result)| *result).filter(|result| result.action_status.is_ok()).collect();
+    let needed = quorum(configured_quorum, results.len());
+    let (action_status, response_time) = if up.len() >= needed {
+        up.sort_by_key(|result| result.response_time);
+        let median = up[up.len() / 2];
+        (median.action_status.clone(), median.response_time)
+    } else {
+        let failures: Vec<String> = results.iter()
+            .filter_map(|(agent, result)| result.action_status.as_ref().err().map(|e| format!("{}: {}", agent, e)))
+            .collect();
+        let error = format!("down for {} of {} agents ({})", failures.len(), results.len(), failures.join("; "));
+        (Err(error), median_response_time(results))
+    };
+    Some(CheckResult {
+        url: request.url.clone(),
+        action_status,
+        response_time,
+        timestamp,
+        upload: None,
+        priority: request.priority,
+        tags: request.tags.clone(),
+        content_hash: None,
+        maintenance: None,
+    })
+}
+
+/// The result of a URL whose agents have all stopped reporting, after `max_age`
+/// without results: down, so losing every probe doesn't go unnoticed.
+pub fn unreported(request: &CheckRequest, max_age: Duration, now: SystemTime) -> CheckResult {
+    CheckResult {
+        url: request.url.clone(),
+        action_status: Err(format!("no results from any agent in {}", format_duration(max_age))),
+        response_time: Duration::ZERO,
+        timestamp: now,
+        upload: None,
+        priority: request.priority,
+        tags: request.tags.clone(),
+        content_hash: None,
+        maintenance: None,
+    }
+}
+
+fn median_response_time(results: &[(&str, &CheckResult)]) -> Duration {
+    let mut times: Vec<Duration> = results.iter().map(|(_, result)| result.response_time).collect();
+    times.sort();
+    times[times.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::testing::result_at;
+    use crate::Priority;
+
+    fn agent_result(action_status: Result<u16, String>, ms: u64, secs: u64) -> CheckResult {
+        let mut result = result_at("https://example.com/", action_status, UNIX_EPOCH + Duration::from_secs(secs));
+        result.response_time = Duration::from_millis(ms);
+        result
+    }
+
+    fn down(ms: u64) -> CheckResult {
+        agent_result(Err("timed out".to_string()), ms, 100)
+    }
+
+    fn up(ms: u64) -> CheckResult {
+        agent_result(Ok(200), ms, 100)
+    }
+
+    #[test]
+    fn default_quorum_is_half_the_agents_rounded_up() {
+        assert_eq!(quorum(None, 1), 1);
+        assert_eq!(quorum(None, 2), 1);
+        assert_eq!(quorum(None, 3), 2);
+        assert_eq!(quorum(None, 4), 2);
+        assert_eq!(quorum(None, 5), 3);
+    }
+
+    #[test]
+    fn quorum_is_capped_at_the_agents_reporting() {
+        assert_eq!(quorum(Some(2), 5), 2);
+        assert_eq!(quorum(Some(5), 3), 3);
+        assert_eq!(quorum(Some(3), 1), 1);
+        // Never zero, or a URL would be up with every agent failing
+        assert_eq!(quorum(Some(0), 3), 1);
+        assert_eq!(quorum(None, 0), 1);
+        assert_eq!(quorum(Some(4), 0), 1);
+    }
+
+    #[test]
+    fn no_agents_merge_to_nothing() {
+        assert!(merge(&CheckRequest::new("https://example.com/"), &[], None).is_none());
+        assert!(merge(&CheckRequest::new("https://example.com/"), &[], Some(2)).is_none());
+    }
+
+    #[test]
+    fn a_single_agent_decides_alone() {
+        let request = CheckRequest::new("https://example.com/");
+        let (ok, failed) = (up(120), down(5000));
+        for configured in [None, Some(1), Some(3)] {
+            let merged = merge(&request, &[("eu", &ok)], configured).unwrap();
+            assert_eq!(merged.action_status, Ok(200));
+            assert_eq!(merged.response_time, Duration::from_millis(120));
+            let merged = merge(&request, &[("eu", &failed)], configured).unwrap();
+            assert_eq!(merged.action_status, Err("down for 1 of 1 agents (eu: timed out)".to_string()));
+        }
+    }
+
+    #[test]
+    fn up_needs_the_quorum() {
+        let request = CheckRequest::new("https://example.com/");
+        let (a, b, c) = (up(300), agent_result(Ok(503), 50, 100), down(5000));
+        let results = [("ap", &a), ("eu", &b), ("us", &c)];
+        // 2 of 3 is the default quorum
+        let merged = merge(&request, &results, None).unwrap();
+        assert!(merged.action_status.is_ok());
+        // A quorum larger than the agents reporting needs all of them
+        let merged = merge(&request, &results, Some(10)).unwrap();
+        assert_eq!(merged.action_status, Err("down for 1 of 3 agents (us: timed out)".to_string()));
+        assert_eq!(merged.response_time, Duration::from_millis(300));
+
+        let (d, e) = (down(100), down(200));
+        let merged = merge(&request, &[("ap", &a), ("eu", &d), ("us", &e)], None).unwrap();
+        assert_eq!(merged.action_status, Err("down for 2 of 3 agents (eu: timed out; us: timed out)".to_string()));
+        assert!(merge(&request, &[("ap", &a), ("eu", &d), ("us", &e)], Some(1)).unwrap().action_status.is_ok());
+    }
+
+    #[test]
+    fn merged_results_take_the_median_up_agent() {
+        let mut request = CheckRequest::new("https://example.com/");
+        request.priority = Priority::High;
+        request.tags = vec!["web".to_string()];
+        let (fast, middle, slow, failed) = (
+            agent_result(Ok(200), 40, 100),
+            agent_result(Ok(204), 90, 130),
+            agent_result(Ok(200), 700, 90),
+            agent_result(Err("refused".to_string()), 5, 160),
+        );
+        let merged = merge(&request, &[("a", &slow), ("b", &fast), ("c", &failed), ("d", &middle)], None).unwrap();
+        assert_eq!(merged.action_status, Ok(204));
+        assert_eq!(merged.response_time, Duration::from_millis(90));
+        assert_eq!(merged.timestamp, UNIX_EPOCH + Duration::from_secs(160));
+        assert_eq!((merged.url.as_str(), merged.priority, merged.tags), ("https://example.com/", Priority::High, vec!["web".to_string()]));
+    }
+
+    #[test]
+    fn unreported_urls_are_down() {
+        let request = CheckRequest::new("https://example.com/");
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        let result = unreported(&request, Duration::from_secs(180), now);
+        assert_eq!(result.action_status, Err("no results from any agent in 3m 0s".to_string()));
+        assert_eq!(result.timestamp, now);
+    }
+}
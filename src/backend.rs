@@ -0,0 +1,156 @@
+//! HTTP transport used by the checker.
+//!
+//! Native builds use a blocking reqwest client ([`ReqwestBackend`], `native` feature).
+//! Targets without sockets or threads, such as `wasm32-wasip1` or edge runtimes, plug in
+//! their own [`HttpBackend`] (typically a wrapper around the host's `fetch`) through
+//! `CheckerBuilder::backend`.
+
+use std::io::Read;
+#[cfg(feature = "native")]
+use std::time::Duration;
+
+/// HTTP method used by a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    Get,
+    Post,
+    Put,
+}
+
+impl HttpMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+        }
+    }
+}
+
+impl std::fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Request body streamed from a reader with a known length.
+pub struct HttpBody {
+    pub reader: Box<dyn Read + Send>,
+    pub len: u64,
+}
+
+/// One outgoing request.
+pub struct HttpRequest<'a> {
+    pub method: HttpMethod,
+    pub url: &'a str,
+    pub headers: Vec<(&'a str, &'a str)>,
+    pub body: Option<HttpBody>,
+    /// Whether the check needs the response body (`--watch-content`).
+    pub read_body: bool,
+}
+
+/// Bodies read for content checks are cut off after this many bytes.
+pub const MAX_BODY_BYTES: u64 = 10 * 1024 * 1024;
+
+/// The parts of a response the checks look at. Header names are lowercase.
+#[derive(Debug, Clone, Default)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, Vec<u8>)>,
+    /// The response body, when the request asked for it with `read_body`. Backends
+    /// may cut it off at [`MAX_BODY_BYTES`].
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpResponse {
+    /// First value of the header `name` (compared case-insensitively).
+    pub fn header(&self, name: &str) -> Option<&[u8]> {
+        self.headers.iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_slice())
+    }
+}
+
+/// Sends requests for the checker. Implementations must be usable from several
+/// worker threads at once (on targets that have threads).
+pub trait HttpBackend: Send + Sync {
+    fn send(&self, request: HttpRequest<'_>) -> Result<HttpResponse, String>;
+}
+
+/// Blocking reqwest client with the per-request timeout applied.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub struct ReqwestBackend {
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "native")]
+impl ReqwestBackend {
+    pub fn new(timeout: Duration) -> Result<Self, String> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+        Ok(ReqwestBackend { client })
+    }
+}
+
+#[cfg(feature = "native")]
+impl HttpBackend for ReqwestBackend {
+    fn send(&self, request: HttpRequest<'_>) -> Result<HttpResponse, String> {
+        let method = match request.method {
+            HttpMethod::Get => reqwest::Method::GET,
+            HttpMethod::Post => reqwest::Method::POST,
+            HttpMethod::Put => reqwest::Method::PUT,
+        };
+        let mut builder = self.client.request(method, request.url);
+        for (name, value) in request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(reqwest::blocking::Body::sized(body.reader, body.len));
+        }
+        let response = builder.send().map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        let headers = response.headers().iter()
+            .map(|(name, value)| (name.as_str().to_string(), value.as_bytes().to_vec()))
+            .collect();
+        let body = if request.read_body {
+            let mut body = Vec::new();
+            response.take(MAX_BODY_BYTES).read_to_end(&mut body)
+                .map_err(|e| format!("error reading response body: {}", e))?;
+            Some(body)
+        } else {
+            None
+        };
+        Ok(HttpResponse { status, headers, body })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_are_found_case_insensitively() {
+        let response = HttpResponse {
+            status: 200,
+            headers: vec![
+                ("set-cookie".to_string(), b"a=1".to_vec()),
+                ("set-cookie".to_string(), b"b=2".to_vec()),
+                ("content-type".to_string(), b"text/html".to_vec()),
+            ],
+            body: None,
+        };
+        assert_eq!(response.header("Content-Type"), Some(&b"text/html"[..]));
+        assert_eq!(response.header("SET-COOKIE"), Some(&b"a=1"[..]));
+        assert_eq!(response.header("server"), None);
+    }
+
+    #[test]
+    fn methods_print_as_http_tokens() {
+        assert_eq!(HttpMethod::Get.to_string(), "GET");
+        assert_eq!(HttpMethod::Post.as_str(), "POST");
+        assert_eq!(HttpMethod::Put.as_str(), "PUT");
+    }
+}
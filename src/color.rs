@@ -0,0 +1,78 @@
+//! ANSI colors for the console output (`--color`), so failures stand out in long
+//! tables. `auto` colors only a terminal and honors the `NO_COLOR` convention.
+
+use std::env;
+
+pub const GREEN: &str = "32";
+pub const YELLOW: &str = "33";
+pub const RED: &str = "31";
+
+/// What `--color` asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(s: &str) -> Result<ColorChoice, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            other => Err(format!("unknown color mode '{}' (expected auto, always or never)", other)),
+        }
+    }
+
+    /// Whether to color output going to a stream that is (or isn't) a terminal. With
+    /// `auto`, a non-empty `NO_COLOR` environment variable turns colors off.
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+        }
+    }
+}
+
+/// `text` wrapped in the escapes for `color` (one of the constants above), or as is
+/// if `enabled` is false. Pad before painting: the escapes count towards `{:<N}`.
+pub fn paint(text: &str, color: &str, enabled: bool) -> String {
+    if enabled { format!("\x1b[{}m{}\x1b[0m", color, text) } else { text.to_string() }
+}
+
+/// The color of a check outcome: green for 2xx (and exit code 0 of `exec://`
+/// checks), yellow for 3xx and 4xx, red for 5xx and errors.
+pub fn status_color(status: &Result<u16, String>) -> &'static str {
+    match status {
+        Ok(code) if *code >= 500 => RED,
+        Ok(code) if *code >= 300 => YELLOW,
+        Ok(_) => GREEN,
+        Err(_) => RED,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_choices() {
+        assert_eq!(ColorChoice::parse("ALWAYS"), Ok(ColorChoice::Always));
+        assert_eq!(ColorChoice::parse("never"), Ok(ColorChoice::Never));
+        assert_eq!(ColorChoice::parse("yes").unwrap_err(), "unknown color mode 'yes' (expected auto, always or never)");
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(!ColorChoice::Auto.enabled(false));
+    }
+
+    #[test]
+    fn painting() {
+        assert_eq!(paint("DOWN", RED, true), "\x1b[31mDOWN\x1b[0m");
+        assert_eq!(paint("DOWN", RED, false), "DOWN");
+        let colors: Vec<_> = [Ok(0), Ok(204), Ok(301), Ok(404), Ok(503), Err("timed out".to_string())].iter().map(status_color).collect();
+        assert_eq!(colors, [GREEN, GREEN, YELLOW, YELLOW, RED, RED]);
+    }
+}
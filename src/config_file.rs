@@ -0,0 +1,102 @@
+//! The `--config` file: TOML settings that don't fit on a command line, such as
+//! notifier credentials and recipient lists.
+//!
+//! ```toml
+//! [[email]]
+//! host = "smtp.example.com"
+//! username = "alerts@example.com"
+//! password_env = "SMTP_PASSWORD"
+//! from = "Status Checker <alerts@example.com>"
+//! to = ["oncall@example.com"]
+//!
+//! [[opsgenie]]
+//! api_key_env = "OPSGENIE_API_KEY"
+//!
+//! [[route]]
+//! channel = "opsgenie"
+//! priority = "critical"
+//! ```
+
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::notify::email::EmailConfig;
+use crate::notify::escalation::EscalationConfig;
+use crate::notify::gotify::GotifyConfig;
+use crate::notify::ntfy::NtfyConfig;
+use crate::notify::opsgenie::OpsgenieConfig;
+use crate::notify::routing::RouteConfig;
+use crate::notify::slack::SlackConfig;
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// SMTP notifiers, one per `[[email]]` table.
+    #[serde(default)]
+    pub email: Vec<EmailConfig>,
+    /// Opsgenie notifiers, one per `[[opsgenie]]` table.
+    #[serde(default)]
+    pub opsgenie: Vec<OpsgenieConfig>,
+    /// ntfy topics, one per `[[ntfy]]` table.
+    #[serde(default)]
+    pub ntfy: Vec<NtfyConfig>,
+    /// Gotify servers, one per `[[gotify]]` table.
+    #[serde(default)]
+    pub gotify: Vec<GotifyConfig>,
+    /// Named Slack webhooks, one per `[[slack]]` table.
+    #[serde(default)]
+    pub slack: Vec<SlackConfig>,
+    /// Alert routes, one per `[[route]]` table.
+    #[serde(default)]
+    pub route: Vec<RouteConfig>,
+    /// Escalation policies, one per `[[escalation]]` table.
+    #[serde(default)]
+    pub escalation: Vec<EscalationConfig>,
+}
+
+impl ConfigFile {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read config file {}: {}", path, e))?;
+        ConfigFile::parse(&text).map_err(|e| format!("Invalid config file {}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::email::TlsMode;
+
+    #[test]
+    fn email_tables_fill_in_defaults() {
+        let config = ConfigFile::parse(
+            r#"
+            [[email]]
+            host = "smtp.example.com"
+            from = "Status Checker <alerts@example.com>"
+            to = ["oncall@example.com"]
+            "#,
+        )
+        .unwrap();
+        let [email] = config.email.as_slice() else { panic!("expected one [[email]] table") };
+        assert_eq!(email.host, "smtp.example.com");
+        assert_eq!((email.port, email.tls), (None, TlsMode::StartTls));
+        assert_eq!(email.subject_prefix, "[status-checker]");
+        assert!(email.digest && email.immediate);
+        assert!(ConfigFile::parse("").unwrap().email.is_empty());
+    }
+
+    #[test]
+    fn unknown_settings_are_errors() {
+        assert!(ConfigFile::parse("[[mail]]\nhost = \"smtp.example.com\"").is_err());
+        let error = ConfigFile::parse("[[email]]\nhost = \"h\"\nfrom = \"a@b.c\"\nto = []\nsubject = \"x\"")
+            .err()
+            .unwrap();
+        assert!(error.contains("unknown field `subject`"), "{}", error);
+        assert!(ConfigFile::load("/nonexistent/config.toml").is_err());
+    }
+}
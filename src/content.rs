@@ -0,0 +1,60 @@
+//! Response body fingerprints for `--watch-content`: a SHA-256 of the body, or of the
+//! parts of an HTML page matched by a CSS selector, so pages can be watched for
+//! unexpected changes (defacement, a broken deploy) and not just for being up.
+
+use sha2::{Digest, Sha256};
+
+/// Hashes `body`, or with a `selector` the HTML of every element it matches (in
+/// document order). A selector that matches nothing hashes as an empty page.
+pub fn fingerprint(body: &[u8], selector: Option<&str>) -> Result<String, String> {
+    let digest = match selector {
+        Some(selector) => Sha256::digest(select(body, selector)?.as_bytes()),
+        None => Sha256::digest(body),
+    };
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!("sha256:{}", hex))
+}
+
+/// Checks that `selector` is a valid CSS selector.
+pub fn validate_selector(selector: &str) -> Result<(), String> {
+    select(b"", selector).map(|_| ())
+}
+
+#[cfg(feature = "native")]
+fn select(body: &[u8], selector: &str) -> Result<String, String> {
+    let parsed = scraper::Selector::parse(selector)
+        .map_err(|e| format!("invalid CSS selector '{}': {:?}", selector, e))?;
+    let document = scraper::Html::parse_document(&String::from_utf8_lossy(body));
+    let matched: Vec<String> = document.select(&parsed).map(|element| element.html()).collect();
+    Ok(matched.join("\n"))
+}
+
+#[cfg(not(feature = "native"))]
+fn select(_body: &[u8], _selector: &str) -> Result<String, String> {
+    Err("CSS selectors need the `native` feature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY_SHA256: &str = "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn bodies_are_hashed_whole_without_a_selector() {
+        assert_eq!(fingerprint(b"", None).unwrap(), EMPTY_SHA256);
+        assert_ne!(fingerprint(b"<p>a</p>", None), fingerprint(b"<p>b</p>", None));
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn selectors_hash_only_the_matched_elements() {
+        let page = |footer: &str| format!("<html><body><main id=\"c\"><p>Hello</p></main><footer>{}</footer></body></html>", footer);
+        let hash = |body: &str, selector: &str| fingerprint(body.as_bytes(), Some(selector)).unwrap();
+        assert_eq!(hash(&page("1 visitor"), "#c"), hash(&page("2 visitors"), "#c"));
+        assert_ne!(hash(&page("1 visitor"), "footer"), hash(&page("2 visitors"), "footer"));
+        assert_eq!(hash(&page(""), "article"), EMPTY_SHA256);
+        assert!(validate_selector("main > p").is_ok());
+        assert!(validate_selector("main >").unwrap_err().starts_with("invalid CSS selector 'main >'"));
+    }
+}
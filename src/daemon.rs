@@ -0,0 +1,227 @@
+//! Running as a background service: `--daemon` detaches from the terminal and keeps a
+//! PID file, and SIGTERM ends a `--period` run cleanly once its current round is done.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+// How long `sleep` may take to notice SIGTERM
+const SLEEP_SLICE: Duration = Duration::from_millis(200);
+
+/// Makes SIGTERM set a flag (see [`terminated`]) instead of killing the process.
+pub fn handle_sigterm() {
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(libc::SIGTERM, on_sigterm as extern "C" fn(libc::c_int) as libc::sighandler_t);
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigterm(_signal: libc::c_int) {
+    // Only async-signal-safe work in here
+    TERMINATED.store(true, Ordering::SeqCst);
+}
+
+/// Whether SIGTERM asked the process to stop.
+pub fn terminated() -> bool {
+    TERMINATED.load(Ordering::SeqCst)
+}
+
+/// Sleeps for `duration`, or less if SIGTERM arrives. Returns false in that case.
+pub fn sleep(duration: Duration) -> bool {
+    let until = Instant::now() + duration;
+    while !terminated() {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return true;
+        }
+        thread::sleep(left.min(SLEEP_SLICE));
+    }
+    false
+}
+
+/// The daemon's PID file, removed when the daemon exits cleanly.
+#[derive(Debug)]
+pub struct PidFile {
+    path: String,
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+pub use unix::daemonize;
+
+/// Detaching needs `fork`, so `--daemon` is Unix-only.
+#[cfg(not(unix))]
+pub fn daemonize(_pid_file: Option<&str>, _log_file: Option<&str>) -> Result<Option<PidFile>, String> {
+    Err("--daemon is only supported on Unix".to_string())
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{self, Read, Write};
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::process;
+
+    use super::PidFile;
+
+    /// Detaches from the terminal: forks twice, starts a new session, points stdin at
+    /// `/dev/null` and stdout and stderr at `log_file` (appended to) or `/dev/null`,
+    /// then writes `pid_file`. Only the daemon returns; the original process exits
+    /// once the daemon is set up, with status 1 if that failed. Must be called before
+    /// any threads are started.
+    pub fn daemonize(pid_file: Option<&str>, log_file: Option<&str>) -> Result<Option<PidFile>, String> {
+        if let Some(path) = pid_file {
+            check_not_running(path)?;
+        }
+        // Opened up front so a bad path is reported on the terminal
+        let output = match log_file {
+            Some(path) => OpenOptions::new().create(true).append(true).open(path)
+                .map_err(|e| format!("Failed to open log file {}: {}", path, e))?,
+            None => dev_null()?,
+        };
+        let stdin = dev_null()?;
+
+        // The daemon reports failures through this pipe; closing it means it's running
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(format!("Failed to create a pipe: {}", io::Error::last_os_error()));
+        }
+        let (mut reader, mut writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+        if fork()? {
+            drop(writer);
+            let mut error = String::new();
+            let _ = reader.read_to_string(&mut error);
+            if error.is_empty() {
+                process::exit(0);
+            }
+            // Formatted like errors returned from main
+            eprintln!("Error: {:?}", error);
+            process::exit(1);
+        }
+        drop(reader);
+        if unsafe { libc::setsid() } < 0 {
+            fail(&mut writer, format!("Failed to start a new session: {}", io::Error::last_os_error()));
+        }
+        // A second fork, so the daemon isn't a session leader and can't get a terminal back
+        match fork() {
+            Ok(true) => unsafe { libc::_exit(0) },
+            Ok(false) => {}
+            Err(e) => fail(&mut writer, e),
+        }
+
+        let pid_file = match pid_file {
+            Some(path) => match fs::write(path, format!("{}\n", process::id())) {
+                Ok(()) => Some(PidFile { path: path.to_string() }),
+                Err(e) => fail(&mut writer, format!("Failed to write PID file {}: {}", path, e)),
+            },
+            None => None,
+        };
+        let redirected = [(&stdin, libc::STDIN_FILENO), (&output, libc::STDOUT_FILENO), (&output, libc::STDERR_FILENO)];
+        for (file, fd) in redirected {
+            if unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+                fail(&mut writer, format!("Failed to redirect output: {}", io::Error::last_os_error()));
+            }
+        }
+        Ok(pid_file)
+    }
+
+    // True in the parent, false in the child
+    fn fork() -> Result<bool, String> {
+        match unsafe { libc::fork() } {
+            -1 => Err(format!("Failed to fork: {}", io::Error::last_os_error())),
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    fn fail(writer: &mut File, error: String) -> ! {
+        let _ = writer.write_all(error.as_bytes());
+        unsafe { libc::_exit(1) }
+    }
+
+    fn dev_null() -> Result<File, String> {
+        OpenOptions::new().read(true).write(true).open("/dev/null")
+            .map_err(|e| format!("Failed to open /dev/null: {}", e))
+    }
+
+    // A PID file left behind by a crash mustn't keep the daemon from starting
+    pub(super) fn check_not_running(path: &str) -> Result<(), String> {
+        let Ok(contents) = fs::read_to_string(path) else { return Ok(()) };
+        let Ok(pid) = contents.trim().parse::<libc::pid_t>() else { return Ok(()) };
+        let alive = pid > 0
+            && (unsafe { libc::kill(pid, 0) } == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM));
+        if alive {
+            return Err(format!("Already running: PID file {} names process {}", path, pid));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sleep_waits_out_the_duration() {
+        let started = Instant::now();
+        assert!(sleep(Duration::from_millis(50)));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(sleep(Duration::ZERO));
+    }
+
+    #[test]
+    fn pid_file_is_removed_on_drop() {
+        let path = crate::testing::temp_dir("pid-file").join("checker.pid");
+        std::fs::write(&path, "1\n").unwrap();
+        drop(PidFile { path: path.to_string_lossy().into_owned() });
+        assert!(!path.exists());
+    }
+
+    // In a child process, so the flag doesn't stop anything else in this one
+    #[cfg(unix)]
+    #[test]
+    fn sigterm_sets_the_flag() {
+        match unsafe { libc::fork() } {
+            -1 => panic!("fork failed"),
+            0 => unsafe {
+                handle_sigterm();
+                let before = terminated();
+                libc::raise(libc::SIGTERM);
+                libc::_exit(if !before && terminated() { 0 } else { 1 });
+            },
+            child => {
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(child, &mut status, 0) }, child);
+                assert!(libc::WIFEXITED(status), "the child was killed by signal {}", libc::WTERMSIG(status));
+                assert_eq!(libc::WEXITSTATUS(status), 0);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pid_files_of_running_processes_block_a_start() {
+        let dir = crate::testing::temp_dir("pid-check");
+        let path = dir.join("checker.pid");
+        let path = path.to_str().unwrap();
+        assert!(unix::check_not_running(path).is_ok());
+
+        std::fs::write(path, format!("{}\n", std::process::id())).unwrap();
+        let error = unix::check_not_running(path).unwrap_err();
+        assert!(error.starts_with("Already running"), "{}", error);
+
+        for stale in ["not a pid\n", "0\n", "-1\n", &format!("{}\n", libc::pid_t::MAX)] {
+            std::fs::write(path, stale).unwrap();
+            assert!(unix::check_not_running(path).is_ok(), "{:?}", stale);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+// The `serve` dashboard: polls GET /status and each check's history, and draws a card
+// per check with its state, an uptime bar and a latency chart. Everything coming from
+// the API goes into the page as text, never as markup: URLs can be added by anyone
+// with access to the API.
+"use strict";
+
+const REFRESH_MS = 5000;
+// Results per check shown in the uptime bars and latency charts
+const SAMPLES = 60;
+const TOKEN_KEY = "website-status-checker-token";
+
+const checksEl = document.getElementById("checks");
+const summaryEl = document.getElementById("summary");
+const errorEl = document.getElementById("error");
+const tokenForm = document.getElementById("token-form");
+const tokenInput = document.getElementById("token");
+document.getElementById("samples").textContent = SAMPLES;
+
+let timer = null;
+
+class Unauthorized extends Error {}
+
+async function api(path) {
+  const headers = {};
+  const token = localStorage.getItem(TOKEN_KEY);
+  if (token) {
+    headers.Authorization = "Bearer " + token;
+  }
+  const response = await fetch(path, { headers, cache: "no-store" });
+  if (response.status === 401) {
+    throw new Unauthorized();
+  }
+  const body = await response.json();
+  if (!response.ok) {
+    throw new Error(body.error || response.statusText);
+  }
+  return body;
+}
+
+function el(tag, className, text) {
+  const node = document.createElement(tag);
+  if (className) {
+    node.className = className;
+  }
+  if (text !== undefined) {
+    node.textContent = text;
+  }
+  return node;
+}
+
+// A result is up if the check got any answer (HTTP status or exit code 0), like in
+// the console output; errors come back as a string status.
+function resultState(result) {
+  if (result.maintenance) {
+    return "maintenance";
+  }
+  return typeof result.status === "number" ? "up" : "down";
+}
+
+function formatMs(ms) {
+  return ms < 1000 ? ms + " ms" : (ms / 1000).toFixed(1) + " s";
+}
+
+function formatTime(epochSeconds) {
+  return new Date(epochSeconds * 1000).toLocaleString();
+}
+
+function uptimeBars(results) {
+  const bars = el("div", "bars");
+  for (let i = results.length; i < SAMPLES; i++) {
+    bars.append(el("span", "empty"));
+  }
+  for (const result of results) {
+    const bar = el("span", resultState(result));
+    bar.title = formatTime(result.timestampEpochS) + ": " + result.status;
+    bars.append(bar);
+  }
+  return bars;
+}
+
+function latencyChart(results) {
+  const svgNs = "http://www.w3.org/2000/svg";
+  const svg = document.createElementNS(svgNs, "svg");
+  svg.setAttribute("class", "chart");
+  svg.setAttribute("viewBox", `0 0 ${SAMPLES - 1} 100`);
+  svg.setAttribute("preserveAspectRatio", "none");
+  const times = results.map((result) => (resultState(result) === "up" ? result.responseTimeMs : null));
+  const max = Math.max(1, ...times.filter((ms) => ms !== null));
+  const offset = SAMPLES - results.length;
+  const points = times
+    .map((ms, i) => (ms === null ? null : `${offset + i},${100 - (ms / max) * 95}`))
+    .filter((point) => point !== null);
+  const line = document.createElementNS(svgNs, "polyline");
+  line.setAttribute("points", points.join(" "));
+  svg.append(line);
+  return { svg, max };
+}
+
+function checkCard(check, results) {
+  const card = el("section", "check");
+  const header = el("div", "check-header");
+  const url = el("span", "url", check.url);
+  url.title = check.url;
+  header.append(url, el("span", "state " + check.state, check.state));
+  card.append(header);
+
+  const details = [];
+  const last = check.lastResult;
+  if (last) {
+    details.push(resultState(last) === "up" ? "status " + last.status : last.status);
+    details.push(formatMs(last.responseTimeMs));
+    details.push("checked " + formatTime(last.timestampEpochS));
+  }
+  if (results.length > 0) {
+    const up = results.filter((result) => resultState(result) !== "down").length;
+    details.push(`uptime ${((up / results.length) * 100).toFixed(1)}%`);
+  }
+  if (check.tags.length > 0) {
+    details.push("tags " + check.tags.join(", "));
+  }
+  details.push("priority " + check.priority);
+  card.append(el("div", "details", details.join(" · ")));
+  // With remote agents, what each of them saw last
+  const agents = Object.entries(check.agents || {});
+  if (agents.length > 0) {
+    const seen = agents.map(([agent, result]) => `${agent}: ${resultState(result)} (${formatMs(result.responseTimeMs)})`);
+    card.append(el("div", "details", "agents " + seen.join(" · ")));
+  }
+
+  const graphs = el("div", "graphs");
+  const uptime = el("div");
+  uptime.append(uptimeBars(results), graphLabel("Uptime", ""));
+  const { svg, max } = latencyChart(results);
+  const latency = el("div");
+  latency.append(svg, graphLabel("Response time", "max " + formatMs(max)));
+  graphs.append(uptime, latency);
+  card.append(graphs);
+  return card;
+}
+
+function graphLabel(left, right) {
+  const label = el("div", "graph-label");
+  label.append(el("span", "", left), el("span", "", right));
+  return label;
+}
+
+function renderSummary(status) {
+  const counts = {};
+  for (const check of status.checks) {
+    counts[check.state] = (counts[check.state] || 0) + 1;
+  }
+  const parts = ["up", "down", "maintenance", "pending"]
+    .filter((state) => counts[state])
+    .map((state) => `${counts[state]} ${state}`);
+  if (status.lastRoundEpochS !== null) {
+    parts.push(`round ${status.round} at ${formatTime(status.lastRoundEpochS)}`);
+  } else {
+    parts.push("waiting for the first round");
+  }
+  summaryEl.textContent = parts.join(" · ");
+}
+
+async function refresh() {
+  try {
+    const status = await api("/status");
+    const histories = await Promise.all(
+      status.checks.map((check) => api(`/checks/${check.id}/history?limit=${SAMPLES}`))
+    );
+    renderSummary(status);
+    const cards = status.checks.map((check, i) => checkCard(check, histories[i].results));
+    if (cards.length === 0) {
+      cards.push(el("p", "", "No checks yet. Add URLs with POST /checks."));
+    }
+    checksEl.replaceChildren(...cards);
+    errorEl.hidden = true;
+    tokenForm.hidden = true;
+  } catch (e) {
+    if (e instanceof Unauthorized) {
+      tokenForm.hidden = false;
+      checksEl.replaceChildren();
+      return;
+    }
+    errorEl.textContent = "Failed to reach the monitor: " + e.message;
+    errorEl.hidden = false;
+  }
+  timer = setTimeout(refresh, REFRESH_MS);
+}
+
+tokenForm.addEventListener("submit", (event) => {
+  event.preventDefault();
+  localStorage.setItem(TOKEN_KEY, tokenInput.value);
+  tokenInput.value = "";
+  clearTimeout(timer);
+  refresh();
+});
+
+refresh();
@@ -0,0 +1,162 @@
+//! Per-domain aggregation for `--group-by`: checks of several paths on one site are
+//! easier to reason about together, so the round summary can add success rate and
+//! latency per host or per registered domain (`api.example.co.uk` and
+//! `www.example.co.uk` both belong to `example.co.uk`).
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::stats::Percentiles;
+use crate::WebsiteStatus;
+
+/// What `--group-by` groups results by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Host,
+    /// Registered domain according to the Public Suffix List.
+    Domain,
+}
+
+impl GroupBy {
+    pub fn parse(s: &str) -> Result<GroupBy, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "host" => Ok(GroupBy::Host),
+            "domain" => Ok(GroupBy::Domain),
+            other => Err(format!("unknown grouping '{}' (expected host or domain)", other)),
+        }
+    }
+}
+
+/// The lowercase host of an `http`/`https` URL, without port or credentials.
+/// `None` for other schemes, which have no host to group by.
+pub fn host(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = if let Some(bracketed) = host_port.strip_prefix('[') {
+        // IPv6 literal, e.g. [::1]:8080
+        bracketed.split(']').next()?
+    } else {
+        host_port.split(':').next()?
+    };
+    if host.is_empty() { None } else { Some(host.to_ascii_lowercase()) }
+}
+
+/// The group of `url`: its host, or the registered domain of its host. Hosts
+/// without one (IP addresses, `localhost`) are their own group.
+pub fn group_key(url: &str, by: GroupBy) -> Option<String> {
+    let host = host(url)?;
+    if by == GroupBy::Host {
+        return Some(host);
+    }
+    if host.parse::<IpAddr>().is_ok() {
+        return Some(host);
+    }
+    let host = host.trim_end_matches('.');
+    let domain = psl::domain_str(host).unwrap_or(host);
+    Some(domain.to_string())
+}
+
+/// One group's results in a round.
+#[derive(Debug, Clone, Default)]
+pub struct GroupStats {
+    pub name: String,
+    pub urls: usize,
+    pub checks: u64,
+    pub successful: u64,
+    /// Response times of the successful checks.
+    pub response_times: Vec<Duration>,
+}
+
+impl GroupStats {
+    pub fn success_rate(&self) -> f64 {
+        if self.checks == 0 { 100.0 } else { self.successful as f64 / self.checks as f64 * 100.0 }
+    }
+
+    pub fn avg_response_time(&self) -> Option<Duration> {
+        if self.response_times.is_empty() {
+            return None;
+        }
+        Some(self.response_times.iter().sum::<Duration>() / self.response_times.len() as u32)
+    }
+
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        Percentiles::from_samples(self.response_times.iter().copied())
+    }
+}
+
+/// Groups results by host or domain, sorted by group name. Results without a host
+/// (e.g. `exec://` checks) are left out.
+pub fn group_results(statuses: &[WebsiteStatus], by: GroupBy) -> Vec<GroupStats> {
+    let mut groups: BTreeMap<String, (GroupStats, Vec<&str>)> = BTreeMap::new();
+    for status in statuses {
+        let Some(key) = group_key(&status.url, by) else { continue };
+        let (group, urls) = groups.entry(key.clone()).or_insert_with(|| {
+            (GroupStats { name: key, ..Default::default() }, Vec::new())
+        });
+        if !urls.contains(&status.url.as_str()) {
+            urls.push(&status.url);
+        }
+        group.checks += 1;
+        if status.action_status.is_ok() {
+            group.successful += 1;
+            group.response_times.push(status.response_time);
+        }
+    }
+    groups.into_values()
+        .map(|(mut group, urls)| {
+            group.urls = urls.len();
+            group
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::result;
+
+    #[test]
+    fn hosts() {
+        assert_eq!(host("https://API.Example.com:8443/v1?q=1").as_deref(), Some("api.example.com"));
+        assert_eq!(host("http://user:pw@example.com#top").as_deref(), Some("example.com"));
+        assert_eq!(host("http://[::1]:8080/").as_deref(), Some("::1"));
+        assert_eq!(host("exec://./check.sh"), None);
+        assert_eq!(host("https:///path"), None);
+        assert_eq!(host("example.com"), None);
+    }
+
+    #[test]
+    fn group_keys() {
+        assert_eq!(group_key("https://api.example.co.uk/", GroupBy::Domain).as_deref(), Some("example.co.uk"));
+        assert_eq!(group_key("https://www.example.co.uk./", GroupBy::Domain).as_deref(), Some("example.co.uk"));
+        assert_eq!(group_key("https://api.example.co.uk/", GroupBy::Host).as_deref(), Some("api.example.co.uk"));
+        assert_eq!(group_key("http://192.168.1.10/", GroupBy::Domain).as_deref(), Some("192.168.1.10"));
+        assert_eq!(group_key("http://localhost:8080/", GroupBy::Domain).as_deref(), Some("localhost"));
+        assert_eq!(GroupBy::parse("Domain"), Ok(GroupBy::Domain));
+        assert!(GroupBy::parse("tld").is_err());
+    }
+
+    #[test]
+    fn results_are_grouped_by_domain() {
+        let statuses = [
+            result("https://www.example.com/", Ok(200)),
+            result("https://api.example.com/health", Err("timed out".to_string())),
+            result("https://www.example.com/", Ok(200)),
+            result("https://example.org/", Ok(200)),
+            result("exec://./check.sh", Ok(0)),
+        ];
+        let groups = group_results(&statuses, GroupBy::Domain);
+        let summary: Vec<_> = groups.iter().map(|g| (g.name.as_str(), g.urls, g.checks, g.successful)).collect();
+        assert_eq!(summary, [("example.com", 2, 3, 2), ("example.org", 1, 1, 1)]);
+        assert_eq!(groups[1].success_rate(), 100.0);
+        assert!((groups[0].success_rate() - 66.667).abs() < 0.001);
+        assert_eq!(groups[0].avg_response_time(), Some(Duration::from_millis(100)));
+        assert_eq!(GroupStats::default().success_rate(), 100.0);
+        assert_eq!(GroupStats::default().percentiles(), None);
+    }
+}
@@ -0,0 +1,571 @@
+//! The history store: what the checker observed over time, kept in the directory given
+//! with `--history-dir` so outages can be looked into after the fact.
+//!
+//! * `checks.jsonl`: one JSON object per check result (see [`CheckRecord`]).
+//! * `incidents.jsonl`: one JSON object per finished outage (see [`Incident`]).
+//! * `incidents.log`: a human-readable line when an outage starts and when it ends.
+//! * `content_changes.jsonl`: one JSON object per `--watch-content` change (see [`ContentChange`]).
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{format_duration, format_rfc3339_utc, CheckResult};
+
+const CHECKS_FILE: &str = "checks.jsonl";
+const INCIDENTS_FILE: &str = "incidents.jsonl";
+const INCIDENT_LOG_FILE: &str = "incidents.log";
+const CONTENT_CHANGES_FILE: &str = "content_changes.jsonl";
+
+/// One stored check result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckRecord {
+    pub url: String,
+    #[serde(deserialize_with = "epoch_seconds")]
+    pub timestamp_epoch_s: u64,
+    /// Whether the check succeeded (any HTTP status counts, as in the live output).
+    pub ok: bool,
+    /// HTTP status code of a successful check.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Error of a failed check.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub response_time_ms: u64,
+    /// Body fingerprint, with `--watch-content`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Summary of the `--maintenance` event the check fell in; such checks don't
+    /// count towards uptime.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<String>,
+}
+
+impl CheckRecord {
+    pub fn timestamp(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.timestamp_epoch_s)
+    }
+}
+
+impl From<&CheckResult> for CheckRecord {
+    fn from(result: &CheckResult) -> Self {
+        CheckRecord {
+            url: result.url.clone(),
+            timestamp_epoch_s: epoch_s(result.timestamp),
+            ok: result.action_status.is_ok(),
+            status: result.action_status.as_ref().ok().copied(),
+            error: result.action_status.as_ref().err().cloned(),
+            response_time_ms: result.response_time.as_millis() as u64,
+            content_hash: result.content_hash.clone(),
+            maintenance: result.maintenance.clone(),
+        }
+    }
+}
+
+/// One outage of a URL, from its first failed check to the next successful one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Incident {
+    pub url: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(deserialize_with = "epoch_seconds")]
+    pub start_epoch_s: u64,
+    #[serde(deserialize_with = "epoch_seconds")]
+    pub end_epoch_s: u64,
+    pub duration_seconds: u64,
+    /// Status of the check that opened the incident.
+    pub first_error: String,
+}
+
+impl Incident {
+    pub fn start(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.start_epoch_s)
+    }
+
+    pub fn end(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.end_epoch_s)
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_seconds)
+    }
+}
+
+/// A change in a URL's up/down state, as reported by [`IncidentTracker::observe`].
+#[derive(Debug, Clone)]
+pub enum IncidentUpdate {
+    Started { url: String, start: SystemTime, error: String },
+    Ended(Incident),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenIncident {
+    start: SystemTime,
+    first_error: String,
+}
+
+/// Open incidents per URL, saved with `--state-file` so a restart doesn't split them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IncidentState(HashMap<String, OpenIncident>);
+
+/// Follows the up/down state of each URL across rounds. Unlike the alert tracker it
+/// applies no thresholds: every failed check opens an incident.
+#[derive(Debug, Clone, Default)]
+pub struct IncidentTracker {
+    open: HashMap<String, OpenIncident>,
+}
+
+impl IncidentTracker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn state(&self) -> IncidentState {
+        IncidentState(self.open.clone())
+    }
+
+    /// Continues from a saved state, replacing the current one.
+    pub fn restore(&mut self, state: IncidentState) {
+        self.open = state.0;
+    }
+
+    /// Records one result; returns the update if it opened or closed an incident.
+    /// Checks during maintenance neither open nor close incidents.
+    pub fn observe(&mut self, result: &CheckResult) -> Option<IncidentUpdate> {
+        if result.maintenance.is_some() {
+            return None;
+        }
+        match &result.action_status {
+            Err(error) => {
+                if self.open.contains_key(&result.url) {
+                    return None;
+                }
+                let open = OpenIncident { start: result.timestamp, first_error: error.clone() };
+                self.open.insert(result.url.clone(), open);
+                Some(IncidentUpdate::Started { url: result.url.clone(), start: result.timestamp, error: error.clone() })
+            }
+            Ok(_) => {
+                let open = self.open.remove(&result.url)?;
+                let (start_epoch_s, end_epoch_s) = (epoch_s(open.start), epoch_s(result.timestamp));
+                Some(IncidentUpdate::Ended(Incident {
+                    url: result.url.clone(),
+                    tags: result.tags.clone(),
+                    start_epoch_s,
+                    end_epoch_s,
+                    duration_seconds: end_epoch_s.saturating_sub(start_epoch_s),
+                    first_error: open.first_error,
+                }))
+            }
+        }
+    }
+}
+
+/// A change of a URL's `--watch-content` fingerprint between two checks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentChange {
+    pub url: String,
+    /// When the check that saw the new content ran.
+    #[serde(deserialize_with = "epoch_seconds")]
+    pub timestamp_epoch_s: u64,
+    pub previous_hash: String,
+    pub hash: String,
+}
+
+impl ContentChange {
+    pub fn timestamp(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.timestamp_epoch_s)
+    }
+}
+
+/// Last seen content fingerprint per URL, saved with `--state-file`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContentState(HashMap<String, String>);
+
+/// Remembers the last content fingerprint of each URL to spot changes. Checks
+/// without a fingerprint (failed or non-2xx) are skipped, so an outage doesn't
+/// count as a change.
+#[derive(Debug, Clone, Default)]
+pub struct ContentTracker {
+    hashes: HashMap<String, String>,
+}
+
+impl ContentTracker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn state(&self) -> ContentState {
+        ContentState(self.hashes.clone())
+    }
+
+    /// Continues from a saved state, replacing the current one.
+    pub fn restore(&mut self, state: ContentState) {
+        self.hashes = state.0;
+    }
+
+    /// Records one result; returns the change if its content differs from the last
+    /// fingerprint seen for the URL. The first fingerprint of a URL is not a change.
+    pub fn observe(&mut self, result: &CheckResult) -> Option<ContentChange> {
+        let hash = result.content_hash.as_ref()?;
+        let previous_hash = self.hashes.insert(result.url.clone(), hash.clone())?;
+        if previous_hash == *hash {
+            return None;
+        }
+        Some(ContentChange {
+            url: result.url.clone(),
+            timestamp_epoch_s: epoch_s(result.timestamp),
+            previous_hash,
+            hash: hash.clone(),
+        })
+    }
+}
+
+/// The `--history-dir` directory.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    dir: PathBuf,
+}
+
+impl HistoryStore {
+    /// Opens the store, creating the directory if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create history directory {}: {}", dir.display(), e))?;
+        Ok(HistoryStore { dir })
+    }
+
+    /// Opens a store for reading, failing if the directory doesn't exist.
+    pub fn open_existing(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        // A results database file is a common guess for `report render --from`
+        if dir.is_file() {
+            return Err(format!(
+                "{} is a file; reports read the history directory written by --history-dir (checks.jsonl, incidents.jsonl)",
+                dir.display()
+            ));
+        }
+        if !dir.is_dir() {
+            return Err(format!("History directory {} does not exist", dir.display()));
+        }
+        Ok(HistoryStore { dir })
+    }
+
+    /// Appends a check result to `checks.jsonl`.
+    pub fn record_check(&self, result: &CheckResult) -> Result<(), String> {
+        let json = serde_json::to_string(&CheckRecord::from(result)).map_err(|e| e.to_string())?;
+        self.append(CHECKS_FILE, &json)
+    }
+
+    /// Writes an incident update: finished incidents go to `incidents.jsonl`, and both
+    /// starts and ends get a line in `incidents.log`.
+    pub fn record_incident(&self, update: &IncidentUpdate) -> Result<(), String> {
+        match update {
+            IncidentUpdate::Started { url, start, error } => {
+                let line = format!("{} DOWN {} (first error: {})", format_rfc3339_utc(*start), url, error);
+                self.append(INCIDENT_LOG_FILE, &line)
+            }
+            IncidentUpdate::Ended(incident) => {
+                let json = serde_json::to_string(incident).map_err(|e| e.to_string())?;
+                self.append(INCIDENTS_FILE, &json)?;
+                let line = format!(
+                    "{} UP   {} after {} down since {} (first error: {})",
+                    format_rfc3339_utc(incident.end()),
+                    incident.url,
+                    format_duration(incident.duration()),
+                    format_rfc3339_utc(incident.start()),
+                    incident.first_error
+                );
+                self.append(INCIDENT_LOG_FILE, &line)
+            }
+        }
+    }
+
+    /// Appends a content change to `content_changes.jsonl`.
+    pub fn record_content_change(&self, change: &ContentChange) -> Result<(), String> {
+        let json = serde_json::to_string(change).map_err(|e| e.to_string())?;
+        self.append(CONTENT_CHANGES_FILE, &json)
+    }
+
+    /// Calls `f` with every stored check result, oldest first. Streams the file, since
+    /// it can get large.
+    pub fn for_each_check(&self, f: impl FnMut(CheckRecord)) -> Result<(), String> {
+        self.read(CHECKS_FILE, f)
+    }
+
+    /// All finished incidents, oldest first.
+    pub fn incidents(&self) -> Result<Vec<Incident>, String> {
+        let mut incidents = Vec::new();
+        self.read(INCIDENTS_FILE, |incident| incidents.push(incident))?;
+        Ok(incidents)
+    }
+
+    /// Drops everything older than `cutoff`: check results and content changes from
+    /// before it, incidents that ended before it and `incidents.log` lines stamped
+    /// before it. Returns the number of rows removed.
+    pub fn prune(&self, cutoff: SystemTime) -> Result<usize, String> {
+        let cutoff_s = epoch_s(cutoff);
+        let mut removed = self.retain::<CheckRecord>(CHECKS_FILE, |check| check.timestamp_epoch_s >= cutoff_s)?;
+        removed += self.retain::<Incident>(INCIDENTS_FILE, |incident| incident.end_epoch_s >= cutoff_s)?;
+        removed += self.retain::<ContentChange>(CONTENT_CHANGES_FILE, |change| change.timestamp_epoch_s >= cutoff_s)?;
+        // Log lines start with a fixed-width UTC timestamp, so they compare as strings
+        let cutoff_stamp = format_rfc3339_utc(cutoff);
+        removed += self.retain_lines(INCIDENT_LOG_FILE, |line| {
+            Ok(line.get(..cutoff_stamp.len()).is_none_or(|stamp| stamp >= cutoff_stamp.as_str()))
+        })?;
+        Ok(removed)
+    }
+
+    // Parses a JSON Lines file; a missing file has no records
+    fn read<T: DeserializeOwned>(&self, file_name: &str, mut f: impl FnMut(T)) -> Result<(), String> {
+        let path = self.dir.join(file_name);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("Failed to open {}: {}", path.display(), e)),
+        };
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            f(serde_json::from_str(&line).map_err(|e| format!("{}:{}: {}", path.display(), n + 1, e))?);
+        }
+        Ok(())
+    }
+
+    fn retain<T: DeserializeOwned>(&self, file_name: &str, keep: impl Fn(&T) -> bool) -> Result<usize, String> {
+        let path = self.dir.join(file_name);
+        self.retain_lines(file_name, |line| {
+            let record = serde_json::from_str(line).map_err(|e| format!("{}: {}", path.display(), e))?;
+            Ok(keep(&record))
+        })
+    }
+
+    // Rewrites a file without the lines `keep` rejects. The kept lines are streamed to a
+    // temporary file that then replaces the original, so an interrupted prune loses nothing.
+    fn retain_lines(&self, file_name: &str, mut keep: impl FnMut(&str) -> Result<bool, String>) -> Result<usize, String> {
+        let path = self.dir.join(file_name);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(format!("Failed to open {}: {}", path.display(), e)),
+        };
+        let tmp_path = self.dir.join(format!("{}.tmp", file_name));
+        let write_error = |e: std::io::Error| format!("Failed to write {}: {}", tmp_path.display(), e);
+        let mut writer = BufWriter::new(File::create(&tmp_path).map_err(write_error)?);
+        let mut removed = 0;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if keep(&line)? {
+                writeln!(writer, "{}", line).map_err(write_error)?;
+            } else {
+                removed += 1;
+            }
+        }
+        writer.flush().map_err(write_error)?;
+        drop(writer);
+
+        if removed == 0 {
+            let _ = fs::remove_file(&tmp_path);
+        } else {
+            fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+        }
+        Ok(removed)
+    }
+
+    fn append(&self, file_name: &str, line: &str) -> Result<(), String> {
+        let path = self.dir.join(file_name);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+// Timestamps read back from a file (or sent by an agent) must fit a `SystemTime`, so
+// that a corrupt line is reported like any other bad line instead of overflowing in
+// the `timestamp()` accessors
+fn epoch_seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let secs = u64::deserialize(deserializer)?;
+    match UNIX_EPOCH.checked_add(Duration::from_secs(secs)) {
+        Some(_) => Ok(secs),
+        None => Err(D::Error::custom(format!("timestamp {} is out of range", secs))),
+    }
+}
+
+fn epoch_s(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{result_at, temp_dir};
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn failures_open_one_incident_until_the_next_success() {
+        let mut tracker = IncidentTracker::new();
+        let url = "https://api.example.com";
+        assert!(tracker.observe(&result_at(url, Ok(200), at(1_000))).is_none());
+        let Some(IncidentUpdate::Started { start, error, .. }) = tracker.observe(&result_at(url, Err("HTTP 503".to_string()), at(1_060))) else {
+            panic!("expected an incident to start");
+        };
+        assert_eq!((start, error.as_str()), (at(1_060), "HTTP 503"));
+        assert!(tracker.observe(&result_at(url, Err("timed out".to_string()), at(1_120))).is_none());
+        let Some(IncidentUpdate::Ended(incident)) = tracker.observe(&result_at(url, Ok(200), at(1_300))) else {
+            panic!("expected the incident to end");
+        };
+        assert_eq!(
+            incident,
+            Incident {
+                url: url.to_string(),
+                tags: Vec::new(),
+                start_epoch_s: 1_060,
+                end_epoch_s: 1_300,
+                duration_seconds: 240,
+                first_error: "HTTP 503".to_string(),
+            }
+        );
+        assert!(tracker.observe(&result_at(url, Ok(200), at(1_360))).is_none());
+    }
+
+    #[test]
+    fn the_store_writes_checks_and_incidents() {
+        let dir = temp_dir("history");
+        let store = HistoryStore::open(dir.join("nested")).unwrap();
+        let mut tracker = IncidentTracker::new();
+        for result in [
+            result_at("https://a.example.com", Err("HTTP 500".to_string()), at(1_715_730_300)),
+            result_at("https://a.example.com", Ok(200), at(1_715_730_420)),
+        ] {
+            store.record_check(&result).unwrap();
+            if let Some(update) = tracker.observe(&result) {
+                store.record_incident(&update).unwrap();
+            }
+        }
+
+        let mut checks = Vec::new();
+        store.for_each_check(|check| checks.push(check)).unwrap();
+        assert_eq!(checks.iter().map(|check| (check.ok, check.status, check.error.as_deref())).collect::<Vec<_>>(),
+            [(false, None, Some("HTTP 500")), (true, Some(200), None)]);
+        assert_eq!(checks[1].timestamp(), at(1_715_730_420));
+        let incidents = store.incidents().unwrap();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].duration(), Duration::from_secs(120));
+        assert_eq!(
+            fs::read_to_string(dir.join("nested").join(INCIDENT_LOG_FILE)).unwrap(),
+            "2024-05-14T23:45:00Z DOWN https://a.example.com (first error: HTTP 500)\n\
+             2024-05-14T23:47:00Z UP   https://a.example.com after 2m 0s down since 2024-05-14T23:45:00Z (first error: HTTP 500)\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reading_needs_an_existing_directory() {
+        let dir = temp_dir("history-existing");
+        assert!(HistoryStore::open_existing(dir.join("missing")).unwrap_err().ends_with("does not exist"));
+        fs::write(dir.join("results.db"), "").unwrap();
+        assert!(HistoryStore::open_existing(dir.join("results.db")).unwrap_err().contains("is a file"));
+        let store = HistoryStore::open_existing(&dir).unwrap();
+        assert!(store.incidents().unwrap().is_empty());
+        fs::write(dir.join(INCIDENTS_FILE), "{\"url\":\n").unwrap();
+        assert!(store.incidents().unwrap_err().contains("incidents.jsonl:1"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn pruning_drops_rows_from_before_the_cutoff() {
+        let dir = temp_dir("history-prune");
+        let store = HistoryStore::open(&dir).unwrap();
+        let url = "https://a.example.com";
+        let mut tracker = IncidentTracker::new();
+        for (up, secs) in [(false, 1_000), (true, 1_100), (false, 2_000), (true, 2_300)] {
+            let result = result_at(url, if up { Ok(200) } else { Err("HTTP 500".to_string()) }, at(secs));
+            store.record_check(&result).unwrap();
+            store.record_incident(&tracker.observe(&result).unwrap()).unwrap();
+        }
+
+        // 2 checks, 1 incident and its 2 log lines
+        assert_eq!(store.prune(at(2_000)).unwrap(), 5);
+        let mut checks = Vec::new();
+        store.for_each_check(|check| checks.push(check.timestamp_epoch_s)).unwrap();
+        assert_eq!(checks, [2_000, 2_300]);
+        assert_eq!(store.incidents().unwrap().iter().map(|incident| incident.start_epoch_s).collect::<Vec<_>>(), [2_000]);
+        let log = fs::read_to_string(dir.join(INCIDENT_LOG_FILE)).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(log.starts_with("1970-01-01T00:33:20Z DOWN"));
+        assert_eq!(store.prune(at(2_000)).unwrap(), 0);
+        assert!(!dir.join("checks.jsonl.tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn content_changes_need_a_previous_fingerprint() {
+        let mut tracker = ContentTracker::new();
+        let url = "https://example.com";
+        let check = |hash: Option<&str>, secs| CheckResult { content_hash: hash.map(str::to_string), ..result_at(url, Ok(200), at(secs)) };
+        assert!(tracker.observe(&check(Some("sha256:aa"), 60)).is_none());
+        assert!(tracker.observe(&check(Some("sha256:aa"), 120)).is_none());
+        assert!(tracker.observe(&check(None, 180)).is_none());
+        assert_eq!(
+            tracker.observe(&check(Some("sha256:bb"), 240)),
+            Some(ContentChange {
+                url: url.to_string(),
+                timestamp_epoch_s: 240,
+                previous_hash: "sha256:aa".to_string(),
+                hash: "sha256:bb".to_string(),
+            })
+        );
+
+        let mut restored = ContentTracker::new();
+        restored.restore(tracker.state());
+        assert!(restored.observe(&check(Some("sha256:bb"), 300)).is_none());
+    }
+
+    #[test]
+    fn checks_during_maintenance_neither_open_nor_close_incidents() {
+        let mut tracker = IncidentTracker::new();
+        let url = "https://api.example.com";
+        let planned = |status| CheckResult { maintenance: Some("Upgrade".to_string()), ..result_at(url, status, at(60)) };
+        assert!(tracker.observe(&planned(Err("HTTP 503".to_string()))).is_none());
+        assert!(tracker.observe(&result_at(url, Err("HTTP 503".to_string()), at(120))).is_some());
+        assert!(tracker.observe(&planned(Ok(200))).is_none());
+        assert!(matches!(tracker.observe(&result_at(url, Ok(200), at(180))), Some(IncidentUpdate::Ended(_))));
+    }
+
+    #[test]
+    fn out_of_range_timestamps_are_bad_lines() {
+        let dir = temp_dir("history-bad-timestamp");
+        let store = HistoryStore::open(&dir).unwrap();
+        store.record_check(&result_at("https://a.example.com", Ok(200), at(1_715_730_300))).unwrap();
+        let mut file = OpenOptions::new().append(true).open(dir.join(CHECKS_FILE)).unwrap();
+        writeln!(file, r#"{{"url":"https://a.example.com","timestampEpochS":18446744073709551615,"ok":true,"responseTimeMs":5}}"#).unwrap();
+        let error = store.for_each_check(|_| {}).unwrap_err();
+        assert!(error.contains("checks.jsonl:2: timestamp 18446744073709551615 is out of range"), "{}", error);
+
+        fs::write(
+            dir.join(INCIDENTS_FILE),
+            r#"{"url":"https://a.example.com","startEpochS":0,"endEpochS":18446744073709551615,"durationSeconds":0,"firstError":"x"}"#,
+        ).unwrap();
+        assert!(store.incidents().unwrap_err().contains("incidents.jsonl:1: timestamp"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
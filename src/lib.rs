@@ -0,0 +1,1562 @@
+//! Checking engine for the website status checker.
+//!
+//! The CLI in `main.rs` is a thin wrapper around this crate; other Rust programs can
+//! embed the same worker pool, retries and assertions through [`Checker`].
+
+#[cfg(feature = "native")]
+pub mod agent;
+pub mod aggregate;
+pub mod backend;
+pub mod color;
+#[cfg(feature = "native")]
+pub mod config_file;
+pub mod content;
+#[cfg(feature = "native")]
+pub mod daemon;
+#[cfg(feature = "native")]
+pub mod domain;
+#[cfg(feature = "native")]
+pub mod history;
+pub mod maintenance;
+pub mod metrics;
+#[cfg(feature = "native")]
+pub mod logging;
+#[cfg(feature = "native")]
+pub mod notify;
+pub mod plugins;
+pub mod progress;
+#[cfg(feature = "native")]
+pub mod report;
+#[cfg(feature = "native")]
+pub mod server;
+#[cfg(feature = "native")]
+pub mod state;
+#[cfg(feature = "native")]
+pub mod shipping;
+pub mod stats;
+#[cfg(feature = "native")]
+pub mod systemd;
+#[cfg(feature = "native")]
+pub mod tui;
+pub mod watch;
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+#[cfg(not(target_family = "wasm"))]
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use color::{paint, GREEN, RED, YELLOW};
+use maintenance::MaintenanceCalendar;
+use stats::{std_dev, Percentiles};
+
+pub use backend::{HttpBackend, HttpBody, HttpMethod, HttpRequest, HttpResponse};
+#[cfg(not(target_family = "wasm"))]
+pub use plugins::exec::ExecPlugin;
+pub use plugins::http::{check_url, HttpPlugin};
+pub use plugins::{CheckPlugin, PluginRegistry};
+#[cfg(feature = "native")]
+pub use backend::ReqwestBackend;
+
+// 3.1 WebsiteStatus Structure
+#[derive(Debug, Clone)]
+pub struct WebsiteStatus {
+    pub url: String,
+    pub action_status: Result<u16, String>,
+    pub response_time: Duration,
+    pub timestamp: SystemTime,
+    pub upload: Option<UploadTiming>, // Only set for upload checks
+    pub priority: Priority,           // Copied from the CheckRequest
+    pub tags: Vec<String>,            // Copied from the CheckRequest
+    pub content_hash: Option<String>, // Only set with --watch-content, for 2xx responses
+    pub maintenance: Option<String>,  // Summary of the --maintenance event the check fell in
+}
+
+/// Result of checking a single URL.
+pub type CheckResult = WebsiteStatus;
+
+// Timing breakdown for upload checks (--upload-size)
+#[derive(Debug, Clone)]
+pub struct UploadTiming {
+    pub bytes: u64,
+    pub upload_time: Duration,     // From request start until the last payload byte was handed to the client
+    pub processing_time: Duration, // From the last payload byte until the response arrived
+}
+
+impl UploadTiming {
+    pub fn throughput_bytes_per_sec(&self) -> f64 {
+        let secs = self.upload_time.as_secs_f64();
+        if secs > 0.0 { self.bytes as f64 / secs } else { 0.0 }
+    }
+}
+
+// Upload check settings: send a generated payload instead of a plain GET
+#[derive(Debug, Clone)]
+pub struct UploadSpec {
+    pub method: HttpMethod, // POST or PUT
+    pub size: u64,
+}
+
+// Content watch settings: fingerprint response bodies, optionally only the elements
+// matched by a CSS selector
+#[derive(Debug, Clone, Default)]
+pub struct ContentWatch {
+    pub selector: Option<String>,
+}
+
+// Struct to hold configuration (updated)
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub timeout: Duration,
+    pub retries: u32,
+    pub header_assertion: Option<(String, String)>, // For --assert-header "Name:Value", name lowercased
+    pub upload: Option<UploadSpec>,                 // For --upload-size / --upload-method
+    pub watch_content: Option<ContentWatch>,        // For --watch-content / --content-selector
+    pub maintenance: Option<MaintenanceCalendar>,   // For --maintenance
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            timeout: Duration::from_secs(5),
+            retries: 0,
+            header_assertion: None,
+            upload: None,
+            watch_content: None,
+            maintenance: None,
+        }
+    }
+}
+
+/// How urgent a check's failures are, from `priority=` in the URL file. Notifiers use
+/// it to decide what to send right away; the five levels match Opsgenie's P1-P5.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Critical,
+    High,
+    #[default]
+    Medium,
+    Low,
+    Info,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Critical => "critical",
+            Priority::High => "high",
+            Priority::Medium => "medium",
+            Priority::Low => "low",
+            Priority::Info => "info",
+        }
+    }
+
+    /// Accepts the level names or `p1`..`p5`, case-insensitive.
+    pub fn parse(s: &str) -> Result<Priority, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "critical" | "p1" => Ok(Priority::Critical),
+            "high" | "p2" => Ok(Priority::High),
+            "medium" | "p3" => Ok(Priority::Medium),
+            "low" | "p4" => Ok(Priority::Low),
+            "info" | "p5" => Ok(Priority::Info),
+            other => Err(format!("unknown priority '{}' (expected critical, high, medium, low, info or p1-p5)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single unit of work for the checker.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CheckRequest {
+    pub url: String,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+}
+
+impl CheckRequest {
+    pub fn new(url: impl Into<String>) -> Self {
+        CheckRequest { url: url.into(), priority: Priority::default(), tags: Vec::new() }
+    }
+
+    /// Parses a URL file line: the URL (or `exec://` command) followed by optional
+    /// `priority=<level>` and `tags=<a,b>` settings, e.g.
+    /// `https://pay.example.com priority=critical tags=payments,api`.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+        let mut request = CheckRequest::new("");
+        // Settings are only recognized at the end so exec:// commands keep their arguments
+        while let Some(token) = tokens.last() {
+            if let Some(value) = token.strip_prefix("priority=") {
+                request.priority = Priority::parse(value)?;
+            } else if let Some(value) = token.strip_prefix("tags=") {
+                request.tags = value.split(',')
+                    .map(|tag| tag.trim().to_string())
+                    .filter(|tag| !tag.is_empty())
+                    .collect();
+            } else {
+                break;
+            }
+            tokens.pop();
+        }
+        if tokens.is_empty() {
+            return Err(format!("missing URL in '{}'", line.trim()));
+        }
+        request.url = tokens.join(" ");
+        Ok(request)
+    }
+}
+
+impl From<String> for CheckRequest {
+    fn from(url: String) -> Self {
+        CheckRequest::new(url)
+    }
+}
+
+impl From<&str> for CheckRequest {
+    fn from(url: &str) -> Self {
+        CheckRequest::new(url)
+    }
+}
+
+/// Callback invoked on the worker thread for every completed check.
+pub type ResultHook = Arc<dyn Fn(&CheckResult) + Send + Sync>;
+
+/// Runs checks on a fixed pool of worker threads sharing one HTTP backend.
+/// On `wasm` targets, which have no threads, checks run one at a time on the caller's thread.
+#[derive(Clone)]
+pub struct Checker {
+    plugins: Arc<PluginRegistry>,
+    config: Arc<Config>,
+    workers: usize,
+    hooks: Arc<Vec<ResultHook>>,
+}
+
+impl std::fmt::Debug for Checker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Checker")
+            .field("config", &self.config)
+            .field("plugins", &self.plugins)
+            .field("workers", &self.workers)
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+impl Checker {
+    /// Starts a [`CheckerBuilder`] with the CLI defaults (5 s timeout, no retries,
+    /// one worker per logical CPU).
+    pub fn builder() -> CheckerBuilder {
+        CheckerBuilder::new()
+    }
+
+    /// Creates a checker using the native reqwest backend.
+    #[cfg(feature = "native")]
+    pub fn new(config: Config, workers: usize) -> Result<Self, String> {
+        let backend = ReqwestBackend::new(config.timeout)?;
+        Checker::with_backend(config, workers, Arc::new(backend))
+    }
+
+    /// Creates a checker sending HTTP requests through `backend`, which is
+    /// responsible for applying `config.timeout`.
+    pub fn with_backend(config: Config, workers: usize, backend: Arc<dyn HttpBackend>) -> Result<Self, String> {
+        if workers == 0 {
+            return Err("workers must be at least 1".to_string());
+        }
+        let mut plugins = PluginRegistry::new();
+        plugins.register(Arc::new(HttpPlugin::new(backend)));
+        #[cfg(not(target_family = "wasm"))]
+        plugins.register(Arc::new(plugins::exec::ExecPlugin));
+        Ok(Checker {
+            plugins: Arc::new(plugins),
+            config: Arc::new(config),
+            workers,
+            hooks: Arc::new(Vec::new()),
+        })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Checks every URL and returns the results in completion order.
+    pub fn check_all<I>(&self, urls: I) -> Vec<CheckResult>
+    where
+        I: IntoIterator,
+        I::Item: Into<CheckRequest>,
+    {
+        self.run(urls.into_iter().map(Into::into).collect(), |_| {})
+    }
+
+    /// Checks every request on the worker pool, calling `on_result` as each one
+    /// completes (in completion order), and returns all results in that order.
+    pub fn run<F>(&self, requests: Vec<CheckRequest>, mut on_result: F) -> Vec<CheckResult>
+    where
+        F: FnMut(&CheckResult),
+    {
+        let mut results: Vec<WebsiteStatus> = Vec::with_capacity(requests.len());
+        for status in self.check_iter(requests) {
+            on_result(&status);
+            results.push(status);
+        }
+        results
+    }
+
+    /// Starts checking every URL on the worker pool and yields results as they
+    /// complete. Dropping the iterator early discards the URLs not yet started.
+    pub fn check_iter<I>(&self, urls: I) -> CheckIter
+    where
+        I: IntoIterator,
+        I::Item: Into<CheckRequest>,
+    {
+        let jobs: VecDeque<CheckRequest> = urls.into_iter().map(Into::into).collect();
+        let num_total_jobs = jobs.len();
+        let jobs_queue = Arc::new(Mutex::new(jobs));
+
+        #[cfg(target_family = "wasm")]
+        {
+            CheckIter {
+                jobs_queue,
+                checker: self.clone(),
+                remaining: num_total_jobs,
+            }
+        }
+
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let (result_tx, result_rx): (Sender<WebsiteStatus>, Receiver<WebsiteStatus>) = channel();
+
+            let mut worker_handles = Vec::new();
+            for worker_id in 0..self.workers.min(num_total_jobs) {
+                let jobs_queue_clone = Arc::clone(&jobs_queue);
+                let result_tx_clone = result_tx.clone();
+                let checker_clone = self.clone();
+
+                let handle = thread::spawn(move || {
+                    loop {
+                        let request: CheckRequest = match jobs_queue_clone.lock() {
+                            Ok(mut queue_guard) => {
+                                if let Some(request) = queue_guard.pop_front() { request } else { break; }
+                            }
+                            Err(p) => { tracing::error!(worker = worker_id, error = %p, "job queue mutex poisoned"); break; }
+                        };
+
+                        let status_to_send = checker_clone.check_one(&request);
+                        if result_tx_clone.send(status_to_send).is_err() { break; }
+                    }
+                });
+                worker_handles.push(handle);
+            }
+
+            CheckIter {
+                jobs_queue,
+                result_rx,
+                worker_handles,
+                remaining: num_total_jobs,
+            }
+        }
+    }
+
+    pub fn plugins(&self) -> &PluginRegistry {
+        &self.plugins
+    }
+
+    // Runs one check on the current thread with the plugin for its scheme, then the result hooks
+    fn check_one(&self, request: &CheckRequest) -> CheckResult {
+        let mut status = match self.plugins.for_url(&request.url) {
+            Ok(plugin) => plugin.check(request, &self.config),
+            Err(e) => WebsiteStatus {
+                url: request.url.clone(),
+                action_status: Err(e),
+                response_time: Duration::ZERO,
+                timestamp: SystemTime::now(),
+                upload: None,
+                priority: Priority::default(),
+                tags: Vec::new(),
+                content_hash: None,
+                maintenance: None,
+            },
+        };
+        status.priority = request.priority;
+        status.tags = request.tags.clone();
+        if let Some(calendar) = &self.config.maintenance {
+            status.maintenance = calendar.active(&status.url, &status.tags, status.timestamp).map(|w| w.summary.clone());
+        }
+        for hook in self.hooks.iter() {
+            hook(&status);
+        }
+        status
+    }
+}
+
+/// Blocking iterator over results in completion order, returned by
+/// [`Checker::check_iter`].
+pub struct CheckIter {
+    jobs_queue: Arc<Mutex<VecDeque<CheckRequest>>>,
+    #[cfg(not(target_family = "wasm"))]
+    result_rx: Receiver<WebsiteStatus>,
+    #[cfg(not(target_family = "wasm"))]
+    worker_handles: Vec<thread::JoinHandle<()>>,
+    #[cfg(target_family = "wasm")]
+    checker: Checker,
+    remaining: usize,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl CheckIter {
+    fn join_workers(&mut self) {
+        for (i, handle) in self.worker_handles.drain(..).enumerate() {
+            if handle.join().is_err() { tracing::error!(worker = i, "worker thread panicked"); }
+        }
+    }
+}
+
+impl Iterator for CheckIter {
+    type Item = CheckResult;
+
+    #[cfg(not(target_family = "wasm"))]
+    fn next(&mut self) -> Option<CheckResult> {
+        if self.remaining == 0 {
+            self.join_workers();
+            return None;
+        }
+        match self.result_rx.recv() {
+            Ok(status) => {
+                self.remaining -= 1;
+                Some(status)
+            }
+            Err(_) => {
+                // Every worker exited without finishing the queue (e.g. a panic)
+                self.remaining = 0;
+                self.join_workers();
+                None
+            }
+        }
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn next(&mut self) -> Option<CheckResult> {
+        let request = self.jobs_queue.lock().ok()?.pop_front()?;
+        self.remaining -= 1;
+        Some(self.checker.check_one(&request))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+impl Drop for CheckIter {
+    fn drop(&mut self) {
+        // Workers finish their in-flight check, then find the queue empty and exit
+        if let Ok(mut queue_guard) = self.jobs_queue.lock() {
+            queue_guard.clear();
+        }
+    }
+}
+
+/// Builder for [`Checker`], e.g.
+/// `Checker::builder().timeout(Duration::from_secs(10)).workers(8).assert_header("Server", "nginx").build()`.
+#[derive(Clone)]
+pub struct CheckerBuilder {
+    config: Config,
+    workers: usize,
+    header_assertion: Option<(String, String)>,
+    hooks: Vec<ResultHook>,
+    backend: Option<Arc<dyn HttpBackend>>,
+    plugins: Vec<Arc<dyn CheckPlugin>>,
+}
+
+impl Default for CheckerBuilder {
+    fn default() -> Self {
+        CheckerBuilder::new()
+    }
+}
+
+impl CheckerBuilder {
+    pub fn new() -> Self {
+        CheckerBuilder {
+            config: Config::default(),
+            workers: thread::available_parallelism().map_or(2, |nz| nz.get()),
+            header_assertion: None,
+            hooks: Vec::new(),
+            backend: None,
+            plugins: Vec::new(),
+        }
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.config.retries = retries;
+        self
+    }
+
+    /// Requires the header `name` (case-insensitive) to equal `value` (case-sensitive).
+    pub fn assert_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.header_assertion = Some((name.into(), value.into()));
+        self
+    }
+
+    /// Turns every check into an upload check sending `size` generated bytes with `method`.
+    pub fn upload(mut self, method: HttpMethod, size: u64) -> Self {
+        self.config.upload = Some(UploadSpec { method, size });
+        self
+    }
+
+    /// Fingerprints the body of every successful (2xx) HTTP response into
+    /// `content_hash`; with a `selector`, only the HTML of the elements it matches.
+    pub fn watch_content(mut self, selector: Option<String>) -> Self {
+        self.config.watch_content = Some(ContentWatch { selector });
+        self
+    }
+
+    /// Marks results of checks that fall inside one of the calendar's events with
+    /// the event's summary in `maintenance`.
+    pub fn maintenance(mut self, calendar: MaintenanceCalendar) -> Self {
+        self.config.maintenance = Some(calendar);
+        self
+    }
+
+    /// Registers a callback run for every result, on the worker thread that produced
+    /// it, before the result is handed to the caller. Hooks run in registration order.
+    pub fn on_result<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&CheckResult) + Send + Sync + 'static,
+    {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Sends requests through a custom transport instead of the native reqwest
+    /// client; required when the `native` feature is disabled (e.g. on wasm).
+    pub fn backend(mut self, backend: Arc<dyn HttpBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Registers a check plugin for its URL schemes, replacing any built-in
+    /// handler for the same scheme (the HTTP plugin handles `http`/`https`).
+    pub fn plugin(mut self, plugin: Arc<dyn CheckPlugin>) -> Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    pub fn build(self) -> Result<Checker, String> {
+        let mut config = self.config;
+        if config.timeout.is_zero() {
+            return Err("timeout must be greater than zero".to_string());
+        }
+        if let Some((name, value)) = self.header_assertion {
+            let name = name.trim().to_lowercase();
+            let value = value.trim().to_string();
+            if name.is_empty() || value.is_empty() {
+                return Err("header assertion name and value cannot be empty".to_string());
+            }
+            config.header_assertion = Some((name, value));
+        }
+        if let Some(upload) = &config.upload {
+            if upload.method == HttpMethod::Get {
+                return Err(format!("upload method must be POST or PUT, got {}", upload.method));
+            }
+            if upload.size == 0 {
+                return Err("upload size must be at least 1 byte".to_string());
+            }
+        }
+        if let Some(selector) = config.watch_content.as_ref().and_then(|watch| watch.selector.as_deref()) {
+            content::validate_selector(selector)?;
+        }
+        let backend: Arc<dyn HttpBackend> = match self.backend {
+            Some(backend) => backend,
+            #[cfg(feature = "native")]
+            None => Arc::new(ReqwestBackend::new(config.timeout)?),
+            #[cfg(not(feature = "native"))]
+            None => return Err("no HTTP backend configured: enable the `native` feature or call backend()".to_string()),
+        };
+        let mut checker = Checker::with_backend(config, self.workers, backend)?;
+        let registry = Arc::make_mut(&mut checker.plugins);
+        for plugin in self.plugins {
+            registry.register(plugin);
+        }
+        checker.hooks = Arc::new(self.hooks);
+        Ok(checker)
+    }
+}
+
+// Struct for round statistics (Bonus Feature)
+#[derive(Debug, Default)]
+pub struct RoundStats {
+    pub min_time: Option<Duration>,
+    pub max_time: Option<Duration>,
+    pub total_time: Duration,
+    pub successful_checks: u64,
+    pub failed_checks: u64,
+    pub upload_bytes: u64,
+    pub upload_time: Duration,
+    pub processing_time: Duration,
+    pub upload_checks: u64,
+    pub response_times: Vec<Duration>, // Successful checks, for the percentiles
+    pub maintenance_checks: u64,       // Checks inside a --maintenance event
+}
+
+impl RoundStats {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn update(&mut self, status: &WebsiteStatus) {
+        if status.maintenance.is_some() {
+            self.maintenance_checks += 1;
+        }
+        match status.action_status {
+            Ok(_) => {
+                self.successful_checks += 1;
+                if self.successful_checks == 1 {
+                    self.min_time = Some(status.response_time);
+                    self.max_time = Some(status.response_time);
+                } else {
+                    if self.min_time.is_none_or(|min| status.response_time < min) {
+                        self.min_time = Some(status.response_time);
+                    }
+                    if self.max_time.is_none_or(|max| status.response_time > max) {
+                        self.max_time = Some(status.response_time);
+                    }
+                }
+                self.total_time += status.response_time;
+                self.response_times.push(status.response_time);
+                if let Some(upload) = &status.upload {
+                    self.upload_checks += 1;
+                    self.upload_bytes += upload.bytes;
+                    self.upload_time += upload.upload_time;
+                    self.processing_time += upload.processing_time;
+                }
+            }
+            Err(_) => {
+                self.failed_checks += 1;
+            }
+        }
+    }
+
+    /// Prints the summary to stdout; `color` highlights the success and failure counts.
+    pub fn print_summary(&self, color: bool) {
+        println!("\n--- Round Summary ---");
+        let total_attempted = self.successful_checks + self.failed_checks;
+        println!("Total URLs Attempted: {}", total_attempted);
+        println!("Successful Checks: {}", paint(&self.successful_checks.to_string(), GREEN, color && self.successful_checks > 0));
+        println!("Failed Checks: {}", paint(&self.failed_checks.to_string(), RED, color && self.failed_checks > 0));
+        if self.maintenance_checks > 0 {
+            println!("Checks During Maintenance: {}", paint(&self.maintenance_checks.to_string(), YELLOW, color));
+        }
+
+        if self.successful_checks > 0 {
+            if let Some(min) = self.min_time {
+                println!("Min Response Time (successful): {} ms", min.as_millis());
+            }
+            if let Some(max) = self.max_time {
+                println!("Max Response Time (successful): {} ms", max.as_millis());
+            }
+            if self.total_time > Duration::ZERO {
+                let avg_time_ms = self.total_time.as_millis() as f64 / self.successful_checks as f64;
+                println!("Average Response Time (successful): {:.2} ms", avg_time_ms);
+            }
+            if let Some(percentiles) = Percentiles::from_samples(self.response_times.iter().copied()) {
+                println!("Response Time Percentiles (successful): {}", percentiles);
+            }
+            if let Some(std_dev) = std_dev(&self.response_times) {
+                println!("Response Time Std Dev (successful): {:.2} ms", std_dev.as_secs_f64() * 1000.0);
+            }
+            if self.upload_checks > 0 {
+                let secs = self.upload_time.as_secs_f64();
+                let throughput = if secs > 0.0 { self.upload_bytes as f64 / secs } else { 0.0 };
+                println!("Average Upload Throughput (successful): {}", format_throughput(throughput));
+                let avg_processing_ms = self.processing_time.as_millis() as f64 / self.upload_checks as f64;
+                println!("Average Server Processing Time (successful): {:.2} ms", avg_processing_ms);
+            }
+        } else if total_attempted > 0 {
+            println!("No successful checks to calculate response time statistics.");
+        }
+        println!("---------------------\n");
+    }
+}
+
+/// Default `--latency-change` threshold, in percent.
+pub const DEFAULT_LATENCY_CHANGE_PERCENT: f64 = 50.0;
+
+// Smaller latency swings are noise however large they are relative to a fast check
+const MIN_LATENCY_CHANGE: Duration = Duration::from_millis(10);
+
+/// What changed from one round to the next, printed after the round summary in
+/// `--period` mode. URLs only in one of the two rounds are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct RoundDelta {
+    /// URLs that succeeded last round and failed this one, with the error.
+    pub newly_failed: Vec<(String, String)>,
+    pub newly_recovered: Vec<String>,
+    /// Successful in both rounds with a response time that changed by more than the
+    /// threshold: `(url, previous, current)`.
+    pub latency_changes: Vec<(String, Duration, Duration)>,
+}
+
+impl RoundDelta {
+    pub fn between(previous: &[WebsiteStatus], current: &[WebsiteStatus], latency_change_percent: f64) -> Self {
+        let previous: HashMap<&str, &WebsiteStatus> = previous.iter().map(|s| (s.url.as_str(), s)).collect();
+        let mut delta = RoundDelta::default();
+        for status in current {
+            let Some(before) = previous.get(status.url.as_str()) else { continue };
+            match (&before.action_status, &status.action_status) {
+                (Ok(_), Err(e)) => delta.newly_failed.push((status.url.clone(), e.clone())),
+                (Err(_), Ok(_)) => delta.newly_recovered.push(status.url.clone()),
+                (Ok(_), Ok(_)) => {
+                    let (old, new) = (before.response_time, status.response_time);
+                    if old.abs_diff(new) < MIN_LATENCY_CHANGE {
+                        continue;
+                    }
+                    let change = percent_change(old, new);
+                    if change.abs() > latency_change_percent {
+                        delta.latency_changes.push((status.url.clone(), old, new));
+                    }
+                }
+                (Err(_), Err(_)) => {}
+            }
+        }
+        delta
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newly_failed.is_empty() && self.newly_recovered.is_empty() && self.latency_changes.is_empty()
+    }
+
+    pub fn print(&self, previous_round: u64) {
+        println!("--- Changes Since Round {} ---", previous_round);
+        if self.is_empty() {
+            println!("No changes.");
+        }
+        for (url, error) in &self.newly_failed {
+            println!("Newly failed: {} ({})", url, error);
+        }
+        for url in &self.newly_recovered {
+            println!("Newly recovered: {}", url);
+        }
+        for (url, old, new) in &self.latency_changes {
+            println!(
+                "Latency changed: {} {} ms -> {} ms ({:+.0}%)",
+                url,
+                old.as_millis(),
+                new.as_millis(),
+                percent_change(*old, *new)
+            );
+        }
+        println!("------------------------------\n");
+    }
+}
+
+fn percent_change(old: Duration, new: Duration) -> f64 {
+    let old_ms = old.as_secs_f64() * 1000.0;
+    if old_ms == 0.0 {
+        return f64::INFINITY;
+    }
+    (new.as_secs_f64() * 1000.0 - old_ms) / old_ms * 100.0
+}
+
+/// Reads URLs from a list file: one per line, blank lines and `#` comments
+/// (whole-line or inline) ignored.
+pub fn read_url_file(path_str: &str) -> Result<Vec<CheckRequest>, String> {
+    let file = File::open(path_str).map_err(|e| format!("Failed to open file {}: {}", path_str, e))?;
+    let reader = io::BufReader::new(file);
+    let mut requests = Vec::new();
+    for (line_number, line_result) in reader.lines().enumerate() {
+        let line = line_result.map_err(|e| format!("Failed to read line from file: {}", e))?;
+        let line_without_comment = if let Some(comment_start) = line.find('#') {
+            if comment_start == 0 { "" } else { &line[..comment_start] }
+        } else { &line };
+        let trimmed_url_part = line_without_comment.trim();
+        if !trimmed_url_part.is_empty() {
+            let request = CheckRequest::parse(trimmed_url_part)
+                .map_err(|e| format!("{}:{}: {}", path_str, line_number + 1, e))?;
+            requests.push(request);
+        }
+    }
+    Ok(requests)
+}
+
+/// Parses a `"Header-Name: Expected Value"` assertion into its trimmed parts.
+pub fn parse_header_assertion(s: &str) -> Result<(String, String), String> {
+    let parts: Vec<&str> = s.splitn(2, ':').collect();
+    if parts.len() == 2 {
+        let name = parts[0].trim();
+        let value = parts[1].trim();
+        if name.is_empty() || value.is_empty() {
+            return Err("Name and Value cannot be empty. Use 'Header-Name: Expected Value'".to_string());
+        }
+        Ok((name.to_string(), value.to_string()))
+    } else {
+        Err("Use 'Header-Name: Expected Value'".to_string())
+    }
+}
+
+/// Parses the upload check method (`POST` or `PUT`, case-insensitive).
+pub fn parse_upload_method(s: &str) -> Result<HttpMethod, String> {
+    match s.to_ascii_uppercase().as_str() {
+        "POST" => Ok(HttpMethod::Post),
+        "PUT" => Ok(HttpMethod::Put),
+        _ => Err(format!("{} (expected POST or PUT)", s)),
+    }
+}
+
+// Parses sizes like "512", "64K", "10M" or "1G" (binary multiples)
+pub fn parse_byte_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.chars().last()?.to_ascii_uppercase() {
+        'K' => (&s[..s.len() - 1], 1024),
+        'M' => (&s[..s.len() - 1], 1024 * 1024),
+        'G' => (&s[..s.len() - 1], 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+    digits.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Parses a duration like `500ms`, `30s`, `5m`, `2h` or `7d`; a bare number is seconds.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, unit) = s.split_at(split);
+    let value: u64 = digits.parse().ok()?;
+    let secs = match unit.trim().to_ascii_lowercase().as_str() {
+        "ms" => return Some(Duration::from_millis(value)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        _ => return None,
+    };
+    value.checked_mul(secs).map(Duration::from_secs)
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp, e.g. `2024-05-14T23:45:00Z`.
+pub fn format_rfc3339_utc(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, secs_of_day / 3600, (secs_of_day % 3600) / 60, secs_of_day % 60
+    )
+}
+
+/// Formats a time for the console: `2024-05-14 23:45:00 UTC`, or with `local` in the
+/// system's time zone, e.g. `2024-05-15 01:45:00 +02:00`. Falls back to UTC where the
+/// time zone is unknown.
+pub fn format_timestamp(time: SystemTime, local: bool) -> String {
+    let offset = if local { local_utc_offset(time) } else { None };
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64 + offset.unwrap_or(0);
+    let (days, secs_of_day) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    let (year, month, day) = civil_from_days(days);
+    let zone = match offset {
+        Some(offset) => format!(
+            "{}{:02}:{:02}",
+            if offset < 0 { '-' } else { '+' },
+            offset.abs() / 3600,
+            offset.abs() % 3600 / 60
+        ),
+        None => "UTC".to_string(),
+    };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}",
+        year, month, day, secs_of_day / 3600, (secs_of_day % 3600) / 60, secs_of_day % 60, zone
+    )
+}
+
+// Seconds east of UTC in the local time zone (TZ, /etc/localtime) at `time`
+#[cfg(all(unix, feature = "native"))]
+fn local_utc_offset(time: SystemTime) -> Option<i64> {
+    let secs = libc::time_t::try_from(time.duration_since(UNIX_EPOCH).ok()?.as_secs()).ok()?;
+    // SAFETY: localtime_r only writes to `tm`, which is plain data
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        return None;
+    }
+    Some(tm.tm_gmtoff as i64)
+}
+
+#[cfg(not(all(unix, feature = "native")))]
+fn local_utc_offset(_time: SystemTime) -> Option<i64> {
+    None
+}
+
+/// Parses a UTC timestamp in the form [`format_rfc3339_utc`] writes (seconds may be
+/// left out), or a plain date such as `2024-05-14`, read as midnight UTC.
+pub fn parse_rfc3339_utc(s: &str) -> Option<SystemTime> {
+    let s = s.trim();
+    let (date, time) = match s.split_once(['T', 't', ' ']) {
+        Some((date, time)) => (date, Some(time.strip_suffix(['Z', 'z'])?)),
+        None => (s, None),
+    };
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+    // RFC 3339 years have four digits, which also keeps `days_from_civil` in range
+    if year > 9999 || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut secs_of_day = 0;
+    if let Some(time) = time {
+        let mut time_parts = time.splitn(3, ':');
+        let hours: u64 = time_parts.next()?.parse().ok()?;
+        let minutes: u64 = time_parts.next()?.parse().ok()?;
+        let seconds: u64 = time_parts.next().map_or(Some(0), |s| s.parse().ok())?;
+        if hours > 23 || minutes > 59 || seconds > 60 {
+            return None;
+        }
+        secs_of_day = hours * 3600 + minutes * 60 + seconds;
+    }
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    let secs = days.checked_mul(86_400)?.checked_add(secs_of_day)?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// (year, month, day) to days since 1970-01-01, the inverse of civil_from_days
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Days since 1970-01-01 to a (year, month, day) date (Howard Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn format_throughput(bytes_per_sec: f64) -> String {
+    if bytes_per_sec >= 1024.0 * 1024.0 {
+        format!("{:.2} MiB/s", bytes_per_sec / (1024.0 * 1024.0))
+    } else if bytes_per_sec >= 1024.0 {
+        format!("{:.2} KiB/s", bytes_per_sec / 1024.0)
+    } else {
+        format!("{:.0} B/s", bytes_per_sec)
+    }
+}
+
+/// Formats a duration for people, to the second: `45s`, `3m 12s`, `2h 5m`, `1d 3h`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, seconds) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Formats a response time for people: `350 ms` below a second, `1.2 s` below a
+/// minute, then as [`format_duration`] does.
+pub fn format_response_time(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{} ms", duration.as_millis())
+    } else if duration < Duration::from_secs(60) {
+        format!("{:.1} s", duration.as_secs_f64())
+    } else {
+        format_duration(duration)
+    }
+}
+
+/// How long before `now` something happened at `time`: `just now`, `42 s ago`,
+/// `3 min ago`, `5 h ago` or `2 d ago`.
+pub fn format_ago(time: SystemTime, now: SystemTime) -> String {
+    let secs = now.duration_since(time).unwrap_or_default().as_secs();
+    match secs {
+        0 => "just now".to_string(),
+        1..=59 => format!("{} s ago", secs),
+        60..=3_599 => format!("{} min ago", secs / 60),
+        3_600..=86_399 => format!("{} h ago", secs / 3_600),
+        _ => format!("{} d ago", secs / 86_400),
+    }
+}
+
+pub fn escape_json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 10);
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes text for HTML element content and double-quoted attributes.
+pub fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Key/value pairs (values already JSON-encoded) shared by the file and compact JSON forms
+fn json_fields(status: &WebsiteStatus) -> Vec<(&'static str, String)> {
+    let status_json_val_str = match &status.action_status {
+        Ok(code) => code.to_string(),
+        Err(e_str) => format!("\"{}\"", escape_json_string(e_str)),
+    };
+    let timestamp_epoch_s = status.timestamp.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut fields = vec![
+        ("url", format!("\"{}\"", escape_json_string(&status.url))),
+        ("status", status_json_val_str),
+        ("responseTimeMs", status.response_time.as_millis().to_string()),
+        ("timestampEpochS", timestamp_epoch_s.to_string()),
+    ];
+    if let Some(upload) = &status.upload {
+        fields.push(("uploadBytes", upload.bytes.to_string()));
+        fields.push(("uploadTimeMs", upload.upload_time.as_millis().to_string()));
+        fields.push(("uploadThroughputBytesPerS", format!("{:.0}", upload.throughput_bytes_per_sec())));
+        fields.push(("serverProcessingMs", upload.processing_time.as_millis().to_string()));
+    }
+    if let Some(hash) = &status.content_hash {
+        fields.push(("contentHash", format!("\"{}\"", hash)));
+    }
+    if let Some(summary) = &status.maintenance {
+        fields.push(("maintenance", format!("\"{}\"", escape_json_string(summary))));
+    }
+    fields
+}
+
+impl WebsiteStatus {
+    /// Single-line JSON object with the same fields as `status.json` entries.
+    pub fn to_json(&self) -> String {
+        let body: Vec<String> = json_fields(self).into_iter()
+            .map(|(key, value)| format!("\"{}\":{}", key, value))
+            .collect();
+        format!("{{{}}}", body.join(","))
+    }
+}
+
+pub fn write_json_output(statuses: &[WebsiteStatus], file_path: &str) -> Result<(), String> {
+    let file = File::create(file_path)
+        .map_err(|e| format!("Failed to create JSON output file {}: {}", file_path, e))?;
+    let mut writer = BufWriter::new(file);
+
+    writer.write_all(b"[\n").map_err(|e| format!("JSON write error: {}", e))?;
+
+    for (i, status) in statuses.iter().enumerate() {
+        writer.write_all(b"  {\n").map_err(|e| format!("JSON write error: {}", e))?;
+
+        let fields = json_fields(status);
+        for (j, (key, value)) in fields.iter().enumerate() {
+            let separator = if j < fields.len() - 1 { "," } else { "" };
+            let field_json = format!("    \"{}\": {}{}\n", key, value, separator);
+            writer.write_all(field_json.as_bytes()).map_err(|e| format!("JSON write error: {}", e))?;
+        }
+
+        writer.write_all(b"  }").map_err(|e| format!("JSON write error: {}", e))?;
+        if i < statuses.len() - 1 {
+            writer.write_all(b",\n").map_err(|e| format!("JSON write error: {}", e))?;
+        } else {
+            writer.write_all(b"\n").map_err(|e| format!("JSON write error: {}", e))?;
+        }
+    }
+
+    writer.write_all(b"]\n").map_err(|e| format!("JSON write error: {}", e))?;
+    writer.flush().map_err(|e| format!("JSON flush error: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+    use std::{fs, process};
+
+    use crate::{CheckResult, HttpBackend, HttpRequest, HttpResponse, Priority};
+
+    /// An [`HttpBackend`] that answers every request by calling its function.
+    pub(crate) struct FakeBackend<F>(pub(crate) F);
+
+    impl<F> HttpBackend for FakeBackend<F>
+    where
+        F: Fn(HttpRequest<'_>) -> Result<HttpResponse, String> + Send + Sync,
+    {
+        fn send(&self, request: HttpRequest<'_>) -> Result<HttpResponse, String> {
+            (self.0)(request)
+        }
+    }
+
+    /// A response with `status` and the given headers (names lowercase) and no body.
+    pub(crate) fn response(status: u16, headers: &[(&str, &str)]) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: headers.iter().map(|(name, value)| (name.to_string(), value.as_bytes().to_vec())).collect(),
+            body: None,
+        }
+    }
+
+    /// A result for `url` checked at `timestamp`, with default priority and no tags.
+    pub(crate) fn result_at(url: &str, action_status: Result<u16, String>, timestamp: SystemTime) -> CheckResult {
+        CheckResult {
+            url: url.to_string(),
+            action_status,
+            response_time: Duration::from_millis(100),
+            timestamp,
+            upload: None,
+            priority: Priority::default(),
+            tags: Vec::new(),
+            content_hash: None,
+            maintenance: None,
+        }
+    }
+
+    /// A result for `url` checked now.
+    pub(crate) fn result(url: &str, action_status: Result<u16, String>) -> CheckResult {
+        result_at(url, action_status, SystemTime::now())
+    }
+
+    /// A request received by a [`TestServer`].
+    #[cfg(feature = "native")]
+    #[derive(Debug, Clone)]
+    pub(crate) struct ReceivedRequest {
+        pub(crate) method: String,
+        /// Path and query string.
+        pub(crate) url: String,
+        pub(crate) headers: Vec<(String, String)>,
+        pub(crate) body: String,
+    }
+
+    #[cfg(feature = "native")]
+    impl ReceivedRequest {
+        /// Value of the header `name` (compared case-insensitively).
+        pub(crate) fn header(&self, name: &str) -> Option<&str> {
+            self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// A local HTTP server for notifier and shipping tests: records every request and
+    /// answers with the status code and body its function returns.
+    #[cfg(feature = "native")]
+    pub(crate) struct TestServer {
+        pub(crate) url: String,
+        requests: std::sync::Arc<std::sync::Mutex<Vec<ReceivedRequest>>>,
+    }
+
+    #[cfg(feature = "native")]
+    impl TestServer {
+        pub(crate) fn start<F>(respond: F) -> TestServer
+        where
+            F: Fn(&ReceivedRequest) -> (u16, String) + Send + 'static,
+        {
+            use std::sync::{Arc, Mutex};
+
+            let server = tiny_http::Server::http("127.0.0.1:0").expect("failed to start a test server");
+            let url = format!("http://{}", server.server_addr());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let received = Arc::clone(&requests);
+            // Serves until the test process exits
+            std::thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let mut body = String::new();
+                    let _ = request.as_reader().read_to_string(&mut body);
+                    let recorded = ReceivedRequest {
+                        method: request.method().to_string(),
+                        url: request.url().to_string(),
+                        headers: request.headers().iter()
+                            .map(|header| (header.field.to_string(), header.value.to_string()))
+                            .collect(),
+                        body,
+                    };
+                    let (status, body) = respond(&recorded);
+                    received.lock().unwrap().push(recorded);
+                    let _ = request.respond(tiny_http::Response::from_string(body).with_status_code(status));
+                }
+            });
+            TestServer { url, requests }
+        }
+
+        /// The requests received so far.
+        pub(crate) fn requests(&self) -> Vec<ReceivedRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    /// An empty directory for the test called `name`, unique to this test run.
+    pub(crate) fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("website-checker-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("failed to create a test directory");
+        dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::testing::{response, result, temp_dir, FakeBackend};
+
+    #[test]
+    fn byte_sizes_take_binary_suffixes() {
+        assert_eq!(parse_byte_size("512"), Some(512));
+        assert_eq!(parse_byte_size("64K"), Some(64 * 1024));
+        assert_eq!(parse_byte_size("10m"), Some(10 * 1024 * 1024));
+        assert_eq!(parse_byte_size(" 1 G "), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_byte_size(""), None);
+        assert_eq!(parse_byte_size("K"), None);
+        assert_eq!(parse_byte_size("1.5M"), None);
+        assert_eq!(parse_byte_size("-1"), None);
+        assert_eq!(parse_byte_size("99999999999G"), None);
+    }
+
+    #[test]
+    fn upload_methods() {
+        assert_eq!(parse_upload_method("post"), Ok(HttpMethod::Post));
+        assert_eq!(parse_upload_method("PUT"), Ok(HttpMethod::Put));
+        assert!(parse_upload_method("GET").is_err());
+    }
+
+    #[test]
+    fn upload_throughput() {
+        let timing = UploadTiming { bytes: 2048, upload_time: Duration::from_millis(500), processing_time: Duration::ZERO };
+        assert_eq!(timing.throughput_bytes_per_sec(), 4096.0);
+        let instant = UploadTiming { upload_time: Duration::ZERO, ..timing };
+        assert_eq!(instant.throughput_bytes_per_sec(), 0.0);
+
+        assert_eq!(format_throughput(512.0), "512 B/s");
+        assert_eq!(format_throughput(1536.0), "1.50 KiB/s");
+        assert_eq!(format_throughput(3.0 * 1024.0 * 1024.0), "3.00 MiB/s");
+    }
+
+    #[test]
+    fn round_stats_count_successful_uploads_only() {
+        let upload = UploadTiming {
+            bytes: 1024,
+            upload_time: Duration::from_millis(40),
+            processing_time: Duration::from_millis(10),
+        };
+        let mut stats = RoundStats::new();
+        for action_status in [Ok(201), Ok(200), Err("timed out".to_string())] {
+            let mut status = result("https://example.com/upload", action_status.clone());
+            status.upload = Some(upload.clone()).filter(|_| action_status.is_ok());
+            stats.update(&status);
+        }
+        stats.update(&result("https://example.com/", Ok(200)));
+        assert_eq!((stats.successful_checks, stats.failed_checks), (3, 1));
+        assert_eq!(stats.upload_checks, 2);
+        assert_eq!(stats.upload_bytes, 2048);
+        assert_eq!(stats.upload_time, Duration::from_millis(80));
+        assert_eq!(stats.processing_time, Duration::from_millis(20));
+    }
+
+    #[test]
+    fn checker_checks_every_url_once() {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&sent);
+        let backend = FakeBackend(move |_: HttpRequest<'_>| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(response(200, &[]))
+        });
+        let checker = Checker::with_backend(Config::default(), 3, Arc::new(backend)).unwrap();
+        let urls: Vec<String> = (0..10).map(|i| format!("https://example.com/{}", i)).collect();
+        let results = checker.check_all(urls.clone());
+        assert_eq!(sent.load(Ordering::SeqCst), 10);
+        let checked: HashSet<String> = results.iter().map(|status| status.url.clone()).collect();
+        assert_eq!(checked, urls.into_iter().collect());
+        assert!(results.iter().all(|status| status.action_status == Ok(200)));
+    }
+
+    #[test]
+    fn checker_needs_a_worker() {
+        let backend = Arc::new(FakeBackend(|_: HttpRequest<'_>| Ok(response(200, &[]))));
+        assert!(Checker::with_backend(Config::default(), 0, backend).is_err());
+    }
+
+    #[test]
+    fn url_files_skip_blank_lines_and_comments() {
+        let path = temp_dir("url-file").join("urls.txt");
+        fs::write(&path, "# sites\nhttps://a.example.com\n\n   \nhttps://b.example.com  # the API\n").unwrap();
+        let requests = read_url_file(path.to_str().unwrap()).unwrap();
+        let urls: Vec<&str> = requests.iter().map(|request| request.url.as_str()).collect();
+        assert_eq!(urls, ["https://a.example.com", "https://b.example.com"]);
+        assert!(read_url_file("/nonexistent/urls.txt").is_err());
+    }
+
+    #[test]
+    fn header_assertions() {
+        assert_eq!(parse_header_assertion(" Server : nginx/1.25 "), Ok(("Server".to_string(), "nginx/1.25".to_string())));
+        assert_eq!(parse_header_assertion("Link: <a>; rel=x:y"), Ok(("Link".to_string(), "<a>; rel=x:y".to_string())));
+        assert!(parse_header_assertion("Server").is_err());
+        assert!(parse_header_assertion("Server:").is_err());
+        assert!(parse_header_assertion(": nginx").is_err());
+    }
+
+    #[test]
+    fn round_stats_track_successful_response_times() {
+        let mut stats = RoundStats::new();
+        for (millis, action_status) in [(300, Ok(200)), (100, Ok(404)), (900, Err("timed out".to_string())), (200, Ok(200))] {
+            let mut status = result("https://example.com", action_status);
+            status.response_time = Duration::from_millis(millis);
+            stats.update(&status);
+        }
+        assert_eq!((stats.successful_checks, stats.failed_checks), (3, 1));
+        assert_eq!(stats.min_time, Some(Duration::from_millis(100)));
+        assert_eq!(stats.max_time, Some(Duration::from_millis(300)));
+        assert_eq!(stats.total_time, Duration::from_millis(600));
+    }
+
+    #[test]
+    fn json_output_escapes_errors() {
+        let mut status = result("https://example.com/\"q\"", Err("bad\nthing".to_string()));
+        status.response_time = Duration::from_millis(1500);
+        status.timestamp = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            status.to_json(),
+            r#"{"url":"https://example.com/\"q\"","status":"bad\nthing","responseTimeMs":1500,"timestampEpochS":1700000000}"#
+        );
+
+        let path = temp_dir("json-output").join("status.json");
+        write_json_output(&[result("https://a.example.com", Ok(200)), status], path.to_str().unwrap()).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("[\n  {\n    \"url\": \"https://a.example.com\",\n    \"status\": 200,\n"));
+        assert!(written.contains("  },\n  {\n"));
+        assert!(written.contains("    \"status\": \"bad\\nthing\",\n"));
+        assert!(written.ends_with("    \"timestampEpochS\": 1700000000\n  }\n]\n"));
+    }
+
+    #[test]
+    fn builder_validates_its_settings() {
+        let backend: Arc<dyn HttpBackend> = Arc::new(FakeBackend(|_: HttpRequest<'_>| Ok(response(200, &[]))));
+        let builder = Checker::builder().backend(backend);
+
+        let checker = builder.clone().timeout(Duration::from_secs(10)).workers(4).retries(2)
+            .assert_header(" Server ", " nginx ")
+            .build()
+            .unwrap();
+        assert_eq!(checker.workers(), 4);
+        assert_eq!(checker.config().timeout, Duration::from_secs(10));
+        assert_eq!(checker.config().retries, 2);
+        assert_eq!(checker.config().header_assertion, Some(("server".to_string(), "nginx".to_string())));
+
+        let error = |builder: CheckerBuilder| builder.build().expect_err("the builder should have failed");
+        assert_eq!(error(builder.clone().timeout(Duration::ZERO)), "timeout must be greater than zero");
+        assert_eq!(error(builder.clone().workers(0)), "workers must be at least 1");
+        assert_eq!(error(builder.clone().assert_header("Server", " ")), "header assertion name and value cannot be empty");
+        assert_eq!(error(builder.clone().upload(HttpMethod::Get, 10)), "upload method must be POST or PUT, got GET");
+        assert_eq!(error(builder.upload(HttpMethod::Post, 0)), "upload size must be at least 1 byte");
+    }
+
+    #[test]
+    fn check_iter_yields_results_as_they_complete() {
+        let backend = FakeBackend(|request: HttpRequest<'_>| {
+            if request.url.ends_with("/slow") {
+                thread::sleep(Duration::from_millis(300));
+            }
+            Ok(response(200, &[]))
+        });
+        let checker = Checker::with_backend(Config::default(), 2, Arc::new(backend)).unwrap();
+        let mut results = checker.check_iter(["https://example.com/slow", "https://example.com/fast"]);
+        assert_eq!(results.size_hint(), (0, Some(2)));
+        assert_eq!(results.next().unwrap().url, "https://example.com/fast");
+        assert_eq!(results.next().unwrap().url, "https://example.com/slow");
+        assert!(results.next().is_none());
+    }
+
+    #[test]
+    fn dropping_check_iter_skips_the_remaining_urls() {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&sent);
+        let backend = FakeBackend(move |_: HttpRequest<'_>| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            Ok(response(200, &[]))
+        });
+        let checker = Checker::with_backend(Config::default(), 1, Arc::new(backend)).unwrap();
+        let first = checker.check_iter((0..50).map(|i| format!("https://example.com/{}", i))).next();
+        assert_eq!(first.unwrap().url, "https://example.com/0");
+        // The worker may have started the second check before the queue was cleared
+        thread::sleep(Duration::from_millis(100));
+        assert!(sent.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn hooks_run_in_order_before_the_caller_sees_the_result() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (first, second) = (Arc::clone(&seen), Arc::clone(&seen));
+        let backend: Arc<dyn HttpBackend> = Arc::new(FakeBackend(|request: HttpRequest<'_>| {
+            if request.url.contains("down") { Err("connection refused".to_string()) } else { Ok(response(200, &[])) }
+        }));
+        let checker = Checker::builder()
+            .backend(backend)
+            .workers(2)
+            .on_result(move |status| first.lock().unwrap().push(format!("first {}", status.url)))
+            .on_result(move |status| second.lock().unwrap().push(format!("second {}", status.url)))
+            .build()
+            .unwrap();
+        let mut delivered = 0;
+        let results = checker.run(vec!["https://up.example.com".into(), "https://down.example.com".into()], |status| {
+            let seen = seen.lock().unwrap();
+            let first = seen.iter().position(|hook| *hook == format!("first {}", status.url)).unwrap();
+            assert_eq!(seen[first + 1], format!("second {}", status.url));
+            delivered += 1;
+        });
+        assert_eq!((delivered, results.len()), (2, 2));
+        assert_eq!(seen.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn url_file_lines_take_priority_and_tags() {
+        let request = CheckRequest::parse("https://pay.example.com  priority=critical tags=payments,,api,").unwrap();
+        assert_eq!(request.url, "https://pay.example.com");
+        assert_eq!(request.priority, Priority::Critical);
+        assert_eq!(request.tags, ["payments", "api"]);
+
+        let request = CheckRequest::parse("https://pay.example.com tags=payments,api priority=P2").unwrap();
+        assert_eq!((request.priority, request.tags), (Priority::High, vec!["payments".to_string(), "api".to_string()]));
+
+        // Settings are only read at the end, so commands keep arguments that look like them
+        let request = CheckRequest::parse("exec://./check.sh priority=x -w 80 tags=web").unwrap();
+        assert_eq!(request.url, "exec://./check.sh priority=x -w 80");
+        assert_eq!(request.priority, Priority::Medium);
+
+        assert_eq!(CheckRequest::parse("priority=high").unwrap_err(), "missing URL in 'priority=high'");
+        assert!(CheckRequest::parse("https://example.com priority=urgent").is_err());
+    }
+
+    #[test]
+    fn priorities() {
+        assert_eq!(Priority::parse(" Critical "), Ok(Priority::Critical));
+        assert_eq!(Priority::parse("p5"), Ok(Priority::Info));
+        assert!(Priority::parse("p6").is_err());
+        assert!(Priority::Critical < Priority::Low);
+        assert_eq!(Priority::default().to_string(), "medium");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration(" 30s "), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2H"), Some(Duration::from_secs(7_200)));
+        assert_eq!(parse_duration("7d"), Some(Duration::from_secs(604_800)));
+        for invalid in ["", "m", "5 minutes", "1.5h", "-5m", "5w", "99999999999999999999d"] {
+            assert_eq!(parse_duration(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn round_deltas() {
+        let timed = |url: &str, action_status: Result<u16, String>, ms: u64| WebsiteStatus {
+            response_time: Duration::from_millis(ms),
+            ..result(url, action_status)
+        };
+        let previous = [
+            timed("https://a.example.com", Ok(200), 100),
+            timed("https://b.example.com", Err("timed out".to_string()), 100),
+            timed("https://c.example.com", Ok(200), 100),
+            timed("https://d.example.com", Ok(200), 4),
+            timed("https://e.example.com", Ok(200), 100),
+        ];
+        let current = [
+            timed("https://a.example.com", Err("HTTP 500".to_string()), 100),
+            timed("https://b.example.com", Ok(200), 100),
+            timed("https://c.example.com", Ok(200), 151),
+            timed("https://d.example.com", Ok(200), 12),
+            timed("https://e.example.com", Ok(200), 150),
+            timed("https://new.example.com", Err("timed out".to_string()), 100),
+        ];
+        let delta = RoundDelta::between(&previous, &current, DEFAULT_LATENCY_CHANGE_PERCENT);
+        assert_eq!(delta.newly_failed, [("https://a.example.com".to_string(), "HTTP 500".to_string())]);
+        assert_eq!(delta.newly_recovered, ["https://b.example.com"]);
+        assert_eq!(delta.latency_changes, [("https://c.example.com".to_string(), Duration::from_millis(100), Duration::from_millis(151))]);
+        assert!(RoundDelta::between(&current, &current, DEFAULT_LATENCY_CHANGE_PERCENT).is_empty());
+
+        assert_eq!(percent_change(Duration::from_millis(200), Duration::from_millis(50)), -75.0);
+        assert_eq!(percent_change(Duration::ZERO, Duration::from_millis(50)), f64::INFINITY);
+    }
+
+    #[test]
+    fn rfc3339_timestamps() {
+        let may_14 = UNIX_EPOCH + Duration::from_secs(1_715_730_300);
+        assert_eq!(parse_rfc3339_utc("2024-05-14T23:45:00Z"), Some(may_14));
+        assert_eq!(parse_rfc3339_utc(" 2024-05-14 23:45z "), Some(may_14));
+        assert_eq!(parse_rfc3339_utc("2024-05-14"), Some(may_14 - Duration::from_secs(85_500)));
+        assert_eq!(format_rfc3339_utc(may_14), "2024-05-14T23:45:00Z");
+        assert_eq!(format_rfc3339_utc(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(parse_rfc3339_utc("2024-02-29").map(format_rfc3339_utc).as_deref(), Some("2024-02-29T00:00:00Z"));
+        assert_eq!(parse_rfc3339_utc("2000-02-29").map(format_rfc3339_utc).as_deref(), Some("2000-02-29T00:00:00Z"));
+        assert_eq!(parse_rfc3339_utc("9999-12-31T23:59:59Z").map(format_rfc3339_utc).as_deref(), Some("9999-12-31T23:59:59Z"));
+        for invalid in ["2023-02-29", "1900-02-29", "2024-13-01", "2024-04-31", "2024-05-14T23:45:00", "2024-05-14T24:00:00Z", "1969-12-31", "May 14", "99999999999999-01-01", "10000-01-01"] {
+            assert_eq!(parse_rfc3339_utc(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn human_readable_times() {
+        let ms = Duration::from_millis;
+        assert_eq!(format_duration(Duration::from_secs(59)), "59s");
+        assert_eq!(format_duration(Duration::from_secs(3_599)), "59m 59s");
+        assert_eq!(format_duration(Duration::from_secs(7_260)), "2h 1m");
+        assert_eq!(format_duration(Duration::from_secs(90_000)), "1d 1h");
+        assert_eq!(format_response_time(ms(999)), "999 ms");
+        assert_eq!(format_response_time(ms(1_250)), "1.2 s");
+        assert_eq!(format_response_time(ms(125_000)), "2m 5s");
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_715_730_300);
+        let ago = |secs| format_ago(now - Duration::from_secs(secs), now);
+        assert_eq!([ago(0), ago(42), ago(180), ago(18_000), ago(200_000)], ["just now", "42 s ago", "3 min ago", "5 h ago", "2 d ago"]);
+        assert_eq!(format_ago(now + Duration::from_secs(5), now), "just now");
+
+        assert_eq!(format_timestamp(now, false), "2024-05-14 23:45:00 UTC");
+        // Depends on the machine's time zone, but always has the same layout
+        let local = format_timestamp(now, true);
+        assert!(local == "2024-05-14 23:45:00 UTC" || local.len() == "2024-05-14 23:45:00 +00:00".len(), "{}", local);
+    }
+}
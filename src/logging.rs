@@ -0,0 +1,216 @@
+//! Diagnostics (`--log-format`, `--log-level`, `--log-target`): worker problems,
+//! retries, failed notifications and URL state changes go through `tracing`, to
+//! stderr as text or one JSON object per line, or to the system logger.
+
+use std::io;
+
+use tracing::level_filters::LevelFilter;
+#[cfg(unix)]
+use tracing_subscriber::layer::SubscriberExt;
+#[cfg(unix)]
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Name the checker logs under in syslog and the journal.
+pub const IDENTIFIER: &str = "website-status-checker";
+
+/// What `--log-format` asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(s: &str) -> Result<LogFormat, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("unknown log format '{}' (expected text or json)", other)),
+        }
+    }
+}
+
+/// Where `--log-target` sends log events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogTarget {
+    #[default]
+    Stderr,
+    /// The local syslog daemon, through `/dev/log`.
+    Syslog,
+    /// systemd-journald, with the event's fields as journal fields.
+    Journald,
+}
+
+impl LogTarget {
+    pub fn parse(s: &str) -> Result<LogTarget, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "stderr" => Ok(LogTarget::Stderr),
+            "syslog" => Ok(LogTarget::Syslog),
+            "journald" => Ok(LogTarget::Journald),
+            other => Err(format!("unknown log target '{}' (expected stderr, syslog or journald)", other)),
+        }
+    }
+
+    /// The least severe level logged without `--log-level`: the system logger does its
+    /// own filtering, so it also gets recoveries and retries.
+    pub fn default_level(self) -> LevelFilter {
+        match self {
+            LogTarget::Stderr => LevelFilter::WARN,
+            LogTarget::Syslog | LogTarget::Journald => LevelFilter::INFO,
+        }
+    }
+}
+
+/// Parses a `--log-level`: `off`, `error`, `warn`, `info`, `debug` or `trace`.
+pub fn parse_level(s: &str) -> Result<LevelFilter, String> {
+    s.parse::<LevelFilter>()
+        .map_err(|_| format!("unknown log level '{}' (expected off, error, warn, info, debug or trace)", s))
+}
+
+/// Installs the global subscriber sending events at `level` or above to `target`.
+/// `format` and `ansi` (coloring the level of text logs) only apply to stderr.
+pub fn init(target: LogTarget, format: LogFormat, level: LevelFilter, ansi: bool) -> Result<(), String> {
+    let result = match target {
+        LogTarget::Stderr => {
+            let builder = tracing_subscriber::fmt()
+                .with_writer(io::stderr)
+                .with_max_level(level)
+                .with_target(false);
+            match format {
+                LogFormat::Text => builder.with_ansi(ansi).try_init(),
+                LogFormat::Json => builder.json().flatten_event(true).try_init(),
+            }
+            .map_err(|e| e.to_string())
+        }
+        #[cfg(unix)]
+        LogTarget::Syslog => {
+            let layer = syslog::SyslogLayer::connect(IDENTIFIER)?;
+            tracing_subscriber::registry().with(level).with(layer).try_init().map_err(|e| e.to_string())
+        }
+        #[cfg(unix)]
+        LogTarget::Journald => {
+            let layer = tracing_journald::layer()
+                .map_err(|e| format!("Failed to connect to journald: {}", e))?
+                .with_syslog_identifier(IDENTIFIER.to_string());
+            tracing_subscriber::registry().with(level).with(layer).try_init().map_err(|e| e.to_string())
+        }
+        #[cfg(not(unix))]
+        LogTarget::Syslog | LogTarget::Journald => {
+            return Err("--log-target syslog and journald are only supported on Unix".to_string());
+        }
+    };
+    result.map_err(|e| format!("Failed to set up logging: {}", e))
+}
+
+#[cfg(unix)]
+mod syslog {
+    use std::fmt::{self, Write};
+    use std::os::unix::net::UnixDatagram;
+    use std::process;
+
+    use tracing::field::{Field, Visit};
+    use tracing::{Event, Level, Subscriber};
+    use tracing_subscriber::layer::{Context, Layer};
+
+    // Where syslog daemons listen on Linux and on macOS
+    const SOCKETS: [&str; 2] = ["/dev/log", "/var/run/syslog"];
+
+    const FACILITY_DAEMON: u8 = 3;
+
+    /// Sends each event as an RFC 3164 message, `<PRI>ident[pid]: message key=value`;
+    /// the daemon adds the timestamp and host.
+    pub struct SyslogLayer {
+        pub(super) socket: UnixDatagram,
+        pub(super) ident: String,
+        pub(super) pid: u32,
+    }
+
+    impl SyslogLayer {
+        pub fn connect(ident: &str) -> Result<SyslogLayer, String> {
+            let socket = UnixDatagram::unbound().map_err(|e| format!("Failed to connect to syslog: {}", e))?;
+            let mut last_error = None;
+            for path in SOCKETS {
+                match socket.connect(path) {
+                    Ok(()) => return Ok(SyslogLayer { socket, ident: ident.to_string(), pid: process::id() }),
+                    Err(e) => last_error = Some(format!("{}: {}", path, e)),
+                }
+            }
+            Err(format!("Failed to connect to syslog ({})", last_error.unwrap_or_default()))
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for SyslogLayer {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let severity = match *event.metadata().level() {
+                Level::ERROR => 3,
+                Level::WARN => 4,
+                Level::INFO => 6,
+                Level::DEBUG | Level::TRACE => 7,
+            };
+            let mut message = Message::default();
+            event.record(&mut message);
+            let line = format!("<{}>{}[{}]: {}{}", FACILITY_DAEMON * 8 + severity, self.ident, self.pid, message.text, message.fields);
+            // Nowhere left to report a failure to log to
+            let _ = self.socket.send(line.as_bytes());
+        }
+    }
+
+    #[derive(Default)]
+    struct Message {
+        text: String,
+        fields: String,
+    }
+
+    impl Visit for Message {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                let _ = write!(self.text, "{:?}", value);
+            } else {
+                let _ = write!(self.fields, " {}={:?}", field.name(), value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_and_levels() {
+        assert_eq!(LogFormat::parse("JSON"), Ok(LogFormat::Json));
+        assert_eq!(LogFormat::parse("logfmt").unwrap_err(), "unknown log format 'logfmt' (expected text or json)");
+        assert_eq!(parse_level("debug"), Ok(LevelFilter::DEBUG));
+        assert_eq!(parse_level("OFF"), Ok(LevelFilter::OFF));
+        assert!(parse_level("verbose").unwrap_err().starts_with("unknown log level 'verbose'"));
+    }
+
+    #[test]
+    fn targets() {
+        assert_eq!(LogTarget::parse("Journald"), Ok(LogTarget::Journald));
+        assert!(LogTarget::parse("file").is_err());
+        assert_eq!(LogTarget::Stderr.default_level(), LevelFilter::WARN);
+        assert_eq!(LogTarget::Syslog.default_level(), LevelFilter::INFO);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn syslog_messages_carry_the_priority_and_fields() {
+        use std::os::unix::net::UnixDatagram;
+
+        let (socket, daemon) = UnixDatagram::pair().unwrap();
+        let layer = syslog::SyslogLayer { socket, ident: IDENTIFIER.to_string(), pid: 42 };
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            tracing::warn!(url = "https://example.com", round = 3, "URL went down");
+            tracing::info!("URL recovered");
+        });
+        let mut buffer = [0; 256];
+        let mut receive = || {
+            let n = daemon.recv(&mut buffer).unwrap();
+            String::from_utf8_lossy(&buffer[..n]).into_owned()
+        };
+        assert_eq!(receive(), "<28>website-status-checker[42]: URL went down url=\"https://example.com\" round=3");
+        assert_eq!(receive(), "<30>website-status-checker[42]: URL recovered");
+    }
+}
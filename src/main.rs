@@ -1,126 +1,1304 @@
-use std::collections::VecDeque;
+use std::collections::HashSet;
 use std::env;
-use std::fs::File;
-use std::io::{self, BufRead, BufWriter, Write};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
 use std::path::Path;
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-
-// 3.1 WebsiteStatus Structure
-#[derive(Debug, Clone)]
-struct WebsiteStatus {
-    url: String,
-    action_status: Result<u16, String>,
-    response_time: Duration,
-    timestamp: SystemTime,
-}
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime};
 
-// Struct to hold configuration (updated)
-#[derive(Debug, Clone)]
-struct Config {
-    timeout: Duration,
-    retries: u32,
-    header_assertion: Option<(String, String)>, // For --assert-header "Name:Value"
-}
+use tracing::level_filters::LevelFilter;
+use tracing::{info, warn};
+
+use website_status_checker_rust::agent::{validate_agent_name, Agent};
+use website_status_checker_rust::color::{paint, status_color, ColorChoice};
+use website_status_checker_rust::config_file::ConfigFile;
+use website_status_checker_rust::content::validate_selector;
+use website_status_checker_rust::daemon;
+use website_status_checker_rust::domain::{group_results, GroupBy};
+use website_status_checker_rust::history::{ContentChange, ContentTracker, HistoryStore, IncidentTracker};
+use website_status_checker_rust::notify::discord::DiscordNotifier;
+use website_status_checker_rust::notify::email::EmailNotifier;
+use website_status_checker_rust::notify::escalation::Escalator;
+use website_status_checker_rust::notify::gotify::GotifyNotifier;
+use website_status_checker_rust::notify::ntfy::NtfyNotifier;
+use website_status_checker_rust::notify::opsgenie::OpsgenieNotifier;
+use website_status_checker_rust::notify::routing::Selector;
+use website_status_checker_rust::notify::slack::{slack_escape, SlackNotifier};
+use website_status_checker_rust::notify::telegram::{TelegramNotifier, DEFAULT_API_URL as TELEGRAM_API_URL};
+use website_status_checker_rust::notify::template::MessageTemplate;
+use website_status_checker_rust::notify::tracker::{AlertPolicy, AlertTracker, FlapThresholds};
+use website_status_checker_rust::notify::webhook::WebhookNotifier;
+use website_status_checker_rust::notify::{
+    notification_client, ping, Dispatcher, Event, EventKind, RetryPolicy, RoundSummary,
+};
+use website_status_checker_rust::plugins::load_plugin_dir;
+use website_status_checker_rust::progress::ProgressBar;
+use website_status_checker_rust::report::{RangeReport, ReportFormat, SlaReport, TimeRange};
+use website_status_checker_rust::server::{self, MonitorApi};
+use website_status_checker_rust::shipping::{ResultPusher, S3Upload};
+use website_status_checker_rust::state::MonitorState;
+use website_status_checker_rust::systemd::Systemd;
+use website_status_checker_rust::tui::Tui;
+use website_status_checker_rust::watch::WatchTable;
+use website_status_checker_rust::logging::{self, LogFormat, LogTarget};
+use website_status_checker_rust::maintenance::MaintenanceCalendar;
+use website_status_checker_rust::metrics::{summary_json, write_atomic, Metrics};
+use website_status_checker_rust::stats::{
+    apdex_rating, default_buckets, parse_buckets, sparkline, ApdexTracker, Percentiles, UrlStats, UrlStatsRow, LATENCY_WINDOW,
+    SPARKLINE_SAMPLES,
+};
+use website_status_checker_rust::{
+    escape_json_string, format_ago, format_response_time, format_throughput, format_timestamp, parse_byte_size, parse_duration, parse_header_assertion, parse_upload_method, read_url_file, write_json_output,
+    CheckRequest, Checker, HttpMethod, Priority, RoundDelta, RoundStats, WebsiteStatus, DEFAULT_LATENCY_CHANGE_PERCENT,
+};
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3_600);
+const TOP_OFFENDERS: usize = 10;
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8080";
+
+fn main() -> Result<(), String> {
+    let mut args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("report") {
+        return run_report(&args[0], &args[2..]);
+    }
+    if args.get(1).map(String::as_str) == Some("healthcheck") {
+        return run_healthcheck(&args[0], &args[2..]);
+    }
+    if args.get(1).map(String::as_str) == Some("agent") {
+        return run_agent(&args[0], &args[2..]);
+    }
+    // `serve` takes the same options as a --period run, plus the API's; `aggregate` is
+    // `serve` with rounds merged from agents' results instead of checked locally
+    let aggregate_mode = args.get(1).map(String::as_str) == Some("aggregate");
+    let serve_mode = aggregate_mode || args.get(1).map(String::as_str) == Some("serve");
+    if serve_mode {
+        args.remove(1);
+    }
+
+    let mut initial_urls_to_check: Vec<CheckRequest> = Vec::new();
+    let mut file_path: Option<String> = None;
+    let mut config_path: Option<String> = None;
+    let mut num_workers: usize = std::thread::available_parallelism().map_or(2, |nz| nz.get());
+    let mut timeout_seconds: u64 = 5;
+    let mut retries_count: u32 = 0;
+    let mut period_seconds: Option<u64> = None;
+    let mut history_dir: Option<String> = None;
+    let mut state_file: Option<String> = None;
+    let mut history_retention: Option<Duration> = None;
+    let mut latency_change_percent = DEFAULT_LATENCY_CHANGE_PERCENT;
+    let mut offenders_interval: u64 = 10;
+    let mut top_n: Option<usize> = None;
+    let mut apdex_threshold: Option<Duration> = None;
+    let mut tui_mode = false;
+    let mut watch_mode = false;
+    let mut color_choice = ColorChoice::Auto;
+    let mut local_time = false;
+    let mut listen_addr = DEFAULT_LISTEN_ADDR.to_string();
+    let mut api_token: Option<String> = None;
+    let mut quorum: Option<usize> = None;
+    let mut log_format = LogFormat::Text;
+    let mut log_level: Option<LevelFilter> = None;
+    let mut log_target: Option<LogTarget> = None;
+    let mut daemon_mode = false;
+    let mut pid_file: Option<String> = None;
+    let mut log_file: Option<String> = None;
+    let mut group_by: Option<GroupBy> = None;
+    let mut summary_json_path: Option<String> = None;
+    let mut metrics_file: Option<String> = None;
+    let mut push_url: Option<String> = None;
+    let mut push_token: Option<String> = None;
+    let mut upload_location: Option<String> = None;
+    let mut histogram_buckets = default_buckets();
+    let mut header_assertion_str: Option<String> = None;
+    let mut upload_size: Option<u64> = None;
+    let mut upload_method_str: Option<String> = None;
+    let mut watch_content = false;
+    let mut content_selector: Option<String> = None;
+    let mut exec_on_failure: Option<String> = None;
+    let mut exec_on_recovery: Option<String> = None;
+    let mut plugin_dirs: Vec<String> = Vec::new();
+    let mut maintenance_files: Vec<String> = Vec::new();
+    let mut webhook_urls: Vec<String> = Vec::new();
+    let mut webhook_events: Vec<EventKind> = vec![EventKind::Failure, EventKind::Recovery, EventKind::Flapping];
+    let mut webhook_template_path: Option<String> = None;
+    let mut webhook_retries: u32 = RetryPolicy::default().retries;
+    let mut slack_webhooks: Vec<String> = Vec::new();
+    let mut slack_template_path: Option<String> = None;
+    let mut discord_webhooks: Vec<String> = Vec::new();
+    let mut telegram_token: Option<String> = None;
+    let mut telegram_chat_id: Option<String> = None;
+    let mut telegram_api_url: String = TELEGRAM_API_URL.to_string();
+    let mut alert_after: u32 = 1;
+    let mut realert_interval: Option<Duration> = None;
+    let mut flap_thresholds: Option<FlapThresholds> = None;
+    let mut heartbeat_url: Option<String> = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        let arg = &args[i];
+        match arg.as_str() {
+            "--file" => {
+                i += 1;
+                if i < args.len() {
+                    file_path = Some(args[i].clone());
+                } else {
+                    return Err("--file requires an argument".to_string());
+                }
+            }
+            "--config" => {
+                i += 1;
+                if i < args.len() {
+                    config_path = Some(args[i].clone());
+                } else {
+                    return Err("--config requires a file path".to_string());
+                }
+            }
+            "--workers" => {
+                i += 1;
+                if i < args.len() {
+                    num_workers = args[i].parse().map_err(|_| format!("Invalid number for --workers: {}", args[i]))?;
+                    if num_workers == 0 { return Err("--workers must be at least 1".to_string()); }
+                } else {
+                    return Err("--workers requires an argument".to_string());
+                }
+            }
+            "--timeout" => {
+                i += 1;
+                if i < args.len() {
+                    timeout_seconds = args[i].parse().map_err(|_| format!("Invalid number for --timeout: {}", args[i]))?;
+                    if timeout_seconds == 0 { return Err("--timeout must be at least 1 second".to_string()); }
+                } else {
+                    return Err("--timeout requires an argument".to_string());
+                }
+            }
+            "--retries" => {
+                i += 1;
+                if i < args.len() {
+                    retries_count = args[i].parse().map_err(|_| format!("Invalid number for --retries: {}", args[i]))?;
+                } else {
+                    return Err("--retries requires an argument".to_string());
+                }
+            }
+            "--period" => {
+                i += 1;
+                if i < args.len() {
+                    let p_val = args[i].parse().map_err(|_| format!("Invalid number for --period: {}", args[i]))?;
+                    if p_val == 0 { return Err("--period must be at least 1 second".to_string()); }
+                    period_seconds = Some(p_val);
+                } else {
+                    return Err("--period requires an argument".to_string());
+                }
+            }
+            "--latency-change" => {
+                i += 1;
+                if i < args.len() {
+                    let percent: f64 = args[i].trim_end_matches('%').parse()
+                        .map_err(|_| format!("Invalid percentage for --latency-change: {}", args[i]))?;
+                    if !(percent > 0.0 && percent.is_finite()) {
+                        return Err("--latency-change must be a positive percentage".to_string());
+                    }
+                    latency_change_percent = percent;
+                } else {
+                    return Err("--latency-change requires a percentage".to_string());
+                }
+            }
+            "--group-by" => {
+                i += 1;
+                if i < args.len() {
+                    group_by = Some(GroupBy::parse(&args[i]).map_err(|e| format!("Invalid value for --group-by: {}", e))?);
+                } else {
+                    return Err("--group-by requires an argument (host or domain)".to_string());
+                }
+            }
+            "--top" => {
+                i += 1;
+                if i < args.len() {
+                    let n: usize = args[i].parse().map_err(|_| format!("Invalid number for --top: {}", args[i]))?;
+                    if n == 0 { return Err("--top must be at least 1".to_string()); }
+                    top_n = Some(n);
+                } else {
+                    return Err("--top requires an argument".to_string());
+                }
+            }
+            "--apdex-threshold" => {
+                i += 1;
+                if i < args.len() {
+                    let threshold = parse_duration(&args[i])
+                        .ok_or_else(|| format!("Invalid duration for --apdex-threshold: {} (e.g. 500ms)", args[i]))?;
+                    if threshold.is_zero() { return Err("--apdex-threshold must be greater than zero".to_string()); }
+                    apdex_threshold = Some(threshold);
+                } else {
+                    return Err("--apdex-threshold requires a duration such as 500ms".to_string());
+                }
+            }
+            "--offenders-interval" => {
+                i += 1;
+                if i < args.len() {
+                    offenders_interval = args[i].parse().map_err(|_| format!("Invalid number for --offenders-interval: {}", args[i]))?;
+                    if offenders_interval == 0 { return Err("--offenders-interval must be at least 1 round".to_string()); }
+                } else {
+                    return Err("--offenders-interval requires an argument".to_string());
+                }
+            }
+            "--summary-json" => {
+                i += 1;
+                if i < args.len() {
+                    summary_json_path = Some(args[i].clone());
+                } else {
+                    return Err("--summary-json requires a file path".to_string());
+                }
+            }
+            "--metrics-file" => {
+                i += 1;
+                if i < args.len() {
+                    metrics_file = Some(args[i].clone());
+                } else {
+                    return Err("--metrics-file requires a file path".to_string());
+                }
+            }
+            "--push-url" => {
+                i += 1;
+                if i < args.len() {
+                    push_url = Some(args[i].clone());
+                } else {
+                    return Err("--push-url requires a URL".to_string());
+                }
+            }
+            "--push-token" => {
+                i += 1;
+                if i < args.len() {
+                    push_token = Some(args[i].clone());
+                } else {
+                    return Err("--push-token requires a token".to_string());
+                }
+            }
+            "--upload" => {
+                i += 1;
+                if i < args.len() {
+                    upload_location = Some(args[i].clone());
+                } else {
+                    return Err("--upload requires an s3://<bucket>/<prefix> location".to_string());
+                }
+            }
+            "--histogram-buckets" => {
+                i += 1;
+                if i < args.len() {
+                    histogram_buckets = parse_buckets(&args[i])
+                        .map_err(|e| format!("Invalid value for --histogram-buckets: {}", e))?;
+                } else {
+                    return Err("--histogram-buckets requires a list of bounds (e.g. 100ms,250ms,1s)".to_string());
+                }
+            }
+            "--history-dir" => {
+                i += 1;
+                if i < args.len() {
+                    history_dir = Some(args[i].clone());
+                } else {
+                    return Err("--history-dir requires a directory".to_string());
+                }
+            }
+            "--history-retention" => {
+                i += 1;
+                if i < args.len() {
+                    let retention = parse_duration(&args[i])
+                        .ok_or_else(|| format!("Invalid duration for --history-retention: {} (e.g. 30d, 12h)", args[i]))?;
+                    if retention.is_zero() { return Err("--history-retention must be greater than zero".to_string()); }
+                    history_retention = Some(retention);
+                } else {
+                    return Err("--history-retention requires a duration".to_string());
+                }
+            }
+            "--state-file" => {
+                i += 1;
+                if i < args.len() {
+                    state_file = Some(args[i].clone());
+                } else {
+                    return Err("--state-file requires a file path".to_string());
+                }
+            }
+            "--assert-header" => {
+                i += 1;
+                if i < args.len() {
+                    header_assertion_str = Some(args[i].clone());
+                } else {
+                    return Err("--assert-header requires an argument in 'Name: Value' format".to_string());
+                }
+            }
+            "--upload-size" => {
+                i += 1;
+                if i < args.len() {
+                    let size = parse_byte_size(&args[i]).ok_or_else(|| format!("Invalid size for --upload-size: {}", args[i]))?;
+                    if size == 0 { return Err("--upload-size must be at least 1 byte".to_string()); }
+                    upload_size = Some(size);
+                } else {
+                    return Err("--upload-size requires an argument".to_string());
+                }
+            }
+            "--upload-method" => {
+                i += 1;
+                if i < args.len() {
+                    upload_method_str = Some(args[i].clone());
+                } else {
+                    return Err("--upload-method requires an argument (POST or PUT)".to_string());
+                }
+            }
+            "--watch-content" => {
+                watch_content = true;
+            }
+            "--tui" => {
+                tui_mode = true;
+            }
+            "--watch" => {
+                watch_mode = true;
+            }
+            "--log-format" => {
+                i += 1;
+                if i < args.len() {
+                    log_format = LogFormat::parse(&args[i]).map_err(|e| format!("Invalid value for --log-format: {}", e))?;
+                } else {
+                    return Err("--log-format requires an argument (text or json)".to_string());
+                }
+            }
+            "--log-level" => {
+                i += 1;
+                if i < args.len() {
+                    log_level = Some(logging::parse_level(&args[i]).map_err(|e| format!("Invalid value for --log-level: {}", e))?);
+                } else {
+                    return Err("--log-level requires a level (error, warn, info, debug or trace)".to_string());
+                }
+            }
+            "--log-target" => {
+                i += 1;
+                if i < args.len() {
+                    log_target = Some(LogTarget::parse(&args[i]).map_err(|e| format!("Invalid value for --log-target: {}", e))?);
+                } else {
+                    return Err("--log-target requires an argument (stderr, syslog or journald)".to_string());
+                }
+            }
+            "--daemon" => {
+                daemon_mode = true;
+            }
+            "--pid-file" => {
+                i += 1;
+                if i < args.len() {
+                    pid_file = Some(args[i].clone());
+                } else {
+                    return Err("--pid-file requires a path".to_string());
+                }
+            }
+            "--log-file" => {
+                i += 1;
+                if i < args.len() {
+                    log_file = Some(args[i].clone());
+                } else {
+                    return Err("--log-file requires a path".to_string());
+                }
+            }
+            "--listen" if serve_mode => {
+                i += 1;
+                if i < args.len() {
+                    listen_addr = args[i].clone();
+                } else {
+                    return Err("--listen requires an address, e.g. 127.0.0.1:8080".to_string());
+                }
+            }
+            "--quorum" if aggregate_mode => {
+                i += 1;
+                if i < args.len() {
+                    let n: usize = args[i].parse().map_err(|_| format!("Invalid number for --quorum: {}", args[i]))?;
+                    if n == 0 { return Err("--quorum must be at least 1".to_string()); }
+                    quorum = Some(n);
+                } else {
+                    return Err("--quorum requires a number of agents".to_string());
+                }
+            }
+            "--api-token" if serve_mode => {
+                i += 1;
+                if i < args.len() {
+                    api_token = Some(args[i].clone());
+                } else {
+                    return Err("--api-token requires a token".to_string());
+                }
+            }
+            "--local-time" => {
+                local_time = true;
+            }
+            "--color" => {
+                i += 1;
+                if i < args.len() {
+                    color_choice = ColorChoice::parse(&args[i]).map_err(|e| format!("Invalid value for --color: {}", e))?;
+                } else {
+                    return Err("--color requires an argument (auto, always or never)".to_string());
+                }
+            }
+            "--content-selector" => {
+                i += 1;
+                if i < args.len() {
+                    content_selector = Some(args[i].clone());
+                    watch_content = true;
+                } else {
+                    return Err("--content-selector requires a CSS selector".to_string());
+                }
+            }
+            "--exec-on-failure" => {
+                i += 1;
+                if i < args.len() {
+                    exec_on_failure = Some(args[i].clone());
+                } else {
+                    return Err("--exec-on-failure requires a command".to_string());
+                }
+            }
+            "--exec-on-recovery" => {
+                i += 1;
+                if i < args.len() {
+                    exec_on_recovery = Some(args[i].clone());
+                } else {
+                    return Err("--exec-on-recovery requires a command".to_string());
+                }
+            }
+            "--plugin-dir" => {
+                i += 1;
+                if i < args.len() {
+                    plugin_dirs.push(args[i].clone());
+                } else {
+                    return Err("--plugin-dir requires a directory".to_string());
+                }
+            }
+            "--maintenance" => {
+                i += 1;
+                if i < args.len() {
+                    maintenance_files.push(args[i].clone());
+                } else {
+                    return Err("--maintenance requires an iCalendar (.ics) file".to_string());
+                }
+            }
+            "--alert-after" => {
+                i += 1;
+                if i < args.len() {
+                    alert_after = args[i].parse().map_err(|_| format!("Invalid number for --alert-after: {}", args[i]))?;
+                    if alert_after == 0 { return Err("--alert-after must be at least 1".to_string()); }
+                } else {
+                    return Err("--alert-after requires an argument".to_string());
+                }
+            }
+            "--realert-interval" => {
+                i += 1;
+                if i < args.len() {
+                    let interval = parse_duration(&args[i])
+                        .ok_or_else(|| format!("Invalid duration for --realert-interval: {} (e.g. 30m, 2h)", args[i]))?;
+                    if interval.is_zero() { return Err("--realert-interval must be greater than zero".to_string()); }
+                    realert_interval = Some(interval);
+                } else {
+                    return Err("--realert-interval requires a duration".to_string());
+                }
+            }
+            "--flap-detection" => {
+                flap_thresholds.get_or_insert_with(FlapThresholds::default);
+            }
+            "--flap-thresholds" => {
+                i += 1;
+                if i < args.len() {
+                    let parsed = args[i].split_once(',')
+                        .and_then(|(low, high)| Some((low.trim().parse::<f64>().ok()?, high.trim().parse::<f64>().ok()?)));
+                    match parsed {
+                        Some((low, high)) if (0.0..=100.0).contains(&low) && (low..=100.0).contains(&high) => {
+                            flap_thresholds = Some(FlapThresholds { low, high });
+                        }
+                        _ => return Err(format!("Invalid value for --flap-thresholds: {} (expected LOW,HIGH percentages with LOW <= HIGH)", args[i])),
+                    }
+                } else {
+                    return Err("--flap-thresholds requires LOW,HIGH percentages".to_string());
+                }
+            }
+            "--heartbeat-url" => {
+                i += 1;
+                if i < args.len() {
+                    heartbeat_url = Some(args[i].clone());
+                } else {
+                    return Err("--heartbeat-url requires a URL".to_string());
+                }
+            }
+            "--webhook" => {
+                i += 1;
+                if i < args.len() {
+                    webhook_urls.push(args[i].clone());
+                } else {
+                    return Err("--webhook requires a URL".to_string());
+                }
+            }
+            "--webhook-events" => {
+                i += 1;
+                if i < args.len() {
+                    webhook_events = args[i].split(',')
+                        .map(EventKind::parse)
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| format!("Invalid value for --webhook-events: {}", e))?;
+                } else {
+                    return Err("--webhook-events requires a list such as failure,recovery,summary".to_string());
+                }
+            }
+            "--webhook-template" => {
+                i += 1;
+                if i < args.len() {
+                    webhook_template_path = Some(args[i].clone());
+                } else {
+                    return Err("--webhook-template requires a file path".to_string());
+                }
+            }
+            "--webhook-retries" => {
+                i += 1;
+                if i < args.len() {
+                    webhook_retries = args[i].parse().map_err(|_| format!("Invalid number for --webhook-retries: {}", args[i]))?;
+                } else {
+                    return Err("--webhook-retries requires an argument".to_string());
+                }
+            }
+            "--slack-webhook" => {
+                i += 1;
+                if i < args.len() {
+                    slack_webhooks.push(args[i].clone());
+                } else {
+                    return Err("--slack-webhook requires a Slack incoming-webhook URL".to_string());
+                }
+            }
+            "--slack-template" => {
+                i += 1;
+                if i < args.len() {
+                    slack_template_path = Some(args[i].clone());
+                } else {
+                    return Err("--slack-template requires a file path".to_string());
+                }
+            }
+            "--discord-webhook" => {
+                i += 1;
+                if i < args.len() {
+                    discord_webhooks.push(args[i].clone());
+                } else {
+                    return Err("--discord-webhook requires a Discord webhook URL".to_string());
+                }
+            }
+            "--telegram-token" => {
+                i += 1;
+                if i < args.len() {
+                    telegram_token = Some(args[i].clone());
+                } else {
+                    return Err("--telegram-token requires a bot token".to_string());
+                }
+            }
+            "--telegram-chat-id" => {
+                i += 1;
+                if i < args.len() {
+                    telegram_chat_id = Some(args[i].clone());
+                } else {
+                    return Err("--telegram-chat-id requires a chat ID".to_string());
+                }
+            }
+            "--telegram-api-url" => {
+                i += 1;
+                if i < args.len() {
+                    telegram_api_url = args[i].clone();
+                } else {
+                    return Err("--telegram-api-url requires a URL".to_string());
+                }
+            }
+            "-h" | "--help" => {
+                print_usage(&args[0]);
+                return Ok(());
+            }
+            s if s.starts_with("--") => {
+                return Err(format!("Unknown option: {}", s));
+            }
+            s => {
+                initial_urls_to_check.push(CheckRequest::parse(s).map_err(|e| format!("Invalid URL argument: {}", e))?);
+            }
+        }
+        i += 1;
+    }
+
+    if daemon_mode && period_seconds.is_none() {
+        return Err("--daemon requires --period".to_string());
+    }
+    if daemon_mode && (tui_mode || watch_mode) {
+        return Err("--daemon can't be combined with --tui or --watch".to_string());
+    }
+    if pid_file.is_some() && !daemon_mode {
+        return Err("--pid-file requires --daemon".to_string());
+    }
+    if log_file.is_some() && !daemon_mode {
+        return Err("--log-file requires --daemon".to_string());
+    }
+    // A daemon without a log file has nowhere else to log to
+    let log_target = log_target.unwrap_or(if daemon_mode && log_file.is_none() { LogTarget::Syslog } else { LogTarget::Stderr });
+    if log_format == LogFormat::Json && log_target != LogTarget::Stderr {
+        return Err("--log-format only applies to --log-target stderr".to_string());
+    }
+    let log_level = log_level.unwrap_or(log_target.default_level());
+    let ansi = !daemon_mode && color_choice.enabled(io::stderr().is_terminal());
+    logging::init(log_target, log_format, log_level, ansi)?;
+
+    if tui_mode && period_seconds.is_none() {
+        return Err("--tui requires --period".to_string());
+    }
+    if serve_mode && period_seconds.is_none() {
+        return Err(format!("{} requires --period", if aggregate_mode { "aggregate" } else { "serve" }));
+    }
+    // The agent endpoints are only enabled with a token
+    if aggregate_mode && api_token.is_none() {
+        return Err("aggregate requires --api-token for its agents".to_string());
+    }
+    if watch_mode && period_seconds.is_none() {
+        return Err("--watch requires --period".to_string());
+    }
+    if watch_mode && tui_mode {
+        return Err("--watch and --tui can't be combined".to_string());
+    }
+
+    let mut checker_builder = Checker::builder()
+        .workers(num_workers)
+        .timeout(Duration::from_secs(timeout_seconds))
+        .retries(retries_count);
+
+    if let Some(s) = header_assertion_str {
+        let (name, value) = parse_header_assertion(&s).map_err(|e| format!("Invalid format for --assert-header: {}", e))?;
+        checker_builder = checker_builder.assert_header(name, value);
+    }
+
+    match (upload_size, upload_method_str) {
+        (Some(size), method_str) => {
+            let method = match method_str {
+                Some(m) => parse_upload_method(&m).map_err(|e| format!("Invalid value for --upload-method: {}", e))?,
+                None => HttpMethod::Post,
+            };
+            checker_builder = checker_builder.upload(method, size);
+        }
+        (None, Some(_)) => return Err("--upload-method requires --upload-size".to_string()),
+        (None, None) => {}
+    }
+
+    if watch_content {
+        if let Some(selector) = &content_selector {
+            validate_selector(selector).map_err(|e| format!("Invalid value for --content-selector: {}", e))?;
+        }
+        checker_builder = checker_builder.watch_content(content_selector);
+    }
+
+    // Kept for the results aggregate mode merges, which no checker marks
+    let mut maintenance_calendar: Option<MaintenanceCalendar> = None;
+    if !maintenance_files.is_empty() {
+        let mut calendar = MaintenanceCalendar::default();
+        for path in &maintenance_files {
+            calendar.extend(MaintenanceCalendar::load(path)?);
+        }
+        println!("Loaded {} maintenance event(s) from {}", calendar.windows().len(), maintenance_files.join(", "));
+        checker_builder = checker_builder.maintenance(calendar.clone());
+        maintenance_calendar = Some(calendar);
+    }
+
+    for dir in &plugin_dirs {
+        for plugin in load_plugin_dir(dir)? {
+            println!("Loaded check plugin '{}' for schemes: {}", plugin.name(), plugin.schemes().join(", "));
+            checker_builder = checker_builder.plugin(plugin);
+        }
+    }
+
+    if let Some(path_str) = &file_path {
+        initial_urls_to_check.extend(read_url_file(path_str)?);
+    }
+    // A served monitor may start empty and get its URLs through the API
+    if initial_urls_to_check.is_empty() && !serve_mode {
+        print_usage(&args[0]);
+        eprintln!("\nError: No URLs provided via --file or positional arguments.");
+        std::process::exit(2);
+    }
+
+    let mut seen_urls_master = HashSet::new();
+    initial_urls_to_check.retain(|request| seen_urls_master.insert(request.url.clone()));
+
+    let config_file = match &config_path {
+        Some(path) => ConfigFile::load(path)?,
+        None => ConfigFile::default(),
+    };
+
+    // Everything from here on may start threads, which don't survive the fork
+    let _pid_file = if daemon_mode { daemon::daemonize(pid_file.as_deref(), log_file.as_deref())? } else { None };
+    if period_seconds.is_some() {
+        daemon::handle_sigterm();
+    }
+
+    let checker = checker_builder.build()?;
+
+    let mut dispatcher = Dispatcher::new();
+    let client = notification_client()?;
+    if !webhook_urls.is_empty() {
+        let template = webhook_template_path.as_deref()
+            .map(|path| MessageTemplate::load(path, escape_json_string))
+            .transpose()?;
+        let retry = RetryPolicy { retries: webhook_retries, ..RetryPolicy::default() };
+        for url in &webhook_urls {
+            let notifier = WebhookNotifier::new(client.clone(), url.clone(), template.clone(), retry);
+            dispatcher.add(Box::new(notifier), webhook_events.clone());
+        }
+    }
+    let slack_template = slack_template_path.as_deref()
+        .map(|path| MessageTemplate::load(path, slack_escape))
+        .transpose()?;
+    for url in &slack_webhooks {
+        let notifier = SlackNotifier::new(client.clone(), url.clone(), slack_template.clone(), RetryPolicy::default());
+        dispatcher.add(Box::new(notifier), vec![EventKind::Failure, EventKind::Recovery, EventKind::Flapping]);
+    }
+    for (n, slack) in config_file.slack.iter().enumerate() {
+        let url = slack.resolve_webhook_url()
+            .map_err(|e| format!("Invalid [[slack]] #{} in config file: {}", n + 1, e))?;
+        let template = slack.template.as_deref()
+            .map(|path| MessageTemplate::load(path, slack_escape))
+            .transpose()
+            .map_err(|e| format!("Invalid [[slack]] #{} in config file: {}", n + 1, e))?;
+        let notifier = SlackNotifier::new(client.clone(), url, template, RetryPolicy::default());
+        let name = slack.name.clone().unwrap_or_else(|| "slack".to_string());
+        dispatcher.add_named(name, Box::new(notifier), vec![EventKind::Failure, EventKind::Recovery, EventKind::Flapping]);
+    }
+    for url in &discord_webhooks {
+        let notifier = DiscordNotifier::new(client.clone(), url.clone(), RetryPolicy::default());
+        dispatcher.add(Box::new(notifier), vec![EventKind::Failure, EventKind::Recovery, EventKind::Flapping]);
+    }
+    match (telegram_token, telegram_chat_id) {
+        (Some(token), Some(chat_id)) => {
+            let notifier = TelegramNotifier::new(client.clone(), telegram_api_url, token, chat_id, RetryPolicy::default());
+            dispatcher.add(Box::new(notifier), vec![EventKind::Failure, EventKind::Recovery, EventKind::Flapping]);
+        }
+        (Some(_), None) => return Err("--telegram-token requires --telegram-chat-id".to_string()),
+        (None, Some(_)) => return Err("--telegram-chat-id requires --telegram-token".to_string()),
+        (None, None) => {}
+    }
+    for (n, email) in config_file.email.iter().enumerate() {
+        let notifier = EmailNotifier::new(email, RetryPolicy::default())
+            .map_err(|e| format!("Invalid [[email]] #{} in config file: {}", n + 1, e))?;
+        let name = email.name.clone().unwrap_or_else(|| "email".to_string());
+        dispatcher.add_named(name, Box::new(notifier), vec![EventKind::Failure, EventKind::Recovery, EventKind::Flapping, EventKind::Summary]);
+    }
+    for (n, opsgenie) in config_file.opsgenie.iter().enumerate() {
+        let notifier = OpsgenieNotifier::new(client.clone(), opsgenie, RetryPolicy::default())
+            .map_err(|e| format!("Invalid [[opsgenie]] #{} in config file: {}", n + 1, e))?;
+        let name = opsgenie.name.clone().unwrap_or_else(|| "opsgenie".to_string());
+        dispatcher.add_named(name, Box::new(notifier), vec![EventKind::Failure, EventKind::Recovery]);
+    }
+    for (n, ntfy) in config_file.ntfy.iter().enumerate() {
+        let notifier = NtfyNotifier::new(client.clone(), ntfy, RetryPolicy::default())
+            .map_err(|e| format!("Invalid [[ntfy]] #{} in config file: {}", n + 1, e))?;
+        let name = ntfy.name.clone().unwrap_or_else(|| "ntfy".to_string());
+        dispatcher.add_named(name, Box::new(notifier), vec![EventKind::Failure, EventKind::Recovery, EventKind::Flapping]);
+    }
+    for (n, gotify) in config_file.gotify.iter().enumerate() {
+        let notifier = GotifyNotifier::new(client.clone(), gotify, RetryPolicy::default())
+            .map_err(|e| format!("Invalid [[gotify]] #{} in config file: {}", n + 1, e))?;
+        let name = gotify.name.clone().unwrap_or_else(|| "gotify".to_string());
+        dispatcher.add_named(name, Box::new(notifier), vec![EventKind::Failure, EventKind::Recovery, EventKind::Flapping]);
+    }
+    for (n, route) in config_file.route.iter().enumerate() {
+        let context = |e: String| format!("Invalid [[route]] #{} in config file: {}", n + 1, e);
+        let selector = Selector::new(&route.tags, route.priority.as_deref()).map_err(context)?;
+        dispatcher.add_route(&route.channel, selector).map_err(context)?;
+    }
+    let mut escalator = Escalator::new(&config_file.escalation)?;
+
+    if push_token.is_some() && push_url.is_none() {
+        return Err("--push-token requires --push-url".to_string());
+    }
+    let mut pusher = push_url.map(|url| ResultPusher::new(client.clone(), url, push_token, RetryPolicy::default()));
+    let upload = upload_location.as_deref()
+        .map(|location| S3Upload::new(client.clone(), location, RetryPolicy::default()))
+        .transpose()
+        .map_err(|e| format!("Invalid value for --upload: {}", e))?;
+    dispatcher.reserve(escalator.channels())
+        .map_err(|e| format!("Invalid [[escalation]] in config file: {}", e))?;
+
+    let mut alert_tracker = AlertTracker::new(AlertPolicy { alert_after, realert_interval, flap_thresholds });
+    let history = history_dir.as_deref().map(HistoryStore::open).transpose()?;
+    let mut incident_tracker = IncidentTracker::new();
+    let mut content_tracker = ContentTracker::new();
+
+    let mut last_prune: Option<Instant> = None;
+    let mut previous_statuses: Option<Vec<WebsiteStatus>> = None;
+    let mut url_stats = UrlStats::new();
+    let mut apdex = apdex_threshold.map(ApdexTracker::new);
+    let mut metrics = Metrics::new(histogram_buckets.clone());
+
+    let mut round_counter: u64 = 0;
+    if let Some(path) = &state_file {
+        if let Some(state) = MonitorState::load(path)? {
+            round_counter = state.round;
+            alert_tracker.restore(state.alerts);
+            incident_tracker.restore(state.incidents);
+            escalator.restore(state.escalations);
+            content_tracker.restore(state.content);
+            url_stats.restore(state.url_stats);
+        }
+    }
+    let api = if serve_mode {
+        let api = MonitorApi::new(initial_urls_to_check.clone(), period_seconds.unwrap_or_default());
+        server::start(&listen_addr, api.clone(), checker.clone(), api_token.clone())?;
+        println!("Serving the API and dashboard on http://{}/", listen_addr);
+        Some(api)
+    } else {
+        None
+    };
+    // With --tui the dashboard replaces the per-round console output, with --watch a
+    // table redrawn after every round does
+    let tui = if tui_mode { Some(Tui::start(checker.clone(), initial_urls_to_check.clone())?) } else { None };
+    let mut watch = if watch_mode { Some(WatchTable::new(local_time)) } else { None };
+    let console = tui.is_none() && watch.is_none();
+    let color = color_choice.enabled(io::stdout().is_terminal());
+    // Under systemd (Type=notify), startup ends here; the watchdog is pinged after
+    // every round and while waiting for the next one
+    let systemd = if period_seconds.is_some() { Systemd::from_env() } else { None };
+    if let Some(systemd) = &systemd {
+        systemd.ready(&format!("Starting round {}", round_counter + 1));
+    }
+    loop {
+        round_counter += 1;
+        if let Some(tui) = &tui {
+            tui.round_started(round_counter);
+        } else if console && period_seconds.is_some() {
+            println!("--- Starting Round {} ---", round_counter);
+        }
+
+        let current_round_urls = match &api {
+            Some(api) => api.requests(),
+            None => initial_urls_to_check.clone(),
+        };
+        // In aggregate mode, the round is the agents' results merged per URL
+        let merged = match &api {
+            Some(api) if aggregate_mode => {
+                let mut merged = api.agent_round(quorum);
+                if let Some(calendar) = &maintenance_calendar {
+                    for status in &mut merged {
+                        status.maintenance = calendar.active(&status.url, &status.tags, status.timestamp).map(|w| w.summary.clone());
+                    }
+                }
+                Some(merged)
+            }
+            _ => None,
+        };
+        let num_total_jobs_this_round = merged.as_ref().map_or(current_round_urls.len(), Vec::len);
+        if num_total_jobs_this_round == 0 {
+            if period_seconds.is_none() { break; }
+            if merged.is_some() {
+                println!("No results from agents yet. Waiting for next period.");
+            } else {
+                println!("No URLs to check in this round. Waiting for next period if applicable.");
+            }
+            if let Some(seconds) = period_seconds {
+                if wait_for_next_round(Duration::from_secs(seconds), systemd.as_ref()) {
+                    continue;
+                }
+            }
+            break;
+        }
+
+        let mut round_stats = RoundStats::new();
+
+        if console && period_seconds.is_some() {
+            println!("{:<30} | {:<8} | {:<10} | {:<26} | Trend", "URL", "Status", "Time", "Checked at");
+            println!("{}", "-".repeat(86 + 3 + SPARKLINE_SAMPLES));
+        } else if console && round_counter == 1 {
+            println!("{:<30} | {:<8} | {:<10} | Checked at", "URL", "Status", "Time");
+            println!("{}", "-".repeat(86));
+        }
+
+        let mut events: Vec<Event> = Vec::new();
+        let mut content_changes: Vec<ContentChange> = Vec::new();
+        let mut progress = if tui.is_none() { ProgressBar::start(num_total_jobs_this_round) } else { None };
+        let mut on_result = |status: &WebsiteStatus| {
+            round_stats.update(status);
+            if let Some(api) = &api {
+                api.record(status);
+            }
+            url_stats.record(status);
+            if let Some(apdex) = &mut apdex {
+                apdex.record(status);
+            }
+            metrics.record(status);
+            if let Some(tui) = &tui {
+                tui.result(status);
+            } else if console {
+                if let Some(progress) = &mut progress {
+                    progress.clear();
+                }
+                // Recent latencies of the URL, including this check
+                let trend = period_seconds.map(|_| sparkline(&url_stats.recent(&status.url, SPARKLINE_SAMPLES)));
+                print_status_row(status, color, local_time, trend.as_deref());
+            }
+            if let Some(progress) = &mut progress {
+                progress.record(status);
+            }
+            if let Some(change) = content_tracker.observe(status) {
+                if let Some(history) = &history {
+                    if let Err(e) = history.record_content_change(&change) {
+                        warn!(error = %e, "failed to record content change");
+                    }
+                }
+                content_changes.push(change);
+            }
+            if let Some(history) = &history {
+                if let Err(e) = history.record_check(status) {
+                    warn!(error = %e, "failed to record check result");
+                }
+                if let Some(update) = incident_tracker.observe(status) {
+                    if let Err(e) = history.record_incident(&update) {
+                        warn!(error = %e, "failed to record incident");
+                    }
+                }
+            }
+            if let Some(event) = alert_tracker.observe(round_counter, status) {
+                if matches!(event, Event::Failure { .. }) && status.priority == Priority::Critical {
+                    dispatcher.dispatch_now(&event);
+                }
+                events.push(event);
+            }
+        };
+        let all_statuses_this_round = match merged {
+            Some(merged) => {
+                merged.iter().for_each(&mut on_result);
+                merged
+            }
+            None => checker.run(current_round_urls, on_result),
+        };
+
+        drop(progress);
+
+        if !all_statuses_this_round.is_empty() {
+            let json_filename = if period_seconds.is_some() {
+                format!("status_round_{}.json", round_counter)
+            } else {
+                "status.json".to_string()
+            };
+            write_json_output(&all_statuses_this_round, &json_filename)?;
+            if console {
+                println!("\nResults for this round written to {}", json_filename);
+            }
+            if let Some(upload) = &upload {
+                let uploaded = fs::read(&json_filename)
+                    .map_err(|e| e.to_string())
+                    .and_then(|body| upload.upload(&json_filename, "application/json", &body));
+                match uploaded {
+                    Ok(()) if console => println!("Uploaded to {}", upload.location(&json_filename)),
+                    Ok(()) => {}
+                    Err(e) => warn!(location = %upload.location(&json_filename), error = %e, "failed to upload round file"),
+                }
+            }
+            if let Some(pusher) = &mut pusher {
+                if let Err(e) = pusher.push(&all_statuses_this_round) {
+                    warn!(url = %pusher.url(), error = %e, "failed to push results");
+                }
+            }
+        } else if num_total_jobs_this_round > 0 && console {
+            println!("\nNo results were successfully processed in this round.");
+        }
+
+        events.push(Event::Summary { round: round_counter, summary: RoundSummary::from(&round_stats) });
+
+        for event in &events {
+            if period_seconds.is_some() {
+                log_state_change(event);
+            }
+            match event {
+                Event::Failure { result, .. } => {
+                    if let Some(cmd) = &exec_on_failure { run_hook_command(cmd, result); }
+                }
+                Event::Recovery { result, .. } => {
+                    if let Some(cmd) = &exec_on_recovery { run_hook_command(cmd, result); }
+                }
+                Event::Flapping { .. } | Event::Summary { .. } => {}
+            }
+        }
+        dispatcher.dispatch(&events);
+        if !escalator.is_empty() {
+            for (channel, escalated) in escalator.escalate(round_counter, &all_statuses_this_round, &events, &alert_tracker) {
+                dispatcher.dispatch_to(&channel, &escalated);
+            }
+        }
+
+        if let Some(watch) = &mut watch {
+            watch.update(&all_statuses_this_round);
+        }
+        if console {
+            round_stats.print_summary(color);
+            if let Some(by) = group_by {
+                print_groups(by, &all_statuses_this_round);
+            }
+            if let Some(n) = top_n {
+                print_top(n, &all_statuses_this_round, &url_stats);
+            }
+            if let Some(apdex) = &apdex {
+                print_apdex(apdex);
+            }
+            for change in &content_changes {
+                println!(
+                    "Content changed: {} at {} ({} -> {})",
+                    change.url,
+                    format_timestamp(change.timestamp(), local_time),
+                    short_hash(&change.previous_hash),
+                    short_hash(&change.hash)
+                );
+            }
+            if !content_changes.is_empty() {
+                println!();
+            }
+            if period_seconds.is_some() {
+                print_url_latency_stats(&url_stats);
+                if round_counter.is_multiple_of(offenders_interval) {
+                    print_top_offenders(&url_stats);
+                }
+                if let Some(previous) = &previous_statuses {
+                    RoundDelta::between(previous, &all_statuses_this_round, latency_change_percent).print(round_counter - 1);
+                }
+                previous_statuses = Some(all_statuses_this_round.clone());
+            }
+        }
+
+        if let Some(api) = &api {
+            api.round_finished(round_counter);
+        }
+
+        if let Some(path) = &summary_json_path {
+            let json = summary_json(round_counter, &round_stats, &histogram_buckets);
+            if let Err(e) = write_atomic(path, &format!("{}\n", json)) {
+                warn!(error = %e, "failed to write summary JSON");
+            }
+        }
+        if let Some(path) = &metrics_file {
+            if let Err(e) = write_atomic(path, &metrics.to_prometheus(&url_stats, apdex.as_ref())) {
+                warn!(error = %e, "failed to write metrics file");
+            }
+        }
+
+        if let Some(path) = &state_file {
+            let state = MonitorState::new(
+                round_counter,
+                alert_tracker.state(),
+                incident_tracker.state(),
+                escalator.state(),
+                content_tracker.state(),
+                url_stats.state(),
+            );
+            if let Err(e) = state.save(path) {
+                warn!(error = %e, "failed to save state");
+            }
+        }
+
+        // Rewriting the history files is expensive, so they're pruned at most hourly
+        if let Some(retention) = history_retention {
+            if last_prune.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+                last_prune = Some(Instant::now());
+                let cutoff = SystemTime::now() - retention;
+                if let Some(history) = &history {
+                    if let Err(e) = history.prune(cutoff) {
+                        warn!(error = %e, "failed to prune history");
+                    }
+                }
+                if period_seconds.is_some() {
+                    prune_round_files(Path::new("."), cutoff);
+                }
+            }
+        }
+
+        // Tells an external dead-man's switch that the checker is still alive
+        if let Some(url) = &heartbeat_url {
+            if let Err(e) = RetryPolicy::default().run(|| ping(&client, url)) {
+                warn!(url = %url, error = %e, "heartbeat ping failed");
+            }
+        }
+
+        if let Some(systemd) = &systemd {
+            systemd.status(&format!(
+                "Round {}: {} up, {} down",
+                round_counter,
+                round_stats.successful_checks,
+                round_stats.failed_checks
+            ));
+            systemd.watchdog();
+        }
+
+        // A SIGTERM during the round waited for it to be recorded, and stops here
+        if daemon::terminated() {
+            break;
+        }
+        if let Some(seconds) = period_seconds {
+            if let Some(tui) = &tui {
+                tui.round_finished(Instant::now() + Duration::from_secs(seconds));
+            } else if let Some(watch) = &watch {
+                redraw_watch_table(watch, round_counter, Duration::from_secs(seconds), color);
+            } else {
+                println!("Waiting for {} seconds before next round...\n", seconds);
+            }
+            if !wait_for_next_round(Duration::from_secs(seconds), systemd.as_ref()) {
+                break;
+            }
+        } else {
+            break;
+        }
+    }
+    if daemon::terminated() {
+        info!("stopped by SIGTERM");
+        if let Some(systemd) = &systemd {
+            systemd.stopping();
+        }
+    }
 
-// Struct for round statistics (Bonus Feature)
-#[derive(Debug, Default)]
-struct RoundStats {
-    min_time: Option<Duration>,
-    max_time: Option<Duration>,
-    total_time: Duration,
-    successful_checks: u64,
-    failed_checks: u64,
+    Ok(())
 }
 
-impl RoundStats {
-    fn new() -> Self {
-        Default::default()
+// Sleeps until the next round, pinging the systemd watchdog meanwhile. False if SIGTERM
+// cut the wait short.
+fn wait_for_next_round(duration: Duration, systemd: Option<&Systemd>) -> bool {
+    let interval = systemd.and_then(Systemd::watchdog_interval);
+    let until = Instant::now() + duration;
+    loop {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return true;
+        }
+        if !daemon::sleep(interval.map_or(left, |interval| left.min(interval))) {
+            return false;
+        }
+        if let Some(systemd) = systemd {
+            systemd.watchdog();
+        }
     }
+}
 
-    fn update(&mut self, status: &WebsiteStatus) {
-        match status.action_status {
-            Ok(_) => {
-                self.successful_checks += 1;
-                if self.successful_checks == 1 {
-                    self.min_time = Some(status.response_time);
-                    self.max_time = Some(status.response_time);
-                } else {
-                    if self.min_time.map_or(true, |min| status.response_time < min) {
-                        self.min_time = Some(status.response_time);
-                    }
-                    if self.max_time.map_or(true, |max| status.response_time > max) {
-                        self.max_time = Some(status.response_time);
-                    }
-                }
-                self.total_time += status.response_time;
-            }
-            Err(_) => {
-                self.failed_checks += 1;
-            }
+// URLs going down, recovering or flapping, for monitors whose output nobody watches
+fn log_state_change(event: &Event) {
+    match event {
+        Event::Failure { result, reminder: false, .. } => {
+            let error = result.action_status.as_ref().err().map_or("", String::as_str);
+            warn!(url = %result.url, error, "URL is down");
+        }
+        Event::Recovery { result, outage, .. } => {
+            info!(url = %result.url, outage_seconds = outage.as_secs(), "URL recovered");
         }
+        Event::Flapping { result, flap_rate, .. } => {
+            warn!(url = %result.url, flap_rate = format!("{:.0}%", flap_rate), "URL is flapping");
+        }
+        Event::Failure { .. } | Event::Summary { .. } => {}
     }
+}
 
-    fn print_summary(&self) {
-        println!("\n--- Round Summary ---");
-        let total_attempted = self.successful_checks + self.failed_checks;
-        println!("Total URLs Attempted: {}", total_attempted);
-        println!("Successful Checks: {}", self.successful_checks);
-        println!("Failed Checks: {}", self.failed_checks);
+// Clears the screen and draws the --watch table. Piped output gets the plain table
+// appended each round instead.
+fn redraw_watch_table(watch: &WatchTable, round: u64, next_round: Duration, color: bool) {
+    let mut stdout = io::stdout().lock();
+    if stdout.is_terminal() {
+        let _ = write!(stdout, "\x1b[2J\x1b[H");
+    }
+    let _ = writeln!(stdout, "{}", watch.render(round, Some(next_round), color));
+    let _ = stdout.flush();
+}
 
-        if self.successful_checks > 0 {
-            if let Some(min) = self.min_time {
-                println!("Min Response Time (successful): {} ms", min.as_millis());
-            }
-            if let Some(max) = self.max_time {
-                println!("Max Response Time (successful): {} ms", max.as_millis());
-            }
-            if self.total_time > Duration::ZERO {
-                let avg_time_ms = self.total_time.as_millis() as f64 / self.successful_checks as f64;
-                println!("Average Response Time (successful): {:.2} ms", avg_time_ms);
+// Deletes status_round_N.json files in `dir` last written before `cutoff`
+fn prune_round_files(dir: &Path, cutoff: SystemTime) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!(error = %e, "failed to list round JSON files");
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(round) = name.to_str().and_then(|n| n.strip_prefix("status_round_")?.strip_suffix(".json")) else {
+            continue;
+        };
+        if round.parse::<u64>().is_err() {
+            continue;
+        }
+        let modified = entry.metadata().and_then(|m| m.modified());
+        if modified.is_ok_and(|modified| modified < cutoff) {
+            if let Err(e) = fs::remove_file(entry.path()) {
+                warn!(path = %entry.path().display(), error = %e, "failed to delete round JSON file");
             }
-        } else if total_attempted > 0 {
-            println!("No successful checks to calculate response time statistics.");
         }
-        println!("---------------------\n");
     }
 }
 
-
-fn main() -> Result<(), String> {
-    let args: Vec<String> = env::args().collect();
-
-    let mut initial_urls_to_check: Vec<String> = Vec::new();
-    let mut file_path: Option<String> = None;
-    let mut num_workers: usize = std::thread::available_parallelism().map_or(2, |nz| nz.get());
-    let mut timeout_seconds: u64 = 5;
-    let mut retries_count: u32 = 0;
-    let mut period_seconds: Option<u64> = None;
-    let mut header_assertion_str: Option<String> = None;
-
+// `report <kind> [OPTIONS]`: reports from a --history-dir store
+fn run_report(program_name: &str, args: &[String]) -> Result<(), String> {
+    let kind = match args.first().map(String::as_str) {
+        Some("sla") => "sla",
+        Some("render") => "render",
+        Some("-h") | Some("--help") | None => {
+            print_usage(program_name);
+            return Ok(());
+        }
+        Some(other) => return Err(format!("Unknown report '{}' (expected: sla, render)", other)),
+    };
+    let mut history_dir: Option<String> = None;
+    let mut range: Option<TimeRange> = None;
+    let mut slo: f64 = 99.9;
+    let mut format = ReportFormat::default();
     let mut i = 1;
     while i < args.len() {
-        let arg = &args[i];
-        match arg.as_str() {
-            "--file" => {
+        match args[i].as_str() {
+            "--history-dir" | "--from" => {
                 i += 1;
                 if i < args.len() {
-                    file_path = Some(args[i].clone());
+                    history_dir = Some(args[i].clone());
                 } else {
-                    return Err("--file requires an argument".to_string());
+                    return Err(format!("{} requires a directory", args[i - 1]));
                 }
             }
-            "--workers" => {
+            "--range" if kind == "render" => {
                 i += 1;
                 if i < args.len() {
-                    num_workers = args[i].parse().map_err(|_| format!("Invalid number for --workers: {}", args[i]))?;
-                    if num_workers == 0 { return Err("--workers must be at least 1".to_string()); }
+                    range = Some(TimeRange::parse(&args[i]).map_err(|e| format!("Invalid value for --range: {}", e))?);
                 } else {
-                    return Err("--workers requires an argument".to_string());
+                    return Err("--range requires <from>..<to>, e.g. 2024-05-01..2024-05-07".to_string());
+                }
+            }
+            "--slo" => {
+                i += 1;
+                if i < args.len() {
+                    slo = args[i].trim_end_matches('%').parse().map_err(|_| format!("Invalid percentage for --slo: {}", args[i]))?;
+                    if !(0.0..=100.0).contains(&slo) {
+                        return Err("--slo must be between 0 and 100".to_string());
+                    }
+                } else {
+                    return Err("--slo requires a percentage such as 99.9".to_string());
+                }
+            }
+            "--format" => {
+                i += 1;
+                if i < args.len() {
+                    format = ReportFormat::parse(&args[i])?;
+                } else {
+                    return Err("--format requires table, json, markdown or html".to_string());
                 }
             }
+            other => return Err(format!("Unknown option for report {}: {}", kind, other)),
+        }
+        i += 1;
+    }
+    let history_dir = history_dir.ok_or_else(|| format!("report {} requires --history-dir <dir>", kind))?;
+    let store = HistoryStore::open_existing(&history_dir)?;
+    let output = if kind == "render" {
+        RangeReport::build(&store, range.unwrap_or_default(), slo, SystemTime::now())?.render(format)
+    } else {
+        SlaReport::build(&store, slo, SystemTime::now())?.render(format)
+    };
+    print!("{}", output);
+    Ok(())
+}
+
+// `healthcheck <url>`: one check, for container health probes. Silent and exit status 0
+// if the URL is healthy; otherwise the reason on stderr and exit status 1.
+fn run_healthcheck(program_name: &str, args: &[String]) -> Result<(), String> {
+    let mut url: Option<CheckRequest> = None;
+    let mut timeout_seconds: u64 = 5;
+    let mut retries: u32 = 0;
+    let mut header_assertion: Option<(String, String)> = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
             "--timeout" => {
                 i += 1;
                 if i < args.len() {
@@ -133,363 +1311,603 @@ fn main() -> Result<(), String> {
             "--retries" => {
                 i += 1;
                 if i < args.len() {
-                    retries_count = args[i].parse().map_err(|_| format!("Invalid number for --retries: {}", args[i]))?;
+                    retries = args[i].parse().map_err(|_| format!("Invalid number for --retries: {}", args[i]))?;
                 } else {
                     return Err("--retries requires an argument".to_string());
                 }
             }
-            "--period" => {
-                i += 1;
-                if i < args.len() {
-                    let p_val = args[i].parse().map_err(|_| format!("Invalid number for --period: {}", args[i]))?;
-                    if p_val == 0 { return Err("--period must be at least 1 second".to_string()); }
-                    period_seconds = Some(p_val);
-                } else {
-                    return Err("--period requires an argument".to_string());
-                }
-            }
             "--assert-header" => {
                 i += 1;
                 if i < args.len() {
-                    header_assertion_str = Some(args[i].clone());
+                    header_assertion = Some(parse_header_assertion(&args[i]).map_err(|e| format!("Invalid format for --assert-header: {}", e))?);
                 } else {
                     return Err("--assert-header requires an argument in 'Name: Value' format".to_string());
                 }
             }
             "-h" | "--help" => {
-                print_usage(&args[0]);
+                print_usage(program_name);
                 return Ok(());
             }
-            s if s.starts_with("--") => {
-                return Err(format!("Unknown option: {}", s));
-            }
-            s => {
-                initial_urls_to_check.push(s.to_string());
+            s if s.starts_with("--") => return Err(format!("Unknown option for healthcheck: {}", s)),
+            s if url.is_none() => {
+                url = Some(CheckRequest::parse(s).map_err(|e| format!("Invalid URL argument: {}", e))?);
             }
+            _ => return Err("healthcheck takes exactly one URL".to_string()),
         }
         i += 1;
     }
+    let url = url.ok_or("healthcheck requires a URL")?;
 
-    let parsed_header_assertion: Option<(String, String)> = match header_assertion_str {
-        Some(s) => {
-            let parts: Vec<&str> = s.splitn(2, ':').collect();
-            if parts.len() == 2 {
-                let name = parts[0].trim().to_string();
-                let value = parts[1].trim().to_string();
-                if name.is_empty() || value.is_empty() {
-                    return Err("Invalid format for --assert-header: Name and Value cannot be empty. Use 'Header-Name: Expected Value'".to_string());
-                }
-                Some((name.to_lowercase(), value))
-            } else {
-                return Err("Invalid format for --assert-header. Use 'Header-Name: Expected Value'".to_string());
-            }
-        }
-        None => None,
-    };
-
-    if let Some(path_str) = &file_path {
-        let path = Path::new(path_str.as_str());
-        let file = File::open(path).map_err(|e| format!("Failed to open file {}: {}", path_str, e))?;
-        let reader = io::BufReader::new(file);
-        for line_result in reader.lines() {
-            let line = line_result.map_err(|e| format!("Failed to read line from file: {}", e))?;
-            let line_without_comment = if let Some(comment_start) = line.find('#') {
-                if comment_start == 0 { "" } else { &line[..comment_start] }
-            } else { &line };
-            let trimmed_url_part = line_without_comment.trim();
-            if !trimmed_url_part.is_empty() {
-                initial_urls_to_check.push(trimmed_url_part.to_string());
-            }
-        }
+    let mut checker_builder = Checker::builder()
+        .workers(1)
+        .timeout(Duration::from_secs(timeout_seconds))
+        .retries(retries);
+    if let Some((name, value)) = header_assertion {
+        checker_builder = checker_builder.assert_header(name, value);
     }
+    let result = checker_builder.build()?.check_all(vec![url]).pop()
+        .ok_or("the check produced no result")?;
+    let Some(problem) = healthcheck_problem(&result) else { return Ok(()) };
+    eprintln!("{}: {} (after {})", result.url, problem, format_response_time(result.response_time));
+    std::process::exit(1);
+}
 
-    if initial_urls_to_check.is_empty() {
-        print_usage(&args[0]);
-        eprintln!("\nError: No URLs provided via --file or positional arguments.");
-        std::process::exit(2);
+// Unlike the monitor, which counts any answer as up, a probe fails on error statuses:
+// health endpoints answer 503 when the service isn't well
+fn healthcheck_problem(result: &WebsiteStatus) -> Option<String> {
+    match &result.action_status {
+        Ok(code) if *code >= 400 => Some(format!("HTTP {}", code)),
+        Ok(_) => None,
+        Err(e) => Some(e.clone()),
     }
+}
 
-    let mut seen_urls_master = std::collections::HashSet::new();
-    initial_urls_to_check.retain(|url| seen_urls_master.insert(url.clone()));
-
-    let base_config = Config {
-        timeout: Duration::from_secs(timeout_seconds),
-        retries: retries_count,
-        header_assertion: parsed_header_assertion,
-    };
-
-    let client = Arc::new(
-        reqwest::blocking::Client::builder()
-            .timeout(base_config.timeout)
-            .build()
-            .map_err(|e| format!("Failed to build HTTP client: {}", e))?,
-    );
-
-    let mut round_counter = 0;
-    loop {
-        round_counter += 1;
-        if period_seconds.is_some() {
-            println!("--- Starting Round {} ---", round_counter);
-        }
-
-        let current_round_urls = initial_urls_to_check.clone();
-        if current_round_urls.is_empty() {
-            if period_seconds.is_none() { break; }
-            println!("No URLs to check in this round. Waiting for next period if applicable.");
-            if let Some(seconds) = period_seconds {
-                thread::sleep(Duration::from_secs(seconds));
-                continue;
-            } else { break; }
-        }
-
-        let jobs_queue = Arc::new(Mutex::new(VecDeque::from(current_round_urls.clone())));
-        let num_total_jobs_this_round = current_round_urls.len();
-
-        let (result_tx, result_rx): (Sender<WebsiteStatus>, Receiver<WebsiteStatus>) = channel();
-        let config_for_round = Arc::new(base_config.clone());
-
-        let mut worker_handles = Vec::new();
-        for worker_id in 0..num_workers {
-            let jobs_queue_clone = Arc::clone(&jobs_queue);
-            let result_tx_clone = result_tx.clone();
-            let client_clone = Arc::clone(&client);
-            let config_clone = Arc::clone(&config_for_round);
-
-            let handle = thread::spawn(move || {
-                loop {
-                    let url_to_check: String = match jobs_queue_clone.lock() {
-                        Ok(mut queue_guard) => {
-                            if let Some(url) = queue_guard.pop_front() { url } else { break; }
-                        }
-                        Err(p) => { eprintln!("Worker {}: job queue mutex poisoned: {}", worker_id, p); break; }
-                    };
-
-                    let mut final_status_result_action: Result<u16, String> = Err("Worker failed to determine status".to_string());
-                    let mut final_response_time = Duration::from_secs(0);
-                    let mut final_timestamp = SystemTime::now();
-
-                    for attempt in 0..=(config_clone.retries) {
-                        let start_time = Instant::now();
-                        let request_result = client_clone.get(&url_to_check).send();
-
-                        final_response_time = start_time.elapsed();
-                        final_timestamp = SystemTime::now();
-
-                        match request_result {
-                            Ok(response) => {
-                                let status_code = response.status().as_u16();
-                                if let Some((assert_name, assert_value)) = &config_clone.header_assertion {
-                                    let found_header = response.headers().iter()
-                                        .find(|(name, _)| name.as_str().to_lowercase() == *assert_name);
-
-                                    match found_header {
-                                        Some((_, actual_value_header)) => {
-                                            match actual_value_header.to_str() {
-                                                Ok(actual_value_str) if actual_value_str == assert_value => {
-                                                    final_status_result_action = Ok(status_code);
-                                                }
-                                                Ok(actual_value_str) => {
-                                                    final_status_result_action = Err(format!(
-                                                        "Header '{}' assertion failed: expected '{}', got '{}'",
-                                                        assert_name, assert_value, actual_value_str
-                                                    ));
-                                                }
-                                                Err(_) => {
-                                                    final_status_result_action = Err(format!(
-                                                        "Header '{}' assertion failed: actual value not valid UTF-8: {:?}",
-                                                        assert_name, actual_value_header
-                                                    ));
-                                                }
-                                            }
-                                        }
-                                        None => {
-                                            final_status_result_action = Err(format!(
-                                                "Header '{}' assertion failed: header not found",
-                                                assert_name
-                                            ));
-                                        }
-                                    }
-                                } else {
-                                    final_status_result_action = Ok(status_code);
-                                }
-                                break;
-                            }
-                            Err(e) => {
-                                final_status_result_action = Err(e.to_string());
-                                if attempt >= config_clone.retries { break; }
-                                if attempt < config_clone.retries { thread::sleep(Duration::from_millis(100));}
-                            }
-                        }
-                    }
-
-                    let status_to_send = WebsiteStatus {
-                        url: url_to_check.clone(),
-                        action_status: final_status_result_action,
-                        response_time: final_response_time,
-                        timestamp: final_timestamp,
-                    };
-
-                    if result_tx_clone.send(status_to_send).is_err() { break; }
+// `agent`: checks what a central `serve` instance assigns, from this host, until SIGTERM
+fn run_agent(program_name: &str, args: &[String]) -> Result<(), String> {
+    let mut server: Option<String> = None;
+    let mut name: Option<String> = None;
+    let mut token: Option<String> = None;
+    let mut workers: Option<usize> = None;
+    let mut timeout_seconds: u64 = 5;
+    let mut retries: u32 = 0;
+    let mut log_level = LevelFilter::INFO;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--server" => {
+                i += 1;
+                if i < args.len() {
+                    server = Some(args[i].clone());
+                } else {
+                    return Err("--server requires the central instance's URL, e.g. http://central:8080".to_string());
                 }
-            });
-            worker_handles.push(handle);
-        }
-
-        drop(result_tx);
-
-        let mut all_statuses_this_round: Vec<WebsiteStatus> = Vec::with_capacity(num_total_jobs_this_round);
-        let mut round_stats = RoundStats::new();
-
-        if round_counter == 1 || period_seconds.is_some() {
-            println!(
-                "{:<30} | {:<8} | {:<12} | {}",
-                "URL", "Status", "Time (ms)", "Timestamp (EpochS)"
-            );
-            println!("{}", "-".repeat(75));
-        }
-
-        for _ in 0..num_total_jobs_this_round {
-            match result_rx.recv() {
-                Ok(status) => {
-                    round_stats.update(&status);
-                    let status_str = match &status.action_status {
-                        Ok(code) => code.to_string(),
-                        Err(e_str) => {
-                            if e_str.len() > 20 { format!("ERR: {}...", &e_str[..17]) } else { format!("ERR: {}", e_str) }
-                        }
-                    };
-                    let time_ms = status.response_time.as_millis();
-                    let timestamp_epoch_s = status.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
-                    println!(
-                        "{:<30} | {:<8} | {:<12} | {}",
-                        truncate_url(&status.url, 28), status_str, time_ms, timestamp_epoch_s
-                    );
-                    all_statuses_this_round.push(status);
+            }
+            "--name" => {
+                i += 1;
+                if i < args.len() {
+                    validate_agent_name(&args[i]).map_err(|e| format!("Invalid value for --name: {}", e))?;
+                    name = Some(args[i].clone());
+                } else {
+                    return Err("--name requires a name, e.g. eu-west".to_string());
                 }
-                Err(_) => { break; }
             }
+            "--token" => {
+                i += 1;
+                if i < args.len() {
+                    token = Some(args[i].clone());
+                } else {
+                    return Err("--token requires a token".to_string());
+                }
+            }
+            "--workers" => {
+                i += 1;
+                if i < args.len() {
+                    let n: usize = args[i].parse().map_err(|_| format!("Invalid number for --workers: {}", args[i]))?;
+                    if n == 0 { return Err("--workers must be at least 1".to_string()); }
+                    workers = Some(n);
+                } else {
+                    return Err("--workers requires an argument".to_string());
+                }
+            }
+            "--timeout" => {
+                i += 1;
+                if i < args.len() {
+                    timeout_seconds = args[i].parse().map_err(|_| format!("Invalid number for --timeout: {}", args[i]))?;
+                    if timeout_seconds == 0 { return Err("--timeout must be at least 1 second".to_string()); }
+                } else {
+                    return Err("--timeout requires an argument".to_string());
+                }
+            }
+            "--retries" => {
+                i += 1;
+                if i < args.len() {
+                    retries = args[i].parse().map_err(|_| format!("Invalid number for --retries: {}", args[i]))?;
+                } else {
+                    return Err("--retries requires an argument".to_string());
+                }
+            }
+            "--log-level" => {
+                i += 1;
+                if i < args.len() {
+                    log_level = logging::parse_level(&args[i]).map_err(|e| format!("Invalid value for --log-level: {}", e))?;
+                } else {
+                    return Err("--log-level requires a level (error, warn, info, debug or trace)".to_string());
+                }
+            }
+            "-h" | "--help" => {
+                print_usage(program_name);
+                return Ok(());
+            }
+            other => return Err(format!("Unknown option for agent: {}", other)),
         }
-
-        for (i,handle) in worker_handles.into_iter().enumerate() {
-            if handle.join().is_err() { eprintln!("Error: Worker thread {} panicked.", i); }
-        }
-
-        if !all_statuses_this_round.is_empty() {
-            let json_filename = if period_seconds.is_some() {
-                format!("status_round_{}.json", round_counter)
-            } else {
-                "status.json".to_string()
-            };
-            write_json_output(&all_statuses_this_round, &json_filename)?;
-            println!("\nResults for this round written to {}", json_filename);
-        } else if num_total_jobs_this_round > 0 {
-            println!("\nNo results were successfully processed in this round.");
-        }
-
-        round_stats.print_summary();
-
-        if let Some(seconds) = period_seconds {
-            if seconds > 0 {
-                println!("Waiting for {} seconds before next round...\n", seconds);
-                thread::sleep(Duration::from_secs(seconds));
-            } else { break; }
-        } else {
-            break;
-        }
+        i += 1;
     }
+    let server = server.ok_or("agent requires --server <url>")?;
+    let name = name.ok_or("agent requires --name <name>")?;
+    logging::init(LogTarget::Stderr, LogFormat::Text, log_level, io::stderr().is_terminal())?;
 
-    Ok(())
+    let mut checker_builder = Checker::builder()
+        .timeout(Duration::from_secs(timeout_seconds))
+        .retries(retries);
+    if let Some(workers) = workers {
+        checker_builder = checker_builder.workers(workers);
+    }
+    daemon::handle_sigterm();
+    Agent::new(&server, &name, token, checker_builder.build()?)?.run()
 }
 
 fn print_usage(program_name: &str) {
     eprintln!("Website Status Checker");
     eprintln!("\nUsage: {} [OPTIONS] [URL...]", program_name);
+    eprintln!("       {} report sla --history-dir <dir> [--slo <percent>] [--format table|json|markdown|html]", program_name);
+    eprintln!("       {} report render --history-dir <dir> [--range <from>..<to>] [--slo <percent>] [--format <fmt>]", program_name);
+    eprintln!("       {} healthcheck [--timeout <seconds>] [--retries <N>] [--assert-header \"Name: Value\"] <URL>", program_name);
+    eprintln!("       {} aggregate --period <seconds> --api-token <token> [--quorum <N>] [--listen <addr>] [OPTIONS] [URL...]", program_name);
+    eprintln!("       {} agent --server <url> --name <name> [--token <token>] [--workers <N>] [--timeout <seconds>] [--retries <N>]", program_name);
+    eprintln!("       {} serve --period <seconds> [--listen <addr>] [--api-token <token>] [OPTIONS] [URL...]", program_name);
     eprintln!("\nChecks the availability of websites concurrently.");
     eprintln!("\nOptions:");
     eprintln!("  --file <path>        Path to a text file containing URLs (one per line).");
-    eprintln!("                       Lines starting with # and blank lines are ignored.");
+    eprintln!("                       Lines starting with # and blank lines are ignored. A URL may be followed by");
+    eprintln!("                       priority=<critical|high|medium|low|info> and tags=<a,b>.");
+    eprintln!("  --config <path>      TOML config file for settings such as [[email]] notifiers.");
     eprintln!("  --workers <N>        Number of worker threads (default: number of logical CPU cores, min 1).");
     eprintln!("  --timeout <seconds>  Per-request timeout in seconds (default: 5, min 1).");
     eprintln!("  --retries <N>        Number of additional attempts after a failure (default: 0).");
+    eprintln!("  --log-format <fmt>   Format of warnings and other diagnostics on stderr: text (default) or json");
+    eprintln!("                       (one object per line).");
+    eprintln!("  --log-level <level>  Least severe diagnostics to log: error, warn (default), info (adds retries),");
+    eprintln!("                       debug, trace or off.");
+    eprintln!("  --log-target <target> Where to log: stderr (default), syslog or journald. The system logger also gets");
+    eprintln!("                       info events (recoveries, retries) unless --log-level says otherwise.");
+    eprintln!("  --daemon             With --period, detach from the terminal and run in the background. Logs go to");
+    eprintln!("                       syslog unless --log-file or --log-target is given. SIGTERM stops after the round.");
+    eprintln!("  --pid-file <path>    With --daemon, write the daemon's PID to <path> (removed when it stops).");
+    eprintln!("  --log-file <path>    With --daemon, append its output and logs to <path>.");
+    eprintln!("  --local-time         Show times in the console output in the local time zone instead of UTC.");
+    eprintln!("  --color <when>       Color status codes and summary counts: auto (default; only on a terminal and");
+    eprintln!("                       if NO_COLOR isn't set), always or never.");
     eprintln!("  -h, --help           Show this help message and exit.");
     eprintln!("\nBonus Features:");
     eprintln!("  --period <seconds>   Loop forever, checking URLs every <seconds> interval (min 1).");
     eprintln!("                       JSON output will be named status_round_N.json for each round.");
+    eprintln!("  --tui                In --period mode, show a live dashboard (state, latency trend, last error per URL)");
+    eprintln!("                       instead of the per-round output. Keys: arrows select, r re-checks the selected URL,");
+    eprintln!("                       / filters, s sorts, q quits.");
+    eprintln!("  --watch              In --period mode, redraw a compact status table (state, last change per URL)");
+    eprintln!("                       after each round instead of appending the per-round output.");
+    eprintln!("  --latency-change <percent> In --period mode, list URLs whose response time changed by more than");
+    eprintln!("                       <percent> since the previous round (default: 50), next to newly failed and");
+    eprintln!("                       newly recovered ones.");
+    eprintln!("  --group-by <host|domain> After each round summary, show success rate and response times per host");
+    eprintln!("                       or per registered domain (e.g. example.co.uk) of the HTTP(S) URLs.");
+    eprintln!("  --top <N>            After each round summary, list the N slowest successful checks of the round");
+    eprintln!("                       and the N URLs with the most failed checks since the start.");
+    eprintln!("  --apdex-threshold <duration> After each round summary, show the Apdex score (satisfied within the");
+    eprintln!("                       threshold, tolerating within 4x, frustrated above or failed) per URL and per tag");
+    eprintln!("                       since the start, e.g. 500ms; also exported with --metrics-file.");
+    eprintln!("  --offenders-interval <rounds> In --period mode, print the URLs doing worst since the start");
+    eprintln!("                       (success rate, average and p95 response time, last failure) every");
+    eprintln!("                       <rounds> rounds (default: 10).");
+    eprintln!("  --summary-json <path> Write the round summary (counts, latency percentiles and histogram) as JSON");
+    eprintln!("                       to <path> after every round.");
+    eprintln!("  --metrics-file <path> Write per-URL metrics in the Prometheus text format to <path> after every");
+    eprintln!("                       round (up, check/failure counters, response time histogram).");
+    eprintln!("  --push-url <url>     POST every round's results to a collector at <url>, in JSON batches of up to 500;");
+    eprintln!("                       undelivered batches are retried, and with --period sent again after the next round.");
+    eprintln!("  --push-token <token> Send Authorization: Bearer <token> with every --push-url request.");
+    eprintln!("  --upload <s3://bucket/prefix/> Upload every round's JSON file to S3 under <prefix>/<run start time>/,");
+    eprintln!("                       with credentials from AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY (and AWS_SESSION_TOKEN),");
+    eprintln!("                       AWS_REGION, and AWS_ENDPOINT_URL for S3-compatible stores.");
+    eprintln!("  --histogram-buckets <list> Latency histogram bucket bounds for both, e.g. 100ms,250ms,1s");
+    eprintln!("                       (default: 10ms,25ms,50ms,100ms,250ms,500ms,1s,2500ms,5s,10s).");
+    eprintln!("  --history-dir <dir>  Keep a history store in <dir>: each outage (start, end, duration, first error)");
+    eprintln!("                       goes to incidents.jsonl and a readable incidents.log.");
+    eprintln!("  --history-retention <duration> Delete history older than <duration> (e.g. 30d) from the");
+    eprintln!("                       --history-dir store, and old status_round_N.json files; checked hourly.");
+    eprintln!("  --state-file <path>  Save per-URL state (failure streaks, open incidents, sent alerts) after every");
+    eprintln!("                       round and resume from it on start, so restarts don't re-fire alerts.");
     eprintln!("  --assert-header \"Name: Value\" Check for a specific HTTP header and its exact value.");
     eprintln!("                       (Header name matching is case-insensitive; value matching is case-sensitive).");
     eprintln!("                       If assertion fails, the URL status will be an error.");
-    eprintln!("\nIf neither --file nor positional URLs are supplied, this message is shown and the program exits with code 2.");
+    eprintln!("  --upload-size <bytes> Upload check: send a generated payload of this size (suffixes K, M, G allowed)");
+    eprintln!("                       and report upload throughput and server processing time.");
+    eprintln!("  --upload-method <POST|PUT> HTTP method for upload checks (default: POST, requires --upload-size).");
+    eprintln!("  --watch-content      Hash the body of every successful HTTP response and report when it changes");
+    eprintln!("                       (across rounds, and across runs with --state-file).");
+    eprintln!("  --content-selector <css> Only hash the elements matching this CSS selector (implies --watch-content).");
+    eprintln!("  --exec-on-failure <cmd> Run <cmd> through the shell for every failure alert (see --alert-after and");
+    eprintln!("                       --realert-interval), with the result as JSON on stdin.");
+    eprintln!("  --exec-on-recovery <cmd> Run <cmd> when a URL reported as failed succeeds again (--period).");
+    eprintln!("  --plugin-dir <dir>   Load check plugins (.so/.dylib/.dll) from <dir>; each handles one or more URL schemes.");
+    eprintln!("                       May be given more than once.");
+    eprintln!("  --maintenance <file.ics> Planned maintenance calendar (may be repeated). Checks during its events");
+    eprintln!("                       are marked as maintenance: no alerts or incidents, and no effect on SLA reports.");
+    eprintln!("\nNotifications:");
+    eprintln!("  --alert-after <N>    Only alert (and run --exec-on-failure) once a URL has failed N rounds in a row (default: 1).");
+    eprintln!("  --realert-interval <duration> Remind about a URL that stays down this often, e.g. 30m (default: alert once per outage).");
+    eprintln!("  --flap-detection     Detect URLs flapping between up and down (Nagios-style, thresholds 5%/20%), suppress");
+    eprintln!("                       their individual alerts and send one flapping notification instead.");
+    eprintln!("  --flap-thresholds <low,high> Flap detection with custom state-change percentages.");
+    eprintln!("  --heartbeat-url <url> GET <url> after every completed round (healthchecks.io-style dead-man's switch).");
+    eprintln!("  --webhook <url>      POST a JSON payload to <url> when a URL goes down or recovers (may be repeated).");
+    eprintln!("  --webhook-events <list> Comma-separated events to send: failure, recovery, flapping, summary");
+    eprintln!("                       (default: failure,recovery,flapping).");
+    eprintln!("  --webhook-template <file> Jinja-style body template with {{{{url}}}}, {{{{status}}}}, {{{{error}}}}, {{{{event}}}}, {{{{round}}}}, ... variables.");
+    eprintln!("  --webhook-retries <N> Delivery retries with exponential backoff (default: 3).");
+    eprintln!("  --slack-webhook <url> Post failures and recoveries to a Slack incoming webhook, one message per round.");
+    eprintln!("  --slack-template <file> Jinja-style template for Slack messages (round, failures, recoveries, ...).");
+    eprintln!("  --discord-webhook <url> Post failures and recoveries to a Discord webhook as embeds.");
+    eprintln!("  --telegram-token <token> --telegram-chat-id <id>");
+    eprintln!("                       Send failures and recoveries through the Telegram Bot API (rate limited).");
+    eprintln!("  --telegram-api-url <url> Bot API base URL (default: https://api.telegram.org).");
+    eprintln!("  [[email]] in --config  SMTP digest of each round's failures, plus immediate mail for priority=critical checks.");
+    eprintln!("  [[opsgenie]] in --config Open an Opsgenie alert per failing URL (priority P1-P5 from priority=) and close it on recovery.");
+    eprintln!("  [[ntfy]], [[gotify]] in --config Push failures and recoveries to an ntfy topic or a Gotify server.");
+    eprintln!("  [[escalation]] in --config Notify more channels the longer a URL stays down (--period).");
+    eprintln!("\nReports (from a --history-dir store):");
+    eprintln!("  report sla           Uptime per URL over the last 24h, 7d and 30d, flagging URLs below the SLO,");
+    eprintln!("                       with downtime, incident counts, MTTR and MTBF per URL and per tag.");
+    eprintln!("  report render        The same figures, plus average and p95 response time and the incident list,");
+    eprintln!("                       for any stored date range, without re-running checks.");
+    eprintln!("    --history-dir <dir> History store to read (required): the directory of checks.jsonl and");
+    eprintln!("                       incidents.jsonl written by --history-dir. --from is an alias; there is no");
+    eprintln!("                       separate results database.");
+    eprintln!("    --range <from>..<to> (render) Dates like 2024-05-01 (an end date is inclusive) or UTC timestamps");
+    eprintln!("                       like 2024-05-01T12:00:00Z; either end may be left out (default: everything).");
+    eprintln!("    --slo <percent>    Target uptime (default: 99.9).");
+    eprintln!("    --format <fmt>     table (default), json, markdown or html.");
+    eprintln!("\nAPI server (serve):");
+    eprintln!("  serve                Run the --period monitor with an HTTP/JSON API: GET /status, GET /checks/<id>/history,");
+    eprintln!("                       POST /checks (add an http(s) URL) and POST /checks/<id>/run (check it now),");
+    eprintln!("                       and a web dashboard at /.");
+    eprintln!("    --listen <addr>    Address to listen on (default: {}).", DEFAULT_LISTEN_ADDR);
+    eprintln!("    --api-token <token> Require an Authorization: Bearer <token> header on every request; also");
+    eprintln!("                       enables the agent endpoints (required by aggregate). Without it anyone who can");
+    eprintln!("                       connect can add and run checks, so set it when --listen isn't a loopback address.");
+    eprintln!("\nRemote agents (agent):");
+    eprintln!("  agent                Check the http(s) URLs a serve instance assigns to this agent (those without");
+    eprintln!("                       agent:<name> tags, and those tagged agent:<name>), at its --period, and send");
+    eprintln!("                       the results back; retries with backoff while it's unreachable.");
+    eprintln!("    --server <url>     The serve instance's API, e.g. http://central:8080 (required).");
+    eprintln!("    --name <name>      This agent's name, e.g. eu-west (required).");
+    eprintln!("    --token <token>    The serve instance's --api-token.");
+    eprintln!("    --log-level <level> Least severe diagnostics to log (default: info).");
+    eprintln!("  aggregate            Like serve, but instead of checking the URLs itself, merge the agents' latest");
+    eprintln!("                       results into one per URL every round, for the output, alerts and history.");
+    eprintln!("    --quorum <N>       Agents that must see a URL up for it to be up (default: half of those that");
+    eprintln!("                       reported on it, rounded up).");
+    eprintln!("\nContainer probes (healthcheck):");
+    eprintln!("  healthcheck <URL>    Check one URL and exit 0 if it answered below HTTP 400, or print why and exit 1;");
+    eprintln!("                       no other output or JSON files. Takes --timeout, --retries and --assert-header.");
+    eprintln!("\nIf neither --file nor positional URLs are supplied (outside serve), this message is shown and the program exits with code 2.");
     eprintln!("\nJSON Output Fields (in status.json or status_round_N.json):");
     eprintln!("  url (String):             The original URL checked.");
     eprintln!("  status (Number or String): HTTP status code (e.g., 200) if successful, or an error message string if failed (including header assertion failures).");
     eprintln!("  responseTimeMs (Number):  Total response time in milliseconds for the final attempt.");
     eprintln!("  timestampEpochS (Number): Timestamp of when the attempt completed, as seconds since UNIX_EPOCH.");
+    eprintln!("  uploadBytes, uploadTimeMs, uploadThroughputBytesPerS, serverProcessingMs (Number):");
+    eprintln!("                            Only present for successful upload checks (--upload-size).");
+    eprintln!("  contentHash (String):     Fingerprint of the response body (--watch-content, 2xx responses only).");
+    eprintln!("  maintenance (String):     Summary of the --maintenance event the check fell in, if any.");
 }
 
-fn truncate_url(url: &str, max_len: usize) -> String {
-    if url.len() > max_len && max_len > 3 {
-        format!("{}...", &url[..max_len - 3])
+// Runs a user command through the shell with the result serialized as JSON on stdin
+fn run_hook_command(cmd: &str, status: &WebsiteStatus) {
+    let mut command = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.args(["/C", cmd]);
+        c
     } else {
-        url.to_string()
+        let mut c = Command::new("sh");
+        c.args(["-c", cmd]);
+        c
+    };
+    let child = command.stdin(Stdio::piped()).spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => { warn!(command = cmd, error = %e, "failed to run hook command"); return; }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores stdin may close it early; that's not an error
+        let _ = stdin.write_all(status.to_json().as_bytes());
+        let _ = stdin.write_all(b"\n");
+    }
+    match child.wait() {
+        Ok(exit) if !exit.success() => warn!(command = cmd, status = %exit, "hook command failed"),
+        Ok(_) => {}
+        Err(e) => warn!(command = cmd, error = %e, "failed to wait for hook command"),
     }
 }
 
-fn escape_json_string(s: &str) -> String {
-    let mut escaped = String::with_capacity(s.len() + 10);
-    for c in s.chars() {
-        match c {
-            '"' => escaped.push_str("\\\""),
-            '\\' => escaped.push_str("\\\\"),
-            '\n' => escaped.push_str("\\n"),
-            '\r' => escaped.push_str("\\r"),
-            '\t' => escaped.push_str("\\t"),
-            _ => escaped.push(c),
-        }
+// Per-URL latency statistics across rounds, printed after the round summary in --period mode
+fn print_url_latency_stats(url_stats: &UrlStats) {
+    let rows: Vec<(UrlStatsRow, Percentiles, Duration)> = url_stats.stats().into_iter()
+        .filter_map(|row| Some((row.clone(), row.percentiles?, row.std_dev?)))
+        .collect();
+    if rows.is_empty() {
+        return;
+    }
+    println!("--- Response Times by URL (last {} successful checks) ---", LATENCY_WINDOW);
+    println!(
+        "{:<30} | {:>6} | {:>8} | {:>8} | {:>8} | {:>8} | {:>10} | {:>9}",
+        "URL", "Checks", "p50 ms", "p90 ms", "p95 ms", "p99 ms", "Std dev ms", "Jitter ms"
+    );
+    println!("{}", "-".repeat(109));
+    for (row, p, std_dev) in rows {
+        println!(
+            "{:<30} | {:>6} | {:>8} | {:>8} | {:>8} | {:>8} | {:>10.1} | {:>9}",
+            truncate_url(row.url, 28), row.window, p.p50.as_millis(), p.p90.as_millis(), p.p95.as_millis(), p.p99.as_millis(),
+            std_dev.as_secs_f64() * 1000.0,
+            row.jitter.map_or("-".to_string(), |jitter| format!("{:.1}", jitter.as_secs_f64() * 1000.0))
+        );
     }
-    escaped
+    println!();
 }
 
-fn write_json_output(statuses: &[WebsiteStatus], file_path: &str) -> Result<(), String> {
-    let file = File::create(file_path)
-        .map_err(|e| format!("Failed to create JSON output file {}: {}", file_path, e))?;
-    let mut writer = BufWriter::new(file);
+// --group-by: success rate and latency of this round per host or registered domain
+fn print_groups(by: GroupBy, statuses: &[WebsiteStatus]) {
+    let groups = group_results(statuses, by);
+    if groups.is_empty() {
+        return;
+    }
+    let title = match by {
+        GroupBy::Host => "Host",
+        GroupBy::Domain => "Domain",
+    };
+    println!("--- By {} ---", title);
+    println!("{:<30} | {:>4} | {:>6} | {:>9} | {:>8} | {:>8}", title, "URLs", "Checks", "Success %", "Avg ms", "p95 ms");
+    println!("{}", "-".repeat(83));
+    for group in &groups {
+        println!(
+            "{:<30} | {:>4} | {:>6} | {:>9.2} | {:>8} | {:>8}",
+            truncate_url(&group.name, 30),
+            group.urls,
+            group.checks,
+            group.success_rate(),
+            group.avg_response_time().map_or("-".to_string(), |avg| avg.as_millis().to_string()),
+            group.percentiles().map_or("-".to_string(), |p| p.p95.as_millis().to_string())
+        );
+    }
+    println!();
+}
 
-    writer.write_all(b"[\n").map_err(|e| format!("JSON write error: {}", e))?;
+// --top N: the slowest successful checks of this round and the most failing URLs so far
+fn print_top(n: usize, statuses: &[WebsiteStatus], url_stats: &UrlStats) {
+    let mut slowest: Vec<&WebsiteStatus> = statuses.iter().filter(|status| status.action_status.is_ok()).collect();
+    slowest.sort_by_key(|status| std::cmp::Reverse(status.response_time));
+    slowest.truncate(n);
+    let most_failing = url_stats.most_failing(n);
 
-    for (i, status) in statuses.iter().enumerate() {
-        writer.write_all(b"  {\n").map_err(|e| format!("JSON write error: {}", e))?;
+    println!("--- Top {} ---", n);
+    println!("Slowest successful checks (this round):");
+    if slowest.is_empty() {
+        println!("  (none)");
+    }
+    for (rank, status) in slowest.iter().enumerate() {
+        println!("  {:>2}. {:<40} {:>9}", rank + 1, truncate_url(&status.url, 40), format_response_time(status.response_time));
+    }
+    println!("Most failing URLs (since start):");
+    if most_failing.is_empty() {
+        println!("  (none)");
+    }
+    for (rank, row) in most_failing.iter().enumerate() {
+        println!(
+            "  {:>2}. {:<40} {:>5} of {} checks failed",
+            rank + 1, truncate_url(row.url, 40), row.totals.failures, row.totals.checks
+        );
+    }
+    println!();
+}
 
-        let url_json = format!("    \"url\": \"{}\",\n", escape_json_string(&status.url));
-        writer.write_all(url_json.as_bytes()).map_err(|e| format!("JSON write error: {}", e))?;
+// The URLs doing worst since the checker started, printed every --offenders-interval rounds
+fn print_top_offenders(url_stats: &UrlStats) {
+    let rows = url_stats.top_offenders(TOP_OFFENDERS);
+    if rows.is_empty() {
+        return;
+    }
+    let now = SystemTime::now();
+    println!("--- Top Offenders (since start) ---");
+    println!(
+        "{:<30} | {:>7} | {:>9} | {:>8} | {:>8} | Last failure",
+        "URL", "Checks", "Success %", "Avg ms", "p95 ms"
+    );
+    println!("{}", "-".repeat(96));
+    for row in rows {
+        println!(
+            "{:<30} | {:>7} | {:>9.2} | {:>8} | {:>8} | {}",
+            truncate_url(row.url, 28),
+            row.totals.checks,
+            row.success_rate(),
+            row.avg_response_time().map_or("-".to_string(), |avg| avg.as_millis().to_string()),
+            row.percentiles.map_or("-".to_string(), |p| p.p95.as_millis().to_string()),
+            row.totals.last_failure.map_or("-".to_string(), |at| format_ago(at, now))
+        );
+    }
+    println!();
+}
 
-        let status_json_val_str = match &status.action_status {
-            Ok(code) => code.to_string(),
-            Err(e_str) => format!("\"{}\"", escape_json_string(e_str)),
-        };
-        let status_json = format!("    \"status\": {},\n", status_json_val_str);
-        writer.write_all(status_json.as_bytes()).map_err(|e| format!("JSON write error: {}", e))?;
+// Apdex per URL and per tag since the checker started (--apdex-threshold)
+fn print_apdex(apdex: &ApdexTracker) {
+    println!("--- Apdex (T = {} ms, since start) ---", apdex.threshold().as_millis());
+    println!(
+        "{:<30} | {:>5} | {:<12} | {:>9} | {:>10} | {:>10}",
+        "URL / tag", "Score", "Rating", "Satisfied", "Tolerating", "Frustrated"
+    );
+    println!("{}", "-".repeat(91));
+    let urls = apdex.urls().iter().map(|(url, counts)| (truncate_url(url, 28), counts));
+    let tags = apdex.tags().iter().map(|(tag, counts)| (truncate_url(&format!("tag:{}", tag), 28), counts));
+    for (name, counts) in urls.chain(tags) {
+        let Some(score) = counts.score() else { continue };
+        println!(
+            "{:<30} | {:>5.2} | {:<12} | {:>9} | {:>10} | {:>10}",
+            name, score, apdex_rating(score), counts.satisfied, counts.tolerating, counts.frustrated
+        );
+    }
+    println!();
+}
+
+// `sha256:` plus the first 12 hex digits, enough to tell fingerprints apart by eye
+fn short_hash(hash: &str) -> &str {
+    &hash[..hash.len().min(19)]
+}
+
+// Counts characters, not bytes: tag names (and IDN URLs) may be any Unicode text
+fn truncate_url(url: &str, max_len: usize) -> String {
+    if url.chars().count() > max_len && max_len > 3 {
+        format!("{}...", url.chars().take(max_len - 3).collect::<String>())
+    } else {
+        url.to_string()
+    }
+}
 
-        let response_time_json = format!(
-            "    \"responseTimeMs\": {},\n",
-            status.response_time.as_millis()
+// `trend` is the sparkline column of --period mode
+fn print_status_row(status: &WebsiteStatus, color: bool, local_time: bool, trend: Option<&str>) {
+    let status_str = match &status.action_status {
+        Ok(code) => code.to_string(),
+        Err(e_str) => {
+            // Plugin errors are arbitrary text, so cut by characters rather than bytes
+            if e_str.chars().count() > 20 {
+                format!("ERR: {}...", e_str.chars().take(17).collect::<String>())
+            } else {
+                format!("ERR: {}", e_str)
+            }
+        }
+    };
+    let time = format_response_time(status.response_time);
+    let checked_at = format_timestamp(status.timestamp, local_time);
+    let status_str = paint(&format!("{:<8}", status_str), status_color(&status.action_status), color);
+    match trend {
+        Some(trend) => println!(
+            "{:<30} | {} | {:<10} | {:<26} | {}",
+            truncate_url(&status.url, 28), status_str, time, checked_at, trend
+        ),
+        None => println!(
+            "{:<30} | {} | {:<10} | {}",
+            truncate_url(&status.url, 28), status_str, time, checked_at
+        ),
+    }
+    if let Some(summary) = &status.maintenance {
+        println!("{:<30} | maintenance: {}", "", summary);
+    }
+    if let Some(upload) = &status.upload {
+        println!(
+            "{:<30} | upload: {} bytes in {} ms ({}), server processing {} ms",
+            "", upload.bytes, upload.upload_time.as_millis(),
+            format_throughput(upload.throughput_bytes_per_sec()), upload.processing_time.as_millis()
         );
-        writer.write_all(response_time_json.as_bytes()).map_err(|e| format!("JSON write error: {}", e))?;
+    }
+}
 
-        let timestamp_epoch_s = status.timestamp.duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let timestamp_json = format!("    \"timestampEpochS\": {}\n", timestamp_epoch_s);
-        writer.write_all(timestamp_json.as_bytes()).map_err(|e| format!("JSON write error: {}", e))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        writer.write_all(b"  }").map_err(|e| format!("JSON write error: {}", e))?;
-        if i < statuses.len() - 1 {
-            writer.write_all(b",\n").map_err(|e| format!("JSON write error: {}", e))?;
-        } else {
-            writer.write_all(b"\n").map_err(|e| format!("JSON write error: {}", e))?;
+    #[cfg(unix)]
+    #[test]
+    fn hook_commands_get_the_result_on_stdin() {
+        let path = env::temp_dir().join(format!("website-checker-{}-hook.json", std::process::id()));
+        let status = WebsiteStatus {
+            url: "https://example.com".to_string(),
+            action_status: Err("timed out".to_string()),
+            response_time: Duration::from_millis(5000),
+            timestamp: std::time::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            upload: None,
+            priority: Priority::default(),
+            tags: Vec::new(),
+            content_hash: None,
+            maintenance: None,
+        };
+        run_hook_command(&format!("cat > '{}'", path.display()), &status);
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(written, format!("{}\n", status.to_json()));
+        // A command that fails or ignores its input only gets a warning
+        run_hook_command("exit 3", &status);
+    }
+
+    #[test]
+    fn only_old_round_files_are_pruned() {
+        let dir = env::temp_dir().join(format!("website-checker-{}-rounds", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let cutoff = SystemTime::now() - Duration::from_secs(3_600);
+        for name in ["status_round_1.json", "status_round_2.json", "status_round_x.json", "notes.json"] {
+            let file = fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(cutoff - Duration::from_secs(60)).unwrap();
         }
+        fs::write(dir.join("status_round_3.json"), "[]").unwrap();
+        prune_round_files(&dir, cutoff);
+
+        let mut left: Vec<String> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect();
+        left.sort();
+        assert_eq!(left, ["notes.json", "status_round_3.json", "status_round_x.json"]);
+        fs::remove_dir_all(dir).unwrap();
     }
 
-    writer.write_all(b"]\n").map_err(|e| format!("JSON write error: {}", e))?;
-    writer.flush().map_err(|e| format!("JSON flush error: {}", e))?;
-    Ok(())
-}
\ No newline at end of file
+    fn probed(action_status: Result<u16, String>) -> WebsiteStatus {
+        WebsiteStatus {
+            url: "http://localhost:8080/health".to_string(),
+            action_status,
+            response_time: Duration::from_millis(12),
+            timestamp: SystemTime::now(),
+            upload: None,
+            priority: Priority::default(),
+            tags: Vec::new(),
+            content_hash: None,
+            maintenance: None,
+        }
+    }
+
+    #[test]
+    fn healthchecks_fail_on_errors_and_error_statuses() {
+        assert_eq!(healthcheck_problem(&probed(Ok(200))), None);
+        assert_eq!(healthcheck_problem(&probed(Ok(302))), None);
+        assert_eq!(healthcheck_problem(&probed(Ok(404))), Some("HTTP 404".to_string()));
+        assert_eq!(healthcheck_problem(&probed(Ok(503))), Some("HTTP 503".to_string()));
+        assert_eq!(healthcheck_problem(&probed(Err("connection refused".to_string()))), Some("connection refused".to_string()));
+    }
+
+    #[test]
+    fn healthcheck_takes_one_url_and_its_options() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let run = |list: &[&str]| run_healthcheck("checker", &args(list));
+        assert_eq!(run(&[]).unwrap_err(), "healthcheck requires a URL");
+        assert_eq!(run(&["http://a/", "http://b/"]).unwrap_err(), "healthcheck takes exactly one URL");
+        assert_eq!(run(&["--timeout", "0", "http://a/"]).unwrap_err(), "--timeout must be at least 1 second");
+        assert_eq!(run(&["--retries"]).unwrap_err(), "--retries requires an argument");
+        assert!(run(&["--assert-header", "no colon", "http://a/"]).unwrap_err().starts_with("Invalid format for --assert-header"));
+        assert_eq!(run(&["--period", "5", "http://a/"]).unwrap_err(), "Unknown option for healthcheck: --period");
+    }
+
+    #[test]
+    fn healthy_urls_pass_the_healthcheck() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/health", server.server_addr().to_ip().unwrap());
+        let serving = std::thread::spawn(move || {
+            let request = server.recv().unwrap();
+            request.respond(tiny_http::Response::from_string("ok")).unwrap();
+        });
+        run_healthcheck("checker", &["--timeout".to_string(), "5".to_string(), url]).unwrap();
+        serving.join().unwrap();
+    }
+}
@@ -0,0 +1,448 @@
+//! Planned maintenance from iCalendar (`.ics`) files, for `--maintenance`. Checks that
+//! fall inside a maintenance event are marked as maintenance: they are still run and
+//! recorded, but don't alert, open incidents or count against the SLA.
+//!
+//! Only the parts of RFC 5545 that maintenance calendars need are read: `VEVENT`s with
+//! `DTSTART`, `DTEND` or `DURATION`, `SUMMARY`, `STATUS:CANCELLED`, and simple daily or
+//! weekly `RRULE`s. An event's `URL` limits it to that URL and its `CATEGORIES` to URLs
+//! with one of those tags; an event with neither covers every URL.
+
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::parse_rfc3339_utc;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Repetition of a recurring event (RRULE with FREQ=DAILY or WEEKLY)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Recurrence {
+    every: Duration,
+    count: Option<u64>,
+    until: Option<SystemTime>,
+}
+
+/// One maintenance event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    pub summary: String,
+    /// Start of the (first) occurrence.
+    pub start: SystemTime,
+    pub duration: Duration,
+    /// The only URL the event covers, from its `URL` property.
+    pub url: Option<String>,
+    /// Tags of the URLs the event covers, from its `CATEGORIES`.
+    pub tags: Vec<String>,
+    recurrence: Option<Recurrence>,
+}
+
+impl MaintenanceWindow {
+    pub fn applies_to(&self, url: &str, tags: &[String]) -> bool {
+        if let Some(only) = &self.url {
+            return only == url;
+        }
+        self.tags.is_empty() || self.tags.iter().any(|tag| tags.contains(tag))
+    }
+
+    /// Whether `time` falls inside the event or one of its occurrences.
+    pub fn active_at(&self, time: SystemTime) -> bool {
+        let Ok(elapsed) = time.duration_since(self.start) else { return false };
+        let Some(recurrence) = &self.recurrence else { return elapsed < self.duration };
+        let n = elapsed.as_secs() / recurrence.every.as_secs();
+        if recurrence.count.is_some_and(|count| n >= count) {
+            return false;
+        }
+        let occurrence = self.start + recurrence.every * n as u32;
+        if recurrence.until.is_some_and(|until| occurrence > until) {
+            return false;
+        }
+        time.duration_since(occurrence).unwrap_or_default() < self.duration
+    }
+}
+
+/// The maintenance events of one or more calendar files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceCalendar {
+    windows: Vec<MaintenanceWindow>,
+}
+
+impl MaintenanceCalendar {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("Invalid calendar {}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut windows = Vec::new();
+        let mut events = 0;
+        let mut event: Option<Vec<ContentLine>> = None;
+        // Components nested in the current event, such as VALARM, whose properties
+        // aren't the event's
+        let mut nested = 0;
+        for line in unfold(text) {
+            let line = ContentLine::parse(&line)?;
+            let component = line.value.trim().to_ascii_uppercase();
+            match (line.name.as_str(), component.as_str()) {
+                ("BEGIN", "VEVENT") if event.is_none() => event = Some(Vec::new()),
+                ("END", "VEVENT") if nested == 0 => {
+                    let lines = event.take().ok_or("END:VEVENT without BEGIN:VEVENT")?;
+                    events += 1;
+                    if let Some(window) = build_window(&lines).map_err(|e| format!("event {}: {}", events, e))? {
+                        windows.push(window);
+                    }
+                }
+                ("BEGIN", _) if event.is_some() => nested += 1,
+                ("END", _) if event.is_some() => {
+                    if nested == 0 {
+                        return Err(format!("END:{} without BEGIN:{}", component, component));
+                    }
+                    nested -= 1;
+                }
+                _ => {
+                    if let (Some(lines), 0) = (&mut event, nested) {
+                        lines.push(line);
+                    }
+                }
+            }
+        }
+        if event.is_some() {
+            return Err("BEGIN:VEVENT without END:VEVENT".to_string());
+        }
+        Ok(MaintenanceCalendar { windows })
+    }
+
+    /// Adds the events of another calendar.
+    pub fn extend(&mut self, other: MaintenanceCalendar) {
+        self.windows.extend(other.windows);
+    }
+
+    pub fn windows(&self) -> &[MaintenanceWindow] {
+        &self.windows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// The first event covering `url` (with `tags`) at `time`.
+    pub fn active(&self, url: &str, tags: &[String], time: SystemTime) -> Option<&MaintenanceWindow> {
+        self.windows.iter().find(|window| window.applies_to(url, tags) && window.active_at(time))
+    }
+}
+
+// Joins folded lines: a line starting with a space or tab continues the previous one
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+// NAME;PARAM=VALUE;...:VALUE
+#[derive(Debug, Clone)]
+struct ContentLine {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl ContentLine {
+    fn parse(line: &str) -> Result<ContentLine, String> {
+        // The first colon outside a quoted parameter value ends the name and parameters
+        let mut quoted = false;
+        let colon = line.char_indices()
+            .find(|&(_, c)| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                c == ':' && !quoted
+            })
+            .map(|(i, _)| i)
+            .ok_or_else(|| format!("invalid line '{}'", line))?;
+        let mut parts = line[..colon].split(';');
+        let name = parts.next().unwrap_or_default().trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.trim().to_ascii_uppercase(), value.trim_matches('"').to_string()))
+            .collect();
+        Ok(ContentLine { name, params, value: line[colon + 1..].to_string() })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+// A maintenance window from the lines of one VEVENT; None for cancelled events
+fn build_window(lines: &[ContentLine]) -> Result<Option<MaintenanceWindow>, String> {
+    let get = |name: &str| lines.iter().find(|line| line.name == name);
+    let summary = get("SUMMARY").map_or_else(|| "Maintenance".to_string(), |line| unescape(&line.value));
+    if get("STATUS").is_some_and(|line| line.value.eq_ignore_ascii_case("CANCELLED")) {
+        return Ok(None);
+    }
+    let context = |e: String| format!("'{}': {}", summary, e);
+    let dtstart = get("DTSTART").ok_or_else(|| context("missing DTSTART".to_string()))?;
+    let (start, all_day) = parse_date_time(dtstart).map_err(context)?;
+    let duration = if let Some(dtend) = get("DTEND") {
+        let (end, _) = parse_date_time(dtend).map_err(context)?;
+        end.duration_since(start).map_err(|_| context("DTEND is before DTSTART".to_string()))?
+    } else if let Some(duration) = get("DURATION") {
+        parse_ical_duration(&duration.value).ok_or_else(|| context(format!("invalid DURATION '{}'", duration.value)))?
+    } else if all_day {
+        DAY
+    } else {
+        return Err(context("missing DTEND or DURATION".to_string()));
+    };
+    let recurrence = get("RRULE").map(|rrule| parse_rrule(&rrule.value, start)).transpose().map_err(context)?;
+    let tags = lines.iter()
+        .filter(|line| line.name == "CATEGORIES")
+        .flat_map(|line| split_list(&line.value))
+        .collect();
+    Ok(Some(MaintenanceWindow {
+        summary,
+        start,
+        duration,
+        url: get("URL").map(|line| line.value.trim().to_string()),
+        tags,
+        recurrence,
+    }))
+}
+
+// A DTSTART/DTEND value, and whether it is a date without a time. Times with a TZID
+// or without the Z suffix are read as UTC, as there is no time zone database here.
+fn parse_date_time(line: &ContentLine) -> Result<(SystemTime, bool), String> {
+    let value = line.value.trim();
+    let parse = |v: &str| -> Option<SystemTime> {
+        let date = format!("{}-{}-{}", v.get(0..4)?, v.get(4..6)?, v.get(6..8)?);
+        match v.get(8..) {
+            Some("") => parse_rfc3339_utc(&date),
+            Some(time) => {
+                let time = time.strip_prefix('T')?.trim_end_matches('Z');
+                let time = format!("{}:{}:{}", time.get(0..2)?, time.get(2..4)?, time.get(4..6)?);
+                parse_rfc3339_utc(&format!("{}T{}Z", date, time))
+            }
+            None => None,
+        }
+    };
+    let time = parse(value).ok_or_else(|| format!("invalid {} '{}'", line.name, value))?;
+    let all_day = line.param("VALUE").is_some_and(|v| v.eq_ignore_ascii_case("DATE")) || value.len() == 8;
+    Ok((time, all_day))
+}
+
+// [+]P[nW][nD][T[nH][nM][nS]], e.g. PT2H30M or P1D
+fn parse_ical_duration(s: &str) -> Option<Duration> {
+    let s = s.trim().strip_prefix('+').unwrap_or(s.trim());
+    let mut rest = s.strip_prefix('P')?;
+    let mut seconds = 0u64;
+    let mut in_time = false;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('T') {
+            in_time = true;
+            rest = after;
+            continue;
+        }
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let n: u64 = rest[..digits].parse().ok()?;
+        let unit = match (rest[digits..].chars().next()?, in_time) {
+            ('W', false) => 7 * 86_400,
+            ('D', false) => 86_400,
+            ('H', true) => 3_600,
+            ('M', true) => 60,
+            ('S', true) => 1,
+            _ => return None,
+        };
+        seconds = seconds.checked_add(n.checked_mul(unit)?)?;
+        rest = &rest[digits + 1..];
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+// FREQ=DAILY|WEEKLY with optional INTERVAL, COUNT and UNTIL. BYDAY may only name the
+// weekday the event starts on, which is what a weekly rule repeats on anyway.
+fn parse_rrule(s: &str, start: SystemTime) -> Result<Recurrence, String> {
+    let mut every = None;
+    let mut interval = 1u32;
+    let mut count = None;
+    let mut until = None;
+    for part in s.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part.split_once('=').ok_or_else(|| format!("invalid RRULE '{}'", s))?;
+        let invalid = || format!("invalid {} in RRULE '{}'", key, s);
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                every = Some(match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => DAY,
+                    "WEEKLY" => DAY * 7,
+                    _ => return Err(format!("unsupported RRULE frequency '{}' (expected DAILY or WEEKLY)", value)),
+                })
+            }
+            "INTERVAL" => interval = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+            "COUNT" => count = Some(value.parse().map_err(|_| invalid())?),
+            "UNTIL" => {
+                let line = ContentLine { name: "UNTIL".to_string(), params: Vec::new(), value: value.to_string() };
+                until = Some(parse_date_time(&line)?.0);
+            }
+            "BYDAY" if value.eq_ignore_ascii_case(weekday(start)) => {}
+            "BYDAY" => {
+                return Err(format!("unsupported BYDAY={} (only the weekday of DTSTART, {}, is supported)", value, weekday(start)))
+            }
+            "WKST" => {}
+            _ => return Err(format!("unsupported RRULE part '{}' (supported: FREQ, INTERVAL, COUNT, UNTIL)", key)),
+        }
+    }
+    let every = every.ok_or_else(|| format!("RRULE '{}' has no FREQ", s))?;
+    Ok(Recurrence { every: every * interval, count, until })
+}
+
+// Two-letter iCalendar weekday of a UTC time
+fn weekday(time: SystemTime) -> &'static str {
+    let days = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400;
+    // 1970-01-01 was a Thursday
+    ["TH", "FR", "SA", "SU", "MO", "TU", "WE"][(days % 7) as usize]
+}
+
+// A comma-separated TEXT list such as CATEGORIES
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    current.push('\\');
+                    current.push(next);
+                }
+            }
+            ',' => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+    items.iter().map(|item| unescape(item).trim().to_string()).filter(|item| !item.is_empty()).collect()
+}
+
+// TEXT escapes: \\ \; \, \n
+fn unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:Database upgrade\\, phase 1\r\n\
+        DTSTART:20240514T220000Z\r\n\
+        DTEND:20240514T233000Z\r\n\
+        URL:https://db.example.com/health\r\n\
+        BEGIN:VALARM\r\n\
+        TRIGGER:-PT15M\r\n\
+        DURATION:PT5M\r\n\
+        END:VALARM\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:Weekly patching\r\n\
+        DTSTART;TZID=Etc/UTC:20240514T020000\r\n\
+        DURATION:PT1H\r\n\
+        RRULE:FREQ=WEEKLY;BYDAY=TU;COUNT=2\r\n\
+        CATEGORIES:web,\r\n \\,edge\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:Called off\r\n\
+        DTSTART:20240515T000000Z\r\n\
+        DURATION:P1D\r\n\
+        STATUS:CANCELLED\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        DTSTART;VALUE=DATE:20240520\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    fn at(s: &str) -> SystemTime {
+        parse_rfc3339_utc(s).unwrap()
+    }
+
+    #[test]
+    fn events_are_read_from_calendars() {
+        let calendar = MaintenanceCalendar::parse(CALENDAR).unwrap();
+        let windows = calendar.windows();
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[0].summary, "Database upgrade, phase 1");
+        assert_eq!((windows[0].start, windows[0].duration), (at("2024-05-14T22:00:00Z"), Duration::from_secs(5_400)));
+        assert_eq!(windows[0].url.as_deref(), Some("https://db.example.com/health"));
+        assert_eq!(windows[1].tags, ["web", ",edge"]);
+        assert_eq!((windows[2].summary.as_str(), windows[2].start, windows[2].duration), ("Maintenance", at("2024-05-20"), DAY));
+    }
+
+    #[test]
+    fn events_cover_their_urls_and_times() {
+        let calendar = MaintenanceCalendar::parse(CALENDAR).unwrap();
+        let web = vec!["web".to_string()];
+        let active = |url: &str, tags: &[String], time: &str| calendar.active(url, tags, at(time)).map(|w| w.summary.as_str());
+
+        assert_eq!(active("https://db.example.com/health", &[], "2024-05-14T23:29:59Z"), Some("Database upgrade, phase 1"));
+        assert_eq!(active("https://db.example.com/health", &[], "2024-05-14T23:30:00Z"), None);
+        assert_eq!(active("https://db.example.com/", &web, "2024-05-14T23:00:00Z"), None);
+
+        assert_eq!(active("https://www.example.com", &web, "2024-05-14T02:30:00Z"), Some("Weekly patching"));
+        assert_eq!(active("https://www.example.com", &web, "2024-05-21T02:59:59Z"), Some("Weekly patching"));
+        assert_eq!(active("https://www.example.com", &web, "2024-05-15T02:30:00Z"), None);
+        assert_eq!(active("https://www.example.com", &web, "2024-05-28T02:30:00Z"), None);
+        assert_eq!(active("https://www.example.com", &[], "2024-05-14T02:30:00Z"), None);
+        assert_eq!(active("https://www.example.com", &web, "2024-05-14T01:59:59Z"), None);
+
+        assert_eq!(active("https://api.example.com", &[], "2024-05-20T23:00:00Z"), Some("Maintenance"));
+        assert_eq!(active("https://api.example.com", &[], "2024-05-15T12:00:00Z"), None);
+    }
+
+    #[test]
+    fn durations_and_rules() {
+        assert_eq!(parse_ical_duration("PT2H30M"), Some(Duration::from_secs(9_000)));
+        assert_eq!(parse_ical_duration("+P1W2DT1S"), Some(Duration::from_secs(9 * 86_400 + 1)));
+        for invalid in ["2H", "P1H", "PT1D", "PTxM", "P99999999999999999W", "P1DT18446744073709551615S"] {
+            assert_eq!(parse_ical_duration(invalid), None, "{}", invalid);
+        }
+
+        let tuesday = at("2024-05-14T02:00:00Z");
+        let rule = parse_rrule("FREQ=DAILY;INTERVAL=2;UNTIL=20240520T000000Z", tuesday).unwrap();
+        assert_eq!(rule, Recurrence { every: DAY * 2, count: None, until: Some(at("2024-05-20")) });
+        assert_eq!(parse_rrule("FREQ=WEEKLY;BYDAY=MO", tuesday).unwrap_err(), "unsupported BYDAY=MO (only the weekday of DTSTART, TU, is supported)");
+        assert!(parse_rrule("FREQ=MONTHLY", tuesday).unwrap_err().starts_with("unsupported RRULE frequency"));
+        assert!(parse_rrule("INTERVAL=0;FREQ=DAILY", tuesday).is_err());
+        assert_eq!(parse_rrule("COUNT=3", tuesday).unwrap_err(), "RRULE 'COUNT=3' has no FREQ");
+    }
+
+    #[test]
+    fn invalid_calendars() {
+        let event = |lines: &str| MaintenanceCalendar::parse(&format!("BEGIN:VEVENT\n{}\nEND:VEVENT\n", lines));
+        assert_eq!(event("SUMMARY:Patch\nDTSTART:20240514T020000Z").unwrap_err(), "event 1: 'Patch': missing DTEND or DURATION");
+        assert_eq!(event("DTSTART:20240514T020000Z\nDTEND:20240514T010000Z").unwrap_err(), "event 1: 'Maintenance': DTEND is before DTSTART");
+        assert!(event("DTSTART:tomorrow\nDURATION:PT1H").unwrap_err().contains("invalid DTSTART 'tomorrow'"));
+        assert_eq!(MaintenanceCalendar::parse("BEGIN:VEVENT\n").unwrap_err(), "BEGIN:VEVENT without END:VEVENT");
+        assert_eq!(MaintenanceCalendar::parse("END:VEVENT\n").unwrap_err(), "END:VEVENT without BEGIN:VEVENT");
+        let stray_end = "BEGIN:VEVENT\nDTSTART:20240514T020000Z\nDURATION:PT1H\nEND:VALARM\nEND:VEVENT\n";
+        assert_eq!(MaintenanceCalendar::parse(stray_end).unwrap_err(), "END:VALARM without BEGIN:VALARM");
+        assert!(event("DTSTART:20240514T020000Z\nDURATION:P99999999999999999W").unwrap_err().contains("invalid DURATION 'P99999999999999999W'"));
+        assert!(MaintenanceCalendar::parse("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,302 @@
+//! Machine-readable metrics written after every round: a JSON summary of the round
+//! (`--summary-json`) and Prometheus text exposition (`--metrics-file`), e.g. for
+//! node_exporter's textfile collector.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::stats::{std_dev, ApdexTracker, Histogram, Percentiles, UrlStats};
+use crate::{RoundStats, WebsiteStatus};
+
+/// The round summary as a JSON object. The histogram covers the round's successful
+/// checks; its bucket counts are cumulative (each includes the buckets below it) and
+/// `count` includes the checks above the last bound.
+pub fn summary_json(round: u64, stats: &RoundStats, buckets: &[Duration]) -> String {
+    let ms = |duration: Option<Duration>| duration.map_or("null".to_string(), |d| d.as_millis().to_string());
+    let avg = if stats.successful_checks > 0 {
+        format!("{:.2}", stats.total_time.as_secs_f64() * 1000.0 / stats.successful_checks as f64)
+    } else {
+        "null".to_string()
+    };
+    let percentiles = Percentiles::from_samples(stats.response_times.iter().copied());
+    let histogram = Histogram::from_samples(buckets.to_vec(), &stats.response_times);
+    let bucket_json: Vec<String> = histogram.cumulative().iter()
+        .map(|(bound, count)| format!("{{\"leMs\":{},\"count\":{}}}", bound.as_millis(), count))
+        .collect();
+    format!(
+        "{{\"round\":{},\"timestampEpochS\":{},\"total\":{},\"successful\":{},\"failed\":{},\
+         \"minResponseTimeMs\":{},\"maxResponseTimeMs\":{},\"avgResponseTimeMs\":{},\
+         \"stdDevMs\":{},\"p50Ms\":{},\"p90Ms\":{},\"p95Ms\":{},\"p99Ms\":{},\
+         \"histogram\":{{\"buckets\":[{}],\"count\":{},\"sumMs\":{}}}}}",
+        round,
+        epoch_s(SystemTime::now()),
+        stats.successful_checks + stats.failed_checks,
+        stats.successful_checks,
+        stats.failed_checks,
+        ms(stats.min_time),
+        ms(stats.max_time),
+        avg,
+        std_dev(&stats.response_times).map_or("null".to_string(), |d| format!("{:.2}", d.as_secs_f64() * 1000.0)),
+        ms(percentiles.map(|p| p.p50)),
+        ms(percentiles.map(|p| p.p90)),
+        ms(percentiles.map(|p| p.p95)),
+        ms(percentiles.map(|p| p.p99)),
+        bucket_json.join(","),
+        histogram.count(),
+        histogram.sum().as_millis()
+    )
+}
+
+// Everything exported about one URL since the checker started
+#[derive(Debug, Clone)]
+struct UrlMetrics {
+    up: bool,
+    checks: u64,
+    failures: u64,
+    last_check: SystemTime,
+    latency: Histogram,
+}
+
+/// Per-URL counters and latency histograms accumulated over the whole run, as
+/// Prometheus expects of counters and histograms.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    buckets: Vec<Duration>,
+    urls: BTreeMap<String, UrlMetrics>,
+}
+
+impl Metrics {
+    pub fn new(buckets: Vec<Duration>) -> Self {
+        Metrics { buckets, urls: BTreeMap::new() }
+    }
+
+    pub fn record(&mut self, status: &WebsiteStatus) {
+        let url = self.urls.entry(status.url.clone()).or_insert_with(|| UrlMetrics {
+            up: false,
+            checks: 0,
+            failures: 0,
+            last_check: status.timestamp,
+            latency: Histogram::new(self.buckets.clone()),
+        });
+        url.up = status.action_status.is_ok();
+        url.checks += 1;
+        url.last_check = status.timestamp;
+        if url.up {
+            url.latency.record(status.response_time);
+        } else {
+            url.failures += 1;
+        }
+    }
+
+    /// Prometheus text exposition format (version 0.0.4). Downtime comes from
+    /// `url_stats`, which `--state-file` keeps across restarts, and Apdex scores from
+    /// `apdex` (with `--apdex-threshold`).
+    pub fn to_prometheus(&self, url_stats: &UrlStats, apdex: Option<&ApdexTracker>) -> String {
+        let now = SystemTime::now();
+        let mut out = String::new();
+        let mut family = |name: &str, kind: &str, help: &str, samples: &mut dyn Iterator<Item = String>| {
+            out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+            for sample in samples {
+                out.push_str(&sample);
+                out.push('\n');
+            }
+        };
+        family(
+            "website_status_up",
+            "gauge",
+            "Whether the last check of the URL succeeded (1) or failed (0).",
+            &mut self.urls.iter().map(|(url, m)| format!("website_status_up{{url=\"{}\"}} {}", escape_label(url), m.up as u8)),
+        );
+        family(
+            "website_status_checks_total",
+            "counter",
+            "Checks of the URL since the checker started.",
+            &mut self.urls.iter().map(|(url, m)| format!("website_status_checks_total{{url=\"{}\"}} {}", escape_label(url), m.checks)),
+        );
+        family(
+            "website_status_failures_total",
+            "counter",
+            "Failed checks of the URL since the checker started.",
+            &mut self.urls.iter().map(|(url, m)| format!("website_status_failures_total{{url=\"{}\"}} {}", escape_label(url), m.failures)),
+        );
+        family(
+            "website_status_last_check_timestamp_seconds",
+            "gauge",
+            "When the last check of the URL completed, as a Unix timestamp.",
+            &mut self.urls.iter().map(|(url, m)| {
+                format!("website_status_last_check_timestamp_seconds{{url=\"{}\"}} {}", escape_label(url), epoch_s(m.last_check))
+            }),
+        );
+        family(
+            "website_status_response_time_seconds",
+            "histogram",
+            "Response time of the URL's successful checks.",
+            &mut self.urls.iter().flat_map(|(url, m)| histogram_samples("website_status_response_time_seconds", url, &m.latency)),
+        );
+        family(
+            "website_status_downtime_seconds_total",
+            "counter",
+            "Time the URL has been down, from a failed check to the next successful one, including the current outage.",
+            &mut url_stats.stats().into_iter().map(|row| {
+                format!(
+                    "website_status_downtime_seconds_total{{url=\"{}\"}} {}",
+                    escape_label(row.url),
+                    row.totals.downtime_at(now).as_secs()
+                )
+            }),
+        );
+        if let Some(apdex) = apdex {
+            family(
+                "website_status_apdex_threshold_seconds",
+                "gauge",
+                "The Apdex threshold T: checks within T are satisfied, within 4T tolerating.",
+                &mut std::iter::once(format!("website_status_apdex_threshold_seconds {}", apdex.threshold().as_secs_f64())),
+            );
+            family(
+                "website_status_apdex_score",
+                "gauge",
+                "Apdex score of the URL since the checker started, from 0 to 1.",
+                &mut apdex.urls().iter().filter_map(|(url, counts)| {
+                    Some(format!("website_status_apdex_score{{url=\"{}\"}} {:.4}", escape_label(url), counts.score()?))
+                }),
+            );
+            family(
+                "website_status_apdex_checks_total",
+                "counter",
+                "Checks of the URL by Apdex zone (satisfied, tolerating, frustrated).",
+                &mut apdex.urls().iter().flat_map(|(url, counts)| {
+                    let url = escape_label(url);
+                    [("satisfied", counts.satisfied), ("tolerating", counts.tolerating), ("frustrated", counts.frustrated)]
+                        .map(|(zone, n)| format!("website_status_apdex_checks_total{{url=\"{}\",zone=\"{}\"}} {}", url, zone, n))
+                }),
+            );
+            family(
+                "website_status_apdex_tag_score",
+                "gauge",
+                "Apdex score of all URLs with the tag since the checker started, from 0 to 1.",
+                &mut apdex.tags().iter().filter_map(|(tag, counts)| {
+                    Some(format!("website_status_apdex_tag_score{{tag=\"{}\"}} {:.4}", escape_label(tag), counts.score()?))
+                }),
+            );
+        }
+        out
+    }
+}
+
+// The _bucket, _sum and _count samples of one histogram
+fn histogram_samples(name: &str, url: &str, histogram: &Histogram) -> Vec<String> {
+    let url = escape_label(url);
+    let mut samples: Vec<String> = histogram.cumulative().iter()
+        .map(|(bound, count)| format!("{}_bucket{{url=\"{}\",le=\"{}\"}} {}", name, url, bound.as_secs_f64(), count))
+        .collect();
+    samples.push(format!("{}_bucket{{url=\"{}\",le=\"+Inf\"}} {}", name, url, histogram.count()));
+    samples.push(format!("{}_sum{{url=\"{}\"}} {}", name, url, histogram.sum().as_secs_f64()));
+    samples.push(format!("{}_count{{url=\"{}\"}} {}", name, url, histogram.count()));
+    samples
+}
+
+// Label values escape backslashes, double quotes and line feeds
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Replaces `path` with `contents` through a temporary file, so readers never see a
+/// half-written file.
+pub fn write_atomic(path: &str, contents: &str) -> Result<(), String> {
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, contents).map_err(|e| format!("Failed to write {}: {}", tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to replace {}: {}", path, e))
+}
+
+fn epoch_s(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::ApdexTracker;
+    use crate::testing::{result, result_at};
+
+    fn timed(url: &str, action_status: Result<u16, String>, ms: u64) -> WebsiteStatus {
+        WebsiteStatus { response_time: Duration::from_millis(ms), ..result(url, action_status) }
+    }
+
+    #[test]
+    fn round_summaries_include_the_histogram() {
+        let mut stats = RoundStats::new();
+        for status in [
+            timed("https://a.example.com", Ok(200), 40),
+            timed("https://b.example.com", Ok(200), 120),
+            timed("https://c.example.com", Ok(200), 900),
+            timed("https://d.example.com", Err("timed out".to_string()), 5_000),
+        ] {
+            stats.update(&status);
+        }
+        let json = summary_json(3, &stats, &[Duration::from_millis(50), Duration::from_millis(500)]);
+        assert!(json.starts_with("{\"round\":3,\"timestampEpochS\":"), "{}", json);
+        assert!(json.contains(",\"total\":4,\"successful\":3,\"failed\":1,\"minResponseTimeMs\":40,\"maxResponseTimeMs\":900,"), "{}", json);
+        assert!(json.contains("\"p50Ms\":120,\"p90Ms\":900,"), "{}", json);
+        assert!(json.ends_with(
+            "\"histogram\":{\"buckets\":[{\"leMs\":50,\"count\":1},{\"leMs\":500,\"count\":2}],\"count\":3,\"sumMs\":1060}}"
+        ), "{}", json);
+
+        let empty = summary_json(1, &RoundStats::new(), &[Duration::from_millis(50)]);
+        assert!(empty.contains("\"avgResponseTimeMs\":null,\"stdDevMs\":null,\"p50Ms\":null,"), "{}", empty);
+    }
+
+    #[test]
+    fn prometheus_histograms_per_url() {
+        let mut metrics = Metrics::new(vec![Duration::from_millis(100), Duration::from_secs(1)]);
+        let url = "https://example.com/?q=\"x\"";
+        for status in [timed(url, Ok(200), 80), timed(url, Ok(200), 1_500), timed(url, Err("HTTP 500".to_string()), 10)] {
+            metrics.record(&status);
+        }
+        let text = metrics.to_prometheus(&UrlStats::new(), None);
+        let label = "url=\"https://example.com/?q=\\\"x\\\"\"";
+        for line in [
+            "# TYPE website_status_response_time_seconds histogram".to_string(),
+            format!("website_status_up{{{}}} 0", label),
+            format!("website_status_checks_total{{{}}} 3", label),
+            format!("website_status_failures_total{{{}}} 1", label),
+            format!("website_status_response_time_seconds_bucket{{{},le=\"0.1\"}} 1", label),
+            format!("website_status_response_time_seconds_bucket{{{},le=\"1\"}} 1", label),
+            format!("website_status_response_time_seconds_bucket{{{},le=\"+Inf\"}} 2", label),
+            format!("website_status_response_time_seconds_sum{{{}}} 1.58", label),
+            format!("website_status_response_time_seconds_count{{{}}} 2", label),
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {} in\n{}", line, text);
+        }
+        assert!(!text.contains("apdex"));
+        assert_eq!(escape_label("a\\b\nc"), "a\\\\b\\nc");
+    }
+
+    #[test]
+    fn downtime_comes_from_the_url_statistics() {
+        let mut url_stats = UrlStats::new();
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        url_stats.record(&result_at("https://example.com", Err("timed out".to_string()), at(60)));
+        url_stats.record(&result_at("https://example.com", Ok(200), at(150)));
+        let text = Metrics::new(Vec::new()).to_prometheus(&url_stats, None);
+        assert!(text.contains("# TYPE website_status_downtime_seconds_total counter\n"));
+        assert!(text.contains("\nwebsite_status_downtime_seconds_total{url=\"https://example.com\"} 90\n"), "{}", text);
+    }
+
+    #[test]
+    fn apdex_scores_are_exported_with_a_threshold() {
+        let mut apdex = ApdexTracker::new(Duration::from_millis(250));
+        let status = WebsiteStatus { tags: vec!["web".to_string()], ..timed("https://example.com", Ok(200), 600) };
+        apdex.record(&status);
+        let text = Metrics::new(Vec::new()).to_prometheus(&UrlStats::new(), Some(&apdex));
+        for line in [
+            "website_status_apdex_threshold_seconds 0.25",
+            "website_status_apdex_score{url=\"https://example.com\"} 0.5000",
+            "website_status_apdex_checks_total{url=\"https://example.com\",zone=\"tolerating\"} 1",
+            "website_status_apdex_checks_total{url=\"https://example.com\",zone=\"frustrated\"} 0",
+            "website_status_apdex_tag_score{tag=\"web\"} 0.5000",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {} in\n{}", line, text);
+        }
+    }
+}
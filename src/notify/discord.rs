@@ -0,0 +1,145 @@
+//! Discord webhook notifier: one embed per failure/recovery/flapping event, batched into as few
+//! messages as Discord allows.
+
+use super::{post, Event, Notifier, RetryPolicy};
+use crate::{escape_json_string, format_duration, format_rfc3339_utc};
+
+/// Discord rejects messages with more than 10 embeds.
+const MAX_EMBEDS_PER_MESSAGE: usize = 10;
+/// Messages per round; anything beyond is summarized in the last embed.
+const MAX_MESSAGES_PER_ROUND: usize = 3;
+
+const COLOR_FAILURE: u32 = 0xE74C3C;
+const COLOR_RECOVERY: u32 = 0x2ECC71;
+const COLOR_FLAPPING: u32 = 0xF39C12;
+const COLOR_OVERFLOW: u32 = 0x95A5A6;
+
+pub struct DiscordNotifier {
+    client: reqwest::blocking::Client,
+    webhook_url: String,
+    retry: RetryPolicy,
+}
+
+impl DiscordNotifier {
+    pub fn new(client: reqwest::blocking::Client, webhook_url: String, retry: RetryPolicy) -> Self {
+        DiscordNotifier { client, webhook_url, retry }
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    fn notify(&self, events: &[Event]) -> Result<(), String> {
+        let mut embeds: Vec<String> = events.iter().filter_map(format_embed).collect();
+        let limit = MAX_EMBEDS_PER_MESSAGE * MAX_MESSAGES_PER_ROUND;
+        if embeds.len() > limit {
+            let omitted = embeds.len() - (limit - 1);
+            embeds.truncate(limit - 1);
+            embeds.push(format!(
+                "{{\"title\":\"...and {} more state changes\",\"color\":{}}}",
+                omitted, COLOR_OVERFLOW
+            ));
+        }
+        for chunk in embeds.chunks(MAX_EMBEDS_PER_MESSAGE) {
+            let body = format!("{{\"embeds\":[{}]}}", chunk.join(","));
+            self.retry.run(|| post(&self.client, &self.webhook_url, "application/json", &body))?;
+        }
+        Ok(())
+    }
+}
+
+fn format_embed(event: &Event) -> Option<String> {
+    let (result, outcome) = (event.result()?, event.outcome()?);
+    let (title, color) = match event {
+        Event::Failure { reminder: true, .. } => ("Check still failing", COLOR_FAILURE),
+        Event::Failure { .. } => ("Check failed", COLOR_FAILURE),
+        Event::Recovery { .. } => ("Check recovered", COLOR_RECOVERY),
+        Event::Flapping { .. } => ("Check flapping", COLOR_FLAPPING),
+        Event::Summary { .. } => return None,
+    };
+    let mut fields = vec![
+        embed_field("Status", &outcome, false),
+        embed_field("Latency", &format!("{} ms", result.response_time.as_millis()), true),
+        embed_field("Round", &event.round().to_string(), true),
+    ];
+    if let Some(outage) = event.outage() {
+        fields.push(embed_field("Down for", &format_duration(outage), true));
+    }
+    if let Some(flap_rate) = event.flap_rate() {
+        fields.push(embed_field("Flap rate", &format!("{:.1}%", flap_rate), true));
+    }
+    Some(format!(
+        "{{\"title\":\"{}\",\"description\":\"{}\",\"color\":{},\"timestamp\":\"{}\",\"fields\":[{}]}}",
+        title,
+        escape_json_string(&result.url),
+        color,
+        format_rfc3339_utc(result.timestamp),
+        fields.join(",")
+    ))
+}
+
+fn embed_field(name: &str, value: &str, inline: bool) -> String {
+    format!(
+        "{{\"name\":\"{}\",\"value\":\"{}\",\"inline\":{}}}",
+        escape_json_string(name),
+        escape_json_string(value),
+        inline
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{result, TestServer};
+
+    fn failure(i: usize) -> Event {
+        Event::Failure { round: 2, result: result(&format!("https://example.com/{}", i), Err("timed out".to_string())), reminder: false }
+    }
+
+    fn embeds(body: &str) -> Vec<serde_json::Value> {
+        let message: serde_json::Value = serde_json::from_str(body).unwrap();
+        message["embeds"].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn embeds_are_valid_json() {
+        let recovery = Event::Recovery {
+            round: 2,
+            result: result("https://example.com/\"quoted\"", Ok(200)),
+            outage: Duration::from_secs(3_700),
+        };
+        let embed: serde_json::Value = serde_json::from_str(&format_embed(&recovery).unwrap()).unwrap();
+        assert_eq!(embed["title"], "Check recovered");
+        assert_eq!(embed["description"], "https://example.com/\"quoted\"");
+        assert_eq!(embed["color"], COLOR_RECOVERY);
+        assert_eq!(embed["fields"][3]["name"], "Down for");
+        assert_eq!(embed["fields"][3]["value"], "1h 1m");
+        assert!(format_embed(&Event::Summary { round: 2, summary: Default::default() }).is_none());
+    }
+
+    #[test]
+    fn events_are_batched_ten_embeds_a_message() {
+        let server = TestServer::start(|_| (204, String::new()));
+        let notifier = DiscordNotifier::new(reqwest::blocking::Client::new(), server.url.clone(), RetryPolicy::default());
+        notifier.notify(&(0..12).map(failure).collect::<Vec<_>>()).unwrap();
+        let sizes: Vec<usize> = server.requests().iter().map(|request| embeds(&request.body).len()).collect();
+        assert_eq!(sizes, [10, 2]);
+    }
+
+    #[test]
+    fn large_rounds_end_with_an_overflow_embed() {
+        let server = TestServer::start(|_| (204, String::new()));
+        let notifier = DiscordNotifier::new(reqwest::blocking::Client::new(), server.url.clone(), RetryPolicy::default());
+        notifier.notify(&(0..45).map(failure).collect::<Vec<_>>()).unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), MAX_MESSAGES_PER_ROUND);
+        let last = embeds(&requests[2].body);
+        assert_eq!(last.len(), 10);
+        assert_eq!(last[8]["description"], "https://example.com/28");
+        assert_eq!(last[9]["title"], "...and 16 more state changes");
+    }
+}
@@ -0,0 +1,439 @@
+//! SMTP e-mail notifier, configured with `[[email]]` tables in the `--config` file.
+//! Sends one digest per round with the failures, recoveries and flapping URLs, and
+//! an immediate alert when a `priority=critical` check fails, which the digest then
+//! leaves out. Mails have plain-text and HTML parts.
+
+use std::env;
+use std::time::Duration;
+
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::Deserialize;
+
+use super::template::MessageTemplate;
+use super::{Event, Notifier, RetryPolicy};
+use crate::{format_rfc3339_utc, html_escape, CheckResult, Priority};
+
+/// How the SMTP connection is secured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum TlsMode {
+    /// Plain connection upgraded with STARTTLS (default port 587).
+    #[default]
+    #[serde(rename = "starttls")]
+    StartTls,
+    /// TLS from the first byte (default port 465).
+    #[serde(rename = "tls")]
+    Tls,
+    /// No encryption (default port 25); only for local relays.
+    #[serde(rename = "none")]
+    Off,
+}
+
+/// One `[[email]]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    /// Channel name for routes and escalation policies (default: `email`).
+    pub name: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: TlsMode,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Environment variable holding the password, to keep it out of the file.
+    pub password_env: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default = "default_subject_prefix")]
+    pub subject_prefix: String,
+    /// Send a digest of failures and recoveries at the end of each round.
+    #[serde(default = "default_true")]
+    pub digest: bool,
+    /// Send an alert as soon as a `priority=critical` check fails.
+    #[serde(default = "default_true")]
+    pub immediate: bool,
+    /// Template file for the plain-text part; also used, preformatted, for the HTML
+    /// part unless `html_template` is set.
+    pub text_template: Option<String>,
+    /// Template file for the HTML part; values are HTML-escaped.
+    pub html_template: Option<String>,
+}
+
+fn default_subject_prefix() -> String {
+    "[status-checker]".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub struct EmailNotifier {
+    transport: SmtpTransport,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    subject_prefix: String,
+    digest: bool,
+    immediate: bool,
+    text_template: Option<MessageTemplate>,
+    html_template: Option<MessageTemplate>,
+    retry: RetryPolicy,
+}
+
+impl EmailNotifier {
+    pub fn new(config: &EmailConfig, retry: RetryPolicy) -> Result<Self, String> {
+        let from: Mailbox = config.from.parse().map_err(|e| format!("invalid from address '{}': {}", config.from, e))?;
+        if config.to.is_empty() {
+            return Err("at least one 'to' address is required".to_string());
+        }
+        let to = config.to.iter()
+            .map(|addr| addr.parse().map_err(|e| format!("invalid to address '{}': {}", addr, e)))
+            .collect::<Result<Vec<Mailbox>, String>>()?;
+
+        let password = match (&config.password, &config.password_env) {
+            (Some(_), Some(_)) => return Err("set either password or password_env, not both".to_string()),
+            (Some(password), None) => Some(password.clone()),
+            (None, Some(var)) => Some(env::var(var).map_err(|_| format!("environment variable {} is not set", var))?),
+            (None, None) => None,
+        };
+
+        let builder = match config.tls {
+            TlsMode::StartTls => SmtpTransport::starttls_relay(&config.host),
+            TlsMode::Tls => SmtpTransport::relay(&config.host),
+            TlsMode::Off => Ok(SmtpTransport::builder_dangerous(&config.host)),
+        };
+        let mut builder = builder
+            .map_err(|e| format!("SMTP setup for {} failed: {}", config.host, e))?
+            .timeout(Some(Duration::from_secs(10)));
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        } else if config.tls == TlsMode::Off {
+            builder = builder.port(25);
+        }
+        match (&config.username, password) {
+            (Some(username), Some(password)) => {
+                builder = builder.credentials(Credentials::new(username.clone(), password));
+            }
+            (Some(_), None) => return Err("username requires password or password_env".to_string()),
+            (None, Some(_)) => return Err("password requires username".to_string()),
+            (None, None) => {}
+        }
+
+        let text_template = config.text_template.as_deref()
+            .map(|path| MessageTemplate::load(path, str::to_string))
+            .transpose()?;
+        let html_template = config.html_template.as_deref()
+            .map(|path| MessageTemplate::load(path, html_escape))
+            .transpose()?;
+
+        Ok(EmailNotifier {
+            transport: builder.build(),
+            from,
+            to,
+            subject_prefix: config.subject_prefix.clone(),
+            digest: config.digest,
+            immediate: config.immediate,
+            text_template,
+            html_template,
+            retry,
+        })
+    }
+
+    // Renders the configured templates, falling back to the built-in bodies
+    fn bodies(
+        &self,
+        events: &[Event],
+        immediate: bool,
+        default: impl FnOnce() -> (String, String),
+    ) -> Result<(String, String), String> {
+        let text = self.text_template.as_ref().map(|t| t.render_round(events, immediate)).transpose()?;
+        let html = self.html_template.as_ref().map(|t| t.render_round(events, immediate)).transpose()?;
+        Ok(match (text, html) {
+            (Some(text), Some(html)) => (text, html),
+            (Some(text), None) => {
+                let html = format!("<pre>{}</pre>\n", html_escape(&text));
+                (text, html)
+            }
+            (None, html) => {
+                let (default_text, default_html) = default();
+                (default_text, html.unwrap_or(default_html))
+            }
+        })
+    }
+
+    fn send(&self, subject: &str, plain: String, html: String) -> Result<(), String> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(format!("{} {}", self.subject_prefix, subject));
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        let message = builder
+            .multipart(MultiPart::alternative_plain_html(plain, html))
+            .map_err(|e| format!("failed to build message: {}", e))?;
+        self.retry.run(|| self.transport.send(&message).map(|_| ()).map_err(|e| e.to_string()))
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn notify(&self, events: &[Event]) -> Result<(), String> {
+        if !self.digest {
+            return Ok(());
+        }
+        let failures: Vec<&Event> = events.iter().filter(|e| matches!(e, Event::Failure { .. })).collect();
+        let recoveries: Vec<&Event> = events.iter().filter(|e| matches!(e, Event::Recovery { .. })).collect();
+        let flapping: Vec<&Event> = events.iter().filter(|e| matches!(e, Event::Flapping { .. })).collect();
+        if failures.is_empty() && recoveries.is_empty() && flapping.is_empty() {
+            return Ok(());
+        }
+        let round = events.first().map_or(0, |e| e.round());
+        let mut subject = format!("Round {}: {} failing, {} recovered", round, failures.len(), recoveries.len());
+        if !flapping.is_empty() {
+            subject.push_str(&format!(", {} flapping", flapping.len()));
+        }
+
+        let sections = [("Failed", &failures), ("Recovered", &recoveries), ("Flapping", &flapping)];
+        let (plain, html) = self.bodies(events, false, || digest_bodies(&subject, &sections, events))?;
+        self.send(&subject, plain, html)
+    }
+
+    fn sends_now(&self, event: &Event) -> bool {
+        matches!(event, Event::Failure { result, .. } if self.immediate && result.priority == Priority::Critical)
+    }
+
+    fn notify_now(&self, event: &Event) -> Result<(), String> {
+        let Some(result) = event.result().filter(|_| self.sends_now(event)) else { return Ok(()) };
+        let outcome = event.outcome().unwrap_or_default();
+        let subject = if event.is_reminder() {
+            format!("CRITICAL: {} is still down", result.url)
+        } else {
+            format!("CRITICAL: {} is down", result.url)
+        };
+        let details = alert_details(result, &outcome, event.round());
+
+        let (plain, html) = self.bodies(std::slice::from_ref(event), true, || alert_bodies(&subject, &details))?;
+        self.send(&subject, plain, html)
+    }
+}
+
+fn digest_bodies(subject: &str, sections: &[(&str, &Vec<&Event>)], events: &[Event]) -> (String, String) {
+    let mut plain = format!("{}\n", subject);
+    let mut html = format!("<h2>{}</h2>\n", html_escape(subject));
+    for &(title, section) in sections {
+        if section.is_empty() {
+            continue;
+        }
+        plain.push_str(&format!("\n{}:\n", title));
+        html.push_str(&format!(
+            "<h3>{}</h3>\n<table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">\n\
+             <tr><th>URL</th><th>Priority</th><th>Status</th><th>Time (ms)</th></tr>\n",
+            title
+        ));
+        for event in section {
+            if let (Some(result), Some(mut outcome)) = (event.result(), event.outcome()) {
+                if let Some(note) = event.note() {
+                    outcome.push_str(&format!(" ({})", note));
+                }
+                plain.push_str(&format!(
+                    "  [{}] {} - {} ({} ms)\n",
+                    result.priority, result.url, outcome, result.response_time.as_millis()
+                ));
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    html_escape(&result.url),
+                    result.priority,
+                    html_escape(&outcome),
+                    result.response_time.as_millis()
+                ));
+            }
+        }
+        html.push_str("</table>\n");
+    }
+    if let Some(Event::Summary { summary, .. }) = events.iter().find(|e| matches!(e, Event::Summary { .. })) {
+        let avg = summary.avg_response_time.map_or("n/a".to_string(), |avg| format!("{} ms", avg.as_millis()));
+        let totals = format!(
+            "Round totals: {} checked, {} successful, {} failed, average response time {}",
+            summary.total, summary.successful, summary.failed, avg
+        );
+        plain.push_str(&format!("\n{}\n", totals));
+        html.push_str(&format!("<p>{}</p>\n", html_escape(&totals)));
+    }
+    (plain, html)
+}
+
+fn alert_bodies(subject: &str, details: &[(&'static str, String)]) -> (String, String) {
+    let mut plain = format!("{}\n\n", subject);
+    let mut html = format!("<h2>{}</h2>\n<table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">\n", html_escape(subject));
+    for (name, value) in details {
+        plain.push_str(&format!("{}: {}\n", name, value));
+        html.push_str(&format!("<tr><th align=\"left\">{}</th><td>{}</td></tr>\n", name, html_escape(value)));
+    }
+    html.push_str("</table>\n");
+    (plain, html)
+}
+
+fn alert_details(result: &CheckResult, outcome: &str, round: u64) -> Vec<(&'static str, String)> {
+    let mut details = vec![
+        ("URL", result.url.clone()),
+        ("Status", outcome.to_string()),
+        ("Response time", format!("{} ms", result.response_time.as_millis())),
+        ("Checked at", format_rfc3339_utc(result.timestamp)),
+        ("Round", round.to_string()),
+        ("Priority", result.priority.to_string()),
+    ];
+    if !result.tags.is_empty() {
+        details.push(("Tags", result.tags.join(", ")));
+    }
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::*;
+    use crate::config_file::ConfigFile;
+    use crate::notify::RoundSummary;
+    use crate::testing::result;
+
+    // A minimal SMTP server accepting every message; returns its port and the DATA
+    // of the messages received
+    fn smtp_server() -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&messages);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let (mut stream, received) = (stream.unwrap(), Arc::clone(&received));
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    stream.write_all(b"220 localhost\r\n").unwrap();
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                        match line.trim_end().to_ascii_uppercase().as_str() {
+                            "DATA" => {
+                                stream.write_all(b"354 go ahead\r\n").unwrap();
+                                let mut data = String::new();
+                                let mut data_line = String::new();
+                                while reader.read_line(&mut data_line).unwrap_or(0) > 0 && data_line != ".\r\n" {
+                                    data.push_str(&data_line);
+                                    data_line.clear();
+                                }
+                                received.lock().unwrap().push(data);
+                                stream.write_all(b"250 queued\r\n").unwrap();
+                            }
+                            "QUIT" => {
+                                stream.write_all(b"221 bye\r\n").unwrap();
+                                break;
+                            }
+                            _ => stream.write_all(b"250 ok\r\n").unwrap(),
+                        }
+                        line.clear();
+                    }
+                });
+            }
+        });
+        (port, messages)
+    }
+
+    fn config(extra: &str) -> EmailConfig {
+        let text = format!(
+            "[[email]]\nhost = \"127.0.0.1\"\ntls = \"none\"\nfrom = \"alerts@example.com\"\nto = [\"oncall@example.com\"]\n{}",
+            extra
+        );
+        ConfigFile::parse(&text).unwrap().email.remove(0)
+    }
+
+    fn notifier(port: u16, extra: &str) -> EmailNotifier {
+        EmailNotifier::new(&config(&format!("port = {}\n{}", port, extra)), RetryPolicy::default()).unwrap()
+    }
+
+    fn failure(url: &str, priority: Priority) -> Event {
+        let mut result = result(url, Err("timed out".to_string()));
+        result.priority = priority;
+        Event::Failure { round: 3, result, reminder: false }
+    }
+
+    fn subjects(messages: &Mutex<Vec<String>>) -> Vec<String> {
+        messages.lock().unwrap().iter()
+            .filter_map(|message| message.lines().find_map(|line| line.strip_prefix("Subject: ")).map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let error = |extra: &str| EmailNotifier::new(&config(extra), RetryPolicy::default()).err().unwrap();
+        assert_eq!(error("username = \"alerts\""), "username requires password or password_env");
+        assert_eq!(error("password = \"x\""), "password requires username");
+        assert_eq!(
+            error("username = \"a\"\npassword = \"x\"\npassword_env = \"SMTP_PASSWORD\""),
+            "set either password or password_env, not both"
+        );
+        let mut no_recipients = config("");
+        no_recipients.to.clear();
+        assert!(EmailNotifier::new(&no_recipients, RetryPolicy::default()).is_err());
+        let mut bad_sender = config("");
+        bad_sender.from = "not an address".to_string();
+        assert!(EmailNotifier::new(&bad_sender, RetryPolicy::default()).is_err());
+    }
+
+    #[test]
+    fn digests_list_the_round_and_skip_quiet_rounds() {
+        let (port, messages) = smtp_server();
+        let notifier = notifier(port, "");
+        let recovery = Event::Recovery { round: 3, result: result("https://api.example.com", Ok(200)), outage: Duration::from_secs(60) };
+        let summary = RoundSummary { total: 2, successful: 1, failed: 1, avg_response_time: Some(Duration::from_millis(80)) };
+        notifier.notify(&[failure("https://blog.example.com", Priority::Low), recovery, Event::Summary { round: 3, summary }]).unwrap();
+        notifier.notify(&[Event::Summary { round: 4, summary: RoundSummary::default() }]).unwrap();
+
+        assert_eq!(subjects(&messages), ["[status-checker] Round 3: 1 failing, 1 recovered"]);
+        let message = messages.lock().unwrap()[0].clone();
+        assert!(message.contains("  [low] https://blog.example.com - timed out (100 ms)"));
+        assert!(message.contains("  [medium] https://api.example.com - 200 (down for 1m 0s) (100 ms)"));
+        assert!(message.contains("Round totals: 2 checked, 1 successful, 1 failed, average response time 80 ms"));
+    }
+
+    #[test]
+    fn critical_failures_are_sent_immediately_and_not_again_in_the_digest() {
+        let (port, messages) = smtp_server();
+        let notifier = notifier(port, "");
+        let critical = failure("https://pay.example.com", Priority::Critical);
+        let high = failure("https://blog.example.com", Priority::High);
+        assert!(notifier.sends_now(&critical) && !notifier.sends_now(&high));
+
+        let mut dispatcher = crate::notify::Dispatcher::new();
+        dispatcher.add(Box::new(notifier), vec![crate::notify::EventKind::Failure]);
+        dispatcher.dispatch_now(&critical);
+        dispatcher.dispatch(&[critical, high]);
+        drop(dispatcher);
+
+        let mut subjects = subjects(&messages);
+        subjects.sort();
+        assert_eq!(
+            subjects,
+            ["[status-checker] CRITICAL: https://pay.example.com is down", "[status-checker] Round 3: 1 failing, 0 recovered"]
+        );
+        let digest = messages.lock().unwrap().iter().find(|message| message.contains("Round 3")).unwrap().clone();
+        assert!(!digest.contains("pay.example.com"));
+    }
+
+    #[test]
+    fn digest_and_immediate_alerts_can_be_turned_off() {
+        let (port, messages) = smtp_server();
+        let notifier = notifier(port, "digest = false\nimmediate = false");
+        let critical = failure("https://pay.example.com", Priority::Critical);
+        assert!(!notifier.sends_now(&critical));
+        notifier.notify(std::slice::from_ref(&critical)).unwrap();
+        notifier.notify_now(&critical).unwrap();
+        assert!(messages.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Escalation policies from `[[escalation]]` tables in the `--config` file: while a URL
+//! stays down, its failure is forwarded to more channels as the outage grows, e.g.
+//! Opsgenie after 5 minutes and the managers' mailing list after 15. Channels that
+//! received an escalation also get the recovery.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::routing::Selector;
+use super::tracker::AlertTracker;
+use super::Event;
+use crate::{parse_duration, CheckResult};
+
+/// One `[[escalation]]` table of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EscalationConfig {
+    /// Only URLs with at least one of these tags; empty matches every URL.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Only URLs with this priority or a more urgent one.
+    pub priority: Option<String>,
+    #[serde(rename = "step")]
+    pub steps: Vec<EscalationStepConfig>,
+}
+
+/// One `[[escalation.step]]`: notify these channels once the URL has been down `after`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EscalationStepConfig {
+    /// Outage duration like `5m` or `1h`.
+    pub after: String,
+    /// Channel names: `webhook`, `slack`, `discord`, `telegram`, or the `name` of a
+    /// config file notifier.
+    pub notify: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Policy {
+    selector: Selector,
+    steps: Vec<(Duration, Vec<String>)>,
+}
+
+/// Channels already escalated to per URL, saved with `--state-file` so a restart
+/// doesn't escalate the same outage again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EscalationState(HashMap<String, Vec<String>>);
+
+/// Runs the escalation policies against each round's results.
+#[derive(Debug, Clone, Default)]
+pub struct Escalator {
+    policies: Vec<Policy>,
+    /// Channels already notified per URL during its current outage.
+    escalated: HashMap<String, Vec<String>>,
+}
+
+impl Escalator {
+    pub fn new(configs: &[EscalationConfig]) -> Result<Self, String> {
+        let mut policies = Vec::new();
+        for (n, config) in configs.iter().enumerate() {
+            let context = |e: String| format!("Invalid [[escalation]] #{} in config file: {}", n + 1, e);
+            let selector = Selector::new(&config.tags, config.priority.as_deref()).map_err(context)?;
+            let mut steps = Vec::new();
+            for step in &config.steps {
+                let after = parse_duration(&step.after)
+                    .ok_or_else(|| context(format!("invalid duration '{}' (expected e.g. 30s, 5m, 1h)", step.after)))?;
+                if step.notify.is_empty() {
+                    return Err(context("every step needs at least one channel in 'notify'".to_string()));
+                }
+                steps.push((after, step.notify.clone()));
+            }
+            if steps.is_empty() {
+                return Err(context("at least one [[escalation.step]] is required".to_string()));
+            }
+            policies.push(Policy { selector, steps });
+        }
+        Ok(Escalator { policies, escalated: HashMap::new() })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    pub fn state(&self) -> EscalationState {
+        EscalationState(self.escalated.clone())
+    }
+
+    /// Continues from a saved state, replacing the current one.
+    pub fn restore(&mut self, state: EscalationState) {
+        self.escalated = state.0;
+    }
+
+    /// Names of all channels that escalation steps notify.
+    pub fn channels(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.policies.iter()
+            .flat_map(|policy| &policy.steps)
+            .flat_map(|(_, channels)| channels.iter().map(String::as_str))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Works out which escalation channels to notify after a round, given the round's
+    /// results and the events the [`AlertTracker`] produced for them. Returns the
+    /// events to send per channel name.
+    pub fn escalate(
+        &mut self,
+        round: u64,
+        results: &[CheckResult],
+        events: &[Event],
+        tracker: &AlertTracker,
+    ) -> BTreeMap<String, Vec<Event>> {
+        let mut outgoing: BTreeMap<String, Vec<Event>> = BTreeMap::new();
+        for result in results {
+            let recovery = events.iter()
+                .find(|event| matches!(event, Event::Recovery { .. }) && event.result().is_some_and(|r| r.url == result.url));
+            if let Some(recovery) = recovery {
+                for channel in self.escalated.remove(&result.url).unwrap_or_default() {
+                    outgoing.entry(channel).or_default().push(recovery.clone());
+                }
+                continue;
+            }
+            let Some(down_since) = tracker.outage_start(&result.url) else {
+                self.escalated.remove(&result.url);
+                continue;
+            };
+            if result.action_status.is_ok() || result.maintenance.is_some() {
+                continue;
+            }
+            let down_for = result.timestamp.duration_since(down_since).unwrap_or_default();
+            let notified = self.escalated.entry(result.url.clone()).or_default();
+            for policy in self.policies.iter().filter(|policy| policy.selector.matches(result)) {
+                for (_, channels) in policy.steps.iter().filter(|(after, _)| down_for >= *after) {
+                    for channel in channels {
+                        if notified.contains(channel) {
+                            continue;
+                        }
+                        notified.push(channel.clone());
+                        let event = Event::Failure { round, result: result.clone(), reminder: false };
+                        outgoing.entry(channel.clone()).or_default().push(event);
+                    }
+                }
+            }
+        }
+        outgoing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::config_file::ConfigFile;
+    use crate::notify::tracker::AlertPolicy;
+    use crate::testing::result_at;
+
+    const POLICY: &str = r#"
+        [[escalation]]
+        tags = ["payments"]
+        [[escalation.step]]
+        after = "5m"
+        notify = ["opsgenie"]
+        [[escalation.step]]
+        after = "15m"
+        notify = ["managers", "opsgenie"]
+    "#;
+
+    fn escalator(config: &str) -> Result<Escalator, String> {
+        Escalator::new(&ConfigFile::parse(config).unwrap().escalation)
+    }
+
+    // Runs a round a minute in which `url` (tagged payments) is up or down, returning
+    // the channels escalated to and the kinds of events they get
+    fn round(escalator: &mut Escalator, tracker: &mut AlertTracker, minute: u64, up: bool) -> Vec<(String, &'static str)> {
+        let mut result = result_at(
+            "https://pay.example.com",
+            if up { Ok(200) } else { Err("timed out".to_string()) },
+            UNIX_EPOCH + Duration::from_secs(minute * 60),
+        );
+        result.tags = vec!["payments".to_string()];
+        let events: Vec<Event> = tracker.observe(minute, &result).into_iter().collect();
+        escalator.escalate(minute, &[result], &events, tracker).into_iter()
+            .flat_map(|(channel, events)| events.into_iter().map(move |event| (channel.clone(), event.kind().as_str())))
+            .collect()
+    }
+
+    #[test]
+    fn channels_are_added_as_the_outage_grows() {
+        let mut escalator = escalator(POLICY).unwrap();
+        let mut tracker = AlertTracker::new(AlertPolicy::default());
+        assert_eq!(escalator.channels(), ["managers", "opsgenie"]);
+        let escalations: Vec<(u64, Vec<(String, &str)>)> = (0..=20)
+            .map(|minute| (minute, round(&mut escalator, &mut tracker, minute, false)))
+            .filter(|(_, escalated)| !escalated.is_empty())
+            .collect();
+        assert_eq!(
+            escalations,
+            [
+                (5, vec![("opsgenie".to_string(), "failure")]),
+                (15, vec![("managers".to_string(), "failure")]),
+            ]
+        );
+        let mut recovered = round(&mut escalator, &mut tracker, 21, true);
+        recovered.sort();
+        assert_eq!(recovered, [("managers".to_string(), "recovery"), ("opsgenie".to_string(), "recovery")]);
+        // The next outage starts over
+        assert!(round(&mut escalator, &mut tracker, 22, false).is_empty());
+        assert_eq!(round(&mut escalator, &mut tracker, 27, false), [("opsgenie".to_string(), "failure")]);
+    }
+
+    #[test]
+    fn short_outages_and_other_urls_are_not_escalated() {
+        let mut escalator = escalator(POLICY).unwrap();
+        let mut tracker = AlertTracker::new(AlertPolicy::default());
+        assert!((0..4).all(|minute| round(&mut escalator, &mut tracker, minute, false).is_empty()));
+        assert!(round(&mut escalator, &mut tracker, 4, true).is_empty());
+
+        let untagged = result_at("https://blog.example.com", Err("timed out".to_string()), UNIX_EPOCH);
+        tracker.observe(0, &untagged);
+        let later = result_at("https://blog.example.com", Err("timed out".to_string()), UNIX_EPOCH + Duration::from_secs(3_600));
+        assert!(escalator.escalate(60, &[later], &[], &tracker).is_empty());
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        let error = |config: &str| escalator(config).unwrap_err();
+        assert_eq!(
+            error("[[escalation]]\nstep = []"),
+            "Invalid [[escalation]] #1 in config file: at least one [[escalation.step]] is required"
+        );
+        assert!(error("[[escalation]]\n[[escalation.step]]\nafter = \"soon\"\nnotify = [\"slack\"]").contains("invalid duration 'soon'"));
+        assert!(error("[[escalation]]\n[[escalation.step]]\nafter = \"5m\"\nnotify = []").contains("at least one channel"));
+        assert!(error("[[escalation]]\npriority = \"urgent\"\n[[escalation.step]]\nafter = \"5m\"\nnotify = [\"slack\"]")
+            .contains("unknown priority 'urgent'"));
+        assert!(escalator("").unwrap().is_empty());
+    }
+}
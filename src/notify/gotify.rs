@@ -0,0 +1,129 @@
+//! [Gotify](https://gotify.net) push notifier, configured with `[[gotify]]` tables in
+//! the `--config` file. Each round's failures, recoveries and flapping URLs become one
+//! message, with the most urgent failing check's priority.
+
+use std::env;
+
+use serde::Deserialize;
+
+use super::{format_plain_text, headline, most_urgent, Event, Notifier, RetryPolicy};
+use crate::{escape_json_string, Priority};
+
+/// One `[[gotify]]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GotifyConfig {
+    /// Channel name for routes and escalation policies (default: `gotify`).
+    pub name: Option<String>,
+    /// Base URL of the Gotify server, e.g. `https://gotify.example.com`.
+    pub server: String,
+    /// Application token.
+    pub token: Option<String>,
+    /// Environment variable holding the application token, to keep it out of the file.
+    pub token_env: Option<String>,
+}
+
+pub struct GotifyNotifier {
+    client: reqwest::blocking::Client,
+    url: String,
+    token: String,
+    retry: RetryPolicy,
+}
+
+impl GotifyNotifier {
+    pub fn new(client: reqwest::blocking::Client, config: &GotifyConfig, retry: RetryPolicy) -> Result<Self, String> {
+        let token = match (&config.token, &config.token_env) {
+            (Some(_), Some(_)) => return Err("set either token or token_env, not both".to_string()),
+            (Some(token), None) => token.clone(),
+            (None, Some(var)) => env::var(var).map_err(|_| format!("environment variable {} is not set", var))?,
+            (None, None) => return Err("token or token_env is required".to_string()),
+        };
+        Ok(GotifyNotifier {
+            client,
+            url: format!("{}/message", config.server.trim_end_matches('/')),
+            token,
+            retry,
+        })
+    }
+
+    fn send(&self, body: &str) -> Result<(), String> {
+        let response = self.client.post(&self.url)
+            .header("X-Gotify-Key", &self.token)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            let text = response.text().unwrap_or_default();
+            Err(format!("HTTP {}: {}", status.as_u16(), text.trim()))
+        }
+    }
+}
+
+impl Notifier for GotifyNotifier {
+    fn name(&self) -> &str {
+        "gotify"
+    }
+
+    fn notify(&self, events: &[Event]) -> Result<(), String> {
+        let message = format_plain_text(events);
+        if message.is_empty() {
+            return Ok(());
+        }
+        let body = format!(
+            "{{\"title\":\"{}\",\"message\":\"{}\",\"priority\":{}}}",
+            escape_json_string(&headline(events)),
+            escape_json_string(&message),
+            most_urgent(events).map_or(2, gotify_priority)
+        );
+        self.retry.run(|| self.send(&body))
+    }
+}
+
+/// Gotify priorities run from 0 to 10; the Android app pops up notifications from 8
+/// and only shows an icon below 4.
+fn gotify_priority(priority: Priority) -> u8 {
+    match priority {
+        Priority::Critical => 10,
+        Priority::High => 8,
+        Priority::Medium => 5,
+        Priority::Low => 3,
+        Priority::Info => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_file::ConfigFile;
+    use crate::testing::{result, TestServer};
+
+    fn notifier(config: &str) -> Result<GotifyNotifier, String> {
+        let config = ConfigFile::parse(config).unwrap().gotify.remove(0);
+        GotifyNotifier::new(reqwest::blocking::Client::new(), &config, RetryPolicy::default())
+    }
+
+    #[test]
+    fn rounds_are_posted_to_the_message_endpoint() {
+        let server = TestServer::start(|_| (200, "{}".to_string()));
+        let notifier = notifier(&format!("[[gotify]]\nserver = \"{}/\"\ntoken = \"AbC\"", server.url)).unwrap();
+        let mut high = result("https://api.example.com", Err("HTTP 502".to_string()));
+        high.priority = Priority::High;
+        notifier.notify(&[Event::Failure { round: 4, result: high, reminder: false }]).unwrap();
+
+        let [request] = server.requests().try_into().unwrap();
+        assert_eq!(request.url, "/message");
+        assert_eq!(request.header("x-gotify-key"), Some("AbC"));
+        let message: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(message["title"], "Round 4: 1 failed");
+        assert_eq!(message["priority"], 8);
+    }
+
+    #[test]
+    fn a_token_is_required() {
+        assert_eq!(notifier("[[gotify]]\nserver = \"https://gotify.example.com\"").err().unwrap(), "token or token_env is required");
+    }
+}
@@ -0,0 +1,768 @@
+//! Notification pipeline: the CLI turns each round into [`Event`]s and a
+//! [`Dispatcher`] delivers them to every configured [`Notifier`].
+
+pub mod discord;
+pub mod email;
+pub mod escalation;
+pub mod gotify;
+pub mod ntfy;
+pub mod opsgenie;
+pub mod routing;
+pub mod slack;
+pub mod telegram;
+pub mod template;
+pub mod tracker;
+pub mod webhook;
+
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, UNIX_EPOCH};
+
+use self::routing::Selector;
+use crate::{format_duration, CheckResult, Priority, RoundStats};
+
+/// What an [`Event`] reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Failure,
+    Recovery,
+    Flapping,
+    Summary,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Failure => "failure",
+            EventKind::Recovery => "recovery",
+            EventKind::Flapping => "flapping",
+            EventKind::Summary => "summary",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<EventKind, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "failure" | "failures" => Ok(EventKind::Failure),
+            "recovery" | "recoveries" => Ok(EventKind::Recovery),
+            "flapping" | "flap" => Ok(EventKind::Flapping),
+            "summary" | "summaries" => Ok(EventKind::Summary),
+            other => Err(format!("unknown event type '{}' (expected failure, recovery, flapping or summary)", other)),
+        }
+    }
+}
+
+/// Aggregate numbers for one round, as sent in summary events.
+#[derive(Debug, Clone, Default)]
+pub struct RoundSummary {
+    pub total: u64,
+    pub successful: u64,
+    pub failed: u64,
+    pub avg_response_time: Option<Duration>,
+}
+
+impl From<&RoundStats> for RoundSummary {
+    fn from(stats: &RoundStats) -> Self {
+        let avg_response_time = if stats.successful_checks > 0 {
+            Some(stats.total_time / stats.successful_checks as u32)
+        } else {
+            None
+        };
+        RoundSummary {
+            total: stats.successful_checks + stats.failed_checks,
+            successful: stats.successful_checks,
+            failed: stats.failed_checks,
+            avg_response_time,
+        }
+    }
+}
+
+/// Something worth telling people about.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A check failed. `reminder` is set when the URL was already reported as down.
+    Failure { round: u64, result: CheckResult, reminder: bool },
+    /// A URL that was reported as down succeeded again. `outage` runs from its first
+    /// failed check to this one.
+    Recovery { round: u64, result: CheckResult, outage: Duration },
+    /// A URL started flapping between up and down; its individual failures and
+    /// recoveries are suppressed until it settles. `flap_rate` is in percent.
+    Flapping { round: u64, result: CheckResult, flap_rate: f64 },
+    /// A round finished.
+    Summary { round: u64, summary: RoundSummary },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::Failure { .. } => EventKind::Failure,
+            Event::Recovery { .. } => EventKind::Recovery,
+            Event::Flapping { .. } => EventKind::Flapping,
+            Event::Summary { .. } => EventKind::Summary,
+        }
+    }
+
+    pub fn round(&self) -> u64 {
+        match self {
+            Event::Failure { round, .. }
+            | Event::Recovery { round, .. }
+            | Event::Flapping { round, .. }
+            | Event::Summary { round, .. } => *round,
+        }
+    }
+
+    pub fn result(&self) -> Option<&CheckResult> {
+        match self {
+            Event::Failure { result, .. } | Event::Recovery { result, .. } | Event::Flapping { result, .. } => {
+                Some(result)
+            }
+            Event::Summary { .. } => None,
+        }
+    }
+
+    /// How long the URL was down, for recovery events.
+    pub fn outage(&self) -> Option<Duration> {
+        match self {
+            Event::Recovery { outage, .. } => Some(*outage),
+            _ => None,
+        }
+    }
+
+    /// Flap rate in percent, for flapping events.
+    pub fn flap_rate(&self) -> Option<f64> {
+        match self {
+            Event::Flapping { flap_rate, .. } => Some(*flap_rate),
+            _ => None,
+        }
+    }
+
+    /// Short human-readable addition to the outcome: outage duration, flap rate or
+    /// that the URL is still failing.
+    pub fn note(&self) -> Option<String> {
+        match self {
+            Event::Failure { reminder: true, .. } => Some("still failing".to_string()),
+            Event::Recovery { outage, .. } => Some(format!("down for {}", format_duration(*outage))),
+            Event::Flapping { flap_rate, .. } => Some(format!("{:.1}% state change", flap_rate)),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a repeated failure alert for a URL that is still down.
+    pub fn is_reminder(&self) -> bool {
+        matches!(self, Event::Failure { reminder: true, .. })
+    }
+
+    /// Status code or error message of the check, for human-readable messages.
+    pub fn outcome(&self) -> Option<String> {
+        self.result().map(|result| match &result.action_status {
+            Ok(code) => code.to_string(),
+            Err(e) => e.clone(),
+        })
+    }
+
+    /// Raw (unescaped) values by name; message templates see them as variables.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("event", self.kind().as_str().to_string()),
+            ("round", self.round().to_string()),
+            ("reminder", self.is_reminder().to_string()),
+        ];
+        if let Some(result) = self.result() {
+            let (status, error) = match &result.action_status {
+                Ok(code) => (code.to_string(), String::new()),
+                Err(e) => (e.clone(), e.clone()),
+            };
+            fields.push(("url", result.url.clone()));
+            fields.push(("priority", result.priority.to_string()));
+            fields.push(("tags", result.tags.join(",")));
+            fields.push(("status", status));
+            fields.push(("error", error));
+            fields.push(("responseTimeMs", result.response_time.as_millis().to_string()));
+            fields.push((
+                "timestampEpochS",
+                result.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs().to_string(),
+            ));
+        }
+        if let Some(outage) = self.outage() {
+            fields.push(("outageSeconds", outage.as_secs().to_string()));
+            fields.push(("outage", format_duration(outage)));
+        }
+        if let Some(flap_rate) = self.flap_rate() {
+            fields.push(("flapRate", format!("{:.1}", flap_rate)));
+        }
+        if let Event::Summary { summary, .. } = self {
+            fields.push(("total", summary.total.to_string()));
+            fields.push(("successful", summary.successful.to_string()));
+            fields.push(("failed", summary.failed.to_string()));
+            fields.push((
+                "avgResponseTimeMs",
+                summary.avg_response_time.map_or(String::new(), |avg| avg.as_millis().to_string()),
+            ));
+        }
+        fields
+    }
+
+    /// Default JSON payload: `{"event": ..., "round": ..., "result": {...}}` for check
+    /// events, summary numbers for summary events.
+    pub fn to_json(&self) -> String {
+        let mut body = format!("{{\"event\":\"{}\",\"round\":{}", self.kind().as_str(), self.round());
+        if let Event::Failure { reminder, .. } = self {
+            body.push_str(&format!(",\"reminder\":{}", reminder));
+        }
+        if let Some(outage) = self.outage() {
+            body.push_str(&format!(",\"outageSeconds\":{}", outage.as_secs()));
+        }
+        if let Some(flap_rate) = self.flap_rate() {
+            body.push_str(&format!(",\"flapRate\":{:.1}", flap_rate));
+        }
+        if let Some(result) = self.result() {
+            body.push_str(&format!(",\"result\":{}", result.to_json()));
+        }
+        if let Event::Summary { summary, .. } = self {
+            body.push_str(&format!(
+                ",\"total\":{},\"successful\":{},\"failed\":{},\"avgResponseTimeMs\":{}",
+                summary.total,
+                summary.successful,
+                summary.failed,
+                summary.avg_response_time.map_or("null".to_string(), |avg| avg.as_millis().to_string())
+            ));
+        }
+        body.push('}');
+        body
+    }
+}
+
+/// A delivery channel (webhook, chat, e-mail, ...).
+pub trait Notifier: Send + Sync {
+    /// Short name used in warnings.
+    fn name(&self) -> &str;
+
+    /// Delivers one round's worth of events (already filtered for this notifier).
+    fn notify(&self, events: &[Event]) -> Result<(), String>;
+
+    /// Whether the channel sends `event` on its own as soon as the check finishes,
+    /// through [`Notifier::notify_now`]. Such events are left out of the round's
+    /// [`Notifier::notify`], so they aren't sent twice. Only asked about critical failures.
+    fn sends_now(&self, _event: &Event) -> bool {
+        false
+    }
+
+    /// Sends an event [`Notifier::sends_now`] accepted, before the round ends.
+    fn notify_now(&self, _event: &Event) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// A notifier plus the event kinds it wants.
+struct Channel {
+    name: String,
+    // Shared with the thread delivering immediate alerts
+    notifier: Arc<dyn Notifier>,
+    kinds: Vec<EventKind>,
+    /// Only receives events sent to it by name (escalation targets).
+    on_demand: bool,
+    /// Events about results matching none of these are dropped; empty passes all.
+    routes: Vec<Selector>,
+}
+
+// An immediate alert and the channels to send it to
+type ImmediateAlert = (Event, Vec<(String, Arc<dyn Notifier>)>);
+
+// Delivers immediate alerts off the checking threads, so retries and rate limits
+// don't hold up the round
+struct ImmediateWorker {
+    sender: Sender<ImmediateAlert>,
+    thread: JoinHandle<()>,
+}
+
+impl ImmediateWorker {
+    fn start() -> ImmediateWorker {
+        let (sender, receiver) = mpsc::channel::<ImmediateAlert>();
+        let thread = thread::spawn(move || {
+            for (event, notifiers) in receiver {
+                for (name, notifier) in notifiers {
+                    if let Err(e) = notifier.notify_now(&event) {
+                        tracing::warn!(channel = %name, error = %e, "notification failed");
+                    }
+                }
+            }
+        });
+        ImmediateWorker { sender, thread }
+    }
+}
+
+/// Fans events out to all notifiers. Delivery errors are reported on stderr and
+/// never stop the checker.
+#[derive(Default)]
+pub struct Dispatcher {
+    channels: Vec<Channel>,
+    immediate: OnceLock<ImmediateWorker>,
+}
+
+impl Drop for Dispatcher {
+    // Immediate alerts still queued are delivered before the checker exits
+    fn drop(&mut self) {
+        if let Some(ImmediateWorker { sender, thread }) = self.immediate.take() {
+            drop(sender);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Dispatcher {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a channel named after its notifier (`slack`, `email`, ...).
+    pub fn add(&mut self, notifier: Box<dyn Notifier>, kinds: Vec<EventKind>) {
+        let name = notifier.name().to_string();
+        self.add_named(name, notifier, kinds);
+    }
+
+    /// Adds a channel that escalation policies can refer to as `name`.
+    pub fn add_named(&mut self, name: String, notifier: Box<dyn Notifier>, kinds: Vec<EventKind>) {
+        self.channels.push(Channel { name, notifier: Arc::from(notifier), kinds, on_demand: false, routes: Vec::new() });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// Takes the named channels out of regular dispatch: they only receive events
+    /// sent with [`Dispatcher::dispatch_to`]. Fails on names that match no channel.
+    pub fn reserve<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) -> Result<(), String> {
+        for name in names {
+            let mut found = false;
+            for channel in self.channels.iter_mut().filter(|channel| channel.name == name) {
+                channel.on_demand = true;
+                found = true;
+            }
+            if !found {
+                return Err(format!("no notification channel named '{}'", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Restricts the channels called `name` to events matching `selector` (or any
+    /// other route added for them). Fails if no channel has that name.
+    pub fn add_route(&mut self, name: &str, selector: Selector) -> Result<(), String> {
+        let mut found = false;
+        for channel in self.channels.iter_mut().filter(|channel| channel.name == name) {
+            channel.routes.push(selector.clone());
+            found = true;
+        }
+        if found {
+            Ok(())
+        } else {
+            Err(format!("no notification channel named '{}'", name))
+        }
+    }
+
+    /// Delivers a round's events, except those channels already sent immediately.
+    pub fn dispatch(&self, events: &[Event]) {
+        for channel in self.channels.iter().filter(|channel| !channel.on_demand) {
+            let events: Vec<Event> = events.iter()
+                .filter(|event| !channel.sends_now(event))
+                .cloned()
+                .collect();
+            channel.deliver(&events);
+        }
+    }
+
+    /// Delivers events to the channels called `name` only, reserved or not.
+    pub fn dispatch_to(&self, name: &str, events: &[Event]) {
+        for channel in self.channels.iter().filter(|channel| channel.name == name) {
+            channel.deliver(events);
+        }
+    }
+
+    /// Queues a critical failure for [`Notifier::notify_now`] of every channel that
+    /// sends it immediately, and returns without waiting for the delivery.
+    pub fn dispatch_now(&self, event: &Event) {
+        let notifiers: Vec<(String, Arc<dyn Notifier>)> = self.channels.iter()
+            .filter(|channel| !channel.on_demand && channel.sends_now(event))
+            .map(|channel| (channel.name.clone(), Arc::clone(&channel.notifier)))
+            .collect();
+        if notifiers.is_empty() {
+            return;
+        }
+        let worker = self.immediate.get_or_init(ImmediateWorker::start);
+        if worker.sender.send((event.clone(), notifiers)).is_err() {
+            tracing::warn!(url = ?event.result().map(|result| &result.url), "immediate notification dropped");
+        }
+    }
+}
+
+impl Channel {
+    fn sends_now(&self, event: &Event) -> bool {
+        self.wants(event) && self.notifier.sends_now(event)
+    }
+
+    // Round summaries aren't about a single URL and pass every route
+    fn wants(&self, event: &Event) -> bool {
+        self.kinds.contains(&event.kind())
+            && (self.routes.is_empty()
+                || event.result().is_none_or(|result| self.routes.iter().any(|route| route.matches(result))))
+    }
+
+    fn deliver(&self, events: &[Event]) {
+        let wanted: Vec<Event> = events.iter()
+            .filter(|event| self.wants(event))
+            .cloned()
+            .collect();
+        if wanted.is_empty() {
+            return;
+        }
+        if let Err(e) = self.notifier.notify(&wanted) {
+            tracing::warn!(channel = %self.name, error = %e, "notification failed");
+        }
+    }
+}
+
+/// Plain-text message for a round's failures, recoveries and flapping URLs, one
+/// section per kind with an emoji marker. Used by the chat and push notifiers.
+pub(crate) fn format_plain_text(events: &[Event]) -> String {
+    let mut sections = Vec::new();
+    let sections_by_kind = [
+        ("🔴", "failed", EventKind::Failure),
+        ("🟢", "recovered", EventKind::Recovery),
+        ("🟠", "flapping", EventKind::Flapping),
+    ];
+    for (marker, verb, kind) in sections_by_kind {
+        let lines: Vec<String> = events.iter()
+            .filter(|event| event.kind() == kind)
+            .filter_map(|event| {
+                let (result, outcome) = (event.result()?, event.outcome()?);
+                let note = event.note().map_or(String::new(), |note| format!(", {}", note));
+                Some(format!("• {} — {} ({} ms{})", result.url, outcome, result.response_time.as_millis(), note))
+            })
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        let round = events.first().map_or(0, |e| e.round());
+        let noun = if lines.len() == 1 { "check" } else { "checks" };
+        sections.push(format!("{} {} {} {} (round {})\n{}", marker, lines.len(), noun, verb, round, lines.join("\n")));
+    }
+    sections.join("\n\n")
+}
+
+/// Short title such as "Round 3: 2 failed, 1 recovered" for push notifications.
+pub(crate) fn headline(events: &[Event]) -> String {
+    let round = events.first().map_or(0, |e| e.round());
+    let counts: Vec<String> = [(EventKind::Failure, "failed"), (EventKind::Recovery, "recovered"), (EventKind::Flapping, "flapping")]
+        .into_iter()
+        .filter_map(|(kind, verb)| match events.iter().filter(|event| event.kind() == kind).count() {
+            0 => None,
+            n => Some(format!("{} {}", n, verb)),
+        })
+        .collect();
+    format!("Round {}: {}", round, counts.join(", "))
+}
+
+/// Most urgent priority among the round's failing and flapping checks; `None` if
+/// there are only recoveries.
+pub(crate) fn most_urgent(events: &[Event]) -> Option<Priority> {
+    events.iter()
+        .filter(|event| matches!(event, Event::Failure { .. } | Event::Flapping { .. }))
+        .filter_map(|event| event.result().map(|result| result.priority))
+        .min()
+}
+
+/// Retry policy for outgoing notification requests: `retries` extra attempts with
+/// exponential backoff starting at `initial_backoff`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { retries: 3, initial_backoff: Duration::from_millis(500) }
+    }
+}
+
+impl RetryPolicy {
+    /// Runs `attempt` until it succeeds or the retries are used up, returning the last error.
+    pub fn run<F>(&self, mut attempt: F) -> Result<(), String>
+    where
+        F: FnMut() -> Result<(), String>,
+    {
+        let mut backoff = self.initial_backoff;
+        let mut tries = 0;
+        loop {
+            match attempt() {
+                Ok(()) => return Ok(()),
+                Err(e) if tries >= self.retries => {
+                    return Err(format!("{} (after {} attempts)", e, tries + 1));
+                }
+                Err(e) => {
+                    tracing::debug!(error = %e, backoff_ms = backoff.as_millis() as u64, "attempt failed, retrying");
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    tries += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Sends `body` with `content_type`, treating non-2xx responses as errors.
+pub fn post(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    content_type: &str,
+    body: &str,
+) -> Result<(), String> {
+    let response = client.post(url)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(body.to_string())
+        .send()
+        .map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", response.status().as_u16()))
+    }
+}
+
+/// GETs `url`, treating non-2xx responses as errors. Used for heartbeat pings.
+pub fn ping(client: &reqwest::blocking::Client, url: &str) -> Result<(), String> {
+    let response = client.get(url).send().map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", response.status().as_u16()))
+    }
+}
+
+/// HTTP client shared by the notifiers.
+pub fn notification_client() -> Result<reqwest::blocking::Client, String> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("Failed to build notification HTTP client: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::testing::{result, TestServer};
+
+    // Records what it was asked to send; sends critical failures immediately, slowly
+    #[derive(Clone, Default)]
+    struct Recorder {
+        rounds: Arc<Mutex<Vec<Vec<String>>>>,
+        now: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Notifier for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn notify(&self, events: &[Event]) -> Result<(), String> {
+            let urls = events.iter().filter_map(|event| event.result()).map(|result| result.url.clone()).collect();
+            self.rounds.lock().unwrap().push(urls);
+            Ok(())
+        }
+
+        fn sends_now(&self, event: &Event) -> bool {
+            event.result().is_some_and(|result| result.priority == Priority::Critical)
+        }
+
+        fn notify_now(&self, event: &Event) -> Result<(), String> {
+            thread::sleep(Duration::from_millis(300));
+            self.now.lock().unwrap().push(event.result().unwrap().url.clone());
+            Ok(())
+        }
+    }
+
+    fn failure(url: &str, priority: Priority) -> Event {
+        let mut result = result(url, Err("timed out".to_string()));
+        result.priority = priority;
+        Event::Failure { round: 1, result, reminder: false }
+    }
+
+    #[test]
+    fn immediate_alerts_are_sent_in_the_background_and_left_out_of_the_round() {
+        let recorder = Recorder::default();
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add(Box::new(recorder.clone()), vec![EventKind::Failure]);
+        let critical = failure("https://pay.example.com", Priority::Critical);
+        let medium = failure("https://blog.example.com", Priority::Medium);
+
+        let started = Instant::now();
+        dispatcher.dispatch_now(&critical);
+        assert!(started.elapsed() < Duration::from_millis(200), "dispatch_now waited for the delivery");
+        dispatcher.dispatch(&[critical.clone(), medium.clone()]);
+        drop(dispatcher);
+
+        assert_eq!(*recorder.now.lock().unwrap(), vec!["https://pay.example.com"]);
+        assert_eq!(*recorder.rounds.lock().unwrap(), vec![vec!["https://blog.example.com"]]);
+    }
+
+    #[test]
+    fn escalations_include_events_sent_immediately() {
+        let recorder = Recorder::default();
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add(Box::new(recorder.clone()), vec![EventKind::Failure]);
+        dispatcher.dispatch_to("recorder", &[failure("https://pay.example.com", Priority::Critical)]);
+        assert_eq!(*recorder.rounds.lock().unwrap(), vec![vec!["https://pay.example.com"]]);
+    }
+
+    #[test]
+    fn channels_not_wanting_failures_get_no_immediate_alerts() {
+        let recorder = Recorder::default();
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add(Box::new(recorder.clone()), vec![EventKind::Recovery]);
+        dispatcher.dispatch_now(&failure("https://pay.example.com", Priority::Critical));
+        drop(dispatcher);
+        assert!(recorder.now.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn retry_policy_backs_off_and_reports_the_last_error() {
+        let policy = RetryPolicy { retries: 2, initial_backoff: Duration::from_millis(20) };
+        let mut attempts = 0;
+        let started = Instant::now();
+        let outcome = policy.run(|| {
+            attempts += 1;
+            Err(format!("HTTP 50{}", attempts))
+        });
+        assert_eq!(outcome, Err("HTTP 503 (after 3 attempts)".to_string()));
+        // 20 ms, then 40 ms
+        assert!(started.elapsed() >= Duration::from_millis(60));
+
+        let mut attempts = 0;
+        let outcome = policy.run(|| {
+            attempts += 1;
+            if attempts == 2 { Ok(()) } else { Err("HTTP 502".to_string()) }
+        });
+        assert_eq!((outcome, attempts), (Ok(()), 2));
+    }
+
+    #[test]
+    fn event_kinds_parse_singular_and_plural() {
+        assert_eq!(EventKind::parse("Failures"), Ok(EventKind::Failure));
+        assert_eq!(EventKind::parse(" recovery "), Ok(EventKind::Recovery));
+        assert_eq!(EventKind::parse("summaries"), Ok(EventKind::Summary));
+        assert!(EventKind::parse("outage").is_err());
+    }
+
+    #[test]
+    fn events_as_json() {
+        let mut failed = result("https://example.com", Err("timed out".to_string()));
+        failed.timestamp = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let event = Event::Failure { round: 4, result: failed, reminder: false };
+        assert_eq!(
+            event.to_json(),
+            r#"{"event":"failure","round":4,"reminder":false,"result":{"url":"https://example.com","status":"timed out","responseTimeMs":100,"timestampEpochS":1700000000}}"#
+        );
+        let summary = RoundSummary { total: 3, successful: 2, failed: 1, avg_response_time: Some(Duration::from_millis(250)) };
+        assert_eq!(
+            Event::Summary { round: 4, summary }.to_json(),
+            r#"{"event":"summary","round":4,"total":3,"successful":2,"failed":1,"avgResponseTimeMs":250}"#
+        );
+        let summary = RoundSummary { total: 1, successful: 0, failed: 1, avg_response_time: None };
+        assert!(Event::Summary { round: 5, summary }.to_json().ends_with(",\"avgResponseTimeMs\":null}"));
+    }
+
+    #[test]
+    fn channels_only_get_the_event_kinds_they_want() {
+        let (failures, recoveries) = (Recorder::default(), Recorder::default());
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add(Box::new(failures.clone()), vec![EventKind::Failure]);
+        dispatcher.add(Box::new(recoveries.clone()), vec![EventKind::Recovery]);
+        let recovery = Event::Recovery {
+            round: 2,
+            result: result("https://api.example.com", Ok(200)),
+            outage: Duration::from_secs(90),
+        };
+        dispatcher.dispatch(&[failure("https://blog.example.com", Priority::Medium), recovery]);
+        dispatcher.dispatch(&[Event::Summary { round: 2, summary: RoundSummary::default() }]);
+        assert_eq!(*failures.rounds.lock().unwrap(), vec![vec!["https://blog.example.com"]]);
+        assert_eq!(*recoveries.rounds.lock().unwrap(), vec![vec!["https://api.example.com"]]);
+    }
+
+    #[test]
+    fn heartbeat_pings_are_plain_gets() {
+        let server = TestServer::start(|request| if request.url == "/ping/ok" { (200, "OK".to_string()) } else { (404, String::new()) });
+        let client = reqwest::blocking::Client::new();
+        ping(&client, &format!("{}/ping/ok", server.url)).unwrap();
+        assert_eq!(ping(&client, &format!("{}/ping/gone", server.url)), Err("HTTP 404".to_string()));
+        let methods: Vec<String> = server.requests().into_iter().map(|request| request.method).collect();
+        assert_eq!(methods, ["GET", "GET"]);
+        assert!(ping(&client, "http://127.0.0.1:1/").is_err());
+    }
+
+    #[test]
+    fn routed_channels_only_get_matching_results() {
+        let (payments, everything) = (Recorder::default(), Recorder::default());
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add_named("payments".to_string(), Box::new(payments.clone()), vec![EventKind::Failure, EventKind::Summary]);
+        dispatcher.add(Box::new(everything.clone()), vec![EventKind::Failure]);
+        dispatcher.add_route("payments", Selector::new(&["payments".to_string()], None).unwrap()).unwrap();
+        dispatcher.add_route("payments", Selector::new(&[], Some("high")).unwrap()).unwrap();
+        assert!(dispatcher.add_route("pager", Selector::default()).is_err());
+
+        let mut tagged = failure("https://pay.example.com", Priority::Low);
+        if let Event::Failure { result, .. } = &mut tagged {
+            result.tags = vec!["payments".to_string()];
+        }
+        let events = [
+            tagged,
+            failure("https://api.example.com", Priority::High),
+            failure("https://blog.example.com", Priority::Medium),
+            Event::Summary { round: 1, summary: RoundSummary::default() },
+        ];
+        dispatcher.dispatch(&events);
+        // The summary passes every route but carries no URL
+        assert_eq!(*payments.rounds.lock().unwrap(), vec![vec!["https://pay.example.com", "https://api.example.com"]]);
+        assert_eq!(everything.rounds.lock().unwrap()[0].len(), 3);
+    }
+
+    #[test]
+    fn reserved_channels_only_get_events_sent_to_them() {
+        let (pager, chat) = (Recorder::default(), Recorder::default());
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add_named("pager".to_string(), Box::new(pager.clone()), vec![EventKind::Failure]);
+        dispatcher.add(Box::new(chat.clone()), vec![EventKind::Failure]);
+        dispatcher.reserve(["pager"]).unwrap();
+        assert!(dispatcher.reserve(["nobody"]).is_err());
+
+        dispatcher.dispatch(&[failure("https://a.example.com", Priority::Medium)]);
+        dispatcher.dispatch_to("pager", &[failure("https://b.example.com", Priority::Medium)]);
+        assert_eq!(*pager.rounds.lock().unwrap(), vec![vec!["https://b.example.com"]]);
+        assert_eq!(*chat.rounds.lock().unwrap(), vec![vec!["https://a.example.com"]]);
+    }
+
+    #[test]
+    fn plain_text_messages_and_headlines() {
+        let recovery = Event::Recovery { round: 1, result: result("https://api.example.com", Ok(200)), outage: Duration::from_secs(45) };
+        let events = [
+            failure("https://a.example.com", Priority::Low),
+            recovery,
+            failure("https://b.example.com", Priority::High),
+            Event::Summary { round: 1, summary: RoundSummary::default() },
+        ];
+        assert_eq!(
+            format_plain_text(&events),
+            "🔴 2 checks failed (round 1)\n\
+             • https://a.example.com — timed out (100 ms)\n\
+             • https://b.example.com — timed out (100 ms)\n\n\
+             🟢 1 check recovered (round 1)\n\
+             • https://api.example.com — 200 (100 ms, down for 45s)"
+        );
+        assert_eq!(headline(&events), "Round 1: 2 failed, 1 recovered");
+        assert_eq!(most_urgent(&events), Some(Priority::High));
+        assert_eq!(most_urgent(&events[1..2]), None);
+        assert_eq!(format_plain_text(&events[3..]), "");
+    }
+}
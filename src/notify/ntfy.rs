@@ -0,0 +1,156 @@
+//! [ntfy](https://ntfy.sh) push notifier, configured with `[[ntfy]]` tables in the
+//! `--config` file. Each round's failures, recoveries and flapping URLs become one
+//! notification on the topic, with the most urgent failing check's priority.
+
+use std::env;
+
+use serde::Deserialize;
+
+use super::{format_plain_text, headline, most_urgent, Event, Notifier, RetryPolicy};
+use crate::{escape_json_string, Priority};
+
+pub const DEFAULT_SERVER: &str = "https://ntfy.sh";
+
+/// One `[[ntfy]]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NtfyConfig {
+    /// Channel name for routes and escalation policies (default: `ntfy`).
+    pub name: Option<String>,
+    /// ntfy.sh or a self-hosted server.
+    #[serde(default = "default_server")]
+    pub server: String,
+    pub topic: String,
+    /// Access token for protected topics.
+    pub token: Option<String>,
+    /// Environment variable holding the access token, to keep it out of the file.
+    pub token_env: Option<String>,
+}
+
+fn default_server() -> String {
+    DEFAULT_SERVER.to_string()
+}
+
+pub struct NtfyNotifier {
+    client: reqwest::blocking::Client,
+    server: String,
+    topic: String,
+    token: Option<String>,
+    retry: RetryPolicy,
+}
+
+impl NtfyNotifier {
+    pub fn new(client: reqwest::blocking::Client, config: &NtfyConfig, retry: RetryPolicy) -> Result<Self, String> {
+        if config.topic.is_empty() {
+            return Err("topic must not be empty".to_string());
+        }
+        let token = match (&config.token, &config.token_env) {
+            (Some(_), Some(_)) => return Err("set either token or token_env, not both".to_string()),
+            (Some(token), None) => Some(token.clone()),
+            (None, Some(var)) => Some(env::var(var).map_err(|_| format!("environment variable {} is not set", var))?),
+            (None, None) => None,
+        };
+        Ok(NtfyNotifier {
+            client,
+            server: config.server.trim_end_matches('/').to_string(),
+            topic: config.topic.clone(),
+            token,
+            retry,
+        })
+    }
+
+    fn publish(&self, body: &str) -> Result<(), String> {
+        // JSON publishing keeps non-ASCII titles out of HTTP headers
+        let mut request = self.client.post(&self.server)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().map_err(|e| e.to_string())?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            let text = response.text().unwrap_or_default();
+            Err(format!("HTTP {}: {}", status.as_u16(), text.trim()))
+        }
+    }
+}
+
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    fn notify(&self, events: &[Event]) -> Result<(), String> {
+        let message = format_plain_text(events);
+        if message.is_empty() {
+            return Ok(());
+        }
+        let urgency = most_urgent(events);
+        let tag = if urgency.is_some() { "rotating_light" } else { "white_check_mark" };
+        let body = format!(
+            "{{\"topic\":\"{}\",\"title\":\"{}\",\"message\":\"{}\",\"priority\":{},\"tags\":[\"{}\"]}}",
+            escape_json_string(&self.topic),
+            escape_json_string(&headline(events)),
+            escape_json_string(&message),
+            urgency.map_or(2, ntfy_priority),
+            tag
+        );
+        self.retry.run(|| self.publish(&body))
+    }
+}
+
+/// ntfy priorities run from 1 (min) to 5 (max/urgent), 3 being the default.
+fn ntfy_priority(priority: Priority) -> u8 {
+    match priority {
+        Priority::Critical => 5,
+        Priority::High => 4,
+        Priority::Medium => 3,
+        Priority::Low => 2,
+        Priority::Info => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_file::ConfigFile;
+    use crate::testing::{result, TestServer};
+
+    fn notifier(config: &str) -> Result<NtfyNotifier, String> {
+        let config = ConfigFile::parse(config).unwrap().ntfy.remove(0);
+        NtfyNotifier::new(reqwest::blocking::Client::new(), &config, RetryPolicy::default())
+    }
+
+    #[test]
+    fn rounds_are_published_as_json_with_the_most_urgent_priority() {
+        let server = TestServer::start(|_| (200, "{}".to_string()));
+        let notifier = notifier(&format!("[[ntfy]]\nserver = \"{}/\"\ntopic = \"alerts\"\ntoken = \"tk_1\"", server.url)).unwrap();
+        let mut critical = result("https://pay.example.com", Err("timed out".to_string()));
+        critical.priority = Priority::Critical;
+        notifier.notify(&[Event::Failure { round: 2, result: critical, reminder: false }]).unwrap();
+        notifier.notify(&[Event::Recovery { round: 3, result: result("https://pay.example.com", Ok(200)), outage: Default::default() }]).unwrap();
+        notifier.notify(&[]).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].url, "/");
+        assert_eq!(requests[0].header("authorization"), Some("Bearer tk_1"));
+        let failed: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(failed["topic"], "alerts");
+        assert_eq!(failed["title"], "Round 2: 1 failed");
+        assert_eq!((failed["priority"].as_u64(), failed["tags"][0].as_str()), (Some(5), Some("rotating_light")));
+        let recovered: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!((recovered["priority"].as_u64(), recovered["tags"][0].as_str()), (Some(2), Some("white_check_mark")));
+    }
+
+    #[test]
+    fn settings_are_checked() {
+        let config = ConfigFile::parse("[[ntfy]]\ntopic = \"alerts\"").unwrap().ntfy.remove(0);
+        assert_eq!(config.server, DEFAULT_SERVER);
+        assert_eq!(notifier("[[ntfy]]\ntopic = \"\"").err().unwrap(), "topic must not be empty");
+        assert!(notifier("[[ntfy]]\ntopic = \"a\"\ntoken = \"x\"\ntoken_env = \"NTFY_TOKEN\"").is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Opsgenie notifier, configured with `[[opsgenie]]` tables in the `--config` file.
+//! A failure creates (or, while the URL stays down, re-counts) an alert aliased by
+//! the URL; a recovery closes it. The check's priority becomes P1-P5 and its tags
+//! are forwarded as alert tags.
+
+use std::env;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::{Event, Notifier, RetryPolicy};
+use crate::{escape_json_string, format_duration, format_rfc3339_utc, CheckResult, Priority};
+
+pub const DEFAULT_API_URL: &str = "https://api.opsgenie.com";
+
+/// Opsgenie truncates longer messages.
+const MAX_MESSAGE_CHARS: usize = 130;
+/// Opsgenie rejects longer aliases.
+const MAX_ALIAS_CHARS: usize = 512;
+
+/// One `[[opsgenie]]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpsgenieConfig {
+    /// Channel name for routes and escalation policies (default: `opsgenie`).
+    pub name: Option<String>,
+    /// API integration key.
+    pub api_key: Option<String>,
+    /// Environment variable holding the API key, to keep it out of the file.
+    pub api_key_env: Option<String>,
+    /// `https://api.eu.opsgenie.com` for EU accounts.
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    /// Tags added to every alert, in addition to the check's own.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_api_url() -> String {
+    DEFAULT_API_URL.to_string()
+}
+
+pub struct OpsgenieNotifier {
+    client: reqwest::blocking::Client,
+    api_url: String,
+    api_key: String,
+    tags: Vec<String>,
+    retry: RetryPolicy,
+}
+
+impl OpsgenieNotifier {
+    pub fn new(client: reqwest::blocking::Client, config: &OpsgenieConfig, retry: RetryPolicy) -> Result<Self, String> {
+        let api_key = match (&config.api_key, &config.api_key_env) {
+            (Some(_), Some(_)) => return Err("set either api_key or api_key_env, not both".to_string()),
+            (Some(key), None) => key.clone(),
+            (None, Some(var)) => env::var(var).map_err(|_| format!("environment variable {} is not set", var))?,
+            (None, None) => return Err("api_key or api_key_env is required".to_string()),
+        };
+        Ok(OpsgenieNotifier {
+            client,
+            api_url: config.api_url.trim_end_matches('/').to_string(),
+            api_key,
+            tags: config.tags.clone(),
+            retry,
+        })
+    }
+
+    fn create_alert(&self, result: &CheckResult, outcome: &str, round: u64) -> Result<(), String> {
+        let mut tags: Vec<&str> = Vec::new();
+        for tag in self.tags.iter().chain(&result.tags) {
+            if !tags.contains(&tag.as_str()) {
+                tags.push(tag);
+            }
+        }
+        let tags_json: Vec<String> = tags.iter().map(|tag| format!("\"{}\"", escape_json_string(tag))).collect();
+        let message = truncate_chars(&format!("{} is down: {}", result.url, outcome), MAX_MESSAGE_CHARS);
+        let body = format!(
+            "{{\"message\":\"{}\",\"alias\":\"{}\",\"description\":\"{}\",\"priority\":\"{}\",\"tags\":[{}],\
+             \"source\":\"website-status-checker\",\"details\":{{\"url\":\"{}\",\"status\":\"{}\",\
+             \"responseTimeMs\":\"{}\",\"checkedAt\":\"{}\",\"round\":\"{}\"}}}}",
+            escape_json_string(&message),
+            escape_json_string(&alias(&result.url)),
+            escape_json_string(&format!("{} failed with: {}", result.url, outcome)),
+            opsgenie_priority(result.priority),
+            tags_json.join(","),
+            escape_json_string(&result.url),
+            escape_json_string(outcome),
+            result.response_time.as_millis(),
+            format_rfc3339_utc(result.timestamp),
+            round
+        );
+        self.retry.run(|| self.post(&format!("{}/v2/alerts", self.api_url), &body))
+    }
+
+    fn close_alert(&self, result: &CheckResult, outcome: &str, outage: Duration) -> Result<(), String> {
+        let url = format!("{}/v2/alerts/{}/close?identifierType=alias", self.api_url, percent_encode(&alias(&result.url)));
+        let body = format!(
+            "{{\"source\":\"website-status-checker\",\"note\":\"{}\"}}",
+            escape_json_string(&format!("Recovered with status {} after {} down", outcome, format_duration(outage)))
+        );
+        self.retry.run(|| self.post(&url, &body))
+    }
+
+    fn post(&self, url: &str, body: &str) -> Result<(), String> {
+        let response = self.client.post(url)
+            .header(reqwest::header::AUTHORIZATION, format!("GenieKey {}", self.api_key))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            let text = response.text().unwrap_or_default();
+            Err(format!("HTTP {}: {}", status.as_u16(), text.trim()))
+        }
+    }
+}
+
+impl Notifier for OpsgenieNotifier {
+    fn name(&self) -> &str {
+        "opsgenie"
+    }
+
+    fn notify(&self, events: &[Event]) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for event in events {
+            let (Some(result), Some(outcome)) = (event.result(), event.outcome()) else { continue };
+            let delivered = match event {
+                Event::Failure { round, .. } => self.create_alert(result, &outcome, *round),
+                Event::Recovery { outage, .. } => self.close_alert(result, &outcome, *outage),
+                Event::Flapping { .. } | Event::Summary { .. } => Ok(()),
+            };
+            if let Err(e) = delivered {
+                errors.push(e);
+            }
+        }
+        match errors.last() {
+            None => Ok(()),
+            Some(last) => Err(format!("{} of {} alert updates failed, last error: {}", errors.len(), events.len(), last)),
+        }
+    }
+}
+
+/// Maps check priorities onto Opsgenie's P1 (critical) to P5 (informational).
+pub fn opsgenie_priority(priority: Priority) -> &'static str {
+    match priority {
+        Priority::Critical => "P1",
+        Priority::High => "P2",
+        Priority::Medium => "P3",
+        Priority::Low => "P4",
+        Priority::Info => "P5",
+    }
+}
+
+// One open alert per URL: Opsgenie de-duplicates creations with the same alias
+fn alias(url: &str) -> String {
+    truncate_chars(&format!("website-status-checker:{}", url), MAX_ALIAS_CHARS)
+}
+
+fn truncate_chars(s: &str, max_chars: usize) -> String {
+    s.chars().take(max_chars).collect()
+}
+
+// Percent-encodes everything but unreserved characters, for use as one path segment
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_file::ConfigFile;
+    use crate::testing::{result, TestServer};
+
+    fn notifier(server: &TestServer) -> OpsgenieNotifier {
+        let config = format!("[[opsgenie]]\napi_key = \"k3y\"\napi_url = \"{}/\"\ntags = [\"monitoring\", \"web\"]", server.url);
+        let config = ConfigFile::parse(&config).unwrap().opsgenie.remove(0);
+        OpsgenieNotifier::new(reqwest::blocking::Client::new(), &config, RetryPolicy::default()).unwrap()
+    }
+
+    #[test]
+    fn failures_create_alerts_and_recoveries_close_them() {
+        let server = TestServer::start(|_| (202, r#"{"result":"Request will be processed"}"#.to_string()));
+        let notifier = notifier(&server);
+        let mut failed = result("https://pay.example.com/a b", Err("timed out".to_string()));
+        failed.priority = Priority::Critical;
+        failed.tags = vec!["web".to_string(), "payments".to_string()];
+        let recovered = result("https://pay.example.com/a b", Ok(200));
+        notifier.notify(&[
+            Event::Failure { round: 5, result: failed, reminder: false },
+            Event::Recovery { round: 6, result: recovered, outage: Duration::from_secs(300) },
+        ]).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].url, "/v2/alerts");
+        assert_eq!(requests[0].header("authorization"), Some("GenieKey k3y"));
+        let alert: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(alert["message"], "https://pay.example.com/a b is down: timed out");
+        assert_eq!(alert["alias"], "website-status-checker:https://pay.example.com/a b");
+        assert_eq!(alert["priority"], "P1");
+        assert_eq!(alert["tags"], serde_json::json!(["monitoring", "web", "payments"]));
+        assert_eq!(alert["details"]["round"], "5");
+        assert_eq!(
+            requests[1].url,
+            "/v2/alerts/website-status-checker%3Ahttps%3A%2F%2Fpay.example.com%2Fa%20b/close?identifierType=alias"
+        );
+        let close: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(close["note"], "Recovered with status 200 after 5m 0s down");
+    }
+
+    #[test]
+    fn rejected_updates_are_reported() {
+        let server = TestServer::start(|_| (422, r#"{"message":"Request body is not processable"}"#.to_string()));
+        let notifier = OpsgenieNotifier { retry: RetryPolicy { retries: 0, ..RetryPolicy::default() }, ..notifier(&server) };
+        let failure = Event::Failure { round: 1, result: result("https://example.com", Err("timed out".to_string())), reminder: false };
+        assert_eq!(
+            notifier.notify(&[failure]).unwrap_err(),
+            r#"1 of 1 alert updates failed, last error: HTTP 422: {"message":"Request body is not processable"} (after 1 attempts)"#
+        );
+    }
+
+    #[test]
+    fn messages_and_aliases_stay_within_limits() {
+        let long_url = format!("https://example.com/{}", "é".repeat(600));
+        assert_eq!(alias(&long_url).chars().count(), MAX_ALIAS_CHARS);
+        assert_eq!(opsgenie_priority(Priority::Info), "P5");
+        let config = ConfigFile::parse("[[opsgenie]]").unwrap().opsgenie.remove(0);
+        assert_eq!(config.api_url, DEFAULT_API_URL);
+        assert!(OpsgenieNotifier::new(reqwest::blocking::Client::new(), &config, RetryPolicy::default()).is_err());
+    }
+}
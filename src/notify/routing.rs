@@ -0,0 +1,73 @@
+//! Alert routing from `[[route]]` tables in the `--config` file: a routed channel
+//! only receives events about URLs that match one of its routes, e.g. the payments
+//! team's Slack only hears about `tags=payments` checks and Opsgenie only about
+//! `priority=critical` ones. Channels without routes receive everything.
+
+use serde::Deserialize;
+
+use crate::{CheckResult, Priority};
+
+/// One `[[route]]` table of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    /// Channel name: `webhook`, `slack`, `discord`, `telegram`, or the `name` of a
+    /// config file notifier.
+    pub channel: String,
+    /// Only URLs with at least one of these tags; empty matches every URL.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Only URLs with this priority or a more urgent one.
+    pub priority: Option<String>,
+}
+
+/// Which check results a route or escalation policy applies to.
+#[derive(Debug, Clone, Default)]
+pub struct Selector {
+    pub tags: Vec<String>,
+    pub priority: Option<Priority>,
+}
+
+impl Selector {
+    pub fn new(tags: &[String], priority: Option<&str>) -> Result<Self, String> {
+        let priority = priority.map(Priority::parse).transpose()?;
+        Ok(Selector { tags: tags.to_vec(), priority })
+    }
+
+    pub fn matches(&self, result: &CheckResult) -> bool {
+        let tag_match = self.tags.is_empty() || self.tags.iter().any(|tag| result.tags.contains(tag));
+        // Priorities are ordered from most to least urgent
+        tag_match && self.priority.is_none_or(|priority| result.priority <= priority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::result;
+
+    fn check(priority: Priority, tags: &[&str]) -> CheckResult {
+        let mut result = result("https://example.com", Ok(200));
+        result.priority = priority;
+        result.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        result
+    }
+
+    #[test]
+    fn selectors_match_any_tag_and_at_least_the_priority() {
+        let everything = Selector::default();
+        assert!(everything.matches(&check(Priority::Info, &[])));
+
+        let payments = Selector::new(&["payments".to_string(), "billing".to_string()], None).unwrap();
+        assert!(payments.matches(&check(Priority::Low, &["api", "billing"])));
+        assert!(!payments.matches(&check(Priority::Critical, &["api"])));
+        assert!(!payments.matches(&check(Priority::Critical, &["Payments"])));
+
+        let urgent_payments = Selector::new(&["payments".to_string()], Some("high")).unwrap();
+        assert!(urgent_payments.matches(&check(Priority::Critical, &["payments"])));
+        assert!(urgent_payments.matches(&check(Priority::High, &["payments"])));
+        assert!(!urgent_payments.matches(&check(Priority::Medium, &["payments"])));
+
+        assert!(Selector::new(&[], Some("sev1")).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Slack incoming-webhook notifier. A round's failures, recoveries and flapping URLs
+//! are batched into a single message so one bad round doesn't post hundreds of messages.
+//! Webhooks come from `--slack-webhook` or from `[[slack]]` tables in the `--config`
+//! file, which can be named for alert routing.
+
+use std::env;
+
+use serde::Deserialize;
+
+use super::template::MessageTemplate;
+use super::{post, Event, Notifier, RetryPolicy};
+use crate::escape_json_string;
+
+/// Checks listed per section before the rest are summarized as "...and N more".
+const MAX_LINES_PER_SECTION: usize = 20;
+
+/// One `[[slack]]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlackConfig {
+    /// Channel name for routes and escalation policies (default: `slack`).
+    pub name: Option<String>,
+    pub webhook_url: Option<String>,
+    /// Environment variable holding the webhook URL, to keep it out of the file.
+    pub webhook_url_env: Option<String>,
+    /// Message template file, like `--slack-template`.
+    pub template: Option<String>,
+}
+
+impl SlackConfig {
+    pub fn resolve_webhook_url(&self) -> Result<String, String> {
+        match (&self.webhook_url, &self.webhook_url_env) {
+            (Some(_), Some(_)) => Err("set either webhook_url or webhook_url_env, not both".to_string()),
+            (Some(url), None) => Ok(url.clone()),
+            (None, Some(var)) => env::var(var).map_err(|_| format!("environment variable {} is not set", var)),
+            (None, None) => Err("webhook_url or webhook_url_env is required".to_string()),
+        }
+    }
+}
+
+pub struct SlackNotifier {
+    client: reqwest::blocking::Client,
+    webhook_url: String,
+    template: Option<MessageTemplate>,
+    retry: RetryPolicy,
+}
+
+impl SlackNotifier {
+    /// `template`, loaded with [`slack_escape`] as its escape function, replaces the
+    /// default message text.
+    pub fn new(
+        client: reqwest::blocking::Client,
+        webhook_url: String,
+        template: Option<MessageTemplate>,
+        retry: RetryPolicy,
+    ) -> Self {
+        SlackNotifier { client, webhook_url, template, retry }
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    fn notify(&self, events: &[Event]) -> Result<(), String> {
+        let text = match &self.template {
+            Some(template) => template.render_round(events, false)?,
+            None => format_message(events),
+        };
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        let body = format!("{{\"text\":\"{}\"}}", escape_json_string(&text));
+        self.retry.run(|| post(&self.client, &self.webhook_url, "application/json", &body))
+    }
+}
+
+/// Slack mrkdwn treats &, < and > as control characters.
+pub fn slack_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn format_message(events: &[Event]) -> String {
+    let failures: Vec<&Event> = events.iter().filter(|e| matches!(e, Event::Failure { .. })).collect();
+    let recoveries: Vec<&Event> = events.iter().filter(|e| matches!(e, Event::Recovery { .. })).collect();
+    let flapping: Vec<&Event> = events.iter().filter(|e| matches!(e, Event::Flapping { .. })).collect();
+    let mut sections = Vec::new();
+    if !failures.is_empty() {
+        sections.push(format_section(":red_circle:", "failed", &failures));
+    }
+    if !recoveries.is_empty() {
+        sections.push(format_section(":large_green_circle:", "recovered", &recoveries));
+    }
+    if !flapping.is_empty() {
+        sections.push(format_section(":large_orange_circle:", "flapping", &flapping));
+    }
+    sections.join("\n\n")
+}
+
+fn format_section(emoji: &str, verb: &str, events: &[&Event]) -> String {
+    let round = events.first().map_or(0, |e| e.round());
+    let noun = if events.len() == 1 { "check" } else { "checks" };
+    let mut lines = vec![format!("{} *{} {} {}* (round {})", emoji, events.len(), noun, verb, round)];
+    for event in events.iter().take(MAX_LINES_PER_SECTION) {
+        if let (Some(result), Some(outcome)) = (event.result(), event.outcome()) {
+            lines.push(format!(
+                "• {} — {} ({} ms){}",
+                slack_escape(&result.url),
+                slack_escape(&outcome),
+                result.response_time.as_millis(),
+                event.note().map_or(String::new(), |note| format!(", _{}_", note))
+            ));
+        }
+    }
+    if events.len() > MAX_LINES_PER_SECTION {
+        lines.push(format!("_...and {} more_", events.len() - MAX_LINES_PER_SECTION));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{result, TestServer};
+
+    fn failure(url: &str) -> Event {
+        Event::Failure { round: 7, result: result(url, Err("HTTP <503>".to_string())), reminder: false }
+    }
+
+    #[test]
+    fn long_sections_are_cut_off() {
+        let mut events: Vec<Event> = (0..25).map(|i| failure(&format!("https://example.com/{}", i))).collect();
+        events.push(Event::Recovery {
+            round: 7,
+            result: result("https://api.example.com?a=1&b=2", Ok(200)),
+            outage: Duration::from_secs(125),
+        });
+        let message = format_message(&events);
+        let (failures, recoveries) = message.split_once("\n\n").unwrap();
+        let failures: Vec<&str> = failures.lines().collect();
+        assert_eq!(failures[0], ":red_circle: *25 checks failed* (round 7)");
+        assert_eq!(failures[1], "• https://example.com/0 — HTTP &lt;503&gt; (100 ms)");
+        assert_eq!(failures.len(), 1 + MAX_LINES_PER_SECTION + 1);
+        assert_eq!(failures[failures.len() - 1], "_...and 5 more_");
+        assert_eq!(
+            recoveries,
+            ":large_green_circle: *1 check recovered* (round 7)\n• https://api.example.com?a=1&amp;b=2 — 200 (100 ms), _down for 2m 5s_"
+        );
+    }
+
+    #[test]
+    fn posts_one_message_per_round() {
+        let server = TestServer::start(|_| (200, "ok".to_string()));
+        let notifier = SlackNotifier::new(reqwest::blocking::Client::new(), server.url.clone(), None, RetryPolicy::default());
+        notifier.notify(&[failure("https://a.example.com"), failure("https://b.example.com")]).unwrap();
+        // Nothing to say about a round with only a summary
+        notifier.notify(&[Event::Summary { round: 7, summary: Default::default() }]).unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["text"], format_message(&[failure("https://a.example.com"), failure("https://b.example.com")]));
+    }
+
+    #[test]
+    fn webhook_urls_from_the_config_file() {
+        let config = |url: Option<&str>, var: Option<&str>| SlackConfig {
+            name: Some("payments".to_string()),
+            webhook_url: url.map(str::to_string),
+            webhook_url_env: var.map(str::to_string),
+            template: None,
+        };
+        assert_eq!(config(Some("https://hooks.slack.com/x"), None).resolve_webhook_url(), Ok("https://hooks.slack.com/x".to_string()));
+        assert_eq!(
+            config(None, Some("WEBSITE_CHECKER_TEST_UNSET_VAR")).resolve_webhook_url(),
+            Err("environment variable WEBSITE_CHECKER_TEST_UNSET_VAR is not set".to_string())
+        );
+        assert!(config(Some("https://hooks.slack.com/x"), Some("SLACK_URL")).resolve_webhook_url().is_err());
+        assert!(config(None, None).resolve_webhook_url().is_err());
+    }
+}
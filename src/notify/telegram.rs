@@ -0,0 +1,202 @@
+//! Telegram Bot API notifier. Each round's failures, recoveries and flapping URLs
+//! become one plain-text message (split at Telegram's 4096 character limit), sent no
+//! faster than Telegram allows for a single chat.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{format_plain_text, Event, Notifier, RetryPolicy};
+use crate::escape_json_string;
+
+pub const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+/// Telegram rejects longer messages.
+const MAX_MESSAGE_CHARS: usize = 4096;
+/// Telegram allows about one message per second to the same chat...
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+/// ...and 20 messages per minute to the same group.
+const WINDOW: Duration = Duration::from_secs(60);
+const MAX_PER_WINDOW: usize = 20;
+
+pub struct TelegramNotifier {
+    client: reqwest::blocking::Client,
+    api_url: String,
+    token: String,
+    chat_id: String,
+    retry: RetryPolicy,
+    sent_at: Mutex<VecDeque<Instant>>,
+}
+
+impl TelegramNotifier {
+    /// `api_url` is normally [`DEFAULT_API_URL`]; a self-hosted Bot API server also works.
+    pub fn new(
+        client: reqwest::blocking::Client,
+        api_url: String,
+        token: String,
+        chat_id: String,
+        retry: RetryPolicy,
+    ) -> Self {
+        TelegramNotifier {
+            client,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            token,
+            chat_id,
+            retry,
+            sent_at: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    // Blocks until sending another message stays within Telegram's limits
+    fn wait_for_slot(&self) {
+        let mut sent_at = match self.sent_at.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        loop {
+            let now = Instant::now();
+            while sent_at.front().is_some_and(|t| now.duration_since(*t) >= WINDOW) {
+                sent_at.pop_front();
+            }
+            let wait = if sent_at.len() >= MAX_PER_WINDOW {
+                sent_at.front().map(|oldest| WINDOW.saturating_sub(now.duration_since(*oldest)))
+            } else {
+                sent_at.back().map(|last| MIN_INTERVAL.saturating_sub(now.duration_since(*last)))
+            };
+            match wait {
+                Some(wait) if !wait.is_zero() => thread::sleep(wait),
+                _ => break,
+            }
+        }
+        sent_at.push_back(Instant::now());
+    }
+
+    fn send_message(&self, text: &str) -> Result<(), String> {
+        let url = format!("{}/bot{}/sendMessage", self.api_url, self.token);
+        let body = format!(
+            "{{\"chat_id\":\"{}\",\"text\":\"{}\",\"disable_web_page_preview\":true}}",
+            escape_json_string(&self.chat_id),
+            escape_json_string(text)
+        );
+        self.retry.run(|| {
+            self.wait_for_slot();
+            let response = self.client.post(&url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                // Errors may contain the request URL, which includes the bot token
+                .map_err(|e| e.without_url().to_string())?;
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+            let text = response.text().unwrap_or_default();
+            if status.as_u16() == 429 {
+                // Telegram says how long to back off: {"parameters":{"retry_after":N}}
+                if let Some(secs) = parse_retry_after(&text) {
+                    thread::sleep(Duration::from_secs(secs));
+                }
+            }
+            Err(format!("HTTP {}: {}", status.as_u16(), text.trim()))
+        })
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn notify(&self, events: &[Event]) -> Result<(), String> {
+        let text = format_plain_text(events);
+        for chunk in split_message(&text, MAX_MESSAGE_CHARS) {
+            self.send_message(&chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_retry_after(body: &str) -> Option<u64> {
+    let start = body.find("\"retry_after\"")? + "\"retry_after\"".len();
+    let digits: String = body[start..].chars()
+        .skip_while(|c| *c == ':' || c.is_whitespace())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+// Splits on line boundaries so each chunk stays under `max_chars` characters
+fn split_message(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_chars = 0;
+    for line in text.lines() {
+        let line: String = line.chars().take(max_chars).collect();
+        let line_chars = line.chars().count();
+        if current_chars > 0 && current_chars + 1 + line_chars > max_chars {
+            chunks.push(std::mem::take(&mut current));
+            current_chars = 0;
+        }
+        if current_chars > 0 {
+            current.push('\n');
+            current_chars += 1;
+        }
+        current.push_str(&line);
+        current_chars += line_chars;
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{result, TestServer};
+
+    #[test]
+    fn messages_split_on_lines_by_characters() {
+        assert_eq!(split_message("", 10), Vec::<String>::new());
+        assert_eq!(split_message("abc\ndef\nghi", 7), ["abc\ndef", "ghi"]);
+        // Characters, not bytes: each of these lines is 4 characters but 8 bytes long
+        assert_eq!(split_message("🔴🔴🔴🔴\n🟢🟢🟢🟢", 9), ["🔴🔴🔴🔴\n🟢🟢🟢🟢"]);
+        assert_eq!(split_message("🔴🔴🔴🔴\n🟢🟢🟢🟢", 8), ["🔴🔴🔴🔴", "🟢🟢🟢🟢"]);
+        // A single line longer than a message is cut off
+        assert_eq!(split_message("ééééé\nok", 3), ["ééé", "ok"]);
+        for chunk in split_message(&"• https://example.com — timed out\n".repeat(500), MAX_MESSAGE_CHARS) {
+            assert!(chunk.chars().count() <= MAX_MESSAGE_CHARS);
+        }
+    }
+
+    #[test]
+    fn retry_after_from_error_responses() {
+        let body = r#"{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 7","parameters":{"retry_after": 7}}"#;
+        assert_eq!(parse_retry_after(body), Some(7));
+        assert_eq!(parse_retry_after(r#"{"ok":false,"error_code":400}"#), None);
+    }
+
+    #[test]
+    fn messages_go_to_the_chat_a_second_apart() {
+        let server = TestServer::start(|_| (200, r#"{"ok":true}"#.to_string()));
+        let notifier = TelegramNotifier::new(
+            reqwest::blocking::Client::new(),
+            format!("{}/", server.url),
+            "123:abc".to_string(),
+            "-100200".to_string(),
+            RetryPolicy::default(),
+        );
+        let failure = Event::Failure { round: 1, result: result("https://example.com", Err("timed out".to_string())), reminder: false };
+        let started = Instant::now();
+        notifier.notify(std::slice::from_ref(&failure)).unwrap();
+        notifier.notify(&[failure]).unwrap();
+        assert!(started.elapsed() >= MIN_INTERVAL);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].url, "/bot123:abc/sendMessage");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["chat_id"], "-100200");
+        assert_eq!(body["text"], "🔴 1 check failed (round 1)\n• https://example.com — timed out (100 ms)");
+    }
+}
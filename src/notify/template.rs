@@ -0,0 +1,191 @@
+//! User-provided message templates (`--webhook-template`, `--slack-template`,
+//! `text_template`/`html_template` in `[[email]]`), rendered with MiniJinja: Jinja2
+//! syntax with `{{ field }}`, `{% if %}`, `{% for %}` and filters like `upper`.
+//!
+//! Webhook templates render one event, with its fields (see [`Event::fields`]) as
+//! top-level variables. Slack and e-mail templates render a whole round, with
+//! `round`, `events`, `failures`, `recoveries`, `flapping` (lists of event fields),
+//! `summary` (summary fields, if any) and `immediate` (true for an e-mail sent as
+//! soon as a critical check fails).
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use minijinja::value::{Value, ValueKind};
+use minijinja::Environment;
+
+use super::{Event, EventKind};
+use crate::format_rfc3339_utc;
+
+const NAME: &str = "message";
+
+/// A compiled template. Every value it prints goes through the escape function
+/// given to [`MessageTemplate::new`] unless marked with the `safe` filter.
+#[derive(Clone)]
+pub struct MessageTemplate {
+    env: Arc<Environment<'static>>,
+}
+
+impl MessageTemplate {
+    /// Compiles `source`, failing on syntax errors.
+    pub fn new(source: String, escape: fn(&str) -> String) -> Result<Self, String> {
+        let mut env = Environment::new();
+        env.set_formatter(move |out, _state, value| {
+            // Missing values print nothing rather than "none"
+            if value.is_none() || value.is_undefined() {
+                return Ok(());
+            }
+            // Booleans print as JSON's true/false rather than Python's True/False
+            let text = match value.kind() {
+                ValueKind::Bool => value.is_true().to_string(),
+                _ => value.to_string(),
+            };
+            let escaped = if value.is_safe() { text } else { escape(&text) };
+            out.write_str(&escaped).map_err(Into::into)
+        });
+        env.add_template_owned(NAME, source).map_err(|e| e.to_string())?;
+        Ok(MessageTemplate { env: Arc::new(env) })
+    }
+
+    pub fn load(path: &str, escape: fn(&str) -> String) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("Failed to read template {}: {}", path, e))?;
+        MessageTemplate::new(source, escape).map_err(|e| format!("Invalid template {}: {}", path, e))
+    }
+
+    /// Renders a single event.
+    pub fn render_event(&self, event: &Event) -> Result<String, String> {
+        self.render(event_value(event))
+    }
+
+    /// Renders a round's events.
+    pub fn render_round(&self, events: &[Event], immediate: bool) -> Result<String, String> {
+        let of_kind = |kind: EventKind| -> Value {
+            events.iter().filter(|event| event.kind() == kind).map(event_value).collect()
+        };
+        let summary = events.iter().find(|event| event.kind() == EventKind::Summary).map(event_value);
+        let context: Value = [
+            ("round", Value::from(events.first().map_or(0, |e| e.round()))),
+            ("events", events.iter().filter(|event| event.result().is_some()).map(event_value).collect()),
+            ("failures", of_kind(EventKind::Failure)),
+            ("recoveries", of_kind(EventKind::Recovery)),
+            ("flapping", of_kind(EventKind::Flapping)),
+            // Empty rather than undefined, so `summary.total` prints nothing
+            ("summary", summary.unwrap_or_else(|| Value::from(BTreeMap::<String, Value>::new()))),
+            ("immediate", Value::from(immediate)),
+        ]
+        .into_iter()
+        .collect();
+        self.render(context)
+    }
+
+    fn render(&self, context: Value) -> Result<String, String> {
+        let template = self.env.get_template(NAME).map_err(|e| e.to_string())?;
+        template.render(context).map_err(|e| format!("template error: {}", e))
+    }
+}
+
+// The event's fields, with numbers and flags as numbers and booleans so templates
+// can compare them, plus `timestamp` as RFC 3339
+fn event_value(event: &Event) -> Value {
+    let mut fields: Vec<(&str, Value)> = event.fields().into_iter()
+        .map(|(name, value)| (name, Value::from(value)))
+        .collect();
+    let mut set = |name: &'static str, value: Value| match fields.iter_mut().find(|(field, _)| *field == name) {
+        Some(field) => field.1 = value,
+        None => fields.push((name, value)),
+    };
+    set("round", Value::from(event.round()));
+    set("reminder", Value::from(event.is_reminder()));
+    if let Some(result) = event.result() {
+        set("responseTimeMs", Value::from(result.response_time.as_millis() as u64));
+        set("timestampEpochS", Value::from(result.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()));
+        set("timestamp", Value::from(format_rfc3339_utc(result.timestamp)));
+        if let Ok(code) = result.action_status {
+            set("status", Value::from(code));
+        }
+    }
+    if let Some(outage) = event.outage() {
+        set("outageSeconds", Value::from(outage.as_secs()));
+    }
+    if let Event::Summary { summary, .. } = event {
+        set("total", Value::from(summary.total));
+        set("successful", Value::from(summary.successful));
+        set("failed", Value::from(summary.failed));
+        set("avgResponseTimeMs", summary.avg_response_time.map_or(Value::from(()), |avg| Value::from(avg.as_millis() as u64)));
+    }
+    fields.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::escape_json_string;
+    use crate::notify::RoundSummary;
+    use crate::testing::result_at;
+
+    fn failure(url: &str, error: &str) -> Event {
+        let result = result_at(url, Err(error.to_string()), UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        Event::Failure { round: 9, result, reminder: false }
+    }
+
+    #[test]
+    fn events_render_with_escaped_values() {
+        let template = MessageTemplate::new(
+            r#"{"text": "{{ url }} {{ event | upper }}: {{ error }}{{ outage }}", "at": "{{ timestamp }}", "slow": {{ responseTimeMs > 50 }}}"#.to_string(),
+            escape_json_string,
+        )
+        .unwrap();
+        let rendered = template.render_event(&failure("https://example.com", "bad \"gateway\"\n")).unwrap();
+        assert_eq!(
+            rendered,
+            r#"{"text": "https://example.com FAILURE: bad \"gateway\"\n", "at": "2023-11-14T22:13:20Z", "slow": true}"#
+        );
+    }
+
+    #[test]
+    fn safe_values_are_not_escaped() {
+        let template = MessageTemplate::new("{{ url }} {{ url | safe }}".to_string(), crate::html_escape).unwrap();
+        let rendered = template.render_event(&failure("https://example.com/?a=1&b=2", "timed out")).unwrap();
+        assert_eq!(rendered, "https://example.com/?a=1&amp;b=2 https://example.com/?a=1&b=2");
+    }
+
+    #[test]
+    fn rounds_render_with_lists_per_kind() {
+        let template = MessageTemplate::new(
+            "{% if immediate %}NOW {% endif %}round {{ round }}: {{ failures | length }} down \
+             ({% for f in failures %}{{ f.url }}{% if not loop.last %}, {% endif %}{% endfor %}), \
+             {{ recoveries | length }} up, {{ summary.total }} checked"
+                .to_string(),
+            str::to_string,
+        )
+        .unwrap();
+        let summary = RoundSummary { total: 5, successful: 3, failed: 2, avg_response_time: None };
+        let events = [
+            failure("https://a.example.com", "timed out"),
+            failure("https://b.example.com", "HTTP 502"),
+            Event::Summary { round: 9, summary },
+        ];
+        assert_eq!(
+            template.render_round(&events, false).unwrap(),
+            "round 9: 2 down (https://a.example.com, https://b.example.com), 0 up, 5 checked"
+        );
+        // Without a summary event its fields print nothing
+        assert_eq!(
+            template.render_round(&events[..1], true).unwrap(),
+            "NOW round 9: 1 down (https://a.example.com), 0 up,  checked"
+        );
+    }
+
+    #[test]
+    fn broken_templates_are_reported() {
+        assert!(MessageTemplate::new("{% if %}".to_string(), str::to_string).is_err());
+        let error = MessageTemplate::load("/nonexistent/template.j2", str::to_string).err().unwrap();
+        assert!(error.starts_with("Failed to read template /nonexistent/template.j2"));
+        let template = MessageTemplate::new("{{ url | nosuchfilter }}".to_string(), str::to_string);
+        assert!(template.is_err() || template.unwrap().render_event(&failure("https://example.com", "x")).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+//! Per-URL alert state: turns the stream of check results into failure and recovery
+//! [`Event`]s, so notifiers only hear about state changes worth alerting on.
+//!
+//! Flap detection follows Nagios: the last 21 up/down states of a URL give up to 20
+//! state changes, weighted from 0.8 (oldest) to 1.2 (newest), and the weighted share
+//! of changes is its flap rate in percent. A URL starts flapping when the rate reaches
+//! the high threshold and stops once it drops below the low one.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::Event;
+use crate::CheckResult;
+
+/// When the [`AlertTracker`] reports failures.
+#[derive(Debug, Clone, Copy)]
+pub struct AlertPolicy {
+    /// Consecutive failed rounds before a URL is alerted on (at least 1).
+    pub alert_after: u32,
+    /// How often a URL that stays down is reported again; `None` reports it once.
+    pub realert_interval: Option<Duration>,
+    /// Flap detection thresholds; `None` disables flap detection.
+    pub flap_thresholds: Option<FlapThresholds>,
+}
+
+impl Default for AlertPolicy {
+    fn default() -> Self {
+        AlertPolicy { alert_after: 1, realert_interval: None, flap_thresholds: None }
+    }
+}
+
+/// Flap rates in percent: flapping starts at `high` and stops below `low`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlapThresholds {
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Default for FlapThresholds {
+    /// Nagios' defaults for services.
+    fn default() -> Self {
+        FlapThresholds { low: 5.0, high: 20.0 }
+    }
+}
+
+/// States kept per URL for flap detection, as in Nagios.
+const FLAP_HISTORY: usize = 21;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UrlState {
+    consecutive_failures: u32,
+    /// Check time of the first failure in the current streak.
+    down_since: Option<SystemTime>,
+    /// Check time of the last failure event sent; set while the URL counts as down.
+    last_alert: Option<SystemTime>,
+    /// Recent states, oldest first (`true` = up); only kept with flap detection.
+    history: VecDeque<bool>,
+    flapping: bool,
+}
+
+/// Per-URL alert state, saved with `--state-file` so a restart doesn't re-fire alerts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertState(HashMap<String, UrlState>);
+
+/// Tracks consecutive failures and sent alerts per URL across rounds.
+#[derive(Debug, Clone)]
+pub struct AlertTracker {
+    policy: AlertPolicy,
+    states: HashMap<String, UrlState>,
+}
+
+impl AlertTracker {
+    pub fn new(policy: AlertPolicy) -> Self {
+        let policy = AlertPolicy { alert_after: policy.alert_after.max(1), ..policy };
+        AlertTracker { policy, states: HashMap::new() }
+    }
+
+    /// Records one result and returns the event to send for it, if any: a failure
+    /// once the URL has failed `alert_after` times in a row, a reminder every
+    /// `realert_interval` while it stays down, and a recovery with the outage duration
+    /// when it succeeds again. Failures below the threshold that clear up on their own
+    /// produce no events. A flapping URL produces one flapping event when it starts
+    /// flapping and nothing else until it settles down. Checks during maintenance
+    /// are ignored.
+    pub fn observe(&mut self, round: u64, result: &CheckResult) -> Option<Event> {
+        if result.maintenance.is_some() {
+            return None;
+        }
+        let state = self.states.entry(result.url.clone()).or_default();
+        let failed = result.action_status.is_err();
+        if failed {
+            state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+            state.down_since.get_or_insert(result.timestamp);
+        }
+
+        if let Some(thresholds) = self.policy.flap_thresholds {
+            if state.history.len() == FLAP_HISTORY {
+                state.history.pop_front();
+            }
+            state.history.push_back(!failed);
+            let flap_rate = flap_rate(&state.history);
+            if !state.flapping && flap_rate >= thresholds.high {
+                state.flapping = true;
+                return Some(Event::Flapping { round, result: result.clone(), flap_rate });
+            }
+            if state.flapping && flap_rate < thresholds.low {
+                state.flapping = false;
+            }
+        }
+        if state.flapping {
+            // Alert state is left alone so a URL that was reported as down still
+            // gets its recovery (covering the whole outage) once it settles
+            if !failed {
+                state.consecutive_failures = 0;
+                if state.last_alert.is_none() {
+                    state.down_since = None;
+                }
+            }
+            return None;
+        }
+
+        if failed {
+            if state.consecutive_failures < self.policy.alert_after {
+                return None;
+            }
+            let reminder = match state.last_alert {
+                None => false,
+                Some(last_alert) => {
+                    let interval = self.policy.realert_interval?;
+                    if result.timestamp.duration_since(last_alert).unwrap_or_default() < interval {
+                        return None;
+                    }
+                    true
+                }
+            };
+            state.last_alert = Some(result.timestamp);
+            Some(Event::Failure { round, result: result.clone(), reminder })
+        } else {
+            state.consecutive_failures = 0;
+            let down_since = state.down_since.take();
+            state.last_alert.take()?;
+            let outage = down_since
+                .and_then(|since| result.timestamp.duration_since(since).ok())
+                .unwrap_or_default();
+            Some(Event::Recovery { round, result: result.clone(), outage })
+        }
+    }
+
+    pub fn state(&self) -> AlertState {
+        AlertState(self.states.clone())
+    }
+
+    /// Continues from a saved state, replacing the current one.
+    pub fn restore(&mut self, state: AlertState) {
+        self.states = state.0;
+    }
+
+    /// Time of the first failed check of `url`'s current outage, if it has been
+    /// reported as down.
+    pub fn outage_start(&self, url: &str) -> Option<SystemTime> {
+        let state = self.states.get(url)?;
+        state.last_alert.and(state.down_since)
+    }
+}
+
+// Weighted percentage of state changes in `history`, padded at the front with its
+// oldest state as Nagios does for URLs with less than a full history
+fn flap_rate(history: &VecDeque<bool>) -> f64 {
+    let Some(&oldest) = history.front() else { return 0.0 };
+    let padded: Vec<bool> = std::iter::repeat_n(oldest, FLAP_HISTORY - history.len())
+        .chain(history.iter().copied())
+        .collect();
+    let changes = FLAP_HISTORY - 1;
+    let weighted: f64 = (1..FLAP_HISTORY)
+        .filter(|&i| padded[i] != padded[i - 1])
+        .map(|i| 0.8 + 0.4 * (i - 1) as f64 / (changes - 1) as f64)
+        .sum();
+    weighted / changes as f64 * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::testing::result_at;
+
+    const URL: &str = "https://example.com";
+
+    // Checks URL in round `round`, `round` minutes after the epoch
+    fn observe(tracker: &mut AlertTracker, round: u64, up: bool) -> Option<Event> {
+        let status = if up { Ok(200) } else { Err("timed out".to_string()) };
+        tracker.observe(round, &result_at(URL, status, UNIX_EPOCH + Duration::from_secs(round * 60)))
+    }
+
+    fn kinds(tracker: &mut AlertTracker, rounds: &[bool]) -> Vec<Option<&'static str>> {
+        (1..).zip(rounds).map(|(round, up)| observe(tracker, round, *up).map(|event| event.kind().as_str())).collect()
+    }
+
+    #[test]
+    fn failures_are_reported_after_the_threshold() {
+        let mut tracker = AlertTracker::new(AlertPolicy { alert_after: 3, ..AlertPolicy::default() });
+        assert_eq!(
+            kinds(&mut tracker, &[false, false, true, false, false, false, true]),
+            [None, None, None, None, None, Some("failure"), Some("recovery")]
+        );
+    }
+
+    #[test]
+    fn a_threshold_of_zero_alerts_on_the_first_failure() {
+        let mut tracker = AlertTracker::new(AlertPolicy { alert_after: 0, ..AlertPolicy::default() });
+        assert_eq!(kinds(&mut tracker, &[true, false]), [None, Some("failure")]);
+    }
+
+    #[test]
+    fn urls_that_stay_down_are_reported_once_without_a_realert_interval() {
+        let mut tracker = AlertTracker::new(AlertPolicy::default());
+        assert_eq!(kinds(&mut tracker, &[false, false, false, false]), [Some("failure"), None, None, None]);
+    }
+
+    #[test]
+    fn reminders_follow_the_realert_interval() {
+        let policy = AlertPolicy { realert_interval: Some(Duration::from_secs(150)), ..AlertPolicy::default() };
+        let mut tracker = AlertTracker::new(policy);
+        let reminders: Vec<Option<bool>> = (1..=7)
+            .map(|round| observe(&mut tracker, round, false).map(|event| event.is_reminder()))
+            .collect();
+        // Rounds are a minute apart: reported at 1, then 4 and 7 minutes
+        assert_eq!(reminders, [Some(false), None, None, Some(true), None, None, Some(true)]);
+        assert_eq!(observe(&mut tracker, 8, true).unwrap().kind(), crate::notify::EventKind::Recovery);
+        assert_eq!(observe(&mut tracker, 9, false).map(|event| event.is_reminder()), Some(false));
+    }
+
+    #[test]
+    fn recoveries_cover_the_outage_from_its_first_failure() {
+        let mut tracker = AlertTracker::new(AlertPolicy { alert_after: 2, ..AlertPolicy::default() });
+        assert!(observe(&mut tracker, 1, false).is_none());
+        assert_eq!(tracker.outage_start(URL), None, "not reported as down yet");
+        assert!(observe(&mut tracker, 2, false).is_some());
+        assert_eq!(tracker.outage_start(URL), Some(UNIX_EPOCH + Duration::from_secs(60)));
+        assert!(observe(&mut tracker, 3, false).is_none());
+
+        let recovery = observe(&mut tracker, 5, true).unwrap();
+        assert_eq!(recovery.outage(), Some(Duration::from_secs(240)));
+        assert_eq!(recovery.note().as_deref(), Some("down for 4m 0s"));
+        assert!(recovery.fields().contains(&("outageSeconds", "240".to_string())));
+        assert_eq!(tracker.outage_start(URL), None);
+    }
+
+    #[test]
+    fn flap_rates_weigh_recent_changes_more() {
+        let rate = |states: &[bool]| flap_rate(&states.iter().copied().collect());
+        assert_eq!(rate(&[]), 0.0);
+        assert_eq!(rate(&[true; 21]), 0.0);
+        let alternating: Vec<bool> = (0..21).map(|i| i % 2 == 0).collect();
+        assert!((rate(&alternating) - 100.0).abs() < 1e-9);
+        // One change, newest (weight 1.2) or oldest (weight 0.8) of the 20
+        assert!((rate(&[false, true]) - 6.0).abs() < 1e-9);
+        let mut oldest_change = vec![true; 21];
+        oldest_change[0] = false;
+        assert!((rate(&oldest_change) - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flapping_urls_send_one_event_until_they_settle() {
+        let policy = AlertPolicy { flap_thresholds: Some(FlapThresholds::default()), ..AlertPolicy::default() };
+        let mut tracker = AlertTracker::new(policy);
+        let alternating: Vec<bool> = (0..12).map(|i| i % 2 == 1).collect();
+        let mut expected = vec![Some("failure"), Some("recovery"), Some("failure"), Some("recovery"), Some("flapping")];
+        expected.resize(12, None);
+        assert_eq!(kinds(&mut tracker, &alternating), expected);
+        // Settled: the last failures were never reported, so there is nothing to recover from
+        assert!((13..60).all(|round| observe(&mut tracker, round, true).is_none()));
+        assert_eq!(observe(&mut tracker, 60, false).unwrap().kind().as_str(), "failure");
+    }
+
+    #[test]
+    fn urls_down_when_they_start_flapping_recover_once_settled() {
+        let policy = AlertPolicy { flap_thresholds: Some(FlapThresholds { low: 2.0, high: 5.0 }), ..AlertPolicy::default() };
+        let mut tracker = AlertTracker::new(policy);
+        assert_eq!(kinds(&mut tracker, &[false, false, true]), [Some("failure"), None, Some("flapping")]);
+        // The change from down to up stays in the 21 state history until round 23
+        assert!((4..23).all(|round| observe(&mut tracker, round, true).is_none()));
+        let recovery = observe(&mut tracker, 23, true).unwrap();
+        assert_eq!(recovery.outage(), Some(Duration::from_secs(22 * 60)));
+    }
+
+    #[test]
+    fn checks_during_maintenance_are_ignored() {
+        let mut tracker = AlertTracker::new(AlertPolicy { alert_after: 2, ..AlertPolicy::default() });
+        assert!(observe(&mut tracker, 1, false).is_none());
+        let mut planned = result_at(URL, Err("timed out".to_string()), UNIX_EPOCH + Duration::from_secs(120));
+        planned.maintenance = Some("Weekly patching".to_string());
+        assert!(tracker.observe(2, &planned).is_none());
+        assert_eq!(kinds(&mut tracker, &[false]), [Some("failure")]);
+    }
+}
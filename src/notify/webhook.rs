@@ -0,0 +1,91 @@
+//! Generic webhook: one JSON POST per event.
+
+use super::template::MessageTemplate;
+use super::{post, Event, Notifier, RetryPolicy};
+
+pub struct WebhookNotifier {
+    client: reqwest::blocking::Client,
+    url: String,
+    template: Option<MessageTemplate>,
+    retry: RetryPolicy,
+}
+
+impl WebhookNotifier {
+    /// `template` replaces the default JSON body. It is rendered per event, with
+    /// values escaped by [`crate::escape_json_string`] so they fit inside JSON strings.
+    pub fn new(
+        client: reqwest::blocking::Client,
+        url: String,
+        template: Option<MessageTemplate>,
+        retry: RetryPolicy,
+    ) -> Self {
+        WebhookNotifier { client, url, template, retry }
+    }
+
+    fn body(&self, event: &Event) -> Result<String, String> {
+        match &self.template {
+            Some(template) => template.render_event(event),
+            None => Ok(event.to_json()),
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn notify(&self, events: &[Event]) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for event in events {
+            let delivery = self.body(event)
+                .and_then(|body| self.retry.run(|| post(&self.client, &self.url, "application/json", &body)));
+            if let Err(e) = delivery {
+                errors.push(e);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{} of {} deliveries failed, last error: {}", errors.len(), events.len(), errors[errors.len() - 1]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{result, TestServer};
+
+    fn failure(url: &str) -> Event {
+        Event::Failure { round: 1, result: result(url, Err("timed out".to_string())), reminder: false }
+    }
+
+    #[test]
+    fn posts_each_event_as_json() {
+        let server = TestServer::start(|_| (204, String::new()));
+        let url = format!("{}/hooks/monitor", server.url);
+        let notifier = WebhookNotifier::new(reqwest::blocking::Client::new(), url, None, RetryPolicy::default());
+        let events = [failure("https://a.example.com"), failure("https://b.example.com")];
+        notifier.notify(&events).unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        for (request, event) in requests.iter().zip(&events) {
+            assert_eq!((request.method.as_str(), request.url.as_str()), ("POST", "/hooks/monitor"));
+            assert_eq!(request.header("content-type"), Some("application/json"));
+            assert_eq!(request.body, event.to_json());
+        }
+    }
+
+    #[test]
+    fn retries_failed_deliveries() {
+        let server = TestServer::start(|_| (503, String::new()));
+        let retry = RetryPolicy { retries: 1, initial_backoff: Duration::from_millis(1) };
+        let notifier = WebhookNotifier::new(reqwest::blocking::Client::new(), server.url.clone(), None, retry);
+        let error = notifier.notify(&[failure("https://a.example.com")]).unwrap_err();
+        assert_eq!(error, "1 of 1 deliveries failed, last error: HTTP 503 (after 2 attempts)");
+        assert_eq!(server.requests().len(), 2);
+    }
+}
@@ -0,0 +1,184 @@
+//! `exec://` checks: run a local command and interpret it like a Nagios plugin.
+//!
+//! `exec://./scripts/check_foo.sh -w 80 -c 90` runs `./scripts/check_foo.sh` with the
+//! whitespace-separated arguments (no shell, no quoting). Exit code 0 (OK) is a success
+//! reported as status `0`; 1 (WARNING), 2 (CRITICAL) and anything else (UNKNOWN) are
+//! failures carrying the first line of stdout without the `|` performance data.
+
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::CheckPlugin;
+use crate::{CheckRequest, Config, WebsiteStatus};
+
+/// Runs `exec://` commands with the checker's timeout.
+#[derive(Debug, Default)]
+pub struct ExecPlugin;
+
+impl CheckPlugin for ExecPlugin {
+    fn name(&self) -> &str {
+        "exec"
+    }
+
+    fn schemes(&self) -> Vec<String> {
+        vec!["exec".to_string()]
+    }
+
+    fn check(&self, request: &CheckRequest, config: &Config) -> WebsiteStatus {
+        let mut final_status_result_action: Result<u16, String> = Err("Command produced no result".to_string());
+        let mut final_response_time = Duration::ZERO;
+
+        // Retries only cover commands that could not run to completion (spawn errors,
+        // timeouts); a CRITICAL answer is the check's verdict, not a transient failure.
+        for attempt in 0..=(config.retries) {
+            let start_time = Instant::now();
+            let outcome = run_command(&request.url, config.timeout);
+            final_response_time = start_time.elapsed();
+
+            match outcome {
+                Ok((exit, stdout)) => {
+                    final_status_result_action = interpret_exit(exit, &stdout);
+                    break;
+                }
+                Err(e) => {
+                    if attempt < config.retries {
+                        tracing::info!(url = %request.url, attempt = attempt + 1, error = %e, "check failed, retrying");
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    final_status_result_action = Err(e);
+                }
+            }
+        }
+
+        WebsiteStatus {
+            url: request.url.clone(),
+            action_status: final_status_result_action,
+            response_time: final_response_time,
+            timestamp: SystemTime::now(),
+            upload: None,
+            priority: request.priority,
+            tags: request.tags.clone(),
+            content_hash: None,
+            maintenance: None,
+        }
+    }
+}
+
+// Spawns the command from an exec:// URL and waits up to `timeout` for it
+fn run_command(url: &str, timeout: Duration) -> Result<(ExitStatus, String), String> {
+    let command_line = url.split_once("://").map_or("", |(_, rest)| rest);
+    let mut parts = command_line.split_whitespace();
+    let program = parts.next().ok_or_else(|| "exec:// check has no command".to_string())?;
+
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    // Drain stdout on a separate thread so a chatty command can't block on a full pipe
+    let mut stdout_pipe = child.stdout.take();
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(pipe) = stdout_pipe.as_mut() {
+            let _ = pipe.read_to_string(&mut output);
+        }
+        output
+    });
+
+    let exit = wait_with_timeout(&mut child, timeout);
+    let stdout = reader.join().unwrap_or_default();
+    match exit {
+        Ok(Some(exit)) => Ok((exit, stdout)),
+        Ok(None) => Err(format!("Command timed out after {} s", timeout.as_secs_f64())),
+        Err(e) => Err(format!("Failed to wait for {}: {}", program, e)),
+    }
+}
+
+// Ok(None) means the command was killed for running past the timeout
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(exit) = child.try_wait()? {
+            return Ok(Some(exit));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn interpret_exit(exit: ExitStatus, stdout: &str) -> Result<u16, String> {
+    let message = stdout.lines().next()
+        .map(|line| line.split('|').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty());
+    let with_message = |state: &str| match message {
+        Some(text) => format!("{}: {}", state, text),
+        None => state.to_string(),
+    };
+    match exit.code() {
+        Some(0) => Ok(0),
+        Some(1) => Err(with_message("WARNING")),
+        Some(2) => Err(with_message("CRITICAL")),
+        Some(code) => Err(with_message(&format!("UNKNOWN (exit {})", code))),
+        None => Err(with_message("UNKNOWN (terminated by signal)")),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::temp_dir;
+
+    fn check(url: &str, timeout: Duration) -> Result<u16, String> {
+        let config = Config { timeout, ..Config::default() };
+        ExecPlugin.check(&CheckRequest::new(url), &config).action_status
+    }
+
+    // A script printing `output` and exiting with `code`, run through sh (executing a
+    // file just written can fail with ETXTBSY while other tests fork)
+    fn script(name: &str, output: &str, code: i32) -> String {
+        let path = temp_dir(&format!("exec-plugin-{}", name)).join("check.sh");
+        fs::write(&path, format!("printf '{}'\nexit {}\n", output, code)).unwrap();
+        format!("exec://sh {}", path.display())
+    }
+
+    #[test]
+    fn exit_codes_follow_nagios() {
+        assert_eq!(check("exec://true", Duration::from_secs(5)), Ok(0));
+        assert_eq!(check("exec://false", Duration::from_secs(5)), Err("WARNING".to_string()));
+        assert_eq!(
+            check(&script("critical", "DISK CRITICAL - 95%% used|used=95%%\\nmore detail\\n", 2), Duration::from_secs(5)),
+            Err("CRITICAL: DISK CRITICAL - 95% used".to_string())
+        );
+        assert_eq!(
+            check(&script("unknown", "", 3), Duration::from_secs(5)),
+            Err("UNKNOWN (exit 3)".to_string())
+        );
+    }
+
+    #[test]
+    fn arguments_are_split_on_whitespace() {
+        assert_eq!(check("exec://test  1 -lt 2", Duration::from_secs(5)), Ok(0));
+        assert_eq!(check("exec://test 2 -lt 1", Duration::from_secs(5)), Err("WARNING".to_string()));
+    }
+
+    #[test]
+    fn commands_that_cannot_finish_are_errors() {
+        assert_eq!(check("exec://", Duration::from_secs(5)), Err("exec:// check has no command".to_string()));
+        let error = check("exec://./no-such-check", Duration::from_secs(5)).unwrap_err();
+        assert!(error.starts_with("Failed to run ./no-such-check: "), "{}", error);
+        let started = Instant::now();
+        assert_eq!(check("exec://sleep 5", Duration::from_millis(200)), Err("Command timed out after 0.2 s".to_string()));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
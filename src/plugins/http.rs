@@ -0,0 +1,292 @@
+//! Built-in `http`/`https` check: GET (or upload) with retries and the header assertion.
+
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::CheckPlugin;
+use crate::content::fingerprint;
+use crate::{CheckRequest, Config, HttpBackend, HttpBody, HttpMethod, HttpRequest, Priority, UploadTiming, WebsiteStatus};
+
+/// Checks `http://` and `https://` URLs through the checker's [`HttpBackend`].
+pub struct HttpPlugin {
+    backend: Arc<dyn HttpBackend>,
+}
+
+impl HttpPlugin {
+    pub fn new(backend: Arc<dyn HttpBackend>) -> Self {
+        HttpPlugin { backend }
+    }
+}
+
+impl CheckPlugin for HttpPlugin {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    fn schemes(&self) -> Vec<String> {
+        vec!["http".to_string(), "https".to_string()]
+    }
+
+    fn check(&self, request: &CheckRequest, config: &Config) -> WebsiteStatus {
+        check_url(self.backend.as_ref(), config, &request.url)
+    }
+}
+
+// Generated request body for upload checks. Records when the last byte was read
+// so the upload phase can be told apart from the server's processing time.
+struct UploadPayload {
+    remaining: u64,
+    offset: usize,
+    finished_at: Arc<Mutex<Option<Instant>>>,
+}
+
+// Pseudo-random filler so compressing proxies can't shrink the payload
+fn upload_chunk() -> &'static [u8] {
+    static CHUNK: std::sync::OnceLock<Vec<u8>> = std::sync::OnceLock::new();
+    CHUNK.get_or_init(|| {
+        let mut state: u32 = 0x2545_f491;
+        (0..8192).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state & 0xff) as u8
+        }).collect()
+    })
+}
+
+impl Read for UploadPayload {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+        let chunk = upload_chunk();
+        let n = buf.len()
+            .min(chunk.len() - self.offset)
+            .min(self.remaining.min(usize::MAX as u64) as usize);
+        buf[..n].copy_from_slice(&chunk[self.offset..self.offset + n]);
+        self.offset = (self.offset + n) % chunk.len();
+        self.remaining -= n as u64;
+        if self.remaining == 0 {
+            if let Ok(mut finished) = self.finished_at.lock() {
+                *finished = Some(Instant::now());
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Checks one URL with the configured retries and header assertion.
+pub fn check_url(backend: &dyn HttpBackend, config: &Config, url_to_check: &str) -> WebsiteStatus {
+    let mut final_status_result_action: Result<u16, String> = Err("Worker failed to determine status".to_string());
+    let mut final_response_time = Duration::from_secs(0);
+    let mut final_timestamp = SystemTime::now();
+    let mut final_upload_timing: Option<UploadTiming> = None;
+    let mut final_content_hash: Option<String> = None;
+
+    for attempt in 0..=(config.retries) {
+        let start_time = Instant::now();
+        let upload_finished_at = Arc::new(Mutex::new(None));
+        let request = match &config.upload {
+            Some(upload) => {
+                let payload = UploadPayload {
+                    remaining: upload.size,
+                    offset: 0,
+                    finished_at: Arc::clone(&upload_finished_at),
+                };
+                HttpRequest {
+                    method: upload.method,
+                    url: url_to_check,
+                    headers: vec![("content-type", "application/octet-stream")],
+                    body: Some(HttpBody { reader: Box::new(payload), len: upload.size }),
+                    read_body: config.watch_content.is_some(),
+                }
+            }
+            None => HttpRequest {
+                method: HttpMethod::Get,
+                url: url_to_check,
+                headers: Vec::new(),
+                body: None,
+                read_body: config.watch_content.is_some(),
+            },
+        };
+        let request_result = backend.send(request);
+
+        final_response_time = start_time.elapsed();
+        final_timestamp = SystemTime::now();
+        final_upload_timing = config.upload.as_ref().map(|upload| {
+            // If the server answered before reading the whole body, count it all as upload time
+            let upload_time = upload_finished_at.lock().ok()
+                .and_then(|finished| *finished)
+                .map_or(final_response_time, |finished| finished.duration_since(start_time));
+            UploadTiming {
+                bytes: upload.size,
+                upload_time,
+                processing_time: final_response_time.saturating_sub(upload_time),
+            }
+        });
+
+        match request_result {
+            Ok(response) => {
+                let status_code = response.status;
+                if let Some((assert_name, assert_value)) = &config.header_assertion {
+                    match response.header(assert_name) {
+                        Some(actual_value_header) => {
+                            match std::str::from_utf8(actual_value_header) {
+                                Ok(actual_value_str) if actual_value_str == assert_value => {
+                                    final_status_result_action = Ok(status_code);
+                                }
+                                Ok(actual_value_str) => {
+                                    final_status_result_action = Err(format!(
+                                        "Header '{}' assertion failed: expected '{}', got '{}'",
+                                        assert_name, assert_value, actual_value_str
+                                    ));
+                                }
+                                Err(_) => {
+                                    final_status_result_action = Err(format!(
+                                        "Header '{}' assertion failed: actual value not valid UTF-8: {:?}",
+                                        assert_name, String::from_utf8_lossy(actual_value_header)
+                                    ));
+                                }
+                            }
+                        }
+                        None => {
+                            final_status_result_action = Err(format!(
+                                "Header '{}' assertion failed: header not found",
+                                assert_name
+                            ));
+                        }
+                    }
+                } else {
+                    final_status_result_action = Ok(status_code);
+                }
+                if let (Some(watch), Some(body)) = (&config.watch_content, &response.body) {
+                    if final_status_result_action.is_ok() && (200..300).contains(&status_code) {
+                        match fingerprint(body, watch.selector.as_deref()) {
+                            Ok(hash) => final_content_hash = Some(hash),
+                            Err(e) => final_status_result_action = Err(e),
+                        }
+                    }
+                }
+                break;
+            }
+            Err(e) => {
+                if attempt < config.retries {
+                    tracing::info!(url = url_to_check, attempt = attempt + 1, error = %e, "check failed, retrying");
+                    thread::sleep(Duration::from_millis(100));
+                }
+                final_status_result_action = Err(e);
+            }
+        }
+    }
+
+    WebsiteStatus {
+        url: url_to_check.to_string(),
+        upload: if final_status_result_action.is_ok() { final_upload_timing } else { None },
+        action_status: final_status_result_action,
+        response_time: final_response_time,
+        timestamp: final_timestamp,
+        priority: Priority::default(),
+        tags: Vec::new(),
+        content_hash: final_content_hash,
+        maintenance: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{response, FakeBackend};
+    use crate::{HttpResponse, UploadSpec};
+
+    #[test]
+    fn upload_payload_has_the_requested_size() {
+        let finished_at = Arc::new(Mutex::new(None));
+        let mut payload = UploadPayload { remaining: 20_000, offset: 0, finished_at: Arc::clone(&finished_at) };
+        let mut body = Vec::new();
+        payload.read_to_end(&mut body).unwrap();
+        assert_eq!(body.len(), 20_000);
+        assert_eq!(&body[..8192], upload_chunk());
+        assert_eq!(&body[8192..16_384], upload_chunk());
+        assert!(finished_at.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn upload_check_sends_the_payload_and_times_it() {
+        let backend = FakeBackend(|request: HttpRequest<'_>| {
+            assert_eq!(request.method, HttpMethod::Put);
+            let mut body = request.body.expect("upload checks send a body");
+            assert_eq!(body.len, 4096);
+            let mut sent = Vec::new();
+            body.reader.read_to_end(&mut sent).map_err(|e| e.to_string())?;
+            assert_eq!(sent.len(), 4096);
+            thread::sleep(Duration::from_millis(20));
+            Ok(response(201, &[]))
+        });
+        let config = Config { upload: Some(UploadSpec { method: HttpMethod::Put, size: 4096 }), ..Config::default() };
+        let status = check_url(&backend, &config, "https://example.com/upload");
+        assert_eq!(status.action_status, Ok(201));
+        let upload = status.upload.expect("upload timing");
+        assert_eq!(upload.bytes, 4096);
+        assert!(upload.processing_time >= Duration::from_millis(20));
+        assert_eq!(upload.upload_time + upload.processing_time, status.response_time);
+    }
+
+    #[test]
+    fn failed_uploads_have_no_timing() {
+        let backend = FakeBackend(|_: HttpRequest<'_>| Err("connection refused".to_string()));
+        let config = Config { upload: Some(UploadSpec { method: HttpMethod::Post, size: 16 }), ..Config::default() };
+        let status = check_url(&backend, &config, "https://example.com/upload");
+        assert!(status.action_status.is_err());
+        assert!(status.upload.is_none());
+    }
+
+    #[test]
+    fn retries_until_a_response() {
+        let attempts = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&attempts);
+        let backend = FakeBackend(move |_: HttpRequest<'_>| {
+            let mut attempts = counter.lock().unwrap();
+            *attempts += 1;
+            if *attempts < 3 { Err("connection reset".to_string()) } else { Ok(response(503, &[])) }
+        });
+        let config = Config { retries: 2, ..Config::default() };
+        assert_eq!(check_url(&backend, &config, "https://example.com").action_status, Ok(503));
+        assert_eq!(*attempts.lock().unwrap(), 3);
+
+        *attempts.lock().unwrap() = 0;
+        let config = Config { retries: 1, ..Config::default() };
+        assert_eq!(check_url(&backend, &config, "https://example.com").action_status, Err("connection reset".to_string()));
+        assert_eq!(*attempts.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn header_assertion() {
+        let backend = FakeBackend(|_: HttpRequest<'_>| Ok(response(200, &[("server", "nginx")])));
+        let expect = |value: &str| Config {
+            header_assertion: Some(("Server".to_string(), value.to_string())),
+            ..Config::default()
+        };
+        assert_eq!(check_url(&backend, &expect("nginx"), "https://example.com").action_status, Ok(200));
+        let error = check_url(&backend, &expect("Nginx"), "https://example.com").action_status.unwrap_err();
+        assert_eq!(error, "Header 'Server' assertion failed: expected 'Nginx', got 'nginx'");
+
+        let config = Config { header_assertion: Some(("x-cache".to_string(), "HIT".to_string())), ..Config::default() };
+        let error = check_url(&backend, &config, "https://example.com").action_status.unwrap_err();
+        assert_eq!(error, "Header 'x-cache' assertion failed: header not found");
+    }
+
+    #[test]
+    fn watched_content_is_fingerprinted_for_2xx_responses() {
+        let backend = FakeBackend(|request: HttpRequest<'_>| {
+            assert!(request.read_body);
+            let status = if request.url.ends_with("/missing") { 404 } else { 200 };
+            Ok(HttpResponse { body: Some(b"hello".to_vec()), ..response(status, &[]) })
+        });
+        let config = Config { watch_content: Some(Default::default()), ..Config::default() };
+        let status = check_url(&backend, &config, "https://example.com/");
+        assert_eq!(status.content_hash, Some(fingerprint(b"hello", None).unwrap()));
+        assert_eq!(check_url(&backend, &config, "https://example.com/missing").content_hash, None);
+    }
+}
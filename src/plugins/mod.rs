@@ -0,0 +1,271 @@
+//! Check plugins: one handler per URL scheme.
+//!
+//! The checker looks up the scheme of every URL (`https` in `https://example.com`) in a
+//! [`PluginRegistry`] and hands the check to that plugin. Protocols live in their own
+//! modules here, and further ones can be loaded at runtime from dynamic libraries
+//! (`--plugin-dir`, see [`load_plugin_dir`] and [`declare_plugin!`](crate::declare_plugin)).
+
+#[cfg(not(target_family = "wasm"))]
+pub mod exec;
+pub mod http;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{CheckRequest, Config, WebsiteStatus};
+
+/// A check handler for one or more URL schemes.
+pub trait CheckPlugin: Send + Sync {
+    /// Short name used in diagnostics.
+    fn name(&self) -> &str;
+
+    /// Lowercase URL schemes this plugin handles, e.g. `["http", "https"]`.
+    fn schemes(&self) -> Vec<String>;
+
+    /// Performs the check, including any retries from `config`, and reports the
+    /// outcome the same way the HTTP check does.
+    fn check(&self, request: &CheckRequest, config: &Config) -> WebsiteStatus;
+}
+
+/// Maps URL schemes to the plugin that checks them. Later registrations win.
+#[derive(Clone, Default)]
+pub struct PluginRegistry {
+    by_scheme: HashMap<String, Arc<dyn CheckPlugin>>,
+}
+
+impl std::fmt::Debug for PluginRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut schemes: Vec<&String> = self.by_scheme.keys().collect();
+        schemes.sort();
+        f.debug_struct("PluginRegistry").field("schemes", &schemes).finish()
+    }
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn register(&mut self, plugin: Arc<dyn CheckPlugin>) {
+        for scheme in plugin.schemes() {
+            self.by_scheme.insert(scheme.to_ascii_lowercase(), Arc::clone(&plugin));
+        }
+    }
+
+    pub fn get(&self, scheme: &str) -> Option<&Arc<dyn CheckPlugin>> {
+        self.by_scheme.get(&scheme.to_ascii_lowercase())
+    }
+
+    /// Finds the plugin for `url`. URLs without a scheme go to the `http` plugin so
+    /// they fail with the HTTP client's usual error.
+    pub fn for_url(&self, url: &str) -> Result<&Arc<dyn CheckPlugin>, String> {
+        let scheme = url_scheme(url).unwrap_or("http");
+        self.get(scheme).ok_or_else(|| format!("No check plugin registered for scheme '{}'", scheme))
+    }
+}
+
+/// The scheme of `url` (the part before `://`), if it has one.
+pub fn url_scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once("://")?;
+    let valid = !scheme.is_empty()
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+    if valid { Some(scheme) } else { None }
+}
+
+/// Version of the dynamic plugin interface. A plugin library must report the same
+/// value, and must be built with the same compiler and version of this crate.
+pub const PLUGIN_ABI_VERSION: u32 = 2;
+
+/// Exports a [`CheckPlugin`] from a `cdylib` so it can be loaded with `--plugin-dir`:
+///
+/// ```ignore
+/// website_status_checker_rust::declare_plugin!(MyPlugin::new());
+/// ```
+#[macro_export]
+macro_rules! declare_plugin {
+    ($constructor:expr) => {
+        #[no_mangle]
+        pub extern "C" fn website_checker_plugin_abi_version() -> u32 {
+            $crate::plugins::PLUGIN_ABI_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn website_checker_plugin_create() -> *mut ::std::ffi::c_void {
+            let plugin: ::std::boxed::Box<dyn $crate::plugins::CheckPlugin> = ::std::boxed::Box::new($constructor);
+            ::std::boxed::Box::into_raw(::std::boxed::Box::new(plugin)) as *mut ::std::ffi::c_void
+        }
+    };
+}
+
+// Plugin from a dynamic library. Field order matters: the plugin must be dropped
+// before the library that contains its code is unloaded.
+#[cfg(feature = "native")]
+struct DynamicPlugin {
+    inner: Box<dyn CheckPlugin>,
+    _library: libloading::Library,
+}
+
+#[cfg(feature = "native")]
+impl CheckPlugin for DynamicPlugin {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn schemes(&self) -> Vec<String> {
+        self.inner.schemes()
+    }
+
+    fn check(&self, request: &CheckRequest, config: &Config) -> WebsiteStatus {
+        self.inner.check(request, config)
+    }
+}
+
+/// Loads every dynamic library (`.so`, `.dylib` or `.dll`) in `dir` that was built
+/// with [`declare_plugin!`](crate::declare_plugin).
+#[cfg(feature = "native")]
+pub fn load_plugin_dir(dir: &str) -> Result<Vec<Arc<dyn CheckPlugin>>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read plugin directory {}: {}", dir, e))?;
+    let mut paths: Vec<std::path::PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension().and_then(|ext| ext.to_str()) == Some(std::env::consts::DLL_EXTENSION)
+        })
+        .collect();
+    paths.sort();
+
+    let mut plugins: Vec<Arc<dyn CheckPlugin>> = Vec::new();
+    for path in paths {
+        plugins.push(Arc::new(load_plugin(&path)?));
+    }
+    Ok(plugins)
+}
+
+#[cfg(feature = "native")]
+fn load_plugin(path: &std::path::Path) -> Result<DynamicPlugin, String> {
+    type AbiVersionFn = unsafe extern "C" fn() -> u32;
+    type CreateFn = unsafe extern "C" fn() -> *mut std::ffi::c_void;
+
+    let display = path.display();
+    // SAFETY: loading runs the library's initializers; plugin directories are trusted
+    // configuration, like the binary itself.
+    let library = unsafe { libloading::Library::new(path) }
+        .map_err(|e| format!("Failed to load plugin {}: {}", display, e))?;
+
+    // SAFETY: the symbol types match what declare_plugin! exports
+    let version = unsafe {
+        let abi_version: libloading::Symbol<AbiVersionFn> = library.get(b"website_checker_plugin_abi_version")
+            .map_err(|e| format!("Plugin {} is missing website_checker_plugin_abi_version: {}", display, e))?;
+        abi_version()
+    };
+    if version != PLUGIN_ABI_VERSION {
+        return Err(format!(
+            "Plugin {} was built for plugin ABI {} but this checker uses {}",
+            display, version, PLUGIN_ABI_VERSION
+        ));
+    }
+
+    // SAFETY: website_checker_plugin_create returns a leaked Box<Box<dyn CheckPlugin>>
+    let inner = unsafe {
+        let create: libloading::Symbol<CreateFn> = library.get(b"website_checker_plugin_create")
+            .map_err(|e| format!("Plugin {} is missing website_checker_plugin_create: {}", display, e))?;
+        let raw = create();
+        if raw.is_null() {
+            return Err(format!("Plugin {} failed to create its handler", display));
+        }
+        *Box::from_raw(raw as *mut Box<dyn CheckPlugin>)
+    };
+
+    Ok(DynamicPlugin { inner, _library: library })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::testing::{response, FakeBackend};
+    use crate::{Checker, HttpRequest, Priority};
+
+    struct Fixed {
+        name: &'static str,
+        schemes: &'static [&'static str],
+        status: u16,
+    }
+
+    impl CheckPlugin for Fixed {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn schemes(&self) -> Vec<String> {
+            self.schemes.iter().map(|scheme| scheme.to_string()).collect()
+        }
+
+        fn check(&self, request: &CheckRequest, _config: &Config) -> WebsiteStatus {
+            WebsiteStatus {
+                url: request.url.clone(),
+                action_status: Ok(self.status),
+                response_time: Duration::ZERO,
+                timestamp: SystemTime::now(),
+                upload: None,
+                priority: Priority::default(),
+                tags: Vec::new(),
+                content_hash: None,
+                maintenance: None,
+            }
+        }
+    }
+
+    #[test]
+    fn url_schemes() {
+        assert_eq!(url_scheme("https://example.com"), Some("https"));
+        assert_eq!(url_scheme("svn+ssh://host/repo"), Some("svn+ssh"));
+        assert_eq!(url_scheme("example.com"), None);
+        assert_eq!(url_scheme("://example.com"), None);
+        assert_eq!(url_scheme("a b://example.com"), None);
+    }
+
+    #[test]
+    fn later_registrations_win() {
+        let mut registry = PluginRegistry::new();
+        registry.register(Arc::new(Fixed { name: "web", schemes: &["http", "https"], status: 200 }));
+        registry.register(Arc::new(Fixed { name: "tls", schemes: &["HTTPS", "tcp"], status: 200 }));
+        assert_eq!(registry.for_url("https://example.com").unwrap().name(), "tls");
+        assert_eq!(registry.for_url("HTTP://example.com").unwrap().name(), "web");
+        assert_eq!(registry.for_url("example.com").unwrap().name(), "web");
+        assert_eq!(
+            registry.for_url("gopher://example.com").err(),
+            Some("No check plugin registered for scheme 'gopher'".to_string())
+        );
+    }
+
+    #[test]
+    fn checker_routes_urls_to_plugins() {
+        let checker = Checker::builder()
+            .backend(Arc::new(FakeBackend(|_: HttpRequest<'_>| Ok(response(200, &[])))))
+            .plugin(Arc::new(Fixed { name: "tcp", schemes: &["tcp"], status: 0 }))
+            .build()
+            .unwrap();
+        let mut results = checker.check_all(["tcp://db:5432", "https://example.com", "ftp://example.com"]);
+        results.sort_by(|a, b| a.url.cmp(&b.url));
+        let statuses: Vec<_> = results.iter().map(|status| status.action_status.clone()).collect();
+        assert_eq!(
+            statuses,
+            [Err("No check plugin registered for scheme 'ftp'".to_string()), Ok(200), Ok(0)]
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn plugin_dirs_only_load_libraries() {
+        let dir = crate::testing::temp_dir("plugin-dir");
+        std::fs::write(dir.join("README.txt"), "not a plugin").unwrap();
+        assert!(load_plugin_dir(dir.to_str().unwrap()).unwrap().is_empty());
+
+        let fake = dir.join(format!("fake.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&fake, "not a library either").unwrap();
+        let error = load_plugin_dir(dir.to_str().unwrap()).err().unwrap();
+        assert!(error.starts_with(&format!("Failed to load plugin {}", fake.display())), "{}", error);
+        assert!(load_plugin_dir("/nonexistent/plugins").is_err());
+    }
+}
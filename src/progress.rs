@@ -0,0 +1,121 @@
+//! A one-line progress bar on stderr for long rounds: completed/total, failures so
+//! far and an ETA. It's only drawn when stderr is a terminal.
+
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crate::{format_duration, WebsiteStatus};
+
+const BAR_WIDTH: usize = 30;
+
+// Redrawing on every result would flood the terminal in rounds of thousands of URLs
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub struct ProgressBar {
+    total: usize,
+    completed: usize,
+    failed: usize,
+    started: Instant,
+    last_draw: Option<Instant>,
+    visible: bool,
+}
+
+impl ProgressBar {
+    /// A bar for a round of `total` checks, or `None` if stderr isn't a terminal.
+    pub fn start(total: usize) -> Option<ProgressBar> {
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+        let mut bar = ProgressBar { total, completed: 0, failed: 0, started: Instant::now(), last_draw: None, visible: false };
+        bar.draw();
+        Some(bar)
+    }
+
+    pub fn record(&mut self, status: &WebsiteStatus) {
+        self.completed += 1;
+        if status.action_status.is_err() {
+            self.failed += 1;
+        }
+        if !self.visible || self.completed == self.total || self.last_draw.is_none_or(|at| at.elapsed() >= REDRAW_INTERVAL) {
+            self.draw();
+        }
+    }
+
+    /// Erases the bar so a line can be printed; the next `record` draws it again.
+    pub fn clear(&mut self) {
+        if self.visible {
+            let _ = write!(io::stderr(), "\r\x1b[2K");
+            self.visible = false;
+        }
+    }
+
+    fn draw(&mut self) {
+        let line = self.line();
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K{}", line);
+        let _ = stderr.flush();
+        self.visible = true;
+        self.last_draw = Some(Instant::now());
+    }
+
+    fn line(&self) -> String {
+        let filled = (self.completed * BAR_WIDTH).checked_div(self.total).unwrap_or(BAR_WIDTH);
+        let percent = (self.completed * 100).checked_div(self.total).unwrap_or(100);
+        let mut line = format!(
+            "[{}{}] {}/{} ({}%) | {} failed",
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            self.completed,
+            self.total,
+            percent,
+            self.failed
+        );
+        if self.completed > 0 && self.completed < self.total {
+            let eta = self.started.elapsed().mul_f64((self.total - self.completed) as f64 / self.completed as f64);
+            line.push_str(&format!(" | ETA {}", format_duration(eta)));
+        }
+        line
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::result;
+
+    fn bar(total: usize) -> ProgressBar {
+        ProgressBar { total, completed: 0, failed: 0, started: Instant::now(), last_draw: None, visible: true }
+    }
+
+    #[test]
+    fn the_line_shows_progress_failures_and_an_eta() {
+        let mut progress = bar(4);
+        assert_eq!(progress.line(), format!("[{}] 0/4 (0%) | 0 failed", "-".repeat(30)));
+        progress.completed = 1;
+        progress.failed = 1;
+        progress.started = Instant::now() - Duration::from_secs(10);
+        assert_eq!(progress.line(), format!("[{}{}] 1/4 (25%) | 1 failed | ETA 30s", "#".repeat(7), "-".repeat(23)));
+        progress.completed = 4;
+        assert_eq!(progress.line(), format!("[{}] 4/4 (100%) | 1 failed", "#".repeat(30)));
+        assert_eq!(bar(0).line(), format!("[{}] 0/0 (100%) | 0 failed", "#".repeat(30)));
+    }
+
+    #[test]
+    fn results_are_counted() {
+        // Drawn just now, so recording doesn't redraw on the test's stderr
+        let mut progress = bar(3);
+        progress.last_draw = Some(Instant::now());
+        progress.record(&result("https://a.example.com", Ok(200)));
+        progress.record(&result("https://b.example.com", Err("timed out".to_string())));
+        assert_eq!((progress.completed, progress.failed), (2, 1));
+        // Nor erase it when dropped
+        progress.visible = false;
+    }
+}
@@ -0,0 +1,982 @@
+//! Reports built from the history store, for the `report` subcommand.
+//!
+//! Uptime is the share of successful checks among all stored checks of a URL in the
+//! window, so it is as fine-grained as the `--period` the checker ran with. Checks
+//! during `--maintenance` don't count. Downtime runs from a failed check to the next
+//! check of the URL, so an outage lasts from its first failed check to the next
+//! successful one, as in the checker's own accounting.
+//!
+//! MTTR (mean time to recovery) is the average incident duration, MTBF (mean time
+//! between failures) the average time a URL stayed up between two incidents. Both
+//! cover incidents that ended in the 30 day window.
+//!
+//! `report render` builds the same figures for any stored date range instead of the
+//! windows ending now, so reports can be regenerated without re-running checks.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::history::{CheckRecord, HistoryStore, Incident};
+use crate::stats::percentile;
+use crate::{escape_json_string, format_duration, format_rfc3339_utc, html_escape, parse_rfc3339_utc};
+
+/// Windows covered by the SLA report, shortest first.
+pub const SLA_WINDOWS: [(&str, Duration); 3] = [
+    ("24h", Duration::from_secs(24 * 60 * 60)),
+    ("7d", Duration::from_secs(7 * 24 * 60 * 60)),
+    ("30d", Duration::from_secs(30 * 24 * 60 * 60)),
+];
+
+/// Output format of a report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Table,
+    Json,
+    Markdown,
+    /// A standalone HTML page.
+    Html,
+}
+
+impl ReportFormat {
+    pub fn parse(s: &str) -> Result<ReportFormat, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "table" | "text" => Ok(ReportFormat::Table),
+            "json" => Ok(ReportFormat::Json),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            other => Err(format!("unknown report format '{}' (expected table, json, markdown or html)", other)),
+        }
+    }
+}
+
+/// Check counts and downtime of one URL in one window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowCounts {
+    pub checks: u64,
+    pub successful: u64,
+    pub downtime: Duration,
+}
+
+impl WindowCounts {
+    /// Uptime in percent, `None` without checks in the window.
+    pub fn uptime(&self) -> Option<f64> {
+        (self.checks > 0).then(|| self.successful as f64 / self.checks as f64 * 100.0)
+    }
+}
+
+/// Incident statistics of a URL or tag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reliability {
+    pub incidents: u64,
+    pub downtime: Duration,
+    /// Number and total length of the up periods between two incidents.
+    pub gaps: u64,
+    pub uptime_between: Duration,
+}
+
+impl Reliability {
+    /// Mean time to recovery.
+    pub fn mttr(&self) -> Option<Duration> {
+        (self.incidents > 0).then(|| self.downtime / self.incidents as u32)
+    }
+
+    /// Mean time between failures; needs at least two incidents.
+    pub fn mtbf(&self) -> Option<Duration> {
+        (self.gaps > 0).then(|| self.uptime_between / self.gaps as u32)
+    }
+
+    fn add(&mut self, incident: &Incident, previous: Option<&Incident>) {
+        self.incidents += 1;
+        self.downtime += incident.duration();
+        if let Some(previous) = previous {
+            self.gaps += 1;
+            self.uptime_between += incident.start().duration_since(previous.end()).unwrap_or_default();
+        }
+    }
+}
+
+/// Uptime of one URL, per window of [`SLA_WINDOWS`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlUptime {
+    pub url: String,
+    pub windows: [WindowCounts; SLA_WINDOWS.len()],
+    pub reliability: Reliability,
+}
+
+/// Incident statistics of all URLs with a tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagReliability {
+    pub tag: String,
+    pub reliability: Reliability,
+}
+
+/// Per-URL uptime over the last 24 hours, 7 days and 30 days against an SLO, with
+/// MTTR and MTBF per URL and per tag.
+#[derive(Debug, Clone)]
+pub struct SlaReport {
+    /// Target uptime in percent, e.g. 99.9.
+    pub slo: f64,
+    pub generated_at: SystemTime,
+    /// Sorted by URL.
+    pub urls: Vec<UrlUptime>,
+    /// Sorted by tag; only tags of URLs with incidents.
+    pub tags: Vec<TagReliability>,
+}
+
+impl SlaReport {
+    pub fn build(store: &HistoryStore, slo: f64, now: SystemTime) -> Result<Self, String> {
+        let mut urls: BTreeMap<String, [WindowCounts; SLA_WINDOWS.len()]> = BTreeMap::new();
+        let mut downtime = DowntimeTracker::default();
+        store.for_each_check(|check| {
+            if let Some((down_from, down_to)) = downtime.observe(&check) {
+                let in_window = |length: Duration| overlap(down_from, down_to, now.checked_sub(length), Some(now));
+                if !in_window(SLA_WINDOWS[SLA_WINDOWS.len() - 1].1).is_zero() {
+                    let windows = urls.entry(check.url.clone()).or_default();
+                    for (counts, (_, length)) in windows.iter_mut().zip(SLA_WINDOWS) {
+                        counts.downtime += in_window(length);
+                    }
+                }
+            }
+            if check.maintenance.is_some() {
+                return;
+            }
+            let Ok(age) = now.duration_since(check.timestamp()) else { return };
+            if age > SLA_WINDOWS[SLA_WINDOWS.len() - 1].1 {
+                return;
+            }
+            let windows = urls.entry(check.url).or_default();
+            for (counts, (_, length)) in windows.iter_mut().zip(SLA_WINDOWS) {
+                if age <= length {
+                    counts.checks += 1;
+                    counts.successful += u64::from(check.ok);
+                }
+            }
+        })?;
+
+        let window_start = now.checked_sub(SLA_WINDOWS[SLA_WINDOWS.len() - 1].1).unwrap_or(UNIX_EPOCH);
+        let incidents: Vec<Incident> = store.incidents()?.into_iter()
+            .filter(|incident| incident.end() >= window_start && incident.end() <= now)
+            .collect();
+        let (by_url, by_tag) = reliability_by_url_and_tag(&incidents);
+        for url in by_url.keys() {
+            urls.entry(url.clone()).or_default();
+        }
+
+        let urls = urls.into_iter()
+            .map(|(url, windows)| {
+                let reliability = by_url.get(&url).copied().unwrap_or_default();
+                UrlUptime { url, windows, reliability }
+            })
+            .collect();
+        let tags = by_tag.into_iter().map(|(tag, reliability)| TagReliability { tag, reliability }).collect();
+        Ok(SlaReport { slo, generated_at: now, urls, tags })
+    }
+
+    /// Whether the URL's uptime is below the SLO in any window.
+    pub fn below_slo(&self, url: &UrlUptime) -> bool {
+        url.windows.iter().any(|counts| counts.uptime().is_some_and(|uptime| uptime < self.slo))
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Table => self.to_table(),
+            ReportFormat::Json => self.to_json(),
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Html => self.to_html(),
+        }
+    }
+
+    pub fn to_table(&self) -> String {
+        let url_width = self.urls.iter().map(|u| u.url.chars().count()).max().unwrap_or(0).max(3);
+        let mut out = format!("SLA report, SLO {}%, generated {}\n\n", self.slo, format_rfc3339_utc(self.generated_at));
+        out.push_str(&format!("{:<width$}", "URL", width = url_width));
+        for (label, _) in SLA_WINDOWS {
+            out.push_str(&format!(" | {:>9}", label));
+        }
+        out.push_str(" | SLO   | Down 30d | Incidents |    MTTR |    MTBF\n");
+        out.push_str(&"-".repeat(url_width + SLA_WINDOWS.len() * 12 + 52));
+        out.push('\n');
+        for url in &self.urls {
+            out.push_str(&format!("{:<width$}", url.url, width = url_width));
+            for counts in &url.windows {
+                out.push_str(&format!(" | {:>9}", format_uptime(counts)));
+            }
+            out.push_str(if self.below_slo(url) { " | BELOW" } else { " | ok   " });
+            out.push_str(&format!(" | {:>8}", format_duration(url.windows[SLA_WINDOWS.len() - 1].downtime)));
+            out.push_str(&format_reliability_row(&url.reliability));
+        }
+        if self.urls.is_empty() {
+            out.push_str("No checks recorded in the last 30 days.\n");
+        }
+        if !self.tags.is_empty() {
+            let tag_width = self.tags.iter().map(|t| t.tag.chars().count()).max().unwrap_or(0).max(3);
+            out.push_str(&format!("\n{:<width$} | Incidents |    MTTR |    MTBF\n", "Tag", width = tag_width));
+            out.push_str(&"-".repeat(tag_width + 34));
+            out.push('\n');
+            for tag in &self.tags {
+                out.push_str(&format!("{:<width$}", tag.tag, width = tag_width));
+                out.push_str(&format_reliability_row(&tag.reliability));
+            }
+        }
+        out
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# SLA report\n\nSLO: {}% uptime. Generated {}.\n\n| URL |",
+            self.slo,
+            format_rfc3339_utc(self.generated_at)
+        );
+        for (label, _) in SLA_WINDOWS {
+            out.push_str(&format!(" {} |", label));
+        }
+        out.push_str(" SLO | Downtime (30d) | Incidents | MTTR | MTBF |\n|---|");
+        out.push_str(&"---:|".repeat(SLA_WINDOWS.len()));
+        out.push_str(":---:|---:|---:|---:|---:|\n");
+        for url in &self.urls {
+            // Pipes would end the table cell
+            out.push_str(&format!("| {} |", url.url.replace('|', "\\|")));
+            for counts in &url.windows {
+                out.push_str(&format!(" {} |", format_uptime(counts)));
+            }
+            out.push_str(if self.below_slo(url) { " :x: below |" } else { " :white_check_mark: |" });
+            out.push_str(&format!(" {} |", format_duration(url.windows[SLA_WINDOWS.len() - 1].downtime)));
+            out.push_str(&format_reliability_cells(&url.reliability));
+        }
+        if !self.tags.is_empty() {
+            out.push_str("\n## By tag\n\n| Tag | Incidents | MTTR | MTBF |\n|---|---:|---:|---:|\n");
+            for tag in &self.tags {
+                out.push_str(&format!("| {} |{}", tag.tag.replace('|', "\\|"), format_reliability_cells(&tag.reliability)));
+            }
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut body = format!(
+            "<h1>SLA report</h1>\n<p>SLO: {}% uptime. Generated {}.</p>\n<table>\n<tr><th>URL</th>",
+            self.slo,
+            format_rfc3339_utc(self.generated_at)
+        );
+        for (label, _) in SLA_WINDOWS {
+            body.push_str(&format!("<th>{}</th>", label));
+        }
+        body.push_str("<th>SLO</th><th>Downtime (30d)</th><th>Incidents</th><th>MTTR</th><th>MTBF</th></tr>\n");
+        for url in &self.urls {
+            body.push_str(&format!("<tr><td>{}</td>", html_escape(&url.url)));
+            for counts in &url.windows {
+                body.push_str(&format!("<td class=\"num\">{}</td>", format_uptime(counts)));
+            }
+            body.push_str(if self.below_slo(url) { "<td class=\"bad\">below</td>" } else { "<td class=\"good\">ok</td>" });
+            body.push_str(&format!(
+                "<td class=\"num\">{}</td>",
+                format_duration(url.windows[SLA_WINDOWS.len() - 1].downtime)
+            ));
+            body.push_str(&format_reliability_html(&url.reliability));
+        }
+        body.push_str("</table>\n");
+        push_tags_html(&mut body, &self.tags);
+        html_page("SLA report", &body)
+    }
+
+    pub fn to_json(&self) -> String {
+        let urls: Vec<String> = self.urls.iter()
+            .map(|url| {
+                let windows: Vec<String> = url.windows.iter().zip(SLA_WINDOWS)
+                    .map(|(counts, (label, _))| {
+                        let uptime = counts.uptime().map_or("null".to_string(), |uptime| format!("{:.4}", uptime));
+                        format!(
+                            "\"{}\":{{\"uptimePercent\":{},\"checks\":{},\"successful\":{},\"downtimeSeconds\":{}}}",
+                            label, uptime, counts.checks, counts.successful, counts.downtime.as_secs()
+                        )
+                    })
+                    .collect();
+                format!(
+                    "{{\"url\":\"{}\",\"windows\":{{{}}},\"belowSlo\":{},{}}}",
+                    escape_json_string(&url.url),
+                    windows.join(","),
+                    self.below_slo(url),
+                    reliability_json(&url.reliability)
+                )
+            })
+            .collect();
+        let tags: Vec<String> = self.tags.iter()
+            .map(|tag| format!("{{\"tag\":\"{}\",{}}}", escape_json_string(&tag.tag), reliability_json(&tag.reliability)))
+            .collect();
+        format!(
+            "{{\"slo\":{},\"generatedAt\":\"{}\",\"urls\":[{}],\"tags\":[{}]}}\n",
+            self.slo,
+            format_rfc3339_utc(self.generated_at),
+            urls.join(","),
+            tags.join(",")
+        )
+    }
+}
+
+/// The `--range` of a rendered report: `from..to`, each a date (`2024-05-01`) or a
+/// UTC timestamp (`2024-05-01T12:00:00Z`). A date as the end includes that whole day,
+/// and either end may be left out (`2024-05-01..` runs until now).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeRange {
+    pub from: Option<SystemTime>,
+    /// Exclusive.
+    pub to: Option<SystemTime>,
+}
+
+impl TimeRange {
+    pub fn parse(s: &str) -> Result<TimeRange, String> {
+        let (from, to) = s.split_once("..")
+            .ok_or_else(|| format!("invalid range '{}' (expected <from>..<to>, e.g. 2024-05-01..2024-05-07)", s))?;
+        let parse = |end: &str| {
+            parse_rfc3339_utc(end).ok_or_else(|| format!("invalid date '{}' (expected e.g. 2024-05-01 or 2024-05-01T12:00:00Z)", end))
+        };
+        let from = if from.trim().is_empty() { None } else { Some(parse(from)?) };
+        let to = if to.trim().is_empty() {
+            None
+        } else if to.contains(['T', 't', ' ']) {
+            Some(parse(to)?)
+        } else {
+            Some(parse(to)? + Duration::from_secs(24 * 60 * 60))
+        };
+        if let (Some(from), Some(to)) = (from, to) {
+            if from >= to {
+                return Err(format!("invalid range '{}': it ends before it starts", s));
+            }
+        }
+        Ok(TimeRange { from, to })
+    }
+
+    pub fn contains(&self, time: SystemTime) -> bool {
+        self.from.is_none_or(|from| time >= from) && self.to.is_none_or(|to| time < to)
+    }
+
+    fn overlaps(&self, incident: &Incident) -> bool {
+        self.from.is_none_or(|from| incident.end() > from) && self.to.is_none_or(|to| incident.start() < to)
+    }
+
+    fn describe(&self) -> String {
+        let end = |time: Option<SystemTime>, open: &str| time.map_or(open.to_string(), format_rfc3339_utc);
+        format!("{} to {}", end(self.from, "start of history"), end(self.to, "now"))
+    }
+}
+
+/// Checks and incidents of one URL in the range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlRangeSummary {
+    pub url: String,
+    pub counts: WindowCounts,
+    /// Of the successful checks.
+    pub avg_response_time: Option<Duration>,
+    pub p95_response_time: Option<Duration>,
+    pub reliability: Reliability,
+}
+
+/// A report over a stored date range, for `report render`: uptime and response times
+/// per URL against an SLO, MTTR and MTBF per URL and per tag, and the incidents.
+#[derive(Debug, Clone)]
+pub struct RangeReport {
+    pub range: TimeRange,
+    pub slo: f64,
+    pub generated_at: SystemTime,
+    /// Sorted by URL.
+    pub urls: Vec<UrlRangeSummary>,
+    /// Sorted by tag; only tags of URLs with incidents.
+    pub tags: Vec<TagReliability>,
+    /// Incidents overlapping the range, oldest first.
+    pub incidents: Vec<Incident>,
+}
+
+impl RangeReport {
+    pub fn build(store: &HistoryStore, range: TimeRange, slo: f64, now: SystemTime) -> Result<Self, String> {
+        let mut checks: BTreeMap<String, (WindowCounts, Vec<Duration>)> = BTreeMap::new();
+        let mut downtime = DowntimeTracker::default();
+        store.for_each_check(|check| {
+            if let Some((down_from, down_to)) = downtime.observe(&check) {
+                let in_range = overlap(down_from, down_to, range.from, range.to);
+                if !in_range.is_zero() {
+                    checks.entry(check.url.clone()).or_default().0.downtime += in_range;
+                }
+            }
+            if check.maintenance.is_some() || !range.contains(check.timestamp()) {
+                return;
+            }
+            let (counts, response_times) = checks.entry(check.url).or_default();
+            counts.checks += 1;
+            if check.ok {
+                counts.successful += 1;
+                response_times.push(Duration::from_millis(check.response_time_ms));
+            }
+        })?;
+
+        let mut incidents: Vec<Incident> = store.incidents()?.into_iter().filter(|incident| range.overlaps(incident)).collect();
+        incidents.sort_by_key(|incident| incident.start_epoch_s);
+        let (by_url, by_tag) = reliability_by_url_and_tag(&incidents);
+        for url in by_url.keys() {
+            checks.entry(url.clone()).or_default();
+        }
+
+        let urls = checks.into_iter()
+            .map(|(url, (counts, mut response_times))| {
+                response_times.sort_unstable();
+                let avg_response_time = (!response_times.is_empty())
+                    .then(|| response_times.iter().sum::<Duration>() / response_times.len() as u32);
+                let reliability = by_url.get(&url).copied().unwrap_or_default();
+                UrlRangeSummary {
+                    url,
+                    counts,
+                    avg_response_time,
+                    p95_response_time: percentile(&response_times, 95.0),
+                    reliability,
+                }
+            })
+            .collect();
+        let tags = by_tag.into_iter().map(|(tag, reliability)| TagReliability { tag, reliability }).collect();
+        Ok(RangeReport { range, slo, generated_at: now, urls, tags, incidents })
+    }
+
+    pub fn below_slo(&self, url: &UrlRangeSummary) -> bool {
+        url.counts.uptime().is_some_and(|uptime| uptime < self.slo)
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Table => self.to_table(),
+            ReportFormat::Json => self.to_json(),
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Html => self.to_html(),
+        }
+    }
+
+    pub fn to_table(&self) -> String {
+        let url_width = self.urls.iter().map(|u| u.url.chars().count()).max().unwrap_or(0).max(3);
+        let mut out = format!(
+            "Report for {}, SLO {}%, generated {}\n\n",
+            self.range.describe(),
+            self.slo,
+            format_rfc3339_utc(self.generated_at)
+        );
+        out.push_str(&format!(
+            "{:<width$} |  Checks |    Uptime | SLO   | Downtime |     Avg |     p95 | Incidents |    MTTR |    MTBF\n",
+            "URL",
+            width = url_width
+        ));
+        out.push_str(&"-".repeat(url_width + 103));
+        out.push('\n');
+        for url in &self.urls {
+            out.push_str(&format!(
+                "{:<width$} | {:>7} | {:>9}{} | {:>8} | {:>7} | {:>7}",
+                url.url,
+                url.counts.checks,
+                format_uptime(&url.counts),
+                if self.below_slo(url) { " | BELOW" } else { " | ok   " },
+                format_duration(url.counts.downtime),
+                format_optional_ms(url.avg_response_time),
+                format_optional_ms(url.p95_response_time),
+                width = url_width
+            ));
+            out.push_str(&format_reliability_row(&url.reliability));
+        }
+        if self.urls.is_empty() {
+            out.push_str("No checks recorded in this range.\n");
+        }
+        if !self.tags.is_empty() {
+            let tag_width = self.tags.iter().map(|t| t.tag.chars().count()).max().unwrap_or(0).max(3);
+            out.push_str(&format!("\n{:<width$} | Incidents |    MTTR |    MTBF\n", "Tag", width = tag_width));
+            out.push_str(&"-".repeat(tag_width + 34));
+            out.push('\n');
+            for tag in &self.tags {
+                out.push_str(&format!("{:<width$}", tag.tag, width = tag_width));
+                out.push_str(&format_reliability_row(&tag.reliability));
+            }
+        }
+        if !self.incidents.is_empty() {
+            out.push_str("\nIncidents:\n");
+            for incident in &self.incidents {
+                out.push_str(&format!(
+                    "  {} .. {} ({}) {} - {}\n",
+                    format_rfc3339_utc(incident.start()),
+                    format_rfc3339_utc(incident.end()),
+                    format_duration(incident.duration()),
+                    incident.url,
+                    incident.first_error
+                ));
+            }
+        }
+        out
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Report for {}\n\nSLO: {}% uptime. Generated {}.\n\n\
+             | URL | Checks | Uptime | SLO | Downtime | Avg | p95 | Incidents | MTTR | MTBF |\n\
+             |---|---:|---:|:---:|---:|---:|---:|---:|---:|---:|\n",
+            self.range.describe(),
+            self.slo,
+            format_rfc3339_utc(self.generated_at)
+        );
+        for url in &self.urls {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} |{}",
+                url.url.replace('|', "\\|"),
+                url.counts.checks,
+                format_uptime(&url.counts),
+                if self.below_slo(url) { ":x: below" } else { ":white_check_mark:" },
+                format_duration(url.counts.downtime),
+                format_optional_ms(url.avg_response_time),
+                format_optional_ms(url.p95_response_time),
+                format_reliability_cells(&url.reliability)
+            ));
+        }
+        if !self.tags.is_empty() {
+            out.push_str("\n## By tag\n\n| Tag | Incidents | MTTR | MTBF |\n|---|---:|---:|---:|\n");
+            for tag in &self.tags {
+                out.push_str(&format!("| {} |{}", tag.tag.replace('|', "\\|"), format_reliability_cells(&tag.reliability)));
+            }
+        }
+        if !self.incidents.is_empty() {
+            out.push_str("\n## Incidents\n\n| Start | End | Duration | URL | First error |\n|---|---|---:|---|---|\n");
+            for incident in &self.incidents {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    format_rfc3339_utc(incident.start()),
+                    format_rfc3339_utc(incident.end()),
+                    format_duration(incident.duration()),
+                    incident.url.replace('|', "\\|"),
+                    incident.first_error.replace('|', "\\|")
+                ));
+            }
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut body = format!(
+            "<h1>Report for {}</h1>\n<p>SLO: {}% uptime. Generated {}.</p>\n<table>\n\
+             <tr><th>URL</th><th>Checks</th><th>Uptime</th><th>SLO</th><th>Downtime</th><th>Avg</th><th>p95</th>\
+             <th>Incidents</th><th>MTTR</th><th>MTBF</th></tr>\n",
+            self.range.describe(),
+            self.slo,
+            format_rfc3339_utc(self.generated_at)
+        );
+        for url in &self.urls {
+            body.push_str(&format!(
+                "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>{}\
+                 <td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>",
+                html_escape(&url.url),
+                url.counts.checks,
+                format_uptime(&url.counts),
+                if self.below_slo(url) { "<td class=\"bad\">below</td>" } else { "<td class=\"good\">ok</td>" },
+                format_duration(url.counts.downtime),
+                format_optional_ms(url.avg_response_time),
+                format_optional_ms(url.p95_response_time)
+            ));
+            body.push_str(&format_reliability_html(&url.reliability));
+        }
+        body.push_str("</table>\n");
+        push_tags_html(&mut body, &self.tags);
+        if !self.incidents.is_empty() {
+            body.push_str(
+                "<h2>Incidents</h2>\n<table>\n\
+                 <tr><th>Start</th><th>End</th><th>Duration</th><th>URL</th><th>First error</th></tr>\n",
+            );
+            for incident in &self.incidents {
+                body.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td><td>{}</td><td>{}</td></tr>\n",
+                    format_rfc3339_utc(incident.start()),
+                    format_rfc3339_utc(incident.end()),
+                    format_duration(incident.duration()),
+                    html_escape(&incident.url),
+                    html_escape(&incident.first_error)
+                ));
+            }
+            body.push_str("</table>\n");
+        }
+        html_page("Website status report", &body)
+    }
+
+    pub fn to_json(&self) -> String {
+        let time = |time: Option<SystemTime>| time.map_or("null".to_string(), |t| format!("\"{}\"", format_rfc3339_utc(t)));
+        let ms = |duration: Option<Duration>| duration.map_or("null".to_string(), |d| d.as_millis().to_string());
+        let urls: Vec<String> = self.urls.iter()
+            .map(|url| {
+                format!(
+                    "{{\"url\":\"{}\",\"checks\":{},\"successful\":{},\"uptimePercent\":{},\"belowSlo\":{},\
+                     \"downtimeSeconds\":{},\"avgResponseTimeMs\":{},\"p95ResponseTimeMs\":{},{}}}",
+                    escape_json_string(&url.url),
+                    url.counts.checks,
+                    url.counts.successful,
+                    url.counts.uptime().map_or("null".to_string(), |uptime| format!("{:.4}", uptime)),
+                    self.below_slo(url),
+                    url.counts.downtime.as_secs(),
+                    ms(url.avg_response_time),
+                    ms(url.p95_response_time),
+                    reliability_json(&url.reliability)
+                )
+            })
+            .collect();
+        let tags: Vec<String> = self.tags.iter()
+            .map(|tag| format!("{{\"tag\":\"{}\",{}}}", escape_json_string(&tag.tag), reliability_json(&tag.reliability)))
+            .collect();
+        let incidents: Vec<String> = self.incidents.iter()
+            .map(|incident| {
+                format!(
+                    "{{\"url\":\"{}\",\"start\":\"{}\",\"end\":\"{}\",\"durationSeconds\":{},\"firstError\":\"{}\"}}",
+                    escape_json_string(&incident.url),
+                    format_rfc3339_utc(incident.start()),
+                    format_rfc3339_utc(incident.end()),
+                    incident.duration_seconds,
+                    escape_json_string(&incident.first_error)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"from\":{},\"to\":{},\"slo\":{},\"generatedAt\":\"{}\",\"urls\":[{}],\"tags\":[{}],\"incidents\":[{}]}}\n",
+            time(self.range.from),
+            time(self.range.to),
+            self.slo,
+            format_rfc3339_utc(self.generated_at),
+            urls.join(","),
+            tags.join(","),
+            incidents.join(",")
+        )
+    }
+}
+
+// Down periods from the stored checks, which come oldest first per URL
+#[derive(Debug, Default)]
+struct DowntimeTracker {
+    // Time of the URL's last check and whether it failed
+    last: HashMap<String, (SystemTime, bool)>,
+}
+
+impl DowntimeTracker {
+    // The period from the URL's previous check to this one, if the previous one failed
+    fn observe(&mut self, check: &CheckRecord) -> Option<(SystemTime, SystemTime)> {
+        if check.maintenance.is_some() {
+            return None;
+        }
+        let (previous, failed) = self.last.insert(check.url.clone(), (check.timestamp(), !check.ok))?;
+        failed.then_some((previous, check.timestamp()))
+    }
+}
+
+// How much of from..to lies in the window; an open end is unbounded
+fn overlap(from: SystemTime, to: SystemTime, window_from: Option<SystemTime>, window_to: Option<SystemTime>) -> Duration {
+    let from = window_from.map_or(from, |start| from.max(start));
+    let to = window_to.map_or(to, |end| to.min(end));
+    to.duration_since(from).unwrap_or_default()
+}
+
+// Incident statistics per URL and per tag. Takes the incidents of every URL oldest
+// first, so each one's predecessor gives the gap.
+fn reliability_by_url_and_tag(incidents: &[Incident]) -> (BTreeMap<String, Reliability>, BTreeMap<String, Reliability>) {
+    let mut per_url: BTreeMap<&str, Vec<&Incident>> = BTreeMap::new();
+    for incident in incidents {
+        per_url.entry(&incident.url).or_default().push(incident);
+    }
+    let mut by_url: BTreeMap<String, Reliability> = BTreeMap::new();
+    let mut by_tag: BTreeMap<String, Reliability> = BTreeMap::new();
+    for (url, mut url_incidents) in per_url {
+        url_incidents.sort_by_key(|incident| incident.start_epoch_s);
+        for (i, incident) in url_incidents.iter().enumerate() {
+            let previous = i.checked_sub(1).map(|p| url_incidents[p]);
+            by_url.entry(url.to_string()).or_default().add(incident, previous);
+            for tag in &incident.tags {
+                by_tag.entry(tag.clone()).or_default().add(incident, previous);
+            }
+        }
+    }
+    (by_url, by_tag)
+}
+
+fn format_uptime(counts: &WindowCounts) -> String {
+    counts.uptime().map_or("n/a".to_string(), |uptime| format!("{:.3}%", uptime))
+}
+
+fn format_optional_duration(duration: Option<Duration>) -> String {
+    duration.map_or("-".to_string(), format_duration)
+}
+
+fn format_optional_ms(duration: Option<Duration>) -> String {
+    duration.map_or("-".to_string(), |d| format!("{} ms", d.as_millis()))
+}
+
+// " | incidents | MTTR | MTBF" columns of the table, with the line break
+fn format_reliability_row(reliability: &Reliability) -> String {
+    format!(
+        " | {:>9} | {:>7} | {:>7}\n",
+        reliability.incidents,
+        format_optional_duration(reliability.mttr()),
+        format_optional_duration(reliability.mtbf())
+    )
+}
+
+fn format_reliability_cells(reliability: &Reliability) -> String {
+    format!(
+        " {} | {} | {} |\n",
+        reliability.incidents,
+        format_optional_duration(reliability.mttr()),
+        format_optional_duration(reliability.mtbf())
+    )
+}
+
+fn reliability_json(reliability: &Reliability) -> String {
+    let seconds = |duration: Option<Duration>| duration.map_or("null".to_string(), |d| d.as_secs().to_string());
+    format!(
+        "\"incidents\":{},\"mttrSeconds\":{},\"mtbfSeconds\":{}",
+        reliability.incidents,
+        seconds(reliability.mttr()),
+        seconds(reliability.mtbf())
+    )
+}
+
+fn format_reliability_html(reliability: &Reliability) -> String {
+    format!(
+        "<td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n",
+        reliability.incidents,
+        format_optional_duration(reliability.mttr()),
+        format_optional_duration(reliability.mtbf())
+    )
+}
+
+fn push_tags_html(body: &mut String, tags: &[TagReliability]) {
+    if tags.is_empty() {
+        return;
+    }
+    body.push_str("<h2>By tag</h2>\n<table>\n<tr><th>Tag</th><th>Incidents</th><th>MTTR</th><th>MTBF</th></tr>\n");
+    for tag in tags {
+        body.push_str(&format!("<tr><td>{}</td>{}", html_escape(&tag.tag), format_reliability_html(&tag.reliability)));
+    }
+    body.push_str("</table>\n");
+}
+
+// A self-contained page, so the report can be mailed or archived as a single file
+fn html_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         table {{ border-collapse: collapse; margin-bottom: 1.5em; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}\n\
+         th {{ background: #f4f4f4; }}\n\
+         td.num {{ text-align: right; }}\n\
+         td.good {{ color: #1a7f37; }}\n\
+         td.bad {{ color: #cf222e; font-weight: bold; }}\n\
+         </style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        html_escape(title),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::IncidentUpdate;
+    use crate::testing::{result_at, temp_dir};
+    use crate::CheckResult;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    // A store holding `checks` of (url, ok, seconds since the epoch), in that order
+    fn store_with(name: &str, checks: &[(&str, bool, u64)]) -> HistoryStore {
+        let store = HistoryStore::open(temp_dir(name)).unwrap();
+        for &(url, ok, secs) in checks {
+            let status = if ok { Ok(200) } else { Err("HTTP 503".to_string()) };
+            store.record_check(&result_at(url, status, at(secs))).unwrap();
+        }
+        store
+    }
+
+    fn incident(url: &str, tags: &[&str], start_epoch_s: u64, end_epoch_s: u64) -> IncidentUpdate {
+        IncidentUpdate::Ended(Incident {
+            url: url.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            start_epoch_s,
+            end_epoch_s,
+            duration_seconds: end_epoch_s - start_epoch_s,
+            first_error: "HTTP 503".to_string(),
+        })
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(ReportFormat::parse(" MD "), Ok(ReportFormat::Markdown));
+        assert_eq!(ReportFormat::parse("text"), Ok(ReportFormat::Table));
+        assert!(ReportFormat::parse("pdf").is_err());
+    }
+
+    #[test]
+    fn uptime_is_counted_per_window() {
+        let now = 100 * DAY;
+        let (a, b) = ("https://a.example.com", "https://b.example.com");
+        let store = store_with("sla", &[
+            (a, true, now - 40 * DAY),
+            (a, false, now - 20 * DAY),
+            (b, true, now - 5 * DAY),
+            (a, true, now - 3 * DAY),
+            (a, true, now - 2 * 3_600),
+            (a, false, now - 3_600),
+        ]);
+        let report = SlaReport::build(&store, 99.9, at(now)).unwrap();
+        let counts = |url: &UrlUptime| url.windows.map(|counts| (counts.checks, counts.successful));
+        assert_eq!(report.urls.iter().map(|url| url.url.as_str()).collect::<Vec<_>>(), [a, b]);
+        assert_eq!(counts(&report.urls[0]), [(2, 1), (3, 2), (4, 2)]);
+        assert_eq!(counts(&report.urls[1]), [(0, 0), (1, 1), (1, 1)]);
+        assert_eq!(report.urls[0].windows[0].uptime(), Some(50.0));
+        assert_eq!(report.urls[1].windows[0].uptime(), None);
+        assert!(report.below_slo(&report.urls[0]));
+        assert!(!report.below_slo(&report.urls[1]));
+
+        let table = report.to_table();
+        assert!(table.contains("  50.000% |   66.667% |   50.000% | BELOW"), "{}", table);
+        assert!(table.contains("      n/a |  100.000% |  100.000% | ok   "), "{}", table);
+        assert!(report.to_json().contains(
+            "\"url\":\"https://a.example.com\",\"windows\":{\"24h\":{\"uptimePercent\":50.0000,\"checks\":2,\"successful\":1,"
+        ));
+        assert!(report.to_markdown().contains(":x: below"));
+    }
+
+    #[test]
+    fn an_empty_store_has_no_urls() {
+        let report = SlaReport::build(&store_with("sla-empty", &[]), 99.0, at(DAY)).unwrap();
+        assert!(report.urls.is_empty());
+        assert!(report.to_table().ends_with("No checks recorded in the last 30 days.\n"));
+    }
+
+    #[test]
+    fn mttr_and_mtbf_per_url_and_tag() {
+        let now = 100 * DAY;
+        let t = now - DAY;
+        let (a, b) = ("https://a.example.com", "https://b.example.com");
+        let store = store_with("reliability", &[]);
+        for update in [
+            incident(a, &["web"], now - 40 * DAY, now - 40 * DAY + 60),
+            incident(a, &["web"], t + 2_000, t + 2_300),
+            incident(a, &["web"], t + 1_000, t + 1_100),
+            incident(b, &["web", "db"], t + 3_000, t + 3_600),
+            incident(a, &["web"], t + 5_000, t + 5_060),
+        ] {
+            store.record_incident(&update).unwrap();
+        }
+        let report = SlaReport::build(&store, 99.9, at(now)).unwrap();
+
+        let url_a = report.urls[0].reliability;
+        assert_eq!((url_a.incidents, url_a.mttr(), url_a.mtbf()), (3, Some(Duration::from_secs(460) / 3), Some(Duration::from_secs(1_800))));
+        let url_b = report.urls[1].reliability;
+        assert_eq!((url_b.incidents, url_b.mttr(), url_b.mtbf()), (1, Some(Duration::from_secs(600)), None));
+        assert_eq!(report.urls[1].windows, [WindowCounts::default(); SLA_WINDOWS.len()]);
+        let tags: Vec<_> = report.tags.iter()
+            .map(|tag| (tag.tag.as_str(), tag.reliability.incidents, tag.reliability.mttr(), tag.reliability.mtbf()))
+            .collect();
+        assert_eq!(tags, [
+            ("db", 1, Some(Duration::from_secs(600)), None),
+            ("web", 4, Some(Duration::from_secs(265)), Some(Duration::from_secs(1_800))),
+        ]);
+
+        assert!(report.to_json().contains("\"incidents\":3,\"mttrSeconds\":153,\"mtbfSeconds\":1800"));
+        assert!(report.to_table().contains("|         1 |  10m 0s |       -\n"), "{}", report.to_table());
+        assert_eq!(Reliability::default().mttr(), None);
+    }
+
+    #[test]
+    fn time_ranges() {
+        let day = |date: &str| parse_rfc3339_utc(date);
+        let range = TimeRange::parse("2024-05-01..2024-05-07").unwrap();
+        assert_eq!(range, TimeRange { from: day("2024-05-01"), to: day("2024-05-08") });
+        assert!(range.contains(day("2024-05-07T23:59:59Z").unwrap()));
+        assert!(!range.contains(day("2024-05-08").unwrap()));
+        assert!(!range.contains(day("2024-04-30T23:59:59Z").unwrap()));
+        assert_eq!(TimeRange::parse("2024-05-01T12:00:00Z..").unwrap(), TimeRange { from: day("2024-05-01T12:00:00Z"), to: None });
+        assert_eq!(TimeRange::parse("..2024-05-01T06:00:00Z").unwrap(), TimeRange { from: None, to: day("2024-05-01T06:00:00Z") });
+        assert!(TimeRange::parse("..").unwrap().contains(UNIX_EPOCH));
+        assert_eq!(TimeRange::parse("2024-05-01").unwrap_err(), "invalid range '2024-05-01' (expected <from>..<to>, e.g. 2024-05-01..2024-05-07)");
+        assert_eq!(TimeRange::parse("2024-05-07..2024-05-01").unwrap_err(), "invalid range '2024-05-07..2024-05-01': it ends before it starts");
+        assert!(TimeRange::parse("2024-05-01..tomorrow").unwrap_err().starts_with("invalid date 'tomorrow'"));
+        assert!(TimeRange::parse("99999999999999-01-01..").unwrap_err().starts_with("invalid date '99999999999999-01-01'"));
+    }
+
+    #[test]
+    fn range_reports_cover_only_the_range() {
+        let start = 1_714_521_600; // 2024-05-01
+        let (a, b) = ("https://a.example.com", "https://b.example.com");
+        let store = HistoryStore::open(temp_dir("range")).unwrap();
+        for (url, status, secs, ms) in [
+            (a, Ok(200), start - 60, 900),
+            (a, Ok(200), start, 100),
+            (a, Err("HTTP 502".to_string()), start + 60, 5_000),
+            (a, Ok(200), start + 120, 300),
+            (b, Ok(200), start + 180, 50),
+            (a, Ok(200), start + 2 * DAY, 700),
+        ] {
+            let check = CheckResult { response_time: Duration::from_millis(ms), ..result_at(url, status, at(secs)) };
+            store.record_check(&check).unwrap();
+        }
+        store.record_incident(&incident(a, &[], start - DAY, start - DAY + 60)).unwrap();
+        store.record_incident(&incident(a, &[], start + 60, start + 120)).unwrap();
+
+        let range = TimeRange::parse("2024-05-01..2024-05-01").unwrap();
+        let report = RangeReport::build(&store, range, 99.0, at(start + 3 * DAY)).unwrap();
+        let summary = &report.urls[0];
+        assert_eq!((summary.url.as_str(), summary.counts.checks, summary.counts.successful), (a, 3, 2));
+        assert_eq!(summary.avg_response_time, Some(Duration::from_millis(200)));
+        assert_eq!(summary.p95_response_time, Some(Duration::from_millis(300)));
+        assert_eq!(summary.reliability.incidents, 1);
+        assert!(report.below_slo(summary));
+        assert_eq!(report.urls[1].counts.checks, 1);
+        assert_eq!(report.incidents.iter().map(|incident| incident.start_epoch_s).collect::<Vec<_>>(), [start + 60]);
+
+        let json = report.to_json();
+        assert!(json.starts_with("{\"from\":\"2024-05-01T00:00:00Z\",\"to\":\"2024-05-02T00:00:00Z\",\"slo\":99,"), "{}", json);
+        assert!(json.contains("\"incidents\":[{\"url\":\"https://a.example.com\",\"start\":\"2024-05-01T00:01:00Z\",\"end\":\"2024-05-01T00:02:00Z\",\"durationSeconds\":60,\"firstError\":\"HTTP 503\"}]"), "{}", json);
+        assert!(report.to_table().starts_with("Report for 2024-05-01T00:00:00Z to 2024-05-02T00:00:00Z, SLO 99%"));
+        assert!(report.to_html().contains("<h2>Incidents</h2>"));
+    }
+
+    #[test]
+    fn downtime_runs_from_a_failed_check_to_the_next_check() {
+        let mut tracker = DowntimeTracker::default();
+        let check = |ok, secs, maintenance: Option<&str>| CheckRecord {
+            url: "https://example.com".to_string(),
+            timestamp_epoch_s: secs,
+            ok,
+            status: None,
+            error: None,
+            response_time_ms: 100,
+            content_hash: None,
+            maintenance: maintenance.map(str::to_string),
+        };
+        assert_eq!(tracker.observe(&check(false, 60, None)), None);
+        assert_eq!(tracker.observe(&check(true, 90, Some("Upgrade"))), None);
+        assert_eq!(tracker.observe(&check(false, 120, None)), Some((at(60), at(120))));
+        assert_eq!(tracker.observe(&check(true, 180, None)), Some((at(120), at(180))));
+        assert_eq!(tracker.observe(&check(true, 240, None)), None);
+
+        assert_eq!(overlap(at(60), at(180), Some(at(100)), None), Duration::from_secs(80));
+        assert_eq!(overlap(at(60), at(180), None, Some(at(90))), Duration::from_secs(30));
+        assert_eq!(overlap(at(60), at(180), Some(at(200)), Some(at(300))), Duration::ZERO);
+    }
+
+    #[test]
+    fn downtime_is_clipped_to_windows_and_ranges() {
+        let now = 100 * DAY;
+        let url = "https://example.com";
+        let store = store_with("downtime", &[
+            (url, false, now - 8 * DAY),
+            (url, true, now - 6 * DAY),
+            (url, false, now - 24 * 3_600 - 600),
+            (url, true, now - 24 * 3_600 + 1_200),
+        ]);
+        let report = SlaReport::build(&store, 99.9, at(now)).unwrap();
+        let downtime = report.urls[0].windows.map(|counts| counts.downtime.as_secs());
+        assert_eq!(downtime, [1_200, DAY + 1_800, 2 * DAY + 1_800]);
+
+        let range = TimeRange { from: Some(at(now - 7 * DAY)), to: Some(at(now - 6 * DAY - 3_600)) };
+        let report = RangeReport::build(&store, range, 99.9, at(now)).unwrap();
+        assert_eq!(report.urls[0].counts.downtime, Duration::from_secs(DAY - 3_600));
+        assert!(report.to_json().contains("\"downtimeSeconds\":82800,"));
+    }
+}
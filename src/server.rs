@@ -0,0 +1,781 @@
+//! The `serve` API: a small HTTP/JSON interface to a running `--period` monitor.
+//!
+//! * `GET /status`: every check with its latest result.
+//! * `GET /checks/{id}/history`: a check's latest results, newest last.
+//! * `POST /checks`: adds an `http(s)` URL, checked from the next round on.
+//! * `POST /checks/{id}/run`: checks one URL right away and returns the result.
+//! * `GET /agent/checks`, `POST /agent/results`: the [`agent`](crate::agent) protocol,
+//!   only with a token, since anyone reaching them could feed in results.
+//!
+//! `GET /` is a dashboard built on these endpoints; its files are compiled into the
+//! binary from `src/dashboard/`.
+//!
+//! The round loop feeds results in through [`MonitorApi`]; requests are served by a
+//! fixed pool of threads, so a slow `run` doesn't hold up the others and a flood of
+//! connections can't start unbounded threads.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::agent::{validate_agent_name, AssignedCheck, Assignment, ResultBatch};
+use crate::aggregate;
+use crate::history::CheckRecord;
+use crate::{escape_json_string, CheckRequest, CheckResult, Checker, Priority};
+
+/// Results kept per check for `GET /checks/{id}/history`.
+pub const HISTORY_LIMIT: usize = 1000;
+
+// Results returned by the history endpoint without ?limit=
+const DEFAULT_HISTORY_RESULTS: usize = 100;
+
+// Requests handled at once; more wait for a free thread
+const HANDLER_THREADS: usize = 16;
+
+// Larger POST bodies are rejected
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+// ... except for agents' results, which come by the thousand
+const MAX_RESULTS_BODY_BYTES: u64 = 16 * 1024 * 1024;
+
+// A check tagged `agent:<name>` is only assigned to the agents it names
+const AGENT_TAG_PREFIX: &str = "agent:";
+
+// An agent's result stops counting towards the merged result this many periods
+// after it arrived
+const AGENT_RESULT_PERIODS: u32 = 3;
+
+struct AgentResult {
+    result: CheckResult,
+    received: SystemTime,
+}
+
+// The dashboard's files: path, content type and contents. They're served without the
+// token, as they hold no data; the page asks for the token itself.
+const ASSETS: [(&str, &str, &str); 3] = [
+    ("/", "text/html; charset=utf-8", include_str!("dashboard/index.html")),
+    ("/dashboard.css", "text/css; charset=utf-8", include_str!("dashboard/dashboard.css")),
+    ("/dashboard.js", "text/javascript; charset=utf-8", include_str!("dashboard/dashboard.js")),
+];
+
+// Keeps the dashboard to its own files, so that an injected script couldn't load more
+const CONTENT_SECURITY_POLICY: &str = "default-src 'self'; style-src 'self'; img-src 'self' data:; frame-ancestors 'none'";
+
+struct ApiCheck {
+    id: u64,
+    request: CheckRequest,
+    /// Latest results, oldest first.
+    history: VecDeque<CheckResult>,
+    /// Latest result from each agent, by agent name.
+    agents: BTreeMap<String, AgentResult>,
+}
+
+impl ApiCheck {
+    fn state(&self) -> &'static str {
+        match self.history.back() {
+            None => "pending",
+            Some(result) if result.maintenance.is_some() => "maintenance",
+            Some(result) if result.action_status.is_ok() => "up",
+            Some(_) => "down",
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let tags: Vec<String> = self.request.tags.iter().map(|tag| format!("\"{}\"", escape_json_string(tag))).collect();
+        let agents: Vec<String> = self.agents.iter()
+            .map(|(agent, latest)| format!("\"{}\":{}", escape_json_string(agent), latest.result.to_json()))
+            .collect();
+        format!(
+            "{{\"id\":{},\"url\":\"{}\",\"priority\":\"{}\",\"tags\":[{}],\"state\":\"{}\",\"lastResult\":{},\"agents\":{{{}}}}}",
+            self.id,
+            escape_json_string(&self.request.url),
+            self.request.priority,
+            tags.join(","),
+            self.state(),
+            self.history.back().map_or("null".to_string(), CheckResult::to_json),
+            agents.join(",")
+        )
+    }
+
+    // Checks without `agent:` tags go to every agent. Only http(s) checks are handed
+    // out: exec:// commands and plugins are set up for the central host.
+    fn assigned_to(&self, agent: &str) -> bool {
+        let lower = self.request.url.to_ascii_lowercase();
+        if !lower.starts_with("http://") && !lower.starts_with("https://") {
+            return false;
+        }
+        let mut agents = self.request.tags.iter().filter_map(|tag| tag.strip_prefix(AGENT_TAG_PREFIX)).peekable();
+        agents.peek().is_none() || agents.any(|name| name == agent)
+    }
+}
+
+struct ApiState {
+    checks: Vec<ApiCheck>,
+    next_id: u64,
+    period_seconds: u64,
+    round: u64,
+    last_round_at: Option<SystemTime>,
+    /// When each agent last sent results, by agent name.
+    agents: BTreeMap<String, SystemTime>,
+}
+
+/// The monitor's checks and their latest results, shared between the round loop and
+/// the API server. Checks are numbered from 1 in the order they were added.
+#[derive(Clone)]
+pub struct MonitorApi {
+    state: Arc<Mutex<ApiState>>,
+}
+
+impl MonitorApi {
+    /// `period_seconds` is the monitor's `--period`, which agents run at too.
+    pub fn new(requests: Vec<CheckRequest>, period_seconds: u64) -> Self {
+        let checks: Vec<ApiCheck> = requests.into_iter()
+            .zip(1..)
+            .map(|(request, id)| ApiCheck { id, request, history: VecDeque::new(), agents: BTreeMap::new() })
+            .collect();
+        let next_id = checks.len() as u64 + 1;
+        let state = ApiState { checks, next_id, period_seconds, round: 0, last_round_at: None, agents: BTreeMap::new() };
+        MonitorApi { state: Arc::new(Mutex::new(state)) }
+    }
+
+    /// The checks to run in the next round, including those added through the API.
+    pub fn requests(&self) -> Vec<CheckRequest> {
+        self.lock().checks.iter().map(|check| check.request.clone()).collect()
+    }
+
+    pub fn record(&self, result: &CheckResult) {
+        let mut state = self.lock();
+        if let Some(check) = state.checks.iter_mut().find(|check| check.request.url == result.url) {
+            if check.history.len() == HISTORY_LIMIT {
+                check.history.pop_front();
+            }
+            check.history.push_back(result.clone());
+        }
+    }
+
+    pub fn round_finished(&self, round: u64) {
+        let mut state = self.lock();
+        state.round = round;
+        state.last_round_at = Some(SystemTime::now());
+    }
+
+    // A panicking request thread mustn't take the API down with it
+    fn lock(&self) -> MutexGuard<'_, ApiState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn status_json(&self) -> String {
+        let state = self.lock();
+        let checks: Vec<String> = state.checks.iter().map(ApiCheck::to_json).collect();
+        let last_round = state.last_round_at.map_or("null".to_string(), |at| epoch_s(at).to_string());
+        let agents: Vec<String> = state.agents.iter()
+            .map(|(agent, seen)| format!("{{\"name\":\"{}\",\"lastSeenEpochS\":{}}}", escape_json_string(agent), epoch_s(*seen)))
+            .collect();
+        format!(
+            "{{\"round\":{},\"lastRoundEpochS\":{},\"agents\":[{}],\"checks\":[{}]}}",
+            state.round,
+            last_round,
+            agents.join(","),
+            checks.join(",")
+        )
+    }
+
+    fn history_json(&self, id: u64, limit: usize) -> Option<String> {
+        let state = self.lock();
+        let check = state.checks.iter().find(|check| check.id == id)?;
+        let results: Vec<String> = check.history.iter()
+            .skip(check.history.len().saturating_sub(limit))
+            .map(CheckResult::to_json)
+            .collect();
+        Some(format!(
+            "{{\"id\":{},\"url\":\"{}\",\"results\":[{}]}}",
+            check.id,
+            escape_json_string(&check.request.url),
+            results.join(",")
+        ))
+    }
+
+    fn add(&self, request: CheckRequest) -> Result<String, (u16, String)> {
+        let mut state = self.lock();
+        if state.checks.iter().any(|check| check.request.url == request.url) {
+            return Err((409, format!("{} is already checked", request.url)));
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        let check = ApiCheck { id, request, history: VecDeque::new(), agents: BTreeMap::new() };
+        let json = check.to_json();
+        state.checks.push(check);
+        Ok(json)
+    }
+
+    fn request(&self, id: u64) -> Option<CheckRequest> {
+        self.lock().checks.iter().find(|check| check.id == id).map(|check| check.request.clone())
+    }
+
+    fn assignment(&self, agent: &str) -> Assignment {
+        let state = self.lock();
+        let checks = state.checks.iter()
+            .filter(|check| check.assigned_to(agent))
+            .map(|check| AssignedCheck::from(&check.request))
+            .collect();
+        Assignment { period_seconds: state.period_seconds, checks }
+    }
+
+    /// One result per check, merged from the agents' results received in the last
+    /// few periods (see [`aggregate::merge`]); checks no agent has reported on yet are
+    /// left out.
+    pub fn agent_round(&self, quorum: Option<usize>) -> Vec<CheckResult> {
+        let state = self.lock();
+        let max_age = Duration::from_secs(state.period_seconds) * AGENT_RESULT_PERIODS;
+        let now = SystemTime::now();
+        state.checks.iter()
+            .filter(|check| !check.agents.is_empty())
+            .map(|check| {
+                let fresh: Vec<(&str, &CheckResult)> = check.agents.iter()
+                    .filter(|(_, latest)| now.duration_since(latest.received).unwrap_or_default() <= max_age)
+                    .map(|(agent, latest)| (agent.as_str(), &latest.result))
+                    .collect();
+                aggregate::merge(&check.request, &fresh, quorum)
+                    .unwrap_or_else(|| aggregate::unreported(&check.request, max_age, now))
+            })
+            .collect()
+    }
+
+    // Keeps each check's latest result from the agent; results for URLs that aren't
+    // assigned to it, and ones older than the result kept, are dropped. Returns how
+    // many were kept.
+    fn record_agent_results(&self, batch: ResultBatch) -> usize {
+        let mut state = self.lock();
+        state.agents.insert(batch.agent.clone(), SystemTime::now());
+        let mut kept = 0;
+        for record in batch.results {
+            let Some(check) = state.checks.iter_mut().find(|check| check.request.url == record.url) else { continue };
+            if !check.assigned_to(&batch.agent) {
+                continue;
+            }
+            let result = result_from_record(record, &check.request);
+            // Batches held back by an outage may arrive out of order
+            let newer = check.agents.get(&batch.agent).is_none_or(|last| last.result.timestamp <= result.timestamp);
+            if newer {
+                check.agents.insert(batch.agent.clone(), AgentResult { result, received: SystemTime::now() });
+                kept += 1;
+            }
+        }
+        kept
+    }
+}
+
+// The record's timestamp was range-checked when the batch was parsed
+fn result_from_record(record: CheckRecord, request: &CheckRequest) -> CheckResult {
+    let timestamp = record.timestamp();
+    let action_status = match (record.ok, record.status, record.error) {
+        (true, Some(status), _) => Ok(status),
+        (true, None, _) => Ok(0),
+        (false, _, error) => Err(error.unwrap_or_else(|| "failed".to_string())),
+    };
+    CheckResult {
+        url: record.url,
+        action_status,
+        response_time: Duration::from_millis(record.response_time_ms),
+        timestamp,
+        upload: None,
+        priority: request.priority,
+        tags: request.tags.clone(),
+        content_hash: record.content_hash,
+        maintenance: record.maintenance,
+    }
+}
+
+fn epoch_s(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Starts serving the API on `addr` (e.g. `127.0.0.1:8080`). `checker` runs the checks
+/// asked for with `POST /checks/{id}/run`; with a `token`, every request needs an
+/// `Authorization: Bearer <token>` header. Without one, anyone who can connect may add
+/// and run checks, so listening beyond loopback without a token logs a warning.
+pub fn start(addr: &str, api: MonitorApi, checker: Checker, token: Option<String>) -> Result<Vec<JoinHandle<()>>, String> {
+    let server = Arc::new(Server::http(addr).map_err(|e| format!("Failed to listen on {}: {}", addr, e))?);
+    if token.is_none() && !is_loopback(server.server_addr().to_ip()) {
+        tracing::warn!(addr, "the API is reachable from other hosts without --api-token: anyone who can connect can add and run checks");
+    }
+    let threads = (0..HANDLER_THREADS)
+        .map(|_| {
+            let (server, api, checker, token) = (Arc::clone(&server), api.clone(), checker.clone(), token.clone());
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    // A panic drops its request but mustn't cost the pool a thread
+                    let handled = panic::catch_unwind(AssertUnwindSafe(|| handle(request, &api, &checker, token.as_deref())));
+                    if handled.is_err() {
+                        tracing::error!("an API request handler panicked");
+                    }
+                }
+            })
+        })
+        .collect();
+    Ok(threads)
+}
+
+fn is_loopback(addr: Option<SocketAddr>) -> bool {
+    addr.is_some_and(|addr| addr.ip().is_loopback())
+}
+
+fn handle(mut request: Request, api: &MonitorApi, checker: &Checker, token: Option<&str>) {
+    if *request.method() == Method::Get {
+        let path = request.url().split('?').next().unwrap_or_default();
+        if let Some((_, content_type, body)) = ASSETS.iter().find(|(asset, _, _)| *asset == path) {
+            let csp = Header::from_bytes("Content-Security-Policy", CONTENT_SECURITY_POLICY).expect("valid header");
+            respond(request, 200, content_type, body.to_string(), Some(csp));
+            return;
+        }
+    }
+    let (status, body) = if token.is_some_and(|token| !authorized(&request, token)) {
+        (401, error_json("missing or wrong bearer token"))
+    } else {
+        route(&mut request, api, checker, token.is_some()).unwrap_or_else(|(status, message)| (status, error_json(&message)))
+    };
+    respond(request, status, "application/json", body, None);
+}
+
+fn respond(request: Request, status: u16, content_type: &str, body: String, extra_header: Option<Header>) {
+    let content_type = Header::from_bytes("Content-Type", content_type).expect("valid header");
+    let mut response = Response::from_string(body).with_status_code(status).with_header(content_type);
+    if let Some(header) = extra_header {
+        response.add_header(header);
+    }
+    if let Err(e) = request.respond(response) {
+        tracing::debug!(error = %e, "failed to send API response");
+    }
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    request.headers().iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .is_some_and(|given| tokens_match(given, token))
+}
+
+// Compares digests of the two tokens in constant time, so response times reveal
+// neither how much of a guess was right nor the token's length
+fn tokens_match(given: &str, token: &str) -> bool {
+    let (given, token) = (Sha256::digest(given.as_bytes()), Sha256::digest(token.as_bytes()));
+    given.iter().zip(token.iter()).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+fn route(request: &mut Request, api: &MonitorApi, checker: &Checker, has_token: bool) -> Result<(u16, String), (u16, String)> {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+    let method = request.method().clone();
+    if segments.first() == Some(&"agent") && !has_token {
+        return Err((403, "agent endpoints are disabled: start the central instance with --api-token".to_string()));
+    }
+    match (&method, segments.as_slice()) {
+        (Method::Get, ["status"]) => Ok((200, api.status_json())),
+        (Method::Get, ["checks", id, "history"]) => {
+            let id = parse_id(id)?;
+            let limit = match query_param(query, "limit") {
+                Some(limit) => limit.parse().map_err(|_| (400, format!("invalid limit '{}'", limit)))?,
+                None => DEFAULT_HISTORY_RESULTS,
+            };
+            api.history_json(id, limit).map(|json| (200, json)).ok_or_else(|| not_found(id))
+        }
+        (Method::Post, ["checks"]) => {
+            let check = parse_new_check(&read_body(request, MAX_BODY_BYTES)?).map_err(|e| (400, e))?;
+            api.add(check).map(|json| (201, json))
+        }
+        (Method::Post, ["checks", id, "run"]) => {
+            let id = parse_id(id)?;
+            let check = api.request(id).ok_or_else(|| not_found(id))?;
+            let result = checker.check_all(vec![check]).pop()
+                .ok_or_else(|| (500, "the check produced no result".to_string()))?;
+            api.record(&result);
+            Ok((200, result.to_json()))
+        }
+        (Method::Get, ["agent", "checks"]) => {
+            let agent = query_param(query, "agent").ok_or_else(|| (400, "missing ?agent=<name>".to_string()))?;
+            validate_agent_name(agent).map_err(|e| (400, e))?;
+            let assignment = api.assignment(agent);
+            serde_json::to_string(&assignment).map(|json| (200, json)).map_err(|e| (500, e.to_string()))
+        }
+        (Method::Post, ["agent", "results"]) => {
+            let body = read_body(request, MAX_RESULTS_BODY_BYTES)?;
+            let batch: ResultBatch = serde_json::from_str(&body).map_err(|e| (400, format!("invalid results: {}", e)))?;
+            validate_agent_name(&batch.agent).map_err(|e| (400, e))?;
+            let kept = api.record_agent_results(batch);
+            Ok((200, format!("{{\"accepted\":{}}}", kept)))
+        }
+        (_, []) | (_, ["status"]) | (_, ["checks"]) | (_, ["checks", _, "history"]) | (_, ["checks", _, "run"])
+        | (_, ["agent", "checks"]) | (_, ["agent", "results"]) => {
+            Err((405, format!("method {} not allowed", method)))
+        }
+        _ => Err((404, format!("no such endpoint: {}", path))),
+    }
+}
+
+fn parse_id(id: &str) -> Result<u64, (u16, String)> {
+    id.parse().map_err(|_| (404, format!("no check with id '{}'", id)))
+}
+
+fn not_found(id: u64) -> (u16, String) {
+    (404, format!("no check with id {}", id))
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+fn read_body(request: &mut Request, limit: u64) -> Result<String, (u16, String)> {
+    let mut body = String::new();
+    request.as_reader()
+        .take(limit + 1)
+        .read_to_string(&mut body)
+        .map_err(|e| (400, format!("failed to read the request body: {}", e)))?;
+    if body.len() as u64 > limit {
+        return Err((413, "request body too large".to_string()));
+    }
+    Ok(body)
+}
+
+// `{"url": "https://...", "priority": "high", "tags": ["a", "b"]}`; only the URL is required
+fn parse_new_check(body: &str) -> Result<CheckRequest, String> {
+    let value: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON: {}", e))?;
+    let url = value.get("url").and_then(|url| url.as_str()).ok_or("missing \"url\"")?.trim();
+    // Anything else could run commands (exec://) or plugins on the monitor's host
+    let lower = url.to_ascii_lowercase();
+    if !lower.starts_with("http://") && !lower.starts_with("https://") {
+        return Err(format!("only http:// and https:// URLs can be added, got '{}'", url));
+    }
+    let mut request = CheckRequest::new(url);
+    if let Some(priority) = value.get("priority") {
+        let priority = priority.as_str().ok_or("\"priority\" must be a string")?;
+        request.priority = Priority::parse(priority)?;
+    }
+    if let Some(tags) = value.get("tags") {
+        let tags = tags.as_array().ok_or("\"tags\" must be an array of strings")?;
+        request.tags = tags.iter()
+            .map(|tag| tag.as_str().map(|tag| tag.trim().to_string()).ok_or("\"tags\" must be an array of strings"))
+            .collect::<Result<Vec<String>, &str>>()?
+            .into_iter()
+            .filter(|tag| !tag.is_empty())
+            .collect();
+    }
+    Ok(request)
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\":\"{}\"}}", escape_json_string(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{response, result, result_at, FakeBackend};
+    use crate::{Config, HttpRequest};
+
+    #[test]
+    fn tokens_match_only_the_same_token() {
+        assert!(tokens_match("s3cret", "s3cret"));
+        assert!(!tokens_match("s3cre", "s3cret"));
+        assert!(!tokens_match("s3cret ", "s3cret"));
+        assert!(!tokens_match("S3CRET", "s3cret"));
+        assert!(!tokens_match("", "s3cret"));
+    }
+
+    #[test]
+    fn query_params_are_found_by_exact_name() {
+        assert_eq!(query_param("limit=5&agent=eu-west", "limit"), Some("5"));
+        assert_eq!(query_param("limit=5&agent=eu-west", "agent"), Some("eu-west"));
+        assert_eq!(query_param("agents=all&agent=eu", "agent"), Some("eu"));
+        assert_eq!(query_param("limit", "limit"), None);
+        assert_eq!(query_param("", "limit"), None);
+    }
+
+    #[test]
+    fn new_checks_take_a_priority_and_tags() {
+        let check = parse_new_check(r#"{"url": " https://example.com/ ", "priority": "high", "tags": ["web", " ", " eu "]}"#).unwrap();
+        assert_eq!(check.url, "https://example.com/");
+        assert_eq!(check.priority, Priority::High);
+        assert_eq!(check.tags, ["web", "eu"]);
+
+        let check = parse_new_check(r#"{"url": "HTTP://example.com/"}"#).unwrap();
+        assert_eq!(check.priority, Priority::default());
+        assert!(check.tags.is_empty());
+    }
+
+    #[test]
+    fn new_checks_must_be_http_urls() {
+        assert!(parse_new_check(r#"{"url": "exec://rm -rf /"}"#).unwrap_err().starts_with("only http:// and https://"));
+        assert!(parse_new_check(r#"{"url": "ftp://example.com/"}"#).is_err());
+        assert_eq!(parse_new_check(r#"{"priority": "high"}"#).unwrap_err(), "missing \"url\"");
+        assert_eq!(parse_new_check(r#"{"url": 5}"#).unwrap_err(), "missing \"url\"");
+        assert!(parse_new_check("not json").unwrap_err().starts_with("invalid JSON"));
+        assert_eq!(parse_new_check(r#"{"url": "https://a/", "priority": 1}"#).unwrap_err(), "\"priority\" must be a string");
+        assert!(parse_new_check(r#"{"url": "https://a/", "priority": "urgent"}"#).is_err());
+        assert_eq!(parse_new_check(r#"{"url": "https://a/", "tags": "web"}"#).unwrap_err(), "\"tags\" must be an array of strings");
+        assert_eq!(parse_new_check(r#"{"url": "https://a/", "tags": [1]}"#).unwrap_err(), "\"tags\" must be an array of strings");
+    }
+
+    #[test]
+    fn added_checks_are_numbered_and_not_duplicated() {
+        let api = MonitorApi::new(vec![CheckRequest::new("https://a.example/")], 60);
+        let added = api.add(CheckRequest::new("https://b.example/")).unwrap();
+        assert!(added.starts_with("{\"id\":2,\"url\":\"https://b.example/\""), "{}", added);
+        assert!(added.contains("\"state\":\"pending\",\"lastResult\":null"), "{}", added);
+        assert_eq!(api.add(CheckRequest::new("https://a.example/")).unwrap_err().0, 409);
+        let urls: Vec<String> = api.requests().into_iter().map(|request| request.url).collect();
+        assert_eq!(urls, ["https://a.example/", "https://b.example/"]);
+        assert_eq!(api.request(2).unwrap().url, "https://b.example/");
+        assert!(api.request(3).is_none());
+    }
+
+    #[test]
+    fn history_keeps_the_latest_results() {
+        let api = MonitorApi::new(vec![CheckRequest::new("https://a.example/")], 60);
+        for i in 0..HISTORY_LIMIT as u64 + 5 {
+            api.record(&result_at("https://a.example/", Ok(200), UNIX_EPOCH + Duration::from_secs(i)));
+        }
+        api.record(&result("https://unknown.example/", Ok(200)));
+        let state = api.lock();
+        let history = &state.checks[0].history;
+        assert_eq!(history.len(), HISTORY_LIMIT);
+        assert_eq!(history.front().unwrap().timestamp, UNIX_EPOCH + Duration::from_secs(5));
+        drop(state);
+
+        let json = api.history_json(1, 2).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let stamps: Vec<u64> = value["results"].as_array().unwrap().iter()
+            .map(|result| result["timestampEpochS"].as_u64().unwrap())
+            .collect();
+        assert_eq!(stamps, [HISTORY_LIMIT as u64 + 3, HISTORY_LIMIT as u64 + 4]);
+        assert!(api.history_json(2, 2).is_none());
+    }
+
+    #[test]
+    fn state_follows_the_latest_result() {
+        let api = MonitorApi::new(vec![CheckRequest::new("https://a.example/")], 60);
+        api.record(&result("https://a.example/", Err("timed out".to_string())));
+        assert!(api.status_json().contains("\"state\":\"down\""));
+        api.record(&result("https://a.example/", Ok(503)));
+        assert!(api.status_json().contains("\"state\":\"up\""));
+        api.round_finished(7);
+        assert!(api.status_json().starts_with("{\"round\":7,\"lastRoundEpochS\":"));
+    }
+
+    // Serves `api` on a free local port, returning the base URL
+    fn serve(api: MonitorApi, token: Option<&str>) -> String {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let backend = FakeBackend(|_: HttpRequest<'_>| Ok(response(204, &[])));
+        let checker = Checker::with_backend(Config::default(), 1, Arc::new(backend)).unwrap();
+        let addr = format!("127.0.0.1:{}", port);
+        start(&addr, api, checker, token.map(str::to_string)).unwrap();
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn api_adds_and_runs_checks() {
+        let api = MonitorApi::new(Vec::new(), 60);
+        let base = serve(api.clone(), None);
+        let client = reqwest::blocking::Client::new();
+
+        let added = client.post(format!("{}/checks", base)).body(r#"{"url": "https://a.example/"}"#).send().unwrap();
+        assert_eq!(added.status(), 201);
+        let bad = client.post(format!("{}/checks", base)).body(r#"{"url": "exec://true"}"#).send().unwrap();
+        assert_eq!(bad.status(), 400);
+        assert!(bad.text().unwrap().starts_with("{\"error\":\"only http://"));
+
+        let run = client.post(format!("{}/checks/1/run", base)).send().unwrap();
+        assert_eq!(run.status(), 200);
+        assert!(run.text().unwrap().contains("\"status\":204"));
+        let history = client.get(format!("{}/checks/1/history?limit=10", base)).send().unwrap().text().unwrap();
+        assert!(history.contains("\"status\":204"), "{}", history);
+
+        assert_eq!(client.post(format!("{}/checks/2/run", base)).send().unwrap().status(), 404);
+        assert_eq!(client.get(format!("{}/checks/1/history?limit=x", base)).send().unwrap().status(), 400);
+        assert_eq!(client.delete(format!("{}/status", base)).send().unwrap().status(), 405);
+        assert_eq!(client.get(format!("{}/nothing", base)).send().unwrap().status(), 404);
+        assert_eq!(client.get(format!("{}/agent/checks?agent=eu", base)).send().unwrap().status(), 403);
+    }
+
+    #[test]
+    fn api_requires_the_token_when_set() {
+        let base = serve(MonitorApi::new(Vec::new(), 60), Some("s3cret"));
+        let client = reqwest::blocking::Client::new();
+        assert_eq!(client.get(format!("{}/status", base)).send().unwrap().status(), 401);
+        assert_eq!(client.get(format!("{}/status", base)).bearer_auth("wrong").send().unwrap().status(), 401);
+        let status = client.get(format!("{}/status", base)).bearer_auth("s3cret").send().unwrap();
+        assert_eq!(status.status(), 200);
+        assert!(status.text().unwrap().ends_with("\"checks\":[]}"));
+    }
+
+    #[test]
+    fn dashboard_is_served_without_the_token() {
+        let base = serve(MonitorApi::new(Vec::new(), 60), Some("s3cret"));
+        let client = reqwest::blocking::Client::new();
+        for (path, content_type, body) in ASSETS {
+            let response = client.get(format!("{}{}", base, path)).send().unwrap();
+            assert_eq!(response.status(), 200, "{}", path);
+            assert_eq!(response.headers()["content-type"], content_type);
+            assert_eq!(response.headers()["content-security-policy"], CONTENT_SECURITY_POLICY);
+            assert_eq!(response.text().unwrap(), body);
+        }
+        assert_eq!(client.get(format!("{}/?x=1", base)).send().unwrap().status(), 200);
+        // Only GETs, and only the dashboard's own files
+        assert_eq!(client.post(format!("{}/", base)).send().unwrap().status(), 401);
+        assert_eq!(client.get(format!("{}/index.html", base)).send().unwrap().status(), 401);
+    }
+
+    fn tagged(url: &str, tags: &[&str]) -> CheckRequest {
+        let mut request = CheckRequest::new(url);
+        request.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        request
+    }
+
+    fn record(url: &str, ok: bool, timestamp_epoch_s: u64) -> CheckRecord {
+        CheckRecord {
+            url: url.to_string(),
+            timestamp_epoch_s,
+            ok,
+            status: ok.then_some(200),
+            error: (!ok).then(|| "timed out".to_string()),
+            response_time_ms: 80,
+            content_hash: None,
+            maintenance: None,
+        }
+    }
+
+    #[test]
+    fn agents_get_their_http_checks() {
+        let api = MonitorApi::new(vec![
+            tagged("https://everywhere.example/", &["web"]),
+            tagged("https://eu.example/", &["agent:eu", "agent:ap"]),
+            tagged("https://us.example/", &["agent:us"]),
+            tagged("exec://./check-disk.sh", &[]),
+        ], 45);
+        let urls = |agent: &str| -> Vec<String> {
+            let assignment = api.assignment(agent);
+            assert_eq!(assignment.period_seconds, 45);
+            assignment.checks.into_iter().map(|check| check.url).collect()
+        };
+        assert_eq!(urls("eu"), ["https://everywhere.example/", "https://eu.example/"]);
+        assert_eq!(urls("ap"), ["https://everywhere.example/", "https://eu.example/"]);
+        assert_eq!(urls("us"), ["https://everywhere.example/", "https://us.example/"]);
+        assert_eq!(urls("other"), ["https://everywhere.example/"]);
+    }
+
+    #[test]
+    fn agents_results_are_kept_for_their_checks_only() {
+        let api = MonitorApi::new(vec![
+            tagged("https://a.example/", &[]),
+            tagged("https://us.example/", &["agent:us"]),
+        ], 60);
+        let batch = ResultBatch {
+            agent: "eu".to_string(),
+            results: vec![
+                record("https://a.example/", true, 100),
+                record("https://us.example/", true, 100),
+                record("https://unknown.example/", true, 100),
+            ],
+        };
+        assert_eq!(api.record_agent_results(batch), 1);
+        // A batch held back by an outage doesn't replace newer results
+        let late = ResultBatch { agent: "eu".to_string(), results: vec![record("https://a.example/", false, 40)] };
+        assert_eq!(api.record_agent_results(late), 0);
+
+        let state = api.lock();
+        assert!(state.agents.contains_key("eu"));
+        let latest = &state.checks[0].agents["eu"].result;
+        assert_eq!(latest.action_status, Ok(200));
+        assert_eq!(latest.timestamp, UNIX_EPOCH + Duration::from_secs(100));
+        assert!(state.checks[1].agents.is_empty());
+        drop(state);
+        let status = api.status_json();
+        assert!(status.contains("\"agents\":[{\"name\":\"eu\",\"lastSeenEpochS\":"), "{}", status);
+    }
+
+    #[test]
+    fn agent_records_become_results_of_the_check() {
+        let mut request = tagged("https://a.example/", &["web"]);
+        request.priority = Priority::Critical;
+        let down = result_from_record(record("https://a.example/", false, 100), &request);
+        assert_eq!(down.action_status, Err("timed out".to_string()));
+        assert_eq!(down.response_time, Duration::from_millis(80));
+        assert_eq!((down.priority, down.tags), (Priority::Critical, vec!["web".to_string()]));
+        let mut bare = record("https://a.example/", false, 100);
+        bare.error = None;
+        assert_eq!(result_from_record(bare, &request).action_status, Err("failed".to_string()));
+    }
+
+    #[test]
+    fn agent_endpoints_need_a_valid_name() {
+        let api = MonitorApi::new(vec![tagged("https://a.example/", &[])], 60);
+        let base = serve(api.clone(), Some("s3cret"));
+        let client = reqwest::blocking::Client::new();
+        let get = |path: &str| client.get(format!("{}{}", base, path)).bearer_auth("s3cret").send().unwrap();
+        assert_eq!(get("/agent/checks").status(), 400);
+        assert_eq!(get("/agent/checks?agent=eu%20west").status(), 400);
+        let assignment: Assignment = serde_json::from_str(&get("/agent/checks?agent=eu").text().unwrap()).unwrap();
+        assert_eq!(assignment.checks.len(), 1);
+
+        let post = |body: &str| client.post(format!("{}/agent/results", base)).bearer_auth("s3cret").body(body.to_string()).send().unwrap();
+        assert_eq!(post("{\"agent\":\"eu\"}").status(), 400);
+        assert_eq!(post("{\"agent\":\"\",\"results\":[]}").status(), 400);
+        let accepted = post(&serde_json::to_string(&ResultBatch { agent: "eu".to_string(), results: vec![record("https://a.example/", true, 100)] }).unwrap());
+        assert_eq!(accepted.text().unwrap(), "{\"accepted\":1}");
+
+        // Timestamps beyond what the system clock can represent are refused, and no
+        // handler thread dies on them
+        let far_future = serde_json::to_string(&ResultBatch { agent: "eu".to_string(), results: vec![record("https://a.example/", true, u64::MAX)] }).unwrap();
+        for _ in 0..HANDLER_THREADS + 1 {
+            let rejected = post(&far_future);
+            assert_eq!(rejected.status(), 400);
+            assert!(rejected.text().unwrap().contains("timestamp 18446744073709551615 is out of range"));
+        }
+        assert_eq!(get("/status").status(), 200);
+    }
+
+    #[test]
+    fn agent_rounds_merge_fresh_results_only() {
+        let api = MonitorApi::new(vec![
+            tagged("https://a.example/", &[]),
+            tagged("https://b.example/", &[]),
+            tagged("https://never.example/", &[]),
+        ], 60);
+        let now = SystemTime::now();
+        let at = |secs_ago: u64| now - Duration::from_secs(secs_ago);
+        {
+            let mut state = api.lock();
+            let agents = &mut state.checks[0].agents;
+            agents.insert("eu".to_string(), AgentResult { result: result("https://a.example/", Ok(200)), received: at(10) });
+            agents.insert("us".to_string(), AgentResult { result: result("https://a.example/", Err("timed out".to_string())), received: at(20) });
+            // Older than three periods: no longer counts
+            agents.insert("ap".to_string(), AgentResult { result: result("https://a.example/", Err("refused".to_string())), received: at(600) });
+            let agents = &mut state.checks[1].agents;
+            agents.insert("eu".to_string(), AgentResult { result: result("https://b.example/", Ok(200)), received: at(600) });
+        }
+        let round = api.agent_round(None);
+        assert_eq!(round.len(), 2);
+        assert_eq!(round[0].url, "https://a.example/");
+        assert_eq!(round[0].action_status, Ok(200));
+        assert_eq!(round[1].action_status, Err("no results from any agent in 3m 0s".to_string()));
+
+        let round = api.agent_round(Some(2));
+        assert_eq!(round[0].action_status, Err("down for 1 of 2 agents (us: timed out)".to_string()));
+    }
+
+    #[test]
+    fn only_loopback_addresses_are_local() {
+        let local = |addr: &str| is_loopback(Some(addr.parse().unwrap()));
+        assert!(local("127.0.0.1:8080"));
+        assert!(local("[::1]:8080"));
+        assert!(!local("0.0.0.0:8080"));
+        assert!(!local("[::]:8080"));
+        assert!(!local("192.168.1.10:8080"));
+    }
+}
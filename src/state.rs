@@ -0,0 +1,127 @@
+//! The `--state-file`: per-URL monitoring state saved after every round and loaded on
+//! start, so restarting the checker doesn't reset outage tracking or re-fire alerts.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::history::{ContentState, IncidentState};
+use crate::notify::escalation::EscalationState;
+use crate::notify::tracker::AlertState;
+use crate::stats::UrlStatsState;
+
+/// Bumped when the file layout changes incompatibly; other versions are ignored.
+const STATE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorState {
+    pub version: u32,
+    /// Last completed round, so round numbers continue after a restart.
+    pub round: u64,
+    #[serde(default)]
+    pub alerts: AlertState,
+    #[serde(default)]
+    pub incidents: IncidentState,
+    #[serde(default)]
+    pub escalations: EscalationState,
+    #[serde(default)]
+    pub content: ContentState,
+    #[serde(default)]
+    pub url_stats: UrlStatsState,
+}
+
+impl MonitorState {
+    pub fn new(
+        round: u64,
+        alerts: AlertState,
+        incidents: IncidentState,
+        escalations: EscalationState,
+        content: ContentState,
+        url_stats: UrlStatsState,
+    ) -> Self {
+        MonitorState { version: STATE_VERSION, round, alerts, incidents, escalations, content, url_stats }
+    }
+
+    /// Reads a saved state; `None` if the file doesn't exist yet or was written by an
+    /// incompatible version.
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>, String> {
+        let path = path.as_ref();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read state file {}: {}", path.display(), e)),
+        };
+        let state: MonitorState = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid state file {}: {}", path.display(), e))?;
+        if state.version != STATE_VERSION {
+            tracing::warn!(path = %path.display(), version = state.version, "ignoring state file with unsupported version");
+            return Ok(None);
+        }
+        Ok(Some(state))
+    }
+
+    /// Writes the state to a temporary file next to `path` and renames it into place,
+    /// so a crash mid-write never leaves a truncated state file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write state file {}: {}", path.display(), e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to write state file {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use crate::history::{IncidentTracker, IncidentUpdate};
+    use crate::notify::tracker::{AlertPolicy, AlertTracker};
+    use crate::notify::Event;
+    use crate::testing::{result_at, temp_dir};
+
+    #[test]
+    fn a_restart_continues_outages_without_alerting_again() {
+        let path = temp_dir("state").join("state.json");
+        let url = "https://api.example.com";
+        let down = result_at(url, Err("timed out".to_string()), UNIX_EPOCH + Duration::from_secs(600));
+        let (mut alerts, mut incidents) = (AlertTracker::new(AlertPolicy::default()), IncidentTracker::new());
+        assert!(matches!(alerts.observe(7, &down), Some(Event::Failure { .. })));
+        assert!(incidents.observe(&down).is_some());
+        let state = MonitorState::new(7, alerts.state(), incidents.state(), Default::default(), Default::default(), Default::default());
+        state.save(&path).unwrap();
+
+        let state = MonitorState::load(&path).unwrap().unwrap();
+        assert_eq!(state.round, 7);
+        let (mut alerts, mut incidents) = (AlertTracker::new(AlertPolicy::default()), IncidentTracker::new());
+        alerts.restore(state.alerts);
+        incidents.restore(state.incidents);
+        let still_down = result_at(url, Err("timed out".to_string()), UNIX_EPOCH + Duration::from_secs(660));
+        assert!(alerts.observe(8, &still_down).is_none());
+        assert!(incidents.observe(&still_down).is_none());
+        let up = result_at(url, Ok(200), UNIX_EPOCH + Duration::from_secs(900));
+        let Some(Event::Recovery { outage, .. }) = alerts.observe(9, &up) else { panic!("expected a recovery") };
+        assert_eq!(outage, Duration::from_secs(300));
+        let Some(IncidentUpdate::Ended(incident)) = incidents.observe(&up) else { panic!("expected the incident to end") };
+        assert_eq!((incident.start_epoch_s, incident.duration_seconds), (600, 300));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn missing_and_other_version_files_start_fresh() {
+        let dir = temp_dir("state-versions");
+        assert!(MonitorState::load(dir.join("missing.json")).unwrap().is_none());
+        fs::write(dir.join("old.json"), "{\"version\":0,\"round\":12}").unwrap();
+        assert!(MonitorState::load(dir.join("old.json")).unwrap().is_none());
+        fs::write(dir.join("minimal.json"), "{\"version\":1,\"round\":12}").unwrap();
+        assert_eq!(MonitorState::load(dir.join("minimal.json")).unwrap().unwrap().round, 12);
+        fs::write(dir.join("broken.json"), "{\"version\":").unwrap();
+        assert!(MonitorState::load(dir.join("broken.json")).unwrap_err().starts_with("Invalid state file"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,638 @@
+//! Statistics beyond the round summary's min/max/average: latency percentiles and
+//! spread per round, cumulative per-URL statistics across rounds, Apdex scores, and
+//! histograms for the metrics exports.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+use crate::{parse_duration, WebsiteStatus};
+
+/// Successful checks kept per URL for its percentiles across rounds.
+pub const LATENCY_WINDOW: usize = 1000;
+
+/// Nearest-rank percentile (`p` in 0..=100) of samples sorted in ascending order.
+pub fn percentile(sorted: &[Duration], p: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Population standard deviation; `None` without samples.
+pub fn std_dev(samples: &[Duration]) -> Option<Duration> {
+    if samples.is_empty() {
+        return None;
+    }
+    let n = samples.len() as f64;
+    let mean = samples.iter().map(Duration::as_secs_f64).sum::<f64>() / n;
+    let variance = samples.iter().map(|s| (s.as_secs_f64() - mean).powi(2)).sum::<f64>() / n;
+    Some(Duration::from_secs_f64(variance.sqrt()))
+}
+
+/// Mean absolute difference between consecutive samples: how much a URL's latency
+/// moves from one check to the next, which a stable average can hide. `None` with
+/// fewer than two samples.
+pub fn jitter(samples: &[Duration]) -> Option<Duration> {
+    if samples.len() < 2 {
+        return None;
+    }
+    let total: Duration = samples.windows(2).map(|pair| pair[0].abs_diff(pair[1])).sum();
+    Some(total / (samples.len() - 1) as u32)
+}
+
+/// Successful response times shown in the sparklines of the console output.
+pub const SPARKLINE_SAMPLES: usize = 20;
+
+/// Samples as a line of block characters (`▁▂▃▄▅▆▇█`), scaled from the smallest to
+/// the largest sample; a flat line when they are all equal.
+pub fn sparkline(samples: &[Duration]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let (Some(min), Some(max)) = (samples.iter().min(), samples.iter().max()) else { return String::new() };
+    let range = (*max - *min).as_secs_f64();
+    samples.iter()
+        .map(|sample| {
+            if range == 0.0 {
+                return BARS[0];
+            }
+            let level = (*sample - *min).as_secs_f64() / range * (BARS.len() - 1) as f64;
+            BARS[level.round() as usize]
+        })
+        .collect()
+}
+
+/// The latency percentiles the summaries report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl Percentiles {
+    /// `None` without samples.
+    pub fn from_samples(samples: impl IntoIterator<Item = Duration>) -> Option<Self> {
+        let mut sorted: Vec<Duration> = samples.into_iter().collect();
+        sorted.sort_unstable();
+        Some(Percentiles {
+            p50: percentile(&sorted, 50.0)?,
+            p90: percentile(&sorted, 90.0)?,
+            p95: percentile(&sorted, 95.0)?,
+            p99: percentile(&sorted, 99.0)?,
+        })
+    }
+}
+
+impl std::fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "p50 {} ms, p90 {} ms, p95 {} ms, p99 {} ms",
+            self.p50.as_millis(),
+            self.p90.as_millis(),
+            self.p95.as_millis(),
+            self.p99.as_millis()
+        )
+    }
+}
+
+// Everything kept about one URL across rounds
+#[derive(Debug, Clone, Default)]
+struct UrlRecord {
+    totals: UrlTotals,
+    // Latest successful response times, oldest first
+    samples: VecDeque<Duration>,
+}
+
+/// A URL's cumulative counters, saved with `--state-file` (the latency window is not,
+/// it refills within a few rounds).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "native", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "native", serde(rename_all = "camelCase"))]
+pub struct UrlTotals {
+    pub checks: u64,
+    pub failures: u64,
+    /// Sum of the response times of the successful checks.
+    pub success_time: Duration,
+    pub last_failure: Option<SystemTime>,
+    /// Length of the finished outages, each from the first failed check to the next
+    /// successful one. Checks during maintenance neither start nor end an outage.
+    #[cfg_attr(feature = "native", serde(default))]
+    pub downtime: Duration,
+    /// Start of the current outage.
+    #[cfg_attr(feature = "native", serde(default))]
+    pub down_since: Option<SystemTime>,
+}
+
+impl UrlTotals {
+    /// Total downtime up to `now`, including the current outage.
+    pub fn downtime_at(&self, now: SystemTime) -> Duration {
+        let current = self.down_since.map_or(Duration::ZERO, |since| now.duration_since(since).unwrap_or_default());
+        self.downtime + current
+    }
+}
+
+/// Per-URL cumulative counters saved with `--state-file`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "native", derive(serde::Serialize, serde::Deserialize))]
+pub struct UrlStatsState(HashMap<String, UrlTotals>);
+
+/// Per-URL statistics across rounds: cumulative check and failure counts, and the
+/// response times of the last [`LATENCY_WINDOW`] successful checks for percentiles,
+/// spread and jitter.
+#[derive(Debug, Clone, Default)]
+pub struct UrlStats {
+    urls: HashMap<String, UrlRecord>,
+}
+
+impl UrlStats {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn state(&self) -> UrlStatsState {
+        UrlStatsState(self.urls.iter().map(|(url, record)| (url.clone(), record.totals.clone())).collect())
+    }
+
+    /// Continues the counters from a saved state, replacing the current ones.
+    pub fn restore(&mut self, state: UrlStatsState) {
+        self.urls = state.0.into_iter()
+            .map(|(url, totals)| (url, UrlRecord { totals, samples: VecDeque::new() }))
+            .collect();
+    }
+
+    pub fn record(&mut self, status: &WebsiteStatus) {
+        let record = self.urls.entry(status.url.clone()).or_default();
+        record.totals.checks += 1;
+        if status.maintenance.is_none() {
+            match (&status.action_status, record.totals.down_since) {
+                (Err(_), None) => record.totals.down_since = Some(status.timestamp),
+                (Ok(_), Some(since)) => {
+                    record.totals.downtime += status.timestamp.duration_since(since).unwrap_or_default();
+                    record.totals.down_since = None;
+                }
+                _ => {}
+            }
+        }
+        if status.action_status.is_err() {
+            record.totals.failures += 1;
+            record.totals.last_failure = Some(status.timestamp);
+            return;
+        }
+        record.totals.success_time += status.response_time;
+        if record.samples.len() == LATENCY_WINDOW {
+            record.samples.pop_front();
+        }
+        record.samples.push_back(status.response_time);
+    }
+
+    /// The response times of the URL's last `n` successful checks, oldest first.
+    pub fn recent(&self, url: &str, n: usize) -> Vec<Duration> {
+        let Some(record) = self.urls.get(url) else { return Vec::new() };
+        record.samples.iter().skip(record.samples.len().saturating_sub(n)).copied().collect()
+    }
+
+    /// Statistics of every URL, sorted by URL.
+    pub fn stats(&self) -> Vec<UrlStatsRow<'_>> {
+        let mut rows: Vec<UrlStatsRow> = self.urls.iter()
+            .map(|(url, record)| {
+                let samples: Vec<Duration> = record.samples.iter().copied().collect();
+                UrlStatsRow {
+                    url,
+                    totals: &record.totals,
+                    window: samples.len(),
+                    percentiles: Percentiles::from_samples(samples.iter().copied()),
+                    std_dev: std_dev(&samples),
+                    jitter: jitter(&samples),
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| a.url.cmp(b.url));
+        rows
+    }
+
+    /// The `n` URLs doing worst: lowest success rate first, then highest p95.
+    pub fn top_offenders(&self, n: usize) -> Vec<UrlStatsRow<'_>> {
+        let mut rows = self.stats();
+        rows.sort_by(|a, b| {
+            a.success_rate().total_cmp(&b.success_rate())
+                .then_with(|| b.percentiles.map(|p| p.p95).cmp(&a.percentiles.map(|p| p.p95)))
+                .then_with(|| a.url.cmp(b.url))
+        });
+        rows.truncate(n);
+        rows
+    }
+
+    /// The `n` URLs with the most failed checks, most first; URLs that never failed
+    /// are left out.
+    pub fn most_failing(&self, n: usize) -> Vec<UrlStatsRow<'_>> {
+        let mut rows: Vec<UrlStatsRow> = self.stats().into_iter().filter(|row| row.totals.failures > 0).collect();
+        rows.sort_by(|a, b| b.totals.failures.cmp(&a.totals.failures).then_with(|| a.url.cmp(b.url)));
+        rows.truncate(n);
+        rows
+    }
+}
+
+/// One URL's row of [`UrlStats::stats`].
+#[derive(Debug, Clone)]
+pub struct UrlStatsRow<'a> {
+    pub url: &'a str,
+    pub totals: &'a UrlTotals,
+    /// Successful checks in the latency window.
+    pub window: usize,
+    /// `None` without successful checks in the window.
+    pub percentiles: Option<Percentiles>,
+    pub std_dev: Option<Duration>,
+    /// `None` until the window has two successful checks.
+    pub jitter: Option<Duration>,
+}
+
+impl UrlStatsRow<'_> {
+    /// Percentage of successful checks since the checker started.
+    pub fn success_rate(&self) -> f64 {
+        if self.totals.checks == 0 {
+            return 100.0;
+        }
+        (self.totals.checks - self.totals.failures) as f64 / self.totals.checks as f64 * 100.0
+    }
+
+    /// Average response time of all successful checks since the checker started.
+    pub fn avg_response_time(&self) -> Option<Duration> {
+        let successful = self.totals.checks - self.totals.failures;
+        if successful == 0 {
+            return None;
+        }
+        Some(self.totals.success_time / successful.min(u32::MAX as u64) as u32)
+    }
+}
+
+/// Apdex counts of a URL or tag for a threshold T: a successful check is satisfied
+/// within T, tolerating within 4T and frustrated above that; failed checks are
+/// frustrated too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Apdex {
+    pub satisfied: u64,
+    pub tolerating: u64,
+    pub frustrated: u64,
+}
+
+impl Apdex {
+    pub fn record(&mut self, status: &WebsiteStatus, threshold: Duration) {
+        match status.action_status {
+            Ok(_) if status.response_time <= threshold => self.satisfied += 1,
+            Ok(_) if status.response_time <= threshold * 4 => self.tolerating += 1,
+            _ => self.frustrated += 1,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.satisfied + self.tolerating + self.frustrated
+    }
+
+    /// (satisfied + tolerating / 2) / total, from 0 to 1; `None` without checks.
+    pub fn score(&self) -> Option<f64> {
+        let total = self.total();
+        (total > 0).then(|| (self.satisfied as f64 + self.tolerating as f64 / 2.0) / total as f64)
+    }
+}
+
+/// The usual rating of an Apdex score.
+pub fn apdex_rating(score: f64) -> &'static str {
+    match score {
+        s if s >= 0.94 => "Excellent",
+        s if s >= 0.85 => "Good",
+        s if s >= 0.70 => "Fair",
+        s if s >= 0.50 => "Poor",
+        _ => "Unacceptable",
+    }
+}
+
+/// Apdex per URL and per tag since the checker started, for `--apdex-threshold`.
+/// Checks during maintenance are left out.
+#[derive(Debug, Clone)]
+pub struct ApdexTracker {
+    threshold: Duration,
+    urls: BTreeMap<String, Apdex>,
+    tags: BTreeMap<String, Apdex>,
+}
+
+impl ApdexTracker {
+    pub fn new(threshold: Duration) -> Self {
+        ApdexTracker { threshold, urls: BTreeMap::new(), tags: BTreeMap::new() }
+    }
+
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    pub fn record(&mut self, status: &WebsiteStatus) {
+        if status.maintenance.is_some() {
+            return;
+        }
+        self.urls.entry(status.url.clone()).or_default().record(status, self.threshold);
+        for tag in &status.tags {
+            self.tags.entry(tag.clone()).or_default().record(status, self.threshold);
+        }
+    }
+
+    /// Sorted by URL.
+    pub fn urls(&self) -> &BTreeMap<String, Apdex> {
+        &self.urls
+    }
+
+    /// Sorted by tag.
+    pub fn tags(&self) -> &BTreeMap<String, Apdex> {
+        &self.tags
+    }
+}
+
+/// Default histogram bucket bounds, in milliseconds.
+pub const DEFAULT_BUCKETS_MS: [u64; 10] = [10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// The default histogram bucket bounds.
+pub fn default_buckets() -> Vec<Duration> {
+    DEFAULT_BUCKETS_MS.iter().map(|&ms| Duration::from_millis(ms)).collect()
+}
+
+/// Parses comma-separated bucket bounds like `50ms,100ms,250ms,1s` (a bare number is
+/// seconds). Returns them sorted without duplicates.
+pub fn parse_buckets(s: &str) -> Result<Vec<Duration>, String> {
+    let mut bounds = Vec::new();
+    for part in s.split(',') {
+        let bound = parse_duration(part).ok_or_else(|| format!("invalid bucket bound '{}'", part.trim()))?;
+        if bound.is_zero() {
+            return Err("bucket bounds must be greater than zero".to_string());
+        }
+        bounds.push(bound);
+    }
+    bounds.sort_unstable();
+    bounds.dedup();
+    Ok(bounds)
+}
+
+/// Latency histogram with fixed upper bounds, as in Prometheus: a sample falls in
+/// the first bucket whose bound is at least its value, and there is an implicit
+/// `+Inf` bucket above the last bound.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    bounds: Vec<Duration>,
+    // One per bound plus the +Inf bucket, not cumulative
+    counts: Vec<u64>,
+    sum: Duration,
+    count: u64,
+}
+
+impl Histogram {
+    /// `bounds` must be sorted, as [`parse_buckets`] returns them.
+    pub fn new(bounds: Vec<Duration>) -> Self {
+        let counts = vec![0; bounds.len() + 1];
+        Histogram { bounds, counts, sum: Duration::ZERO, count: 0 }
+    }
+
+    pub fn from_samples(bounds: Vec<Duration>, samples: &[Duration]) -> Self {
+        let mut histogram = Histogram::new(bounds);
+        for &sample in samples {
+            histogram.record(sample);
+        }
+        histogram
+    }
+
+    pub fn record(&mut self, value: Duration) {
+        let bucket = self.bounds.partition_point(|&bound| bound < value);
+        self.counts[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Each finite bound with the number of samples at or below it.
+    pub fn cumulative(&self) -> Vec<(Duration, u64)> {
+        let mut total = 0;
+        self.bounds.iter().zip(&self.counts)
+            .map(|(&bound, &count)| {
+                total += count;
+                (bound, total)
+            })
+            .collect()
+    }
+
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{result, result_at};
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&ms| Duration::from_millis(ms)).collect()
+    }
+
+    fn timed(url: &str, action_status: Result<u16, String>, ms: u64) -> WebsiteStatus {
+        WebsiteStatus { response_time: Duration::from_millis(ms), ..result(url, action_status) }
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let sorted = ms(&[15, 20, 35, 40, 50]);
+        assert_eq!(percentile(&sorted, 0.0), Some(Duration::from_millis(15)));
+        assert_eq!(percentile(&sorted, 30.0), Some(Duration::from_millis(20)));
+        assert_eq!(percentile(&sorted, 40.0), Some(Duration::from_millis(20)));
+        assert_eq!(percentile(&sorted, 50.0), Some(Duration::from_millis(35)));
+        assert_eq!(percentile(&sorted, 100.0), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&[], 50.0), None);
+
+        let mut samples: Vec<u64> = (1..=100).collect();
+        samples.reverse();
+        let percentiles = Percentiles::from_samples(ms(&samples)).unwrap();
+        assert_eq!(percentiles, Percentiles {
+            p50: Duration::from_millis(50),
+            p90: Duration::from_millis(90),
+            p95: Duration::from_millis(95),
+            p99: Duration::from_millis(99),
+        });
+        assert_eq!(percentiles.to_string(), "p50 50 ms, p90 90 ms, p95 95 ms, p99 99 ms");
+        assert_eq!(Percentiles::from_samples(ms(&[7])).unwrap().p99, Duration::from_millis(7));
+        assert_eq!(Percentiles::from_samples(Vec::new()), None);
+    }
+
+    #[test]
+    fn bucket_bounds() {
+        assert_eq!(parse_buckets("250ms, 50ms,1s,50ms").unwrap(), ms(&[50, 250, 1_000]));
+        assert_eq!(parse_buckets("2").unwrap(), ms(&[2_000]));
+        assert_eq!(parse_buckets("50ms,,1s").unwrap_err(), "invalid bucket bound ''");
+        assert_eq!(parse_buckets("0ms").unwrap_err(), "bucket bounds must be greater than zero");
+        assert_eq!(default_buckets().len(), DEFAULT_BUCKETS_MS.len());
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let histogram = Histogram::from_samples(ms(&[50, 100, 250]), &ms(&[10, 50, 51, 300, 400]));
+        assert_eq!(
+            histogram.cumulative(),
+            [(Duration::from_millis(50), 2), (Duration::from_millis(100), 3), (Duration::from_millis(250), 3)]
+        );
+        assert_eq!((histogram.count(), histogram.sum()), (5, Duration::from_millis(811)));
+        assert_eq!(Histogram::new(Vec::new()).cumulative(), []);
+    }
+
+    #[test]
+    fn spread_and_jitter() {
+        assert_eq!(std_dev(&ms(&[2, 4, 4, 4, 5, 5, 7, 9])), Some(Duration::from_millis(2)));
+        assert_eq!(std_dev(&ms(&[30])), Some(Duration::ZERO));
+        assert_eq!(std_dev(&[]), None);
+
+        assert_eq!(jitter(&ms(&[100, 120, 90, 90])), Some(Duration::from_millis(50) / 3));
+        assert_eq!(jitter(&ms(&[100, 200, 100, 200])), Some(Duration::from_millis(100)));
+        assert_eq!(jitter(&ms(&[100])), None);
+    }
+
+    #[test]
+    fn url_statistics_accumulate_across_rounds() {
+        let mut stats = UrlStats::new();
+        let (a, b) = ("https://a.example.com", "https://b.example.com");
+        for status in [
+            timed(a, Ok(200), 100),
+            timed(a, Err("timed out".to_string()), 5_000),
+            timed(a, Ok(200), 300),
+            timed(a, Ok(200), 200),
+            timed(b, Ok(200), 20),
+        ] {
+            stats.record(&status);
+        }
+        let rows = stats.stats();
+        assert_eq!(rows.iter().map(|row| row.url).collect::<Vec<_>>(), [a, b]);
+        let row = &rows[0];
+        assert_eq!((row.totals.checks, row.totals.failures, row.window), (4, 1, 3));
+        assert_eq!(row.success_rate(), 75.0);
+        assert_eq!(row.avg_response_time(), Some(Duration::from_millis(200)));
+        assert_eq!(row.percentiles.map(|p| p.p50), Some(Duration::from_millis(200)));
+        assert_eq!(row.jitter, Some(Duration::from_millis(150)));
+        assert!(row.totals.last_failure.is_some());
+        assert_eq!(stats.recent(a, 2), ms(&[300, 200]));
+        assert_eq!(stats.recent("https://unknown.example.com", 2), []);
+
+        assert_eq!(stats.top_offenders(1).iter().map(|row| row.url).collect::<Vec<_>>(), [a]);
+        assert_eq!(stats.top_offenders(5).len(), 2);
+    }
+
+    #[test]
+    fn the_latency_window_keeps_the_latest_checks() {
+        let mut stats = UrlStats::new();
+        for i in 0..LATENCY_WINDOW as u64 + 5 {
+            stats.record(&timed("https://example.com", Ok(200), i));
+        }
+        let rows = stats.stats();
+        assert_eq!((rows[0].window, rows[0].totals.checks), (LATENCY_WINDOW, LATENCY_WINDOW as u64 + 5));
+        assert_eq!(stats.recent("https://example.com", LATENCY_WINDOW)[0], Duration::from_millis(5));
+    }
+
+    #[test]
+    fn restored_statistics_keep_the_counters() {
+        let mut stats = UrlStats::new();
+        stats.record(&timed("https://example.com", Ok(200), 100));
+        stats.record(&timed("https://example.com", Err("timed out".to_string()), 100));
+        let mut restored = UrlStats::new();
+        restored.restore(stats.state());
+        let rows = restored.stats();
+        assert_eq!((rows[0].totals, rows[0].window), (stats.stats()[0].totals, 0));
+        assert_eq!(rows[0].avg_response_time(), Some(Duration::from_millis(100)));
+        assert_eq!(rows[0].percentiles, None);
+    }
+
+    #[test]
+    fn most_failing_urls() {
+        let mut stats = UrlStats::new();
+        let failed = || Err("timed out".to_string());
+        for (url, status) in [
+            ("https://a.example.com", failed()),
+            ("https://b.example.com", failed()),
+            ("https://b.example.com", failed()),
+            ("https://c.example.com", failed()),
+            ("https://d.example.com", Ok(200)),
+        ] {
+            stats.record(&timed(url, status, 100));
+        }
+        let urls = |rows: Vec<UrlStatsRow>| rows.iter().map(|row| row.url.to_string()).collect::<Vec<_>>();
+        assert_eq!(urls(stats.most_failing(2)), ["https://b.example.com", "https://a.example.com"]);
+        assert_eq!(urls(stats.most_failing(10)), ["https://b.example.com", "https://a.example.com", "https://c.example.com"]);
+    }
+
+    #[test]
+    fn downtime_counts_finished_and_current_outages() {
+        let mut stats = UrlStats::new();
+        let url = "https://example.com";
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        let mut planned = result_at(url, Err("timed out".to_string()), at(0));
+        planned.maintenance = Some("Upgrade".to_string());
+        for status in [
+            planned,
+            result_at(url, Err("timed out".to_string()), at(60)),
+            result_at(url, Err("timed out".to_string()), at(90)),
+            result_at(url, Ok(200), at(180)),
+            result_at(url, Err("HTTP 500".to_string()), at(300)),
+        ] {
+            stats.record(&status);
+        }
+        let totals = stats.stats()[0].totals.clone();
+        assert_eq!((totals.downtime, totals.down_since), (Duration::from_secs(120), Some(at(300))));
+        assert_eq!(totals.downtime_at(at(330)), Duration::from_secs(150));
+        assert_eq!(totals.failures, 4);
+    }
+
+    #[test]
+    fn apdex_zones_and_scores() {
+        let threshold = Duration::from_millis(500);
+        let mut apdex = Apdex::default();
+        for status in [
+            timed("https://example.com", Ok(200), 500),
+            timed("https://example.com", Ok(404), 501),
+            timed("https://example.com", Ok(200), 2_000),
+            timed("https://example.com", Ok(200), 2_001),
+            timed("https://example.com", Err("timed out".to_string()), 10),
+        ] {
+            apdex.record(&status, threshold);
+        }
+        assert_eq!(apdex, Apdex { satisfied: 1, tolerating: 2, frustrated: 2 });
+        assert_eq!((apdex.total(), apdex.score()), (5, Some(0.4)));
+        assert_eq!(Apdex::default().score(), None);
+
+        let ratings: Vec<_> = [1.0, 0.94, 0.93, 0.85, 0.7, 0.5, 0.49].into_iter().map(apdex_rating).collect();
+        assert_eq!(ratings, ["Excellent", "Excellent", "Good", "Good", "Fair", "Poor", "Unacceptable"]);
+    }
+
+    #[test]
+    fn apdex_per_url_and_tag() {
+        let mut tracker = ApdexTracker::new(Duration::from_millis(100));
+        let tagged = |url: &str, ms, tags: &[&str]| WebsiteStatus {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..timed(url, Ok(200), ms)
+        };
+        let mut planned = tagged("https://a.example.com", 5_000, &["web"]);
+        planned.maintenance = Some("Upgrade".to_string());
+        for status in [
+            tagged("https://a.example.com", 50, &["web"]),
+            tagged("https://b.example.com", 300, &["web", "api"]),
+            planned,
+        ] {
+            tracker.record(&status);
+        }
+        assert_eq!(tracker.urls().len(), 2);
+        assert_eq!(tracker.urls()["https://a.example.com"].score(), Some(1.0));
+        assert_eq!(tracker.tags()["web"], Apdex { satisfied: 1, tolerating: 1, frustrated: 0 });
+        assert_eq!(tracker.tags()["api"].score(), Some(0.5));
+    }
+
+    #[test]
+    fn sparklines_scale_from_the_fastest_to_the_slowest() {
+        assert_eq!(sparkline(&ms(&[100, 200, 800, 450, 100])), "▁▂█▅▁");
+        assert_eq!(sparkline(&ms(&[40, 40])), "▁▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+}
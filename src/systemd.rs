@@ -0,0 +1,180 @@
+//! systemd `Type=notify` support: tells the service manager when the monitor is
+//! ready, pings its watchdog and reports progress in `systemctl status`. All of it is
+//! a no-op unless systemd started the checker with `NOTIFY_SOCKET` set.
+
+use std::env;
+#[cfg(unix)]
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::process;
+use std::time::Duration;
+
+/// A connection to the service manager's notification socket.
+#[derive(Debug)]
+pub struct Systemd {
+    #[cfg(unix)]
+    socket: UnixDatagram,
+    watchdog: Option<Duration>,
+}
+
+impl Systemd {
+    /// Connects to `$NOTIFY_SOCKET`, or returns `None` if systemd didn't set it (or
+    /// the socket can't be reached, which is logged).
+    #[cfg(unix)]
+    pub fn from_env() -> Option<Systemd> {
+        let path = env::var_os("NOTIFY_SOCKET")?;
+        let socket = match connect(&path) {
+            Ok(socket) => socket,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to connect to the systemd notification socket");
+                return None;
+            }
+        };
+        let watchdog = watchdog_timeout(env::var("WATCHDOG_USEC").ok(), env::var("WATCHDOG_PID").ok());
+        Some(Systemd { socket, watchdog })
+    }
+
+    /// systemd only exists on Unix.
+    #[cfg(not(unix))]
+    pub fn from_env() -> Option<Systemd> {
+        None
+    }
+
+    /// How often to ping the watchdog: half its timeout (`WatchdogSec=`), as systemd
+    /// recommends, or `None` if it's off.
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog.map(|timeout| timeout / 2)
+    }
+
+    /// `READY=1`: startup is done, units ordered after this one may start.
+    pub fn ready(&self, status: &str) {
+        self.notify(&format!("READY=1\nSTATUS={}", status));
+    }
+
+    /// `WATCHDOG=1`, if the watchdog is on.
+    pub fn watchdog(&self) {
+        if self.watchdog.is_some() {
+            self.notify("WATCHDOG=1");
+        }
+    }
+
+    /// The status line shown by `systemctl status`.
+    pub fn status(&self, status: &str) {
+        self.notify(&format!("STATUS={}", status));
+    }
+
+    /// `STOPPING=1`: the service is shutting down.
+    pub fn stopping(&self) {
+        self.notify("STOPPING=1");
+    }
+
+    fn notify(&self, message: &str) {
+        #[cfg(unix)]
+        if let Err(e) = self.socket.send(message.as_bytes()) {
+            tracing::debug!(error = %e, message, "failed to notify systemd");
+        }
+        #[cfg(not(unix))]
+        let _ = message;
+    }
+}
+
+// The watchdog timeout from `$WATCHDOG_USEC`; it's meant for the main process, not
+// for ones started from it, which `$WATCHDOG_PID` tells apart
+#[cfg(unix)]
+fn watchdog_timeout(usec: Option<String>, pid: Option<String>) -> Option<Duration> {
+    let for_us = pid.is_none_or(|pid| pid.parse() == Ok(process::id()));
+    usec.and_then(|usec| usec.parse().ok())
+        .filter(|usec| *usec > 0 && for_us)
+        .map(Duration::from_micros)
+}
+
+// `$NOTIFY_SOCKET` is a path, or an abstract socket name if it starts with `@`
+#[cfg(unix)]
+fn connect(path: &OsStr) -> std::io::Result<UnixDatagram> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let socket = UnixDatagram::unbound()?;
+    match path.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            socket.connect_addr(&std::os::unix::net::SocketAddr::from_abstract_name(name)?)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return Err(std::io::Error::other("abstract sockets are only supported on Linux")),
+        None => socket.connect(path)?,
+    }
+    Ok(socket)
+}
+
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn received(socket: &UnixDatagram) -> String {
+        let mut buffer = [0; 256];
+        let len = socket.recv(&mut buffer).unwrap();
+        String::from_utf8_lossy(&buffer[..len]).into_owned()
+    }
+
+    #[test]
+    fn notifications_are_sent_as_datagrams() {
+        let (socket, manager) = UnixDatagram::pair().unwrap();
+        manager.set_nonblocking(true).unwrap();
+        let systemd = Systemd { socket, watchdog: Some(Duration::from_secs(30)) };
+        assert_eq!(systemd.watchdog_interval(), Some(Duration::from_secs(15)));
+        systemd.ready("checking 3 URLs");
+        assert_eq!(received(&manager), "READY=1\nSTATUS=checking 3 URLs");
+        systemd.watchdog();
+        assert_eq!(received(&manager), "WATCHDOG=1");
+        systemd.status("round 2: 3 up");
+        assert_eq!(received(&manager), "STATUS=round 2: 3 up");
+        systemd.stopping();
+        assert_eq!(received(&manager), "STOPPING=1");
+    }
+
+    #[test]
+    fn watchdog_pings_need_the_watchdog() {
+        let (socket, manager) = UnixDatagram::pair().unwrap();
+        manager.set_nonblocking(true).unwrap();
+        let systemd = Systemd { socket, watchdog: None };
+        assert_eq!(systemd.watchdog_interval(), None);
+        systemd.watchdog();
+        systemd.stopping();
+        assert_eq!(received(&manager), "STOPPING=1");
+    }
+
+    #[test]
+    fn watchdog_timeout_is_for_the_main_process_only() {
+        let us = Some(process::id().to_string());
+        assert_eq!(watchdog_timeout(Some("20000000".to_string()), None), Some(Duration::from_secs(20)));
+        assert_eq!(watchdog_timeout(Some("20000000".to_string()), us), Some(Duration::from_secs(20)));
+        assert_eq!(watchdog_timeout(Some("20000000".to_string()), Some((process::id() + 1).to_string())), None);
+        assert_eq!(watchdog_timeout(Some("0".to_string()), None), None);
+        assert_eq!(watchdog_timeout(Some("soon".to_string()), None), None);
+        assert_eq!(watchdog_timeout(None, None), None);
+    }
+
+    #[test]
+    fn connects_to_path_and_abstract_sockets() {
+        let path = crate::testing::temp_dir("systemd").join("notify");
+        let manager = UnixDatagram::bind(&path).unwrap();
+        connect(path.as_os_str()).unwrap().send(b"READY=1").unwrap();
+        assert_eq!(received(&manager), "READY=1");
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            let name = format!("website-checker-test-{}", process::id());
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+            let manager = UnixDatagram::bind_addr(&address).unwrap();
+            connect(OsStr::new(&format!("@{}", name))).unwrap().send(b"WATCHDOG=1").unwrap();
+            assert_eq!(received(&manager), "WATCHDOG=1");
+        }
+
+        assert!(connect(OsStr::new("/nonexistent/notify")).is_err());
+    }
+}
@@ -0,0 +1,461 @@
+//! The `--tui` dashboard: a live table of the checked URLs with their state, latency
+//! trend and last error, drawn with ratatui on the alternate screen.
+//!
+//! The round loop keeps running as usual and feeds results in through [`Tui`]; the
+//! dashboard runs on its own thread, so it stays responsive during long rounds and
+//! can re-check a single URL on demand.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Cell, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::stats::sparkline;
+use crate::{format_ago, format_response_time, CheckRequest, CheckResult, Checker};
+
+/// Successful response times kept per URL for its sparkline.
+const TREND_SAMPLES: usize = 30;
+
+// How often the countdown is redrawn while no input arrives
+const TICK: Duration = Duration::from_millis(250);
+
+enum Message {
+    RoundStarted(u64),
+    Result(CheckResult),
+    RoundFinished { next_round: Instant },
+    Stop,
+}
+
+/// Handle to the running dashboard. Dropping it restores the terminal.
+pub struct Tui {
+    messages: Sender<Message>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Tui {
+    /// Switches the terminal to the dashboard. `checker` runs the re-checks asked for
+    /// with `r`; quitting (`q`) exits the process.
+    pub fn start(checker: Checker, requests: Vec<CheckRequest>) -> Result<Tui, String> {
+        let terminal = ratatui::try_init().map_err(|e| format!("Failed to start the dashboard: {}", e))?;
+        let (messages, receiver) = channel();
+        let app = App::new(checker, requests, messages.clone());
+        let thread = thread::spawn(move || app.run(terminal, receiver));
+        Ok(Tui { messages, thread: Some(thread) })
+    }
+
+    pub fn round_started(&self, round: u64) {
+        let _ = self.messages.send(Message::RoundStarted(round));
+    }
+
+    pub fn result(&self, result: &CheckResult) {
+        let _ = self.messages.send(Message::Result(result.clone()));
+    }
+
+    pub fn round_finished(&self, next_round: Instant) {
+        let _ = self.messages.send(Message::RoundFinished { next_round });
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = self.messages.send(Message::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        ratatui::restore();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortBy {
+    Url,
+    /// Down first, then maintenance, pending and up.
+    State,
+    /// Slowest first.
+    Latency,
+}
+
+impl SortBy {
+    fn next(self) -> SortBy {
+        match self {
+            SortBy::Url => SortBy::State,
+            SortBy::State => SortBy::Latency,
+            SortBy::Latency => SortBy::Url,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortBy::Url => "url",
+            SortBy::State => "state",
+            SortBy::Latency => "latency",
+        }
+    }
+}
+
+// What the dashboard knows about one URL
+struct UrlRow {
+    request: CheckRequest,
+    last: Option<CheckResult>,
+    trend: VecDeque<Duration>,
+    last_error: Option<(SystemTime, String)>,
+    rechecking: bool,
+}
+
+impl UrlRow {
+    // Sort rank for SortBy::State, worst first
+    fn state_rank(&self) -> u8 {
+        match &self.last {
+            Some(result) if result.maintenance.is_some() => 1,
+            Some(result) if result.action_status.is_err() => 0,
+            Some(_) => 3,
+            None => 2,
+        }
+    }
+
+    fn state(&self) -> (&'static str, Color) {
+        match &self.last {
+            None => ("PENDING", Color::DarkGray),
+            Some(result) if result.maintenance.is_some() => ("MAINT", Color::Yellow),
+            Some(result) if result.action_status.is_ok() => ("UP", Color::Green),
+            Some(_) => ("DOWN", Color::Red),
+        }
+    }
+}
+
+struct App {
+    checker: Checker,
+    messages: Sender<Message>,
+    rows: Vec<UrlRow>,
+    index: HashMap<String, usize>,
+    round: u64,
+    round_running: bool,
+    next_round: Option<Instant>,
+    sort_by: SortBy,
+    filter: String,
+    editing_filter: bool,
+    table: TableState,
+}
+
+impl App {
+    fn new(checker: Checker, requests: Vec<CheckRequest>, messages: Sender<Message>) -> Self {
+        let index = requests.iter().enumerate().map(|(i, request)| (request.url.clone(), i)).collect();
+        let rows = requests.into_iter()
+            .map(|request| UrlRow { request, last: None, trend: VecDeque::new(), last_error: None, rechecking: false })
+            .collect();
+        App {
+            checker,
+            messages,
+            rows,
+            index,
+            round: 0,
+            round_running: false,
+            next_round: None,
+            sort_by: SortBy::Url,
+            filter: String::new(),
+            editing_filter: false,
+            table: TableState::default().with_selected(Some(0)),
+        }
+    }
+
+    fn run(mut self, mut terminal: DefaultTerminal, receiver: Receiver<Message>) {
+        loop {
+            match receiver.recv_timeout(Duration::ZERO) {
+                Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+                Ok(message) => {
+                    self.handle_message(message);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if terminal.draw(|frame| self.draw(frame)).is_err() {
+                return;
+            }
+            match event::poll(TICK) {
+                Ok(true) => {
+                    if let Ok(Event::Key(key)) = event::read() {
+                        if key.kind == KeyEventKind::Press && self.handle_key(key) {
+                            ratatui::restore();
+                            std::process::exit(0);
+                        }
+                    }
+                }
+                Ok(false) => {}
+                Err(_) => return,
+            }
+        }
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::RoundStarted(round) => {
+                self.round = round;
+                self.round_running = true;
+            }
+            Message::Result(result) => {
+                let Some(&i) = self.index.get(&result.url) else { return };
+                let row = &mut self.rows[i];
+                row.rechecking = false;
+                match &result.action_status {
+                    Ok(_) => {
+                        if row.trend.len() == TREND_SAMPLES {
+                            row.trend.pop_front();
+                        }
+                        row.trend.push_back(result.response_time);
+                    }
+                    Err(e) => row.last_error = Some((result.timestamp, e.clone())),
+                }
+                row.last = Some(result);
+            }
+            Message::RoundFinished { next_round } => {
+                self.round_running = false;
+                self.next_round = Some(next_round);
+            }
+            Message::Stop => {}
+        }
+    }
+
+    // Returns true to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            self.table.select(Some(0));
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('s') => self.sort_by = self.sort_by.next(),
+            KeyCode::Char('r') => self.recheck_selected(),
+            _ => {}
+        }
+        false
+    }
+
+    // Indices of the rows to show, filtered and sorted
+    fn visible(&self) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+        let mut visible: Vec<usize> = (0..self.rows.len())
+            .filter(|&i| self.rows[i].request.url.to_lowercase().contains(&filter))
+            .collect();
+        match self.sort_by {
+            SortBy::Url => visible.sort_by(|&a, &b| self.rows[a].request.url.cmp(&self.rows[b].request.url)),
+            SortBy::State => visible.sort_by_key(|&i| (self.rows[i].state_rank(), &self.rows[i].request.url)),
+            SortBy::Latency => visible.sort_by(|&a, &b| {
+                let latency = |i: usize| self.rows[i].last.as_ref().map(|result| result.response_time);
+                latency(b).cmp(&latency(a))
+            }),
+        }
+        visible
+    }
+
+    fn recheck_selected(&mut self) {
+        let visible = self.visible();
+        let Some(&i) = self.table.selected().and_then(|selected| visible.get(selected)) else { return };
+        let row = &mut self.rows[i];
+        if row.rechecking {
+            return;
+        }
+        row.rechecking = true;
+        let (checker, request, messages) = (self.checker.clone(), row.request.clone(), self.messages.clone());
+        thread::spawn(move || {
+            for result in checker.check_all([request]) {
+                let _ = messages.send(Message::Result(result));
+            }
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header_area, table_area, footer_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        let (mut up, mut down, mut maintenance) = (0, 0, 0);
+        for row in &self.rows {
+            match row.state().0 {
+                "UP" => up += 1,
+                "DOWN" => down += 1,
+                "MAINT" => maintenance += 1,
+                _ => {}
+            }
+        }
+        let timing = match (self.round_running, self.next_round) {
+            (true, _) => "checking...".to_string(),
+            (false, Some(next)) => format!("next round in {}s", next.saturating_duration_since(Instant::now()).as_secs()),
+            (false, None) => String::new(),
+        };
+        let mut header = vec![
+            Span::styled(format!("Round {} ", self.round), Style::new().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("| {} | ", timing)),
+            Span::styled(format!("{} up", up), Style::new().fg(Color::Green)),
+            Span::raw(", "),
+            Span::styled(format!("{} down", down), Style::new().fg(Color::Red)),
+        ];
+        if maintenance > 0 {
+            header.push(Span::raw(", "));
+            header.push(Span::styled(format!("{} maintenance", maintenance), Style::new().fg(Color::Yellow)));
+        }
+        header.push(Span::raw(format!(" | sort: {}", self.sort_by.label())));
+        if self.editing_filter || !self.filter.is_empty() {
+            header.push(Span::raw(format!(" | filter: {}{}", self.filter, if self.editing_filter { "_" } else { "" })));
+        }
+        frame.render_widget(Paragraph::new(Line::from(header)), header_area);
+
+        let now = SystemTime::now();
+        let rows: Vec<Row> = self.visible().into_iter()
+            .map(|i| {
+                let row = &self.rows[i];
+                let (state, color) = row.state();
+                let (code, latency) = match &row.last {
+                    Some(result) => (
+                        result.action_status.as_ref().map_or("-".to_string(), |code| code.to_string()),
+                        format_response_time(result.response_time),
+                    ),
+                    None => ("-".to_string(), "-".to_string()),
+                };
+                let trend: Vec<Duration> = row.trend.iter().copied().collect();
+                let last_error = row.last_error.as_ref()
+                    .map_or(String::new(), |(at, error)| format!("{} {}", format_ago(*at, now), error));
+                Row::new(vec![
+                    Cell::from(row.request.url.clone()),
+                    Cell::from(if row.rechecking { "..." } else { state }).style(Style::new().fg(color)),
+                    Cell::from(code),
+                    Cell::from(latency),
+                    Cell::from(sparkline(&trend)),
+                    Cell::from(last_error),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Fill(2),
+            Constraint::Length(7),
+            Constraint::Length(5),
+            Constraint::Length(9),
+            Constraint::Length(TREND_SAMPLES as u16),
+            Constraint::Fill(3),
+        ];
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(["URL", "State", "Code", "Latency", "Trend", "Last error"])
+                    .style(Style::new().add_modifier(Modifier::BOLD | Modifier::UNDERLINED)),
+            )
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let help = if self.editing_filter {
+            "type to filter by URL | Enter: keep filter | Esc: clear filter"
+        } else {
+            "↑/↓: select | r: re-check selected | /: filter | s: sort | q: quit"
+        };
+        frame.render_widget(Paragraph::new(help).style(Style::new().fg(Color::DarkGray)), footer_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::testing::{response, result, FakeBackend};
+    use crate::{Config, HttpRequest};
+
+    fn app(urls: &[&str]) -> (App, Receiver<Message>) {
+        let backend = FakeBackend(|_: HttpRequest<'_>| Ok(response(204, &[])));
+        let checker = Checker::with_backend(Config::default(), 1, Arc::new(backend)).unwrap();
+        let requests = urls.iter().map(|url| CheckRequest::parse(url).unwrap()).collect();
+        let (messages, receiver) = channel();
+        (App::new(checker, requests, messages), receiver)
+    }
+
+    fn timed(url: &str, action_status: Result<u16, String>, ms: u64) -> Message {
+        Message::Result(CheckResult { response_time: Duration::from_millis(ms), ..result(url, action_status) })
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn urls(app: &App) -> Vec<&str> {
+        app.visible().into_iter().map(|i| app.rows[i].request.url.as_str()).collect()
+    }
+
+    #[test]
+    fn results_update_their_rows() {
+        let (mut app, _) = app(&["https://b.example.com", "https://a.example.com"]);
+        app.handle_message(Message::RoundStarted(4));
+        assert!(app.round_running);
+        app.handle_message(timed("https://a.example.com", Ok(200), 120));
+        app.handle_message(timed("https://a.example.com", Err("timed out".to_string()), 5_000));
+        app.handle_message(timed("https://unknown.example.com", Ok(200), 1));
+        for ms in 0..TREND_SAMPLES as u64 + 2 {
+            app.handle_message(timed("https://b.example.com", Ok(200), ms));
+        }
+
+        let (a, b) = (&app.rows[1], &app.rows[0]);
+        assert_eq!(a.state().0, "DOWN");
+        assert_eq!(a.trend, [Duration::from_millis(120)]);
+        assert_eq!(a.last_error.as_ref().map(|(_, e)| e.as_str()), Some("timed out"));
+        assert_eq!(b.state().0, "UP");
+        assert_eq!((b.trend.len(), b.trend[0]), (TREND_SAMPLES, Duration::from_millis(2)));
+        app.handle_message(Message::RoundFinished { next_round: Instant::now() });
+        assert!(!app.round_running && app.next_round.is_some());
+    }
+
+    #[test]
+    fn rows_can_be_sorted_and_filtered() {
+        let (mut app, _) = app(&["https://c.example.com", "https://a.example.com", "https://b.example.org", "https://d.example.com"]);
+        app.handle_message(timed("https://a.example.com", Ok(200), 300));
+        app.handle_message(timed("https://b.example.org", Err("HTTP 500".to_string()), 50));
+        let mut planned = result("https://c.example.com", Ok(200));
+        planned.maintenance = Some("Upgrade".to_string());
+        app.handle_message(Message::Result(planned));
+
+        assert_eq!(urls(&app), ["https://a.example.com", "https://b.example.org", "https://c.example.com", "https://d.example.com"]);
+        app.handle_key(key(KeyCode::Char('s')));
+        assert_eq!((app.sort_by, app.rows[3].state().0), (SortBy::State, "PENDING"));
+        assert_eq!(urls(&app), ["https://b.example.org", "https://c.example.com", "https://d.example.com", "https://a.example.com"]);
+        app.handle_key(key(KeyCode::Char('s')));
+        assert_eq!(urls(&app), ["https://a.example.com", "https://c.example.com", "https://b.example.org", "https://d.example.com"]);
+
+        for code in [KeyCode::Char('/'), KeyCode::Char('.'), KeyCode::Char('C'), KeyCode::Char('x'), KeyCode::Backspace, KeyCode::Char('O')] {
+            assert!(!app.handle_key(key(code)));
+        }
+        assert_eq!(urls(&app), ["https://a.example.com", "https://c.example.com", "https://d.example.com"]);
+        app.handle_key(key(KeyCode::Enter));
+        assert!(!app.editing_filter);
+        assert!(app.handle_key(key(KeyCode::Char('q'))));
+        assert!(app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+    }
+
+    #[test]
+    fn the_selected_url_can_be_rechecked() {
+        let (mut app, receiver) = app(&["https://b.example.com", "https://a.example.com"]);
+        app.handle_key(key(KeyCode::Char('j')));
+        app.recheck_selected();
+        assert!(app.rows[0].rechecking);
+        let Ok(Message::Result(result)) = receiver.recv_timeout(Duration::from_secs(5)) else { panic!("expected a result") };
+        assert_eq!((result.url.as_str(), &result.action_status), ("https://b.example.com", &Ok(204)));
+        app.handle_message(Message::Result(result));
+        assert!(!app.rows[0].rechecking);
+    }
+}
@@ -0,0 +1,217 @@
+//! `--watch`: a compact status table redrawn in place after every round, one row per
+//! URL, for `--period` runs where the full per-round output would scroll away.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+use crate::color::{self, paint, status_color};
+use crate::stats::{sparkline, SPARKLINE_SAMPLES};
+use crate::{format_ago, format_duration, format_response_time, format_timestamp, CheckResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Up,
+    Down,
+    Maintenance,
+}
+
+impl State {
+    fn of(result: &CheckResult) -> State {
+        if result.maintenance.is_some() {
+            State::Maintenance
+        } else if result.action_status.is_ok() {
+            State::Up
+        } else {
+            State::Down
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            State::Up => "UP",
+            State::Down => "DOWN",
+            State::Maintenance => "MAINT",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            State::Up => color::GREEN,
+            State::Down => color::RED,
+            State::Maintenance => color::YELLOW,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct WatchRow {
+    last: CheckResult,
+    state: State,
+    /// When the URL entered its current state (or was first checked).
+    since: SystemTime,
+    /// Latest successful response times, oldest first.
+    trend: VecDeque<Duration>,
+}
+
+/// The latest result and last state change of every URL seen so far, in the order
+/// they were first checked.
+#[derive(Debug, Clone, Default)]
+pub struct WatchTable {
+    rows: Vec<WatchRow>,
+    index: HashMap<String, usize>,
+    local_time: bool,
+}
+
+impl WatchTable {
+    /// `local_time` shows times in the local time zone instead of UTC.
+    pub fn new(local_time: bool) -> Self {
+        WatchTable { local_time, ..Default::default() }
+    }
+
+    pub fn update(&mut self, results: &[CheckResult]) {
+        for result in results {
+            let state = State::of(result);
+            let i = *self.index.entry(result.url.clone()).or_insert_with(|| {
+                self.rows.push(WatchRow { last: result.clone(), state, since: result.timestamp, trend: VecDeque::new() });
+                self.rows.len() - 1
+            });
+            let row = &mut self.rows[i];
+            if row.state != state {
+                row.state = state;
+                row.since = result.timestamp;
+            }
+            row.last = result.clone();
+            if result.action_status.is_ok() {
+                if row.trend.len() == SPARKLINE_SAMPLES {
+                    row.trend.pop_front();
+                }
+                row.trend.push_back(result.response_time);
+            }
+        }
+    }
+
+    /// The table with a header line for `round`; `next_round` is the time until the
+    /// next one. States are colored if `color` is set.
+    pub fn render(&self, round: u64, next_round: Option<Duration>, color: bool) -> String {
+        let now = SystemTime::now();
+        let down = self.rows.iter().filter(|row| row.state == State::Down).count();
+        let maintenance = self.rows.iter().filter(|row| row.state == State::Maintenance).count();
+        let mut out = format!(
+            "Round {} at {}: {} up, {} down",
+            round,
+            format_timestamp(now, self.local_time),
+            self.rows.len() - down - maintenance,
+            down
+        );
+        if maintenance > 0 {
+            out.push_str(&format!(", {} in maintenance", maintenance));
+        }
+        if let Some(next_round) = next_round {
+            out.push_str(&format!(" (next round in {})", format_duration(next_round)));
+        }
+        let url_width = self.rows.iter().map(|row| row.last.url.chars().count()).max().unwrap_or(0).clamp(3, 50);
+        out.push_str(&format!(
+            "\n\n{:<url_width$}  {:<5}  {:>6}  {:>8}  {:<trend_width$}  Last change\n",
+            "URL",
+            "State",
+            "Status",
+            "Time",
+            "Trend",
+            url_width = url_width,
+            trend_width = SPARKLINE_SAMPLES
+        ));
+        for row in &self.rows {
+            let url: String = if row.last.url.chars().count() > url_width {
+                row.last.url.chars().take(url_width - 3).chain("...".chars()).collect()
+            } else {
+                row.last.url.clone()
+            };
+            let state = paint(&format!("{:<5}", row.state.label()), row.state.color(), color);
+            let status = match &row.last.action_status {
+                Ok(code) => code.to_string(),
+                Err(_) => "ERR".to_string(),
+            };
+            let status = paint(&format!("{:>6}", status), status_color(&row.last.action_status), color);
+            let trend = sparkline(&row.trend.iter().copied().collect::<Vec<_>>());
+            out.push_str(&format!(
+                "{:<url_width$}  {}  {}  {:>8}  {:<trend_width$}  {} ({})\n",
+                url,
+                state,
+                status,
+                format_response_time(row.last.response_time),
+                trend,
+                format_timestamp(row.since, self.local_time),
+                format_ago(row.since, now),
+                url_width = url_width,
+                trend_width = SPARKLINE_SAMPLES
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::testing::result_at;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn rows_remember_their_last_state_change() {
+        let mut table = WatchTable::new(false);
+        let (a, b) = ("https://a.example.com", "https://b.example.com");
+        table.update(&[result_at(a, Ok(200), at(60)), result_at(b, Err("timed out".to_string()), at(60))]);
+        table.update(&[result_at(a, Ok(200), at(120)), result_at(b, Err("timed out".to_string()), at(120))]);
+        table.update(&[result_at(b, Ok(301), at(180))]);
+
+        let rows: Vec<_> = table.rows.iter().map(|row| (row.last.url.as_str(), row.state, row.since, row.trend.len())).collect();
+        assert_eq!(rows, [(a, State::Up, at(60), 2), (b, State::Up, at(180), 1)]);
+    }
+
+    #[test]
+    fn the_table_has_a_row_per_url() {
+        let mut table = WatchTable::new(false);
+        let long_url = format!("https://example.com/{}", "x".repeat(60));
+        let mut planned = result_at("https://db.example.com", Ok(200), at(0));
+        planned.maintenance = Some("Upgrade".to_string());
+        table.update(&[
+            result_at("https://api.example.com", Err("HTTP 503".to_string()), at(1_715_730_300)),
+            result_at(&long_url, Ok(200), at(1_715_730_300)),
+            planned,
+        ]);
+        let text = table.render(7, Some(Duration::from_secs(45)), false);
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("Round 7 at "), "{}", text);
+        assert!(lines[0].ends_with(" UTC: 1 up, 1 down, 1 in maintenance (next round in 45s)"), "{}", text);
+        assert_eq!(lines[2], format!("{:<50}  State  Status      Time  {:<20}  Last change", "URL", "Trend"));
+        assert!(lines[3].starts_with(&format!(
+            "{:<50}  DOWN      ERR    100 ms  {:<20}  2024-05-14 23:45:00 UTC (",
+            "https://api.example.com", ""
+        )), "{}", text);
+        assert!(lines[4].starts_with(&format!("https://example.com/{}...  UP        200    100 ms  ▁", "x".repeat(27))), "{}", text);
+        assert!(lines[5].contains("  MAINT  "), "{}", text);
+        assert!(!text.contains('\x1b'));
+        assert!(table.render(7, None, true).contains(&paint("DOWN ", color::RED, true)));
+    }
+
+    #[test]
+    fn the_trend_keeps_the_latest_successful_checks() {
+        let mut table = WatchTable::new(false);
+        let url = "https://example.com";
+        for i in 0..SPARKLINE_SAMPLES as u64 + 3 {
+            let result = CheckResult { response_time: Duration::from_millis(100 + i), ..result_at(url, Ok(200), at(i)) };
+            table.update(&[result]);
+        }
+        table.update(&[result_at(url, Err("timed out".to_string()), at(60))]);
+        let trend = &table.rows[0].trend;
+        assert_eq!((trend.len(), trend[0]), (SPARKLINE_SAMPLES, Duration::from_millis(103)));
+        let expected = sparkline(&trend.iter().copied().collect::<Vec<_>>());
+        assert!(expected.starts_with('▁') && expected.ends_with('█'));
+        assert!(table.render(1, None, false).contains(&format!("  {}  ", expected)));
+    }
+}